@@ -0,0 +1,239 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Client
+use crate::error::ClientError;
+use crate::request::ChatCompletionRequest;
+use llm_router_core::config::{Llm, Policy};
+use reqwest::{Method, RequestBuilder, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+const POLICIES_PATH: [&str; 2] = ["admin", "policies"];
+
+#[derive(Debug, Clone)]
+pub struct RouterClient {
+    http: reqwest::Client,
+    base_url: Url,
+    api_key: Option<String>,
+    admin_key: Option<String>,
+}
+
+impl RouterClient {
+    /// `base_url` is the router controller, e.g. `http://router-controller:8084`.
+    pub fn new(base_url: &str) -> Result<Self, ClientError> {
+        let base_url = Url::parse(base_url).map_err(|e| ClientError::Url(e.to_string()))?;
+        if base_url.cannot_be_a_base() {
+            return Err(ClientError::Url(format!(
+                "{} cannot be a base URL",
+                base_url
+            )));
+        }
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base_url,
+            api_key: None,
+            admin_key: None,
+        })
+    }
+
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Bearer token sent on completion requests.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Bearer token sent on `/admin` requests (`admin.api_key`).
+    pub fn with_admin_key(mut self, admin_key: impl Into<String>) -> Self {
+        self.admin_key = Some(admin_key.into());
+        self
+    }
+
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        url
+    }
+
+    fn request(&self, method: Method, segments: &[&str], key: Option<&String>) -> RequestBuilder {
+        let builder = self.http.request(method, self.url(segments));
+        match key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(&self, builder: RequestBuilder) -> Result<T, ClientError> {
+        let response = builder.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let bytes = response.bytes().await?;
+            let body = serde_json::from_slice(&bytes)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+            return Err(ClientError::from_body(status, body));
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn admin<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        segments: &[&str],
+        body: Option<&impl Serialize>,
+    ) -> Result<T, ClientError> {
+        let mut builder = self.request(method, segments, self.admin_key.as_ref());
+        if let Some(body) = body {
+            builder = builder.json(body);
+        }
+        self.send(builder).await
+    }
+
+    /// Sends a non-streaming chat completion and returns the LLM's response.
+    pub async fn chat_completion(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<Value, ClientError> {
+        let mut body = request.to_json();
+        body["stream"] = Value::Bool(false);
+        let builder = self
+            .request(
+                Method::POST,
+                &["v1", "chat", "completions"],
+                self.api_key.as_ref(),
+            )
+            .json(&body);
+        self.send(builder).await
+    }
+
+    pub async fn health(&self) -> Result<Value, ClientError> {
+        self.send(self.request(Method::GET, &["health"], None))
+            .await
+    }
+
+    pub async fn reset_metrics(&self) -> Result<Value, ClientError> {
+        self.admin(Method::POST, &["admin", "metrics", "reset"], None::<&Value>)
+            .await
+    }
+
+    pub async fn list_policies(&self) -> Result<Vec<Policy>, ClientError> {
+        self.admin(Method::GET, &POLICIES_PATH, None::<&Value>)
+            .await
+    }
+
+    pub async fn get_policy(&self, name: &str) -> Result<Policy, ClientError> {
+        self.admin(
+            Method::GET,
+            &[POLICIES_PATH[0], POLICIES_PATH[1], name],
+            None::<&Value>,
+        )
+        .await
+    }
+
+    /// Returns the policies after the change, as do the other mutations.
+    pub async fn create_policy(&self, policy: &Policy) -> Result<Vec<Policy>, ClientError> {
+        self.admin(Method::POST, &POLICIES_PATH, Some(policy)).await
+    }
+
+    pub async fn update_policy(&self, policy: &Policy) -> Result<Vec<Policy>, ClientError> {
+        let path = [POLICIES_PATH[0], POLICIES_PATH[1], policy.name.as_str()];
+        self.admin(Method::PUT, &path, Some(policy)).await
+    }
+
+    pub async fn delete_policy(&self, name: &str) -> Result<Vec<Policy>, ClientError> {
+        let path = [POLICIES_PATH[0], POLICIES_PATH[1], name];
+        self.admin(Method::DELETE, &path, None::<&Value>).await
+    }
+
+    pub async fn list_llms(&self, policy: &str) -> Result<Vec<Llm>, ClientError> {
+        let path = [POLICIES_PATH[0], POLICIES_PATH[1], policy, "llms"];
+        self.admin(Method::GET, &path, None::<&Value>).await
+    }
+
+    pub async fn get_llm(&self, policy: &str, name: &str) -> Result<Llm, ClientError> {
+        let path = [POLICIES_PATH[0], POLICIES_PATH[1], policy, "llms", name];
+        self.admin(Method::GET, &path, None::<&Value>).await
+    }
+
+    pub async fn create_llm(&self, policy: &str, llm: &Llm) -> Result<Vec<Policy>, ClientError> {
+        let path = [POLICIES_PATH[0], POLICIES_PATH[1], policy, "llms"];
+        self.admin(Method::POST, &path, Some(llm)).await
+    }
+
+    pub async fn update_llm(&self, policy: &str, llm: &Llm) -> Result<Vec<Policy>, ClientError> {
+        let path = [
+            POLICIES_PATH[0],
+            POLICIES_PATH[1],
+            policy,
+            "llms",
+            llm.name.as_str(),
+        ];
+        self.admin(Method::PUT, &path, Some(llm)).await
+    }
+
+    pub async fn delete_llm(&self, policy: &str, name: &str) -> Result<Vec<Policy>, ClientError> {
+        let path = [POLICIES_PATH[0], POLICIES_PATH[1], policy, "llms", name];
+        self.admin(Method::DELETE, &path, None::<&Value>).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_chat_completion_and_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(body_partial_json(json!({
+                "nim-llm-router": {"policy": "task_router", "routing_strategy": "triton"}
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "1"})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/admin/policies/missing%20policy"))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "error": {"type": "policy_not_found", "message": "Policy 'missing policy' not found"}
+            })))
+            .mount(&server)
+            .await;
+
+        let client = RouterClient::new(&server.uri())
+            .unwrap()
+            .with_admin_key("secret");
+        let response = client
+            .chat_completion(&ChatCompletionRequest::triton("task_router").user("hi"))
+            .await
+            .unwrap();
+        assert_eq!(response["id"], "1");
+
+        let error = client.get_policy("missing policy").await.unwrap_err();
+        assert_eq!(error.status(), Some(reqwest::StatusCode::NOT_FOUND));
+        assert_eq!(error.error_type(), Some("policy_not_found"));
+    }
+}
@@ -0,0 +1,72 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Error
+use reqwest::StatusCode;
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Invalid URL: {0}")]
+    Url(String),
+
+    /// The router answered with its error envelope,
+    /// `{"error": {"type": ..., "message": ...}}`.
+    #[error("{status} {error_type}: {message}")]
+    Api {
+        status: StatusCode,
+        error_type: String,
+        message: String,
+        body: Value,
+    },
+}
+
+impl ClientError {
+    pub(crate) fn from_body(status: StatusCode, body: Value) -> Self {
+        let error = body.get("error");
+        let field = |name: &str| {
+            error
+                .and_then(|e| e.get(name))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        Self::Api {
+            status,
+            error_type: field("type").unwrap_or_else(|| "unknown".to_string()),
+            message: field("message").unwrap_or_else(|| body.to_string()),
+            body,
+        }
+    }
+
+    /// The router's `error.type`, when the error came from the router.
+    pub fn error_type(&self) -> Option<&str> {
+        match self {
+            Self::Api { error_type, .. } => Some(error_type),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Api { status, .. } => Some(*status),
+            Self::Http(e) => e.status(),
+            Self::Url(_) => None,
+        }
+    }
+}
@@ -0,0 +1,27 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! LLM Router Client
+//!
+//! Typed client for the router controller: chat completions carrying the
+//! `nim-llm-router` extension block, and the `/admin` APIs.
+pub mod client;
+pub mod error;
+pub mod request;
+
+pub use client::RouterClient;
+pub use error::ClientError;
+pub use llm_router_core::config::{Llm, Policy};
+pub use request::{ChatCompletionRequest, Message, RouterParams, RoutingStrategy};
@@ -0,0 +1,204 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Request
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RoutingStrategy {
+    Manual,
+    Triton,
+    #[serde(rename = "context_length")]
+    ContextLength,
+    Rules,
+    Split,
+}
+
+/// The `nim-llm-router` extension block.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RouterParams {
+    pub policy: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_strategy: Option<RoutingStrategy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
+    /// Conversation the request belongs to, for `sticky_routing`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Cost attribution tags attached to the request's usage records.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+impl Message {
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+        }
+    }
+}
+
+/// A chat completion routed by `policy`.
+///
+/// ```
+/// use llm_router_client::ChatCompletionRequest;
+///
+/// let request = ChatCompletionRequest::triton("task_router")
+///     .user("Explain quicksort")
+///     .max_tokens(256);
+/// assert_eq!(request.to_json()["nim-llm-router"]["routing_strategy"], "triton");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatCompletionRequest {
+    pub router: RouterParams,
+    pub messages: Vec<Message>,
+    /// Any other OpenAI parameters (`max_tokens`, `temperature`, ...).
+    pub params: Map<String, Value>,
+}
+
+impl ChatCompletionRequest {
+    pub fn new(policy: impl Into<String>) -> Self {
+        Self {
+            router: RouterParams {
+                policy: policy.into(),
+                routing_strategy: None,
+                model: None,
+                threshold: None,
+                session_id: None,
+                tags: BTreeMap::new(),
+            },
+            messages: Vec::new(),
+            params: Map::new(),
+        }
+    }
+
+    /// Lets the policy's classifier pick the LLM.
+    pub fn triton(policy: impl Into<String>) -> Self {
+        Self::new(policy).strategy(RoutingStrategy::Triton)
+    }
+
+    /// Sends the request to the LLM named `model` in the policy.
+    pub fn manual(policy: impl Into<String>, model: impl Into<String>) -> Self {
+        let mut request = Self::new(policy).strategy(RoutingStrategy::Manual);
+        request.router.model = Some(model.into());
+        request
+    }
+
+    pub fn strategy(mut self, strategy: RoutingStrategy) -> Self {
+        self.router.routing_strategy = Some(strategy);
+        self
+    }
+
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.router.threshold = Some(threshold);
+        self
+    }
+
+    /// Keeps the turns of conversation `id` on one LLM.
+    pub fn session(mut self, id: impl Into<String>) -> Self {
+        self.router.session_id = Some(id.into());
+        self
+    }
+
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.router.tags.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn message(mut self, role: impl Into<String>, content: impl Into<String>) -> Self {
+        self.messages.push(Message::new(role, content));
+        self
+    }
+
+    pub fn system(self, content: impl Into<String>) -> Self {
+        self.message("system", content)
+    }
+
+    pub fn user(self, content: impl Into<String>) -> Self {
+        self.message("user", content)
+    }
+
+    pub fn max_tokens(self, max_tokens: u32) -> Self {
+        self.param("max_tokens", max_tokens)
+    }
+
+    pub fn temperature(self, temperature: f64) -> Self {
+        self.param("temperature", temperature)
+    }
+
+    /// Sets an arbitrary top level request field.
+    pub fn param(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.params.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut body = self.params.clone();
+        body.entry("model").or_insert_with(|| Value::from(""));
+        body.insert(
+            "messages".to_string(),
+            serde_json::to_value(&self.messages).unwrap_or_default(),
+        );
+        body.insert(
+            "nim-llm-router".to_string(),
+            serde_json::to_value(&self.router).unwrap_or_default(),
+        );
+        Value::Object(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_manual_request_json() {
+        let request = ChatCompletionRequest::manual("task_router", "Brainstorming")
+            .system("Be brief")
+            .user("Name a color")
+            .temperature(0.5)
+            .tag("team", "search");
+        assert_eq!(
+            request.to_json(),
+            json!({
+                "model": "",
+                "messages": [
+                    {"role": "system", "content": "Be brief"},
+                    {"role": "user", "content": "Name a color"}
+                ],
+                "temperature": 0.5,
+                "nim-llm-router": {
+                    "policy": "task_router",
+                    "routing_strategy": "manual",
+                    "model": "Brainstorming",
+                    "tags": {"team": "search"}
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,91 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Caller
+use crate::config::CallerKey;
+use http::header::AUTHORIZATION;
+use http::HeaderMap;
+use std::net::IpAddr;
+
+/// Who a request is counted against.
+#[derive(Debug, Clone, Default)]
+pub struct Caller<'a> {
+    pub subject: Option<&'a str>,
+    pub api_key: Option<&'a str>,
+    pub ip: Option<IpAddr>,
+    /// Whether `api_key` was found among the configured keys. Anyone can
+    /// make up a new key per request, so other keys do not identify the
+    /// caller.
+    pub key_verified: bool,
+}
+
+impl<'a> Caller<'a> {
+    pub fn new(headers: &'a HeaderMap, subject: Option<&'a str>, ip: Option<IpAddr>) -> Self {
+        let api_key = headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|key| !key.is_empty());
+        Self {
+            subject,
+            api_key,
+            ip,
+            key_verified: false,
+        }
+    }
+
+    /// The identifying value per-caller overrides are matched against, and
+    /// the key the caller's state is stored under. Bearer keys are only kept
+    /// hashed, and only count when verified or among `known_keys`; callers
+    /// with neither a JWT subject nor such a key are counted by address.
+    pub fn identify<'k>(
+        &self,
+        key_by: CallerKey,
+        known_keys: impl IntoIterator<Item = &'k str>,
+    ) -> (String, String) {
+        let ip = || {
+            let ip = self.ip.map_or("unknown".to_string(), |ip| ip.to_string());
+            (ip.clone(), format!("ip:{}", ip))
+        };
+        match key_by {
+            CallerKey::ClientIp => ip(),
+            CallerKey::ApiKey => match (self.subject, self.verified_key(known_keys)) {
+                (Some(subject), _) => (subject.to_string(), format!("sub:{}", subject)),
+                (None, Some(key)) => {
+                    let digest: String = openssl::sha::sha256(key.as_bytes())
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect();
+                    (key.to_string(), format!("key:{}", digest))
+                }
+                (None, None) => ip(),
+            },
+        }
+    }
+
+    fn verified_key<'k>(&self, known_keys: impl IntoIterator<Item = &'k str>) -> Option<&'a str> {
+        let key = self.api_key?;
+        if self.key_verified || known_keys.into_iter().any(|known| key_eq(key, known)) {
+            return Some(key);
+        }
+        None
+    }
+}
+
+/// Compares a bearer key with a configured one in constant time.
+pub fn key_eq(key: &str, expected: &str) -> bool {
+    key.len() == expected.len() && openssl::memcmp::eq(key.as_bytes(), expected.as_bytes())
+}
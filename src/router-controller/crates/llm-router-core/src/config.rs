@@ -0,0 +1,3268 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Config
+use crate::error::{ConfigError, GatewayApiError};
+use crate::expr::{Condition, Facts};
+use crate::pii::{PiiKind, PiiPattern, RedactionMode};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// Placeholder shown instead of secrets in `/config` and admin responses.
+pub const REDACTED: &str = "[REDACTED]";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RouterConfig {
+    pub policies: Vec<Policy>,
+    /// Policy of requests without a `nim-llm-router` block, so unmodified
+    /// OpenAI clients are routed too.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_policy: Option<String>,
+    /// Policies of requests without a `nim-llm-router` block by where they
+    /// arrive. The first matching route wins over `default_policy`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routes: Vec<Route>,
+    /// `model` values naming a policy rather than an LLM, so OpenAI clients
+    /// are routed without a `nim-llm-router` block.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub virtual_models: Vec<VirtualModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin: Option<AdminConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency: Option<IdempotencyConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anomaly_detection: Option<AnomalyDetectionConfig>,
+    /// Restricts router endpoints to allowlisted client networks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_acls: Vec<NetworkAcl>,
+    /// Default retry policy for upstream LLM calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryConfig>,
+    /// Reports aggregated usage and cost at the end of streamed responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_usage: Option<StreamUsageConfig>,
+    /// Stops sending requests to upstream LLMs that keep failing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// Keeps traffic off upstream LLMs that answered `429` until their
+    /// rate limit resets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saturation: Option<SaturationConfig>,
+    /// Delivers usage and audit events to an external sink.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_sink: Option<EventSinkConfig>,
+    /// Replaces non-streaming upstream responses that do not match the
+    /// OpenAI schema with a 502.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub validate_responses: bool,
+    /// Rewrites upstream error bodies into the OpenAI error schema, keeping
+    /// the original under `details`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub normalize_errors: bool,
+    /// Connection pool of the shared upstream client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_pool: Option<UpstreamPoolConfig>,
+    /// Aggregates token usage per `X-Session-Id` conversation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversations: Option<ConversationConfig>,
+    /// Keeps every turn of a conversation on the LLM of its first turn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sticky_routing: Option<StickyRoutingConfig>,
+    /// Replaces the Triton classifier with generated scores, for soak tests
+    /// without a Triton deployment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synthetic_classifier: Option<SyntheticClassifierConfig>,
+    /// Requires a valid JWT bearer token on completion requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwt: Option<JwtConfig>,
+    /// Token bucket rate limit on completion requests per caller.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Caps the completion requests the gateway works on at once, queueing
+    /// and then shedding the rest with a `503`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admission: Option<AdmissionConfig>,
+    /// Which request tags also become metric labels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_tags: Option<RequestTagsConfig>,
+    /// Caps the tokens each caller may use per day or month.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_budget: Option<TokenBudgetConfig>,
+    /// Daily spend limits that raise an alert and can move traffic to a
+    /// cheaper LLM once crossed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spend_alerts: Vec<SpendAlert>,
+    /// Records anonymized request bodies for replay against staging.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traffic_capture: Option<TrafficCaptureConfig>,
+    /// Writes one structured record per completion request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_log: Option<AuditLogConfig>,
+    /// Path answered with an empty `200` for any method, without logging
+    /// or metrics, for load balancer probes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_path: Option<String>,
+    /// Lets browser pages on other origins call the gateway.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>,
+    /// Reuses Triton scores for recently classified text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifier_cache: Option<ClassifierCacheConfig>,
+    /// Keeps routing while the classifier is down, from last-known-good
+    /// decisions or each policy's `default_llm`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub degraded_routing: Option<DegradedRoutingConfig>,
+    /// Checks behind `/readyz`. All of them run when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readiness: Option<ReadinessConfig>,
+    /// Keeps user identifiers and request metadata out of events, captures,
+    /// logs and metric labels.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub privacy_mode: bool,
+    /// Addresses the gateway accepts connections on, with their protocol
+    /// limits. A single listener on `0.0.0.0:8084` when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub listeners: Vec<ListenerConfig>,
+    /// Queue for usage accounting and other work done after a response.
+    /// Defaults apply when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_tasks: Option<BackgroundTasksConfig>,
+    /// Extra labels on the request, latency and token usage metrics, read
+    /// from a request header or the `nim-llm-router` block. Label names are
+    /// fixed at startup.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_metric_labels: Vec<CustomMetricLabel>,
+    /// Where rate limit buckets, token budgets, conversations, idempotency
+    /// entries and circuit breaker state are kept. In memory when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_store: Option<StateStoreConfig>,
+    /// Delays, aborts or fails a share of upstream calls, for chaos tests.
+    /// Can be changed at runtime through `/admin/faults`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault_injection: Option<FaultInjectionConfig>,
+    /// HashiCorp Vault server resolving `vault:` API key references.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault: Option<VaultConfig>,
+    /// Teams sharing the gateway, each limited to its own policies and
+    /// LLMs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenants: Option<TenantsConfig>,
+}
+
+/// Most custom metric labels allowed; each multiplies the series count of
+/// every per-request metric.
+pub const MAX_CUSTOM_METRIC_LABELS: usize = 4;
+
+/// Label names already used by the per-request metrics.
+const RESERVED_METRIC_LABELS: &[&str] = &[
+    "policy",
+    "model",
+    "strategy",
+    "tenant",
+    "error_type",
+    "category",
+    "stream",
+    "finish_reason",
+    "reason",
+    "le",
+];
+
+/// A custom metric label. Values outside `allowed_values` are recorded as
+/// `other`, and requests without one as `unknown`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct CustomMetricLabel {
+    pub name: String,
+    /// Request header carrying the value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Field of the request's `nim-llm-router` block carrying the value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub allowed_values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BackgroundTasksConfig {
+    /// Tasks waiting to run before new ones are dropped.
+    #[serde(default = "default_background_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+fn default_background_queue_capacity() -> usize {
+    10_000
+}
+
+impl Default for BackgroundTasksConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: default_background_queue_capacity(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StateBackend {
+    /// Per process; state is lost on restart and not shared by replicas.
+    #[default]
+    Memory,
+    /// A local database file, surviving restarts and shared by processes
+    /// on the same host.
+    Sqlite,
+    /// A Redis server shared by every replica.
+    Redis,
+}
+
+impl StateBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Memory => "memory",
+            Self::Sqlite => "sqlite",
+            Self::Redis => "redis",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StateStoreConfig {
+    #[serde(default)]
+    pub backend: StateBackend,
+    /// Keys the `memory` backend holds before evicting those closest to
+    /// expiry.
+    #[serde(default = "default_state_store_max_entries")]
+    pub max_entries: usize,
+    /// Database file of the `sqlite` backend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// `redis://` or `rediss://` URL of the `redis` backend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Prepended to every Redis key, so several deployments can share a
+    /// server.
+    #[serde(default = "default_state_store_key_prefix")]
+    pub key_prefix: String,
+    /// Connect, read and write timeout of the `redis` backend, and how long
+    /// the `sqlite` backend waits for a locked database.
+    #[serde(default = "default_state_store_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_state_store_max_entries() -> usize {
+    200_000
+}
+
+fn default_state_store_key_prefix() -> String {
+    "llm-router:".to_string()
+}
+
+fn default_state_store_timeout_ms() -> u64 {
+    500
+}
+
+impl Default for StateStoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: StateBackend::default(),
+            max_entries: default_state_store_max_entries(),
+            path: None,
+            url: None,
+            key_prefix: default_state_store_key_prefix(),
+            timeout_ms: default_state_store_timeout_ms(),
+        }
+    }
+}
+
+/// Smallest `max_header_bytes` hyper accepts for its HTTP/1 read buffer.
+pub const MIN_LISTENER_HEADER_BYTES: usize = 8192;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ListenerConfig {
+    #[serde(default = "default_listener_address")]
+    pub address: String,
+    /// Serves HTTP/1.1 only; HTTP/2 connections are refused.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub http1_only: bool,
+    /// Concurrent HTTP/2 streams one connection may open.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_streams: Option<u32>,
+    /// Largest request header block: the HTTP/1 read buffer and the HTTP/2
+    /// header list size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_header_bytes: Option<usize>,
+    /// Largest number of HTTP/1 request headers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_headers: Option<usize>,
+    /// Time an HTTP/1 client has to send a request's headers, including
+    /// while an idle keep-alive connection waits for its next request.
+    #[serde(default = "default_listener_header_read_timeout_seconds")]
+    pub header_read_timeout_seconds: u64,
+    /// Keeps HTTP/1 connections open between requests.
+    #[serde(default = "default_listener_keep_alive")]
+    pub keep_alive: bool,
+    /// Interval of HTTP/2 keep-alive pings; connections that do not answer
+    /// within `keep_alive_timeout_seconds` are closed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive_interval_seconds: Option<u64>,
+    #[serde(default = "default_listener_keep_alive_timeout_seconds")]
+    pub keep_alive_timeout_seconds: u64,
+}
+
+fn default_listener_address() -> String {
+    "0.0.0.0:8084".to_string()
+}
+
+fn default_listener_header_read_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_listener_keep_alive() -> bool {
+    true
+}
+
+fn default_listener_keep_alive_timeout_seconds() -> u64 {
+    20
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        Self {
+            address: default_listener_address(),
+            http1_only: false,
+            max_concurrent_streams: None,
+            max_header_bytes: None,
+            max_headers: None,
+            header_read_timeout_seconds: default_listener_header_read_timeout_seconds(),
+            keep_alive: default_listener_keep_alive(),
+            keep_alive_interval_seconds: None,
+            keep_alive_timeout_seconds: default_listener_keep_alive_timeout_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DegradedRoutingConfig {
+    /// How often an unavailable classifier's readiness endpoint is polled.
+    #[serde(default = "default_degraded_probe_interval_seconds")]
+    pub probe_interval_seconds: u64,
+}
+
+fn default_degraded_probe_interval_seconds() -> u64 {
+    5
+}
+
+impl Default for DegradedRoutingConfig {
+    fn default() -> Self {
+        Self {
+            probe_interval_seconds: default_degraded_probe_interval_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReadinessConfig {
+    /// Every Triton classifier must answer its readiness endpoint.
+    #[serde(default = "default_true")]
+    pub classifier: bool,
+    /// Every policy must have an LLM whose circuit breaker is not open.
+    #[serde(default = "default_true")]
+    pub upstreams: bool,
+    /// How long a classifier's readiness endpoint may take to answer.
+    #[serde(default = "default_readiness_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_readiness_timeout_ms() -> u64 {
+    2000
+}
+
+impl Default for ReadinessConfig {
+    fn default() -> Self {
+        Self {
+            classifier: true,
+            upstreams: true,
+            timeout_ms: default_readiness_timeout_ms(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClassifierCacheConfig {
+    #[serde(default = "default_classifier_cache_ttl_seconds")]
+    pub ttl_seconds: u64,
+    #[serde(default = "default_classifier_cache_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_classifier_cache_ttl_seconds() -> u64 {
+    60
+}
+
+fn default_classifier_cache_max_entries() -> usize {
+    10_000
+}
+
+impl Default for ClassifierCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_seconds: default_classifier_cache_ttl_seconds(),
+            max_entries: default_classifier_cache_max_entries(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TrafficCaptureConfig {
+    /// JSON lines file captured requests are appended to.
+    pub path: String,
+    /// Fraction of requests captured, between 0 and 1.
+    #[serde(default = "default_capture_sample_rate")]
+    pub sample_rate: f64,
+    #[serde(default)]
+    pub anonymize: AnonymizeConfig,
+}
+
+fn default_capture_sample_rate() -> f64 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnonymizeConfig {
+    /// How PII in captured strings is scrubbed; `null` keeps it.
+    #[serde(default = "default_anonymize_pii")]
+    pub pii: Option<RedactionMode>,
+    /// Top level request fields dropped from captures.
+    #[serde(default = "default_anonymize_strip_fields")]
+    pub strip_fields: Vec<String>,
+}
+
+fn default_anonymize_pii() -> Option<RedactionMode> {
+    Some(RedactionMode::Hash)
+}
+
+fn default_anonymize_strip_fields() -> Vec<String> {
+    vec!["user".to_string(), "metadata".to_string()]
+}
+
+impl Default for AnonymizeConfig {
+    fn default() -> Self {
+        Self {
+            pii: default_anonymize_pii(),
+            strip_fields: default_anonymize_strip_fields(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CorsConfig {
+    /// Origins allowed to call the gateway, e.g. `https://playground.example.com`,
+    /// or `*` for any.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default = "default_cors_allowed_methods")]
+    pub allowed_methods: Vec<String>,
+    /// Request headers pages may send, or `*` for any.
+    #[serde(default = "default_cors_allowed_headers")]
+    pub allowed_headers: Vec<String>,
+    /// Response headers pages may read besides the safelisted ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expose_headers: Vec<String>,
+    /// Lets pages send cookies and credentials.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response.
+    #[serde(default = "default_cors_max_age_seconds")]
+    pub max_age_seconds: u64,
+}
+
+fn default_cors_allowed_methods() -> Vec<String> {
+    ["GET", "POST", "OPTIONS"].map(String::from).to_vec()
+}
+
+fn default_cors_allowed_headers() -> Vec<String> {
+    ["authorization", "content-type"].map(String::from).to_vec()
+}
+
+fn default_cors_max_age_seconds() -> u64 {
+    600
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: default_cors_allowed_methods(),
+            allowed_headers: default_cors_allowed_headers(),
+            expose_headers: Vec::new(),
+            allow_credentials: false,
+            max_age_seconds: default_cors_max_age_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditLogConfig {
+    /// JSON lines file records are appended to. Records go to stdout when
+    /// unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Size at which the file is rotated to `<path>.1`.
+    #[serde(default = "default_audit_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// Rotated files kept besides the current one.
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+    /// How the prompt is recorded.
+    #[serde(default = "default_audit_prompt")]
+    pub prompt: AuditContent,
+    /// How the response text of non-streaming requests is recorded.
+    #[serde(default)]
+    pub response: AuditContent,
+    /// Characters of `redacted` text kept.
+    #[serde(default = "default_audit_max_chars")]
+    pub max_chars: usize,
+}
+
+fn default_audit_max_file_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_audit_max_files() -> usize {
+    5
+}
+
+fn default_audit_prompt() -> AuditContent {
+    AuditContent::Hash
+}
+
+fn default_audit_max_chars() -> usize {
+    256
+}
+
+impl Default for AuditLogConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_file_bytes: default_audit_max_file_bytes(),
+            max_files: default_audit_max_files(),
+            prompt: default_audit_prompt(),
+            response: AuditContent::default(),
+            max_chars: default_audit_max_chars(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditContent {
+    /// Left out of the record.
+    #[default]
+    Omit,
+    /// SHA-256 of the text, to match records against a known prompt.
+    Hash,
+    /// The text with PII hashed, truncated to `max_chars`.
+    Redacted,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    #[default]
+    Daily,
+    Monthly,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Refuse requests with `429` `quota_exceeded`.
+    #[default]
+    Reject,
+    /// Serve requests from the policy's `downgrade_to` LLM instead.
+    Downgrade,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TokenBudgetConfig {
+    /// Budget per period; periods start at midnight UTC.
+    #[serde(default)]
+    pub period: BudgetPeriod,
+    pub max_tokens: u64,
+    #[serde(default)]
+    pub action: BudgetAction,
+    /// LLM name served once the budget is exhausted with `downgrade`.
+    /// Policies without an LLM of that name reject instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downgrade_to: Option<String>,
+    #[serde(default)]
+    pub key_by: CallerKey,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<TokenBudgetOverride>,
+}
+
+/// Spend per UTC day, priced from `usage` at each LLM's `pricing`, of the
+/// completions matching every filter that is set.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SpendAlert {
+    pub name: String,
+    pub daily_limit_usd: f64,
+    /// LLM name the spend is counted for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    /// Receives a POST when the limit is crossed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// LLM serving the alert's requests for the rest of the day once the
+    /// limit is crossed. Policies without an LLM of that name are left as
+    /// they are.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downgrade_to: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TokenBudgetOverride {
+    pub key: String,
+    pub max_tokens: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RequestTagsConfig {
+    /// Tag keys counted in `llm_tagged_token_usage`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metric_tags: Vec<String>,
+    /// Distinct values recorded per metric tag; later values are counted as
+    /// `other`.
+    #[serde(default = "default_max_metric_tag_values")]
+    pub max_metric_values: usize,
+}
+
+fn default_max_metric_tag_values() -> usize {
+    50
+}
+
+impl Default for RequestTagsConfig {
+    fn default() -> Self {
+        Self {
+            metric_tags: Vec::new(),
+            max_metric_values: default_max_metric_tag_values(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CallerKey {
+    /// The JWT subject or bearer key, or the client IP without either.
+    #[default]
+    ApiKey,
+    ClientIp,
+}
+
+impl CallerKey {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ApiKey => "api_key",
+            Self::ClientIp => "client_ip",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RateLimitConfig {
+    #[serde(default = "default_rate_limit_requests_per_second")]
+    pub requests_per_second: f64,
+    #[serde(default = "default_rate_limit_burst")]
+    pub burst: u32,
+    #[serde(default)]
+    pub key_by: CallerKey,
+    /// Limits for individual callers, matched against the bearer key, JWT
+    /// subject or client IP.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<RateLimitOverride>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RateLimitOverride {
+    pub key: String,
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+fn default_rate_limit_requests_per_second() -> f64 {
+    10.0
+}
+
+fn default_rate_limit_burst() -> u32 {
+    20
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: default_rate_limit_requests_per_second(),
+            burst: default_rate_limit_burst(),
+            key_by: CallerKey::default(),
+            overrides: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AdmissionConfig {
+    /// Completion requests handled at once across the gateway.
+    #[serde(default = "default_admission_max_in_flight")]
+    pub max_in_flight: usize,
+    /// Requests waiting for one of them before new ones are shed.
+    #[serde(default = "default_admission_max_queue")]
+    pub max_queue: usize,
+    /// Longest a request waits in the queue before it is shed.
+    #[serde(default = "default_admission_queue_timeout_ms")]
+    pub queue_timeout_ms: u64,
+    /// Request header carrying the priority of requests whose tenant sets
+    /// none, e.g. `X-Priority`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_header: Option<String>,
+}
+
+/// Order in which queued requests are admitted. When the queue is full, a
+/// request displaces the newest one of a lower priority.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::High => "high",
+        }
+    }
+
+    /// The priority named `text`, in any case.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "normal" => Some(Self::Normal),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+}
+
+fn default_admission_max_in_flight() -> usize {
+    512
+}
+
+fn default_admission_max_queue() -> usize {
+    256
+}
+
+fn default_admission_queue_timeout_ms() -> u64 {
+    2_000
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: default_admission_max_in_flight(),
+            max_queue: default_admission_max_queue(),
+            queue_timeout_ms: default_admission_queue_timeout_ms(),
+            priority_header: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JwtConfig {
+    /// JWKS document holding the keys tokens are signed with.
+    pub jwks_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    /// Accepted `aud` values. The audience is not checked when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audiences: Vec<String>,
+    #[serde(default = "default_jwt_algorithms")]
+    pub algorithms: Vec<JwtAlgorithm>,
+    #[serde(default = "default_jwt_leeway_seconds")]
+    pub leeway_seconds: u64,
+    #[serde(default = "default_jwks_refresh_seconds")]
+    pub jwks_refresh_seconds: u64,
+    /// Claim naming the caller's tenant, exposed to later stages.
+    #[serde(default = "default_jwt_tenant_claim")]
+    pub tenant_claim: String,
+    /// Claims a token must carry, each with its accepted values. Array
+    /// claims pass when any element is accepted.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub required_claims: BTreeMap<String, Vec<String>>,
+}
+
+fn default_jwt_algorithms() -> Vec<JwtAlgorithm> {
+    vec![JwtAlgorithm::RS256]
+}
+
+/// JWS signing algorithm, named as in a token's `alg` header.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JwtAlgorithm {
+    HS256,
+    HS384,
+    HS512,
+    ES256,
+    ES384,
+    RS256,
+    RS384,
+    RS512,
+    PS256,
+    PS384,
+    PS512,
+    EdDSA,
+}
+
+#[cfg(feature = "server")]
+impl From<JwtAlgorithm> for jsonwebtoken::Algorithm {
+    fn from(algorithm: JwtAlgorithm) -> Self {
+        match algorithm {
+            JwtAlgorithm::HS256 => Self::HS256,
+            JwtAlgorithm::HS384 => Self::HS384,
+            JwtAlgorithm::HS512 => Self::HS512,
+            JwtAlgorithm::ES256 => Self::ES256,
+            JwtAlgorithm::ES384 => Self::ES384,
+            JwtAlgorithm::RS256 => Self::RS256,
+            JwtAlgorithm::RS384 => Self::RS384,
+            JwtAlgorithm::RS512 => Self::RS512,
+            JwtAlgorithm::PS256 => Self::PS256,
+            JwtAlgorithm::PS384 => Self::PS384,
+            JwtAlgorithm::PS512 => Self::PS512,
+            JwtAlgorithm::EdDSA => Self::EdDSA,
+        }
+    }
+}
+
+fn default_jwt_leeway_seconds() -> u64 {
+    60
+}
+
+fn default_jwks_refresh_seconds() -> u64 {
+    300
+}
+
+fn default_jwt_tenant_claim() -> String {
+    "org".to_string()
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            jwks_url: String::new(),
+            issuer: None,
+            audiences: Vec::new(),
+            algorithms: default_jwt_algorithms(),
+            leeway_seconds: default_jwt_leeway_seconds(),
+            jwks_refresh_seconds: default_jwks_refresh_seconds(),
+            tenant_claim: default_jwt_tenant_claim(),
+            required_claims: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SyntheticScores {
+    /// Each request scores the next LLM of the policy highest.
+    #[default]
+    RoundRobin,
+    /// Uniformly random scores, normalized to sum to one.
+    Random,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SyntheticClassifierConfig {
+    #[serde(default)]
+    pub scores: SyntheticScores,
+    /// Simulated classification latency.
+    #[serde(default)]
+    pub latency_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FaultInjectionConfig {
+    /// Faults are only injected while set, so they can be switched off
+    /// without losing them.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// The first fault matching an upstream call applies to it.
+    #[serde(default)]
+    pub faults: Vec<Fault>,
+}
+
+impl Default for FaultInjectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            faults: Vec::new(),
+        }
+    }
+}
+
+/// Faults injected into the upstream calls of a policy and LLM. Shares are
+/// percentages from `0` to `100`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Fault {
+    /// Every policy when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    /// Every LLM when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm: Option<String>,
+    #[serde(default)]
+    pub delay_percent: f64,
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Share of calls failing as if the LLM were unreachable.
+    #[serde(default)]
+    pub abort_percent: f64,
+    /// Share of calls answered with `error_status`.
+    #[serde(default)]
+    pub error_percent: f64,
+    #[serde(default = "default_fault_error_status")]
+    pub error_status: u16,
+}
+
+fn default_fault_error_status() -> u16 {
+    503
+}
+
+impl Default for Fault {
+    fn default() -> Self {
+        Self {
+            policy: None,
+            llm: None,
+            delay_percent: 0.0,
+            delay_ms: 0,
+            abort_percent: 0.0,
+            error_percent: 0.0,
+            error_status: default_fault_error_status(),
+        }
+    }
+}
+
+impl Fault {
+    pub fn matches(&self, policy: &str, llm: &str) -> bool {
+        self.policy.as_deref().is_none_or(|p| p == policy)
+            && self.llm.as_deref().is_none_or(|l| l == llm)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct VirtualModel {
+    /// Value of the request's `model` field, e.g. `auto` or `router/chat`.
+    pub name: String,
+    pub policy: String,
+    /// Overrides the policy's `default_routing_strategy`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_strategy: Option<RoutingStrategy>,
+}
+
+/// Matches requests by path, host and headers; every condition set must
+/// hold.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Route {
+    /// Matches paths under this prefix, which is removed before the request
+    /// is handled: with `/search`, `/search/v1/chat/completions` is served
+    /// as `/v1/chat/completions`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_prefix: Option<String>,
+    /// Matches the `Host` header, ignoring case and port.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Header values the request must carry, by header name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    pub policy: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TenantsConfig {
+    /// Header naming the caller's tenant, for callers authenticated in
+    /// front of the gateway. Ignored on requests carrying a bearer key or
+    /// JWT, which are resolved from those alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Refuses requests whose tenant cannot be resolved. Otherwise they are
+    /// served unrestricted.
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub tenants: Vec<Tenant>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Tenant {
+    pub id: String,
+    /// Bearer keys identifying callers of the tenant.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<String>,
+    /// Policies the tenant may use. Every policy when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_policies: Vec<String>,
+    /// LLM names the tenant may be served by. Every LLM when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_models: Vec<String>,
+    /// Regions the tenant's prompts may be sent to, on top of the policy's
+    /// `data_residency`. Every region when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data_residency: Vec<String>,
+    /// Admission priority of the tenant's requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+}
+
+impl Tenant {
+    pub fn allows_policy(&self, policy: &str) -> bool {
+        self.allowed_policies.is_empty() || self.allowed_policies.iter().any(|p| p == policy)
+    }
+
+    pub fn allows_model(&self, llm: &str) -> bool {
+        self.allowed_models.is_empty() || self.allowed_models.iter().any(|m| m == llm)
+    }
+
+    #[cfg(feature = "server")]
+    pub fn owns_key(&self, key: &str) -> bool {
+        self.api_keys
+            .iter()
+            .any(|expected| crate::caller::key_eq(key, expected))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VaultConfig {
+    /// Base URL of the server, e.g. `https://vault:8200`.
+    pub address: String,
+    pub token: String,
+    /// Vault Enterprise namespace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// How often secrets are read again when Vault grants no shorter lease.
+    #[serde(default = "default_vault_refresh_secs")]
+    pub refresh_secs: u64,
+}
+
+fn default_vault_refresh_secs() -> u64 {
+    300
+}
+
+impl Default for VaultConfig {
+    fn default() -> Self {
+        Self {
+            address: String::new(),
+            token: String::new(),
+            namespace: None,
+            refresh_secs: default_vault_refresh_secs(),
+        }
+    }
+}
+
+/// A secret of Vault referenced as `vault:<path>#<key>`, e.g.
+/// `vault:secret/data/llm-router#nvidia_api_key`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VaultRef {
+    pub path: String,
+    pub key: String,
+}
+
+impl VaultRef {
+    pub const PREFIX: &'static str = "vault:";
+
+    /// The reference `value` holds, if it is one. Malformed references
+    /// parse with an empty path or key.
+    pub fn parse(value: &str) -> Option<Self> {
+        let reference = value.strip_prefix(Self::PREFIX)?;
+        let (path, key) = reference.split_once('#').unwrap_or((reference, ""));
+        Some(Self {
+            path: path.trim_matches('/').to_string(),
+            key: key.to_string(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConversationConfig {
+    /// Total tokens a conversation may use before further turns are refused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    /// Conversations idle for longer than this are forgotten.
+    #[serde(default = "default_conversation_idle_ttl_seconds")]
+    pub idle_ttl_seconds: u64,
+    /// Deprecated and ignored; entries live in the `state_store`.
+    #[serde(default = "default_conversation_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_conversation_idle_ttl_seconds() -> u64 {
+    3600
+}
+
+fn default_conversation_max_entries() -> usize {
+    100_000
+}
+
+impl Default for ConversationConfig {
+    fn default() -> Self {
+        Self {
+            max_tokens: None,
+            idle_ttl_seconds: default_conversation_idle_ttl_seconds(),
+            max_entries: default_conversation_max_entries(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StickyRoutingConfig {
+    /// A conversation's LLM is forgotten after this long without a turn.
+    #[serde(default = "default_sticky_ttl_seconds")]
+    pub ttl_seconds: u64,
+}
+
+fn default_sticky_ttl_seconds() -> u64 {
+    3600
+}
+
+impl Default for StickyRoutingConfig {
+    fn default() -> Self {
+        Self {
+            ttl_seconds: default_sticky_ttl_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UpstreamPoolConfig {
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub max_idle_per_host: usize,
+    #[serde(default = "default_pool_idle_timeout_seconds")]
+    pub idle_timeout_seconds: u64,
+    #[serde(default)]
+    pub http2: Http2Mode,
+    /// Requests in flight to one host at a time, streams included; more
+    /// wait for one to finish. Unlimited when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_streams_per_host: Option<usize>,
+}
+
+/// How upstream connections pick their HTTP version.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Http2Mode {
+    /// HTTP/2 when the server offers it over TLS (ALPN), HTTP/1.1
+    /// otherwise.
+    #[default]
+    Auto,
+    /// HTTP/2 without negotiation, also over cleartext (h2c). Every
+    /// upstream must support it.
+    PriorKnowledge,
+    /// HTTP/1.1 only.
+    Disabled,
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    32
+}
+
+fn default_pool_idle_timeout_seconds() -> u64 {
+    90
+}
+
+impl Default for UpstreamPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: default_pool_max_idle_per_host(),
+            idle_timeout_seconds: default_pool_idle_timeout_seconds(),
+            http2: Http2Mode::default(),
+            max_streams_per_host: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct EventSinkConfig {
+    /// Receives batches of events as a JSON array.
+    pub webhook_url: String,
+    /// Directory undelivered batches are spooled to.
+    #[serde(default = "default_event_spool_dir")]
+    pub spool_dir: String,
+    /// Size limit of the spool; the oldest batches are dropped beyond it.
+    #[serde(default = "default_event_max_spool_bytes")]
+    pub max_spool_bytes: u64,
+    /// How often delivery of spooled events is retried.
+    #[serde(default = "default_event_replay_interval_seconds")]
+    pub replay_interval_seconds: u64,
+}
+
+fn default_event_spool_dir() -> String {
+    "/var/lib/llm-router/spool".to_string()
+}
+
+fn default_event_max_spool_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_event_replay_interval_seconds() -> u64 {
+    10
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Number of most recent calls the failure rate is computed over.
+    #[serde(default = "default_breaker_window_size")]
+    pub window_size: usize,
+    /// Calls needed in the window before the breaker can open.
+    #[serde(default = "default_breaker_min_requests")]
+    pub min_requests: usize,
+    #[serde(default = "default_breaker_failure_rate_threshold")]
+    pub failure_rate_threshold: f64,
+    /// How long the breaker stays open before a probe is let through.
+    #[serde(default = "default_breaker_open_seconds")]
+    pub open_seconds: u64,
+}
+
+fn default_breaker_window_size() -> usize {
+    20
+}
+
+fn default_breaker_min_requests() -> usize {
+    10
+}
+
+fn default_breaker_failure_rate_threshold() -> f64 {
+    0.5
+}
+
+fn default_breaker_open_seconds() -> u64 {
+    30
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            window_size: default_breaker_window_size(),
+            min_requests: default_breaker_min_requests(),
+            failure_rate_threshold: default_breaker_failure_rate_threshold(),
+            open_seconds: default_breaker_open_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SaturationConfig {
+    /// Cool-down after a `429` whose headers give no reset time.
+    #[serde(default = "default_saturation_cooldown_seconds")]
+    pub default_cooldown_seconds: u64,
+    /// Longest cool-down kept, whatever the provider asks for.
+    #[serde(default = "default_saturation_max_cooldown_seconds")]
+    pub max_cooldown_seconds: u64,
+}
+
+fn default_saturation_cooldown_seconds() -> u64 {
+    10
+}
+
+fn default_saturation_max_cooldown_seconds() -> u64 {
+    300
+}
+
+impl Default for SaturationConfig {
+    fn default() -> Self {
+        Self {
+            default_cooldown_seconds: default_saturation_cooldown_seconds(),
+            max_cooldown_seconds: default_saturation_max_cooldown_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StreamUsageConfig {
+    /// Send a final `nim-llm-router.usage` SSE event before `[DONE]`.
+    #[serde(default = "default_true")]
+    pub sse_event: bool,
+    /// Send the summary as HTTP trailers.
+    #[serde(default = "default_true")]
+    pub trailers: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for StreamUsageConfig {
+    fn default() -> Self {
+        Self {
+            sse_event: true,
+            trailers: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NetworkAcl {
+    /// Paths the rule covers, including their sub-paths.
+    pub paths: Vec<String>,
+    /// Networks allowed to reach those paths, in CIDR notation.
+    pub allow: Vec<IpNet>,
+}
+
+/// Retries upstream LLM calls that fail with a connection error or one of
+/// `retry_on_status`, backing off exponentially between attempts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// Total attempts, including the first.
+    #[serde(default = "default_retry_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_retry_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_retry_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    #[serde(default = "default_retry_multiplier")]
+    pub multiplier: f64,
+    #[serde(default = "default_retry_on_status")]
+    pub retry_on_status: Vec<u16>,
+}
+
+fn default_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_initial_backoff_ms() -> u64 {
+    100
+}
+
+fn default_retry_max_backoff_ms() -> u64 {
+    2_000
+}
+
+fn default_retry_multiplier() -> f64 {
+    2.0
+}
+
+fn default_retry_on_status() -> Vec<u16> {
+    vec![502, 503, 504]
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_retry_max_attempts(),
+            initial_backoff_ms: default_retry_initial_backoff_ms(),
+            max_backoff_ms: default_retry_max_backoff_ms(),
+            multiplier: default_retry_multiplier(),
+            retry_on_status: default_retry_on_status(),
+        }
+    }
+}
+
+/// Settings for the `/admin` surface. Admin endpoints are disabled unless
+/// this section is present.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AdminConfig {
+    /// Grants the `admin` role.
+    pub api_key: String,
+    /// Write admin changes back to the config file they were loaded from.
+    #[serde(default)]
+    pub persist: bool,
+    /// Further bearer tokens, each granting one role.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<AdminToken>,
+    /// Grants roles to bearer JWTs validated with the top-level `jwt`
+    /// settings, by the values of one of their claims.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwt_roles: Option<AdminJwtRoles>,
+    /// Requires the `viewer` role for `/config` and `/slo`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub protect_read_endpoints: bool,
+}
+
+/// Access levels of the admin surface, each including the ones before it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum AdminRole {
+    /// Reads configuration, policies, conversations and the log level.
+    Viewer,
+    /// Also resets metrics and conversations and changes the log level.
+    Operator,
+    /// Also creates, changes and deletes policies.
+    Admin,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AdminToken {
+    /// Shown in the audit log instead of the token.
+    pub name: String,
+    pub token: String,
+    pub role: AdminRole,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AdminJwtRoles {
+    /// Claim holding the caller's groups or roles; array claims grant the
+    /// highest role any element is bound to.
+    #[serde(default = "default_admin_jwt_roles_claim")]
+    pub claim: String,
+    /// Claim value to the role it grants.
+    pub bindings: BTreeMap<String, AdminRole>,
+}
+
+fn default_admin_jwt_roles_claim() -> String {
+    "groups".to_string()
+}
+
+/// Replays cached responses for retried requests carrying the same
+/// `Idempotency-Key` header.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IdempotencyConfig {
+    #[serde(default = "default_idempotency_ttl_seconds")]
+    pub ttl_seconds: u64,
+    /// Deprecated and ignored; entries live in the `state_store`.
+    #[serde(default = "default_idempotency_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_idempotency_ttl_seconds() -> u64 {
+    300
+}
+
+fn default_idempotency_max_entries() -> usize {
+    10_000
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl_seconds: default_idempotency_ttl_seconds(),
+            max_entries: default_idempotency_max_entries(),
+        }
+    }
+}
+
+/// Periodically compares classifier routing decisions against the previous
+/// window and reports anomalies to the log and an optional webhook.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnomalyDetectionConfig {
+    #[serde(default = "default_anomaly_interval_seconds")]
+    pub interval_seconds: u64,
+    /// Decisions a policy needs in a window before it is evaluated.
+    #[serde(default = "default_anomaly_min_requests")]
+    pub min_requests: u64,
+    /// Total variation distance between consecutive windows' class
+    /// distributions that counts as a shift.
+    #[serde(default = "default_anomaly_distribution_shift")]
+    pub distribution_shift_threshold: f64,
+    /// Winning scores below this are low-confidence decisions.
+    #[serde(default = "default_anomaly_low_confidence_threshold")]
+    pub low_confidence_threshold: f64,
+    /// Share of low-confidence decisions in a window that counts as a spike.
+    #[serde(default = "default_anomaly_low_confidence_ratio")]
+    pub low_confidence_ratio: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+}
+
+fn default_anomaly_interval_seconds() -> u64 {
+    60
+}
+
+fn default_anomaly_min_requests() -> u64 {
+    20
+}
+
+fn default_anomaly_distribution_shift() -> f64 {
+    0.3
+}
+
+fn default_anomaly_low_confidence_threshold() -> f64 {
+    0.5
+}
+
+fn default_anomaly_low_confidence_ratio() -> f64 {
+    0.2
+}
+
+impl Default for AnomalyDetectionConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: default_anomaly_interval_seconds(),
+            min_requests: default_anomaly_min_requests(),
+            distribution_shift_threshold: default_anomaly_distribution_shift(),
+            low_confidence_threshold: default_anomaly_low_confidence_threshold(),
+            low_confidence_ratio: default_anomaly_low_confidence_ratio(),
+            webhook_url: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Policy {
+    pub name: String,
+    /// Triton endpoint of the classifier. Not needed with a local one.
+    #[serde(default)]
+    pub url: String,
+    pub llms: Vec<Llm>,
+    /// Regions this policy may send prompts to. Empty means unrestricted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data_residency: Vec<String>,
+    /// Redacts sensitive spans before text is sent to the classifier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifier_redaction: Option<ClassifierRedaction>,
+    /// Masks sensitive values in prompts before the classifier, the rules
+    /// or any LLM sees them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pii_redaction: Option<PiiRedactionConfig>,
+    /// Checks the last user message with a moderation endpoint before the
+    /// request is routed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moderation: Option<ModerationConfig>,
+    /// Treats the classifier output as multi-label and picks among the
+    /// classes above threshold instead of taking the single highest score.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multi_label: Option<MultiLabelConfig>,
+    /// Pins sampling parameters on every request routed by this policy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<ExperimentConfig>,
+    /// Upper bound on the estimated cost of a single request, in USD.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost_per_request_usd: Option<f64>,
+    /// What to do with requests over `max_cost_per_request_usd`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost_action: Option<CostLimitAction>,
+    /// What to do with requests that do not fit the chosen LLM's
+    /// `max_context`. Unset passes them on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_overflow: Option<ContextOverflow>,
+    /// Overrides the top level `retry` policy for this policy's LLMs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryConfig>,
+    /// LLMs tried in order when the chosen LLM fails and declares no
+    /// fallbacks of its own.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<String>,
+    /// Objectives whose burn rates are reported on `/slo`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slo: Option<SloConfig>,
+    /// Races the first eligible fallback against a slow primary on
+    /// non-streaming requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speculative_fallback: Option<SpeculativeFallbackConfig>,
+    /// Evaluated in order by the `rules` routing strategy; the first rule
+    /// whose condition holds picks the LLM.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RoutingRule>,
+    /// Also evaluates `rules` with the "triton" strategy, before the
+    /// classifier, which only sees requests no rule matches.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rules_before_classifier: bool,
+    /// Strategy of requests that name none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_routing_strategy: Option<RoutingStrategy>,
+    /// LLM of "manual" requests that name none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_model: Option<String>,
+    /// Admission priority of requests routed here by `routes`, the policy
+    /// header or `default_policy`, when neither their tenant nor the
+    /// priority header sets one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// LLM chosen when the classifier is unavailable and no last-known-good
+    /// decision is cached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_llm: Option<String>,
+    /// LLM chosen when the classifier's winning score is below the
+    /// request's `threshold`. Without one the winner is kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_confidence_llm: Option<String>,
+    /// Arms of the "split" routing strategy, whose percentages add up to 100.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub split: Vec<SplitArm>,
+    /// Names the LLM of each classifier output class, in class order. Without
+    /// it class `i` routes to the `i`th of `llms`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    /// Classifies over Triton's KServe v2 gRPC API instead of `url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub triton_grpc: Option<TritonGrpcConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifier_timeouts: Option<UpstreamTimeouts>,
+    /// Client certificate and CAs for an HTTPS `url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifier_tls: Option<UpstreamTls>,
+    /// Sends an SSE `: keep-alive` comment on streamed responses after this
+    /// many seconds without a chunk from the LLM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_keep_alive_seconds: Option<u64>,
+    /// Falls back to the next LLM of the chain when a streamed response
+    /// fails before its first chunk, which clients cannot retry themselves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_failover: Option<StreamFailoverConfig>,
+    /// Which classifier scores requests for the "triton" strategy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifier: Option<ClassifierConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RoutingStrategy {
+    Manual,
+    Triton,
+    #[serde(rename = "context_length")]
+    ContextLength,
+    Rules,
+    Split,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ClassifierType {
+    /// The Triton model at the policy's `url`.
+    #[default]
+    Triton,
+    /// A BERT-style model run in process.
+    Local,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClassifierConfig {
+    #[serde(rename = "type", default)]
+    pub kind: ClassifierType,
+    /// Directory holding `config.json`, `tokenizer.json` and
+    /// `model.safetensors` of a local model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Hugging Face hub repository a local model is downloaded from when
+    /// `path` is unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    #[serde(default = "default_classifier_revision")]
+    pub revision: String,
+    /// Classifier input is truncated to this many tokens.
+    #[serde(default = "default_classifier_max_tokens")]
+    pub max_tokens: usize,
+}
+
+fn default_classifier_revision() -> String {
+    "main".to_string()
+}
+
+fn default_classifier_max_tokens() -> usize {
+    512
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        Self {
+            kind: ClassifierType::default(),
+            path: None,
+            repo: None,
+            revision: default_classifier_revision(),
+            max_tokens: default_classifier_max_tokens(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TritonGrpcConfig {
+    /// Triton's gRPC port, e.g. `http://triton:8001`, or an `https://` URL
+    /// for TLS.
+    pub url: String,
+    pub model_name: String,
+    /// Empty lets Triton pick the model version.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub model_version: String,
+    /// Retries a failed gRPC call over the HTTP/JSON API at the policy's
+    /// `url`.
+    #[serde(default = "default_true")]
+    pub fallback_to_http: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RoutingRule {
+    pub when: Condition,
+    /// Name of an LLM in the same policy.
+    pub llm: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SplitArm {
+    /// Name of an LLM in the same policy.
+    pub llm: String,
+    /// Share of the policy's "split" traffic sent to `llm`.
+    pub percent: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SpeculativeFallbackConfig {
+    /// Soft deadline after which the fallback is started alongside the
+    /// primary.
+    pub deadline_ms: u64,
+    /// Fallback of the chain to race, e.g. one at another provider. The
+    /// first eligible fallback when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm: Option<String>,
+    /// Also races streamed requests, until the first response headers.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct StreamFailoverConfig {
+    /// Also falls back when the first chunk takes longer than this after
+    /// the response headers. Only errors and empty streams fall back when
+    /// unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_chunk_timeout_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SloConfig {
+    /// Fraction of requests that must not fail, e.g. `0.99`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub availability: Option<f64>,
+    /// Requests slower than this count against the latency objective.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_seconds: Option<f64>,
+    /// Fraction of requests that must finish within `latency_seconds`.
+    #[serde(default = "default_slo_latency_target")]
+    pub latency_target: f64,
+    #[serde(default = "default_slo_windows_seconds")]
+    pub windows_seconds: Vec<u64>,
+}
+
+fn default_slo_latency_target() -> f64 {
+    0.99
+}
+
+fn default_slo_windows_seconds() -> Vec<u64> {
+    vec![300, 3600]
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            availability: None,
+            latency_seconds: None,
+            latency_target: default_slo_latency_target(),
+            windows_seconds: default_slo_windows_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CostLimitAction {
+    #[default]
+    Reject,
+    /// Lower `max_tokens` until the estimate fits.
+    Clamp,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextOverflow {
+    /// Answer `400` `context_length_exceeded`.
+    Reject,
+    /// Drop the oldest messages until the request fits.
+    Truncate,
+    /// Move the request to the LLM of the policy with the smallest window
+    /// that fits.
+    Reroute,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ExperimentConfig {
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MultiLabelConfig {
+    #[serde(default = "default_multi_label_threshold")]
+    pub threshold: f64,
+    #[serde(default = "default_multi_label_top_k")]
+    pub top_k: usize,
+    #[serde(default)]
+    pub selection: CandidateSelection,
+}
+
+fn default_multi_label_threshold() -> f64 {
+    0.5
+}
+
+fn default_multi_label_top_k() -> usize {
+    3
+}
+
+/// How to choose among multi-label candidates.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CandidateSelection {
+    /// Lowest `pricing`; unpriced LLMs are treated as the most expensive.
+    #[default]
+    Cost,
+    /// Lowest observed average response time.
+    Latency,
+    /// Fewest requests currently in flight.
+    Load,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ClassifierRedaction {
+    #[serde(default)]
+    pub mode: RedactionMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PiiRedactionConfig {
+    /// Built-in recognizers applied, all of them when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<PiiKind>,
+    /// Further recognizers, applied after the built-in ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<PiiPattern>,
+    /// Puts the masked values back in place of their placeholders in
+    /// non-streaming responses.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub restore: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationProvider {
+    /// OpenAI's `/v1/moderations`.
+    #[default]
+    Openai,
+    /// The NeMo Guardrails microservice's `/v1/guardrail/checks`.
+    NemoGuardrails,
+    /// A Triton safety model taking the text as its `INPUT` tensor.
+    Triton,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationAction {
+    /// Refuses flagged requests.
+    #[default]
+    Reject,
+    /// Sends flagged requests to the moderation `llm`.
+    Reroute,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModerationConfig {
+    #[serde(default)]
+    pub provider: ModerationProvider,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub api_key: String,
+    /// Moderation model of `openai`, or the model named in guardrail checks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Guardrails configuration of `nemo_guardrails` checks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_id: Option<String>,
+    /// Output classes of a `triton` model that count as unsafe.
+    #[serde(default = "default_moderation_flagged_classes")]
+    pub flagged_classes: Vec<usize>,
+    /// Score at which an unsafe class of a `triton` model flags the text.
+    #[serde(default = "default_moderation_threshold")]
+    pub threshold: f64,
+    #[serde(default)]
+    pub action: ModerationAction,
+    /// LLM flagged requests are sent to with `reroute`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm: Option<String>,
+    /// Error message returned for rejected requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default = "default_moderation_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Lets requests through when the endpoint fails instead of refusing
+    /// them.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fail_open: bool,
+}
+
+fn default_moderation_flagged_classes() -> Vec<usize> {
+    vec![1]
+}
+
+fn default_moderation_threshold() -> f64 {
+    0.5
+}
+
+fn default_moderation_timeout_ms() -> u64 {
+    2000
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        Self {
+            provider: ModerationProvider::default(),
+            url: String::new(),
+            api_key: String::new(),
+            model: None,
+            config_id: None,
+            flagged_classes: default_moderation_flagged_classes(),
+            threshold: default_moderation_threshold(),
+            action: ModerationAction::default(),
+            llm: None,
+            message: None,
+            timeout_ms: default_moderation_timeout_ms(),
+            fail_open: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Llm {
+    pub name: String,
+    pub api_base: String,
+    #[serde(default)]
+    pub api_key: String,
+    /// File holding the API key, e.g. a mounted Kubernetes Secret. Read
+    /// into `api_key` whenever the configuration is loaded or changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_file: Option<String>,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Adjusts this LLM's classifier score before the highest score is picked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_adjustment: Option<ScoreAdjustment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<Pricing>,
+    /// Whether the backend accepts the OpenAI `seed` parameter. Defaults to
+    /// `true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_seed: Option<bool>,
+    /// LLMs in the same policy tried in order when this one fails.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<String>,
+    /// Context window in tokens, prompt plus completion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_context: Option<u64>,
+    /// Tokenizer counting this LLM's tokens when a response leaves out
+    /// `usage`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<TokenizerConfig>,
+    /// Requests this replica sends the LLM at once. Further requests go to
+    /// its fallbacks, or are refused with `429` when there are none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_requests: Option<usize>,
+    /// Bounds on `max_tokens` and `temperature` of requests sent to this
+    /// LLM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<LlmLimits>,
+    /// API flavour of the backend. Inferred from `api_base` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Provider>,
+    /// o1-style reasoning model: `max_tokens` is sent as
+    /// `max_completion_tokens` and sampling parameters are dropped.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reasoning: bool,
+    /// Model sent `/v1/embeddings` requests. LLMs without one do not serve
+    /// embeddings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    /// Requests for which this condition is false are not sent to this LLM;
+    /// as a fallback it is skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guard: Option<Condition>,
+    /// Deployment and API version of an Azure OpenAI LLM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azure: Option<AzureConfig>,
+    /// Region and credentials of an AWS Bedrock LLM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bedrock: Option<BedrockConfig>,
+    /// Safety settings sent with requests to a Google Gemini LLM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gemini: Option<GeminiConfig>,
+    /// Workarounds for a local server such as Ollama or llama.cpp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local: Option<LocalConfig>,
+    /// Sends a share of the requests routed to this LLM to another one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canary: Option<CanaryConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeouts: Option<UpstreamTimeouts>,
+    /// Client certificate and CAs for an HTTPS `api_base`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<UpstreamTls>,
+    /// Answers requests routed to this LLM with generated responses instead
+    /// of calling `api_base`, for load and integration tests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mock: Option<MockUpstreamConfig>,
+}
+
+/// Bounds on the parameters of requests sent to an LLM.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct LlmLimits {
+    /// Largest `max_tokens` sent. Requests without one are sent this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_temperature: Option<f64>,
+    /// What to do with requests outside the bounds.
+    #[serde(default)]
+    pub action: LlmLimitAction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmLimitAction {
+    /// Bring the parameter to the nearest bound.
+    #[default]
+    Clamp,
+    Reject,
+}
+
+/// Hugging Face tokenizer of an LLM's model.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TokenizerConfig {
+    /// Local `tokenizer.json`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Hugging Face hub repository `tokenizer.json` is downloaded from
+    /// when `path` is unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    #[serde(default = "default_classifier_revision")]
+    pub revision: String,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            repo: None,
+            revision: default_classifier_revision(),
+        }
+    }
+}
+
+/// Limits on an upstream call. Unset limits wait indefinitely.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct UpstreamTimeouts {
+    /// Time to establish a connection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_ms: Option<u64>,
+    /// Time to the response headers, including retries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_ms: Option<u64>,
+}
+
+/// TLS settings of an upstream connection. Files are PEM encoded and read
+/// when the first request is sent.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct UpstreamTls {
+    /// CA bundle trusted in addition to the system roots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<String>,
+    /// Certificate chain presented for mutual TLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<String>,
+    /// PKCS#8 private key of `client_cert`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<String>,
+}
+
+impl UpstreamTls {
+    /// Why the settings cannot be used, if they cannot.
+    pub fn problem(&self) -> Option<&'static str> {
+        if self.client_cert.is_some() != self.client_key.is_some() {
+            Some("client_cert and client_key must be set together")
+        } else if *self == Self::default() {
+            Some("must set ca_cert or client_cert")
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CanaryConfig {
+    /// Name of an LLM in the same policy, typically a new model version.
+    pub llm: String,
+    /// Share of this LLM's requests sent to the canary.
+    pub percent: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LocalConfig {
+    /// Estimates `usage` from the prompt and completion text when the
+    /// server does not report it.
+    #[serde(default = "default_true")]
+    pub estimate_usage: bool,
+    /// Ends streams the server closes without `data: [DONE]` with one.
+    #[serde(default = "default_true")]
+    pub terminate_stream: bool,
+}
+
+impl Default for LocalConfig {
+    fn default() -> Self {
+        Self {
+            estimate_usage: true,
+            terminate_stream: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MockResponse {
+    /// The configured `content`.
+    #[default]
+    Canned,
+    /// The last user message, or the prompt of a legacy completion.
+    Echo,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MockUpstreamConfig {
+    #[serde(default)]
+    pub response: MockResponse,
+    /// Completion text of `canned` responses.
+    #[serde(default = "default_mock_content")]
+    pub content: String,
+    /// Simulated time to the response headers.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Simulated time between the chunks of a streamed response, one word
+    /// per chunk.
+    #[serde(default)]
+    pub chunk_delay_ms: u64,
+}
+
+fn default_mock_content() -> String {
+    "This is a mock response.".to_string()
+}
+
+impl Default for MockUpstreamConfig {
+    fn default() -> Self {
+        Self {
+            response: MockResponse::default(),
+            content: default_mock_content(),
+            latency_ms: 0,
+            chunk_delay_ms: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct GeminiConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub safety_settings: Vec<GeminiSafetySetting>,
+}
+
+/// A Gemini harm category and the threshold at which it is blocked, e.g.
+/// `HARM_CATEGORY_HARASSMENT` and `BLOCK_ONLY_HIGH`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GeminiSafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+/// Credentials left unset are read from the ambient AWS chain: the
+/// `AWS_ACCESS_KEY_ID` environment variables, then the shared credentials
+/// file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct BedrockConfig {
+    /// Signing region. Inferred from a `bedrock-runtime.{region}` `api_base`
+    /// when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_access_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AzureConfig {
+    /// Deployment name in the request path. Defaults to the LLM's `model`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<String>,
+    #[serde(default = "default_azure_api_version")]
+    pub api_version: String,
+}
+
+fn default_azure_api_version() -> String {
+    "2024-10-21".to_string()
+}
+
+impl Default for AzureConfig {
+    fn default() -> Self {
+        Self {
+            deployment: None,
+            api_version: default_azure_api_version(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// OpenAI and OpenAI compatible APIs such as NIM.
+    #[default]
+    OpenAi,
+    Anthropic,
+    /// Azure OpenAI: deployment based paths and an `api-key` header.
+    Azure,
+    /// AWS Bedrock Converse API with SigV4 signed requests.
+    Bedrock,
+    /// Google Gemini `generateContent` API with an `x-goog-api-key` header.
+    Gemini,
+    /// Local OpenAI compatible servers such as Ollama and llama.cpp, which
+    /// may not require an API key or report usage.
+    Local,
+}
+
+/// Price in USD per million tokens.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Pricing {
+    #[serde(default)]
+    pub input_per_million: f64,
+    #[serde(default)]
+    pub output_per_million: f64,
+}
+
+/// `adjusted = score * multiplier + bias`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScoreAdjustment {
+    #[serde(default = "default_score_multiplier")]
+    pub multiplier: f64,
+    #[serde(default)]
+    pub bias: f64,
+}
+
+fn default_score_multiplier() -> f64 {
+    1.0
+}
+
+impl RouterConfig {
+    /// Reads, expands and validates the configuration file at `path`.
+    pub fn load_config(path: &str) -> Result<RouterConfig> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_yaml(&content, |name| std::env::var(name).ok())
+    }
+
+    /// Expands `${NAME}` references in `content` through `lookup`, then
+    /// parses and validates it.
+    pub fn from_yaml(
+        content: &str,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<RouterConfig> {
+        let content = expand_vars(content, lookup)?;
+        let mut config: RouterConfig = serde_yaml::from_str(&content)?;
+        config.read_api_key_files()?;
+        validate_config(&config)?;
+        Ok(config)
+    }
+
+    /// Sets the `api_key` of every LLM with an `api_key_file` to the
+    /// file's content, without trailing whitespace.
+    pub fn read_api_key_files(&mut self) -> Result<()> {
+        for llm in self.policies.iter_mut().flat_map(|p| &mut p.llms) {
+            let Some(path) = &llm.api_key_file else {
+                continue;
+            };
+            let content =
+                std::fs::read_to_string(path).map_err(|e| ConfigError::InvalidLlmField {
+                    llm: llm.name.clone(),
+                    field: "api_key_file".to_string(),
+                    reason: format!("cannot read '{}': {}", path, e),
+                })?;
+            llm.api_key = content.trim_end().to_string();
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        validate_config(self)
+    }
+
+    /// Writes the configuration to `path`. Keys read from an
+    /// `api_key_file` are left out.
+    pub fn save_config(&self, path: &str) -> Result<()> {
+        let mut config = self.clone();
+        for llm in config.policies.iter_mut().flat_map(|p| &mut p.llms) {
+            if llm.api_key_file.is_some() {
+                llm.api_key.clear();
+            }
+        }
+        let content = serde_yaml::to_string(&config)?;
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, content)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn virtual_model(&self, name: &str) -> Option<&VirtualModel> {
+        self.virtual_models.iter().find(|model| model.name == name)
+    }
+
+    pub fn get_policy_by_name(&self, name: &str) -> Option<Policy> {
+        self.policies
+            .iter()
+            .find(|policy| policy.name.trim() == name.trim())
+            .cloned()
+    }
+
+    pub fn get_policy_by_index(&self, index: usize) -> Option<Policy> {
+        self.policies.get(index).cloned()
+    }
+
+    /// The configured listeners, or the default one.
+    pub fn listeners(&self) -> Vec<ListenerConfig> {
+        if self.listeners.is_empty() {
+            vec![ListenerConfig::default()]
+        } else {
+            self.listeners.clone()
+        }
+    }
+
+    pub fn sanitized(&self) -> Self {
+        RouterConfig {
+            policies: self.policies.iter().map(Policy::sanitized).collect(),
+            admin: self.admin.as_ref().map(|admin| AdminConfig {
+                api_key: REDACTED.to_string(),
+                tokens: admin
+                    .tokens
+                    .iter()
+                    .map(|token| AdminToken {
+                        token: REDACTED.to_string(),
+                        ..token.clone()
+                    })
+                    .collect(),
+                ..admin.clone()
+            }),
+            state_store: self.state_store.as_ref().map(|store| StateStoreConfig {
+                // Redis URLs may carry a password.
+                url: store.url.as_ref().map(|url| {
+                    if url.contains('@') {
+                        REDACTED.to_string()
+                    } else {
+                        url.clone()
+                    }
+                }),
+                ..store.clone()
+            }),
+            tenants: self.tenants.as_ref().map(|tenants| TenantsConfig {
+                tenants: tenants
+                    .tenants
+                    .iter()
+                    .map(|tenant| Tenant {
+                        api_keys: vec![REDACTED.to_string(); tenant.api_keys.len()],
+                        ..tenant.clone()
+                    })
+                    .collect(),
+                ..tenants.clone()
+            }),
+            vault: self.vault.as_ref().map(|vault| VaultConfig {
+                token: REDACTED.to_string(),
+                ..vault.clone()
+            }),
+            rate_limit: self.rate_limit.as_ref().map(|rate_limit| RateLimitConfig {
+                overrides: rate_limit
+                    .overrides
+                    .iter()
+                    .map(|o| RateLimitOverride {
+                        key: REDACTED.to_string(),
+                        ..o.clone()
+                    })
+                    .collect(),
+                ..rate_limit.clone()
+            }),
+            token_budget: self.token_budget.as_ref().map(|budget| TokenBudgetConfig {
+                overrides: budget
+                    .overrides
+                    .iter()
+                    .map(|o| TokenBudgetOverride {
+                        key: REDACTED.to_string(),
+                        ..o.clone()
+                    })
+                    .collect(),
+                ..budget.clone()
+            }),
+            ..self.clone()
+        }
+    }
+}
+
+/// The live configuration shared by every connection. Requests work on a
+/// snapshot; the admin API swaps in validated replacements.
+#[derive(Debug, Clone)]
+pub struct SharedConfig {
+    config: Arc<RwLock<RouterConfig>>,
+    path: Option<String>,
+}
+
+impl SharedConfig {
+    pub fn new(config: RouterConfig, path: Option<String>) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+            path,
+        }
+    }
+
+    pub fn snapshot(&self) -> RouterConfig {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Applies `mutate` to a copy of the live configuration and swaps it in
+    /// if both the mutation and validation succeed. Concurrent updates are
+    /// serialized so none are lost.
+    pub fn update(
+        &self,
+        mutate: impl FnOnce(&mut RouterConfig) -> std::result::Result<(), GatewayApiError>,
+    ) -> std::result::Result<RouterConfig, GatewayApiError> {
+        let mut guard = self.config.write().unwrap_or_else(|e| e.into_inner());
+        let mut updated = guard.clone();
+        mutate(&mut updated)?;
+        updated.read_api_key_files()?;
+        validate_config(&updated)?;
+        *guard = updated.clone();
+        Ok(updated)
+    }
+}
+
+impl Policy {
+    pub fn sanitized(&self) -> Self {
+        Policy {
+            llms: self.llms.iter().map(Llm::sanitized).collect(),
+            moderation: self.moderation.as_ref().map(|moderation| ModerationConfig {
+                api_key: REDACTED.to_string(),
+                ..moderation.clone()
+            }),
+            ..self.clone()
+        }
+    }
+
+    /// The in-process classifier of this policy, if it has one.
+    pub fn local_classifier(&self) -> Option<&ClassifierConfig> {
+        self.classifier
+            .as_ref()
+            .filter(|classifier| classifier.kind == ClassifierType::Local)
+    }
+
+    /// Identifies the model scoring this policy's requests: the local
+    /// model's location, or the Triton URL.
+    pub fn classifier_source(&self) -> &str {
+        self.local_classifier()
+            .and_then(|local| local.path.as_deref().or(local.repo.as_deref()))
+            .unwrap_or(&self.url)
+    }
+
+    /// Index of the LLM picked by the first rule matching `facts`.
+    pub fn matching_rule(&self, facts: &Facts) -> Option<usize> {
+        self.rules
+            .iter()
+            .find(|rule| rule.when.evaluate(facts))
+            .and_then(|rule| self.llms.iter().position(|llm| llm.name == rule.llm))
+    }
+
+    pub fn get_llm_by_name(&self, name: &str) -> Option<Llm> {
+        self.llms
+            .iter()
+            .find(|llm| llm.name.trim() == name.trim())
+            .cloned()
+    }
+
+    pub fn get_llm_by_index(&self, index: usize) -> Option<Llm> {
+        self.llms.get(index).cloned()
+    }
+
+    pub fn get_llm_name_by_index(&self, index: usize) -> Option<String> {
+        self.llms.get(index).map(|llm| llm.name.clone())
+    }
+
+    /// The LLM at `index` followed by its fallbacks, or the policy's when it
+    /// declares none. Unknown and repeated names are skipped.
+    pub fn fallback_chain(&self, index: usize) -> Vec<Llm> {
+        let Some(primary) = self.get_llm_by_index(index) else {
+            return Vec::new();
+        };
+        let fallbacks = if primary.fallbacks.is_empty() {
+            &self.fallbacks
+        } else {
+            &primary.fallbacks
+        };
+        let mut chain = vec![primary.clone()];
+        for name in fallbacks {
+            if chain.iter().all(|llm| llm.name != *name) {
+                if let Some(llm) = self.get_llm_by_name(name) {
+                    chain.push(llm);
+                }
+            }
+        }
+        chain
+    }
+}
+
+impl Llm {
+    pub fn sanitized(&self) -> Self {
+        let redact = |secret: &Option<String>| secret.as_ref().map(|_| REDACTED.to_string());
+        Llm {
+            api_key: REDACTED.to_string(),
+            bedrock: self.bedrock.as_ref().map(|bedrock| BedrockConfig {
+                secret_access_key: redact(&bedrock.secret_access_key),
+                session_token: redact(&bedrock.session_token),
+                ..bedrock.clone()
+            }),
+            ..self.clone()
+        }
+    }
+
+    /// API flavour of the backend. Mocked LLMs answer in the OpenAI shape.
+    pub fn provider(&self) -> Provider {
+        if self.mock.is_some() {
+            return Provider::OpenAi;
+        }
+        self.provider.unwrap_or_else(|| {
+            if self.api_base.contains("anthropic.com") {
+                Provider::Anthropic
+            } else if self.api_base.contains(".openai.azure.com") || self.azure.is_some() {
+                Provider::Azure
+            } else if self.api_base.contains("bedrock-runtime.") || self.bedrock.is_some() {
+                Provider::Bedrock
+            } else if self.api_base.contains("generativelanguage.googleapis.com")
+                || self.gemini.is_some()
+            {
+                Provider::Gemini
+            } else if self.api_base.contains(":11434") || self.local.is_some() {
+                Provider::Local
+            } else {
+                Provider::OpenAi
+            }
+        })
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ConfigError>;
+
+/// Replaces `${NAME}` references with the value of the environment
+/// variable `NAME`, so secrets can stay out of the file. Unset variables
+/// are an error; text that is not a reference is kept as is.
+pub fn expand_env(content: &str) -> Result<String> {
+    expand_vars(content, |name| std::env::var(name).ok())
+}
+
+/// Replaces `${NAME}` references with the value `lookup` gives `NAME`.
+pub fn expand_vars(content: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut expanded = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let name = after.find('}').map(|end| &after[..end]).filter(|name| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+        match name {
+            Some(name) => {
+                let value = lookup(name).ok_or_else(|| ConfigError::MissingEnvVar {
+                    name: name.to_string(),
+                })?;
+                expanded.push_str(&value);
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                expanded.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn validate_config(config: &RouterConfig) -> Result<()> {
+    if let Some(admin) = &config.admin {
+        if admin.api_key.is_empty() {
+            return Err(ConfigError::MissingAdminField {
+                field: "api_key".to_string(),
+            });
+        }
+        if admin.tokens.iter().any(|token| token.token.is_empty()) {
+            return Err(ConfigError::MissingAdminField {
+                field: "tokens.token".to_string(),
+            });
+        }
+        if admin.jwt_roles.is_some() && config.jwt.is_none() {
+            return Err(ConfigError::InvalidAdminField {
+                field: "jwt_roles".to_string(),
+                reason: "requires the top-level jwt section".to_string(),
+            });
+        }
+    }
+
+    if config.custom_metric_labels.len() > MAX_CUSTOM_METRIC_LABELS {
+        return Err(ConfigError::InvalidMetricLabel {
+            label: config.custom_metric_labels[MAX_CUSTOM_METRIC_LABELS]
+                .name
+                .clone(),
+            reason: format!(
+                "at most {} custom labels are allowed",
+                MAX_CUSTOM_METRIC_LABELS
+            ),
+        });
+    }
+    for (i, label) in config.custom_metric_labels.iter().enumerate() {
+        let invalid = |reason: &str| ConfigError::InvalidMetricLabel {
+            label: label.name.clone(),
+            reason: reason.to_string(),
+        };
+        let mut chars = label.name.chars();
+        let valid_name = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !label.name.starts_with("__");
+        if !valid_name {
+            return Err(invalid("is not a valid Prometheus label name"));
+        }
+        if RESERVED_METRIC_LABELS.contains(&label.name.as_str()) {
+            return Err(invalid("is already used by the gateway metrics"));
+        }
+        if config.custom_metric_labels[..i]
+            .iter()
+            .any(|other| other.name == label.name)
+        {
+            return Err(invalid("is declared more than once"));
+        }
+        if label.header.is_some() == label.field.is_some() {
+            return Err(invalid("must set exactly one of `header` and `field`"));
+        }
+        if label.allowed_values.is_empty() {
+            return Err(invalid("must list its `allowed_values`"));
+        }
+    }
+
+    if let Some(admission) = &config.admission {
+        if admission.max_in_flight == 0 {
+            return Err(ConfigError::InvalidAdmissionField {
+                field: "max_in_flight".to_string(),
+                reason: "must be at least 1".to_string(),
+            });
+        }
+        if admission
+            .priority_header
+            .as_ref()
+            .is_some_and(|name| http::HeaderName::from_bytes(name.as_bytes()).is_err())
+        {
+            return Err(ConfigError::InvalidAdmissionField {
+                field: "priority_header".to_string(),
+                reason: "is not a valid header name".to_string(),
+            });
+        }
+    }
+
+    if let Some(store) = &config.state_store {
+        let required = match store.backend {
+            StateBackend::Memory => None,
+            StateBackend::Sqlite => store.path.is_none().then_some("path"),
+            StateBackend::Redis => store.url.is_none().then_some("url"),
+        };
+        if let Some(field) = required {
+            return Err(ConfigError::InvalidStateStoreField {
+                field: field.to_string(),
+                reason: format!("is required by the {} backend", store.backend.as_str()),
+            });
+        }
+        if store.backend == StateBackend::Memory && store.max_entries == 0 {
+            return Err(ConfigError::InvalidStateStoreField {
+                field: "max_entries".to_string(),
+                reason: "must be at least 1".to_string(),
+            });
+        }
+    }
+
+    if let Some(vault) = &config.vault {
+        if vault.address.is_empty() || vault.token.is_empty() {
+            let field = if vault.address.is_empty() {
+                "address"
+            } else {
+                "token"
+            };
+            return Err(ConfigError::InvalidVaultField {
+                field: field.to_string(),
+                reason: "must not be empty".to_string(),
+            });
+        }
+    }
+
+    if let Some(name) = config
+        .default_policy
+        .as_ref()
+        .filter(|name| config.get_policy_by_name(name).is_none())
+    {
+        return Err(ConfigError::UnknownPolicy {
+            field: "default_policy".to_string(),
+            policy: name.clone(),
+        });
+    }
+
+    for route in &config.routes {
+        if config.get_policy_by_name(&route.policy).is_none() {
+            return Err(ConfigError::UnknownPolicy {
+                field: "routes.policy".to_string(),
+                policy: route.policy.clone(),
+            });
+        }
+        let invalid = |field: &str, reason: &str| ConfigError::InvalidRouteField {
+            policy: route.policy.clone(),
+            field: field.to_string(),
+            reason: reason.to_string(),
+        };
+        if route.path_prefix.is_none() && route.host.is_none() && route.headers.is_empty() {
+            return Err(invalid(
+                "path_prefix",
+                "a route needs a path_prefix, host or headers",
+            ));
+        }
+        if route
+            .path_prefix
+            .as_ref()
+            .is_some_and(|prefix| !prefix.starts_with('/') || prefix.len() < 2)
+        {
+            return Err(invalid(
+                "path_prefix",
+                "must start with '/' and name a path",
+            ));
+        }
+        if route
+            .headers
+            .keys()
+            .any(|name| http::HeaderName::from_bytes(name.as_bytes()).is_err())
+        {
+            return Err(invalid("headers", "must name valid HTTP headers"));
+        }
+    }
+
+    for (i, model) in config.virtual_models.iter().enumerate() {
+        let invalid = |field: &str, reason: &str| ConfigError::InvalidVirtualModelField {
+            model: model.name.clone(),
+            field: field.to_string(),
+            reason: reason.to_string(),
+        };
+        if model.name.is_empty() {
+            return Err(invalid("name", "must not be empty"));
+        }
+        if config.virtual_models[..i]
+            .iter()
+            .any(|other| other.name == model.name)
+        {
+            return Err(invalid("name", "is used by another virtual model"));
+        }
+        if config.get_policy_by_name(&model.policy).is_none() {
+            return Err(ConfigError::UnknownPolicy {
+                field: "virtual_models.policy".to_string(),
+                policy: model.policy.clone(),
+            });
+        }
+    }
+
+    for (i, alert) in config.spend_alerts.iter().enumerate() {
+        let invalid = |field: &str, reason: &str| ConfigError::InvalidSpendAlertField {
+            alert: alert.name.clone(),
+            field: field.to_string(),
+            reason: reason.to_string(),
+        };
+        if alert.name.is_empty() {
+            return Err(invalid("name", "must not be empty"));
+        }
+        if config.spend_alerts[..i]
+            .iter()
+            .any(|other| other.name == alert.name)
+        {
+            return Err(invalid("name", "is used by another spend alert"));
+        }
+        if !(alert.daily_limit_usd.is_finite() && alert.daily_limit_usd > 0.0) {
+            return Err(invalid("daily_limit_usd", "must be a positive amount"));
+        }
+        let Some(name) = &alert.policy else {
+            continue;
+        };
+        let Some(policy) = config.get_policy_by_name(name) else {
+            return Err(ConfigError::UnknownPolicy {
+                field: "spend_alerts.policy".to_string(),
+                policy: name.clone(),
+            });
+        };
+        if let Some(llm) = &alert.downgrade_to {
+            if policy.get_llm_by_name(llm).is_none() {
+                return Err(invalid("downgrade_to", "is not an LLM of the policy"));
+            }
+        }
+    }
+
+    let tenants = config.tenants.iter().flat_map(|t| &t.tenants);
+    for (i, tenant) in tenants.clone().enumerate() {
+        let invalid = |field: &str, reason: &str| ConfigError::InvalidTenantField {
+            tenant: tenant.id.clone(),
+            field: field.to_string(),
+            reason: reason.to_string(),
+        };
+        if tenant.id.is_empty() {
+            return Err(invalid("id", "must not be empty"));
+        }
+        let earlier = tenants.clone().take(i);
+        if earlier.clone().any(|other| other.id == tenant.id) {
+            return Err(invalid("id", "is used by another tenant"));
+        }
+        let shared_key = earlier
+            .flat_map(|other| &other.api_keys)
+            .any(|key| tenant.api_keys.contains(key));
+        if shared_key || tenant.api_keys.iter().any(String::is_empty) {
+            return Err(invalid(
+                "api_keys",
+                "must be non-empty and unique to the tenant",
+            ));
+        }
+    }
+
+    let faults = config.fault_injection.iter().flat_map(|f| &f.faults);
+    for (i, fault) in faults.enumerate() {
+        let invalid = |field: &str, reason: &str| ConfigError::InvalidFaultField {
+            field: format!("faults[{}].{}", i, field),
+            reason: reason.to_string(),
+        };
+        let shares = [
+            ("delay_percent", fault.delay_percent),
+            ("abort_percent", fault.abort_percent),
+            ("error_percent", fault.error_percent),
+        ];
+        if let Some((field, _)) = shares
+            .iter()
+            .find(|(_, share)| !(0.0..=100.0).contains(share))
+        {
+            return Err(invalid(field, "must be between 0 and 100"));
+        }
+        if !(400..=599).contains(&fault.error_status) {
+            return Err(invalid("error_status", "must be a 4xx or 5xx status"));
+        }
+        if let Some(name) = &fault.policy {
+            if config.get_policy_by_name(name).is_none() {
+                return Err(invalid("policy", "is not a configured policy"));
+            }
+        }
+        if let Some(name) = &fault.llm {
+            let known = config
+                .policies
+                .iter()
+                .filter(|p| fault.matches(&p.name, name))
+                .any(|p| p.llms.iter().any(|llm| llm.name == *name));
+            if !known {
+                return Err(invalid("llm", "is not an LLM of the faulted policies"));
+            }
+        }
+    }
+
+    let mut addresses = std::collections::HashSet::new();
+    for listener in &config.listeners {
+        let invalid = |field: &str, reason: &str| ConfigError::InvalidListenerField {
+            listener: listener.address.clone(),
+            field: field.to_string(),
+            reason: reason.to_string(),
+        };
+        if listener.address.parse::<std::net::SocketAddr>().is_err() {
+            return Err(invalid("address", "must be an IP address and port"));
+        }
+        if !addresses.insert(listener.address.as_str()) {
+            return Err(invalid("address", "is used by another listener"));
+        }
+        if listener
+            .max_header_bytes
+            .is_some_and(|bytes| bytes < MIN_LISTENER_HEADER_BYTES)
+        {
+            return Err(invalid("max_header_bytes", "must be at least 8192"));
+        }
+        if listener.max_concurrent_streams == Some(0) {
+            return Err(invalid(
+                "max_concurrent_streams",
+                "must be greater than zero",
+            ));
+        }
+    }
+
+    for (i, policy) in config.policies.iter().enumerate() {
+        if policy.name.is_empty() {
+            return Err(ConfigError::MissingPolicyField {
+                policy: policy.name.clone(),
+                field: "name".to_string(),
+            });
+        }
+        if config.policies[..i]
+            .iter()
+            .any(|other| other.name.trim() == policy.name.trim())
+        {
+            return Err(ConfigError::InvalidPolicyField {
+                policy: policy.name.clone(),
+                field: "name".to_string(),
+                reason: "is used by another policy".to_string(),
+            });
+        }
+        if let Some((j, llm)) = policy
+            .llms
+            .iter()
+            .enumerate()
+            .find(|(j, llm)| policy.llms[..*j].iter().any(|other| other.name == llm.name))
+        {
+            return Err(ConfigError::InvalidPolicyField {
+                policy: policy.name.clone(),
+                field: format!("llms[{}].name", j),
+                reason: format!("LLM '{}' is declared more than once", llm.name),
+            });
+        }
+        if policy
+            .max_cost_per_request_usd
+            .is_some_and(|limit| limit <= 0.0)
+        {
+            return Err(ConfigError::InvalidPolicyField {
+                policy: policy.name.clone(),
+                field: "max_cost_per_request_usd".to_string(),
+                reason: "must be greater than zero".to_string(),
+            });
+        }
+
+        let classifier_tls_problem = policy.classifier_tls.as_ref().and_then(|tls| {
+            if policy.triton_grpc.is_some() {
+                Some("not supported with triton_grpc")
+            } else {
+                tls.problem()
+            }
+        });
+        if let Some(reason) = classifier_tls_problem {
+            return Err(ConfigError::InvalidPolicyField {
+                policy: policy.name.clone(),
+                field: "classifier_tls".to_string(),
+                reason: reason.to_string(),
+            });
+        }
+        for llm in &policy.llms {
+            if let Some(reason) = llm.tls.as_ref().and_then(UpstreamTls::problem) {
+                return Err(ConfigError::InvalidPolicyField {
+                    policy: policy.name.clone(),
+                    field: format!("llms.{}.tls", llm.name),
+                    reason: reason.to_string(),
+                });
+            }
+        }
+
+        for llm in &policy.llms {
+            let Some(canary) = &llm.canary else {
+                continue;
+            };
+            let reason = if canary.llm == llm.name {
+                Some("an LLM cannot be its own canary".to_string())
+            } else if policy.llms.iter().all(|other| other.name != canary.llm) {
+                Some(format!("unknown LLM '{}'", canary.llm))
+            } else if !(0.0..=100.0).contains(&canary.percent) {
+                Some("percent must be between 0 and 100".to_string())
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(ConfigError::InvalidPolicyField {
+                    policy: policy.name.clone(),
+                    field: format!("llms.{}.canary", llm.name),
+                    reason,
+                });
+            }
+        }
+
+        let unknown_fallback = policy
+            .fallbacks
+            .iter()
+            .chain(policy.llms.iter().flat_map(|llm| llm.fallbacks.iter()))
+            .find(|name| policy.llms.iter().all(|llm| llm.name != **name));
+        if let Some(name) = unknown_fallback {
+            return Err(ConfigError::InvalidPolicyField {
+                policy: policy.name.clone(),
+                field: "fallbacks".to_string(),
+                reason: format!("unknown LLM '{}'", name),
+            });
+        }
+        if let Some(name) = policy
+            .speculative_fallback
+            .as_ref()
+            .and_then(|speculative| speculative.llm.as_ref())
+            .filter(|name| policy.llms.iter().all(|llm| llm.name != **name))
+        {
+            return Err(ConfigError::InvalidPolicyField {
+                policy: policy.name.clone(),
+                field: "speculative_fallback.llm".to_string(),
+                reason: format!("unknown LLM '{}'", name),
+            });
+        }
+        if let Some(moderation) = &policy.moderation {
+            let reason = match (&moderation.action, &moderation.llm) {
+                (ModerationAction::Reroute, None) => Some("reroute needs an llm".to_string()),
+                (_, Some(name)) if policy.llms.iter().all(|llm| llm.name != *name) => {
+                    Some(format!("unknown LLM '{}'", name))
+                }
+                _ => None,
+            };
+            if let Some(reason) = reason {
+                return Err(ConfigError::InvalidPolicyField {
+                    policy: policy.name.clone(),
+                    field: "moderation.llm".to_string(),
+                    reason,
+                });
+            }
+        }
+        if policy.pii_redaction.as_ref().is_some_and(|redaction| {
+            redaction
+                .patterns
+                .iter()
+                .any(|pattern| pattern.label.trim().is_empty())
+        }) {
+            return Err(ConfigError::InvalidPolicyField {
+                policy: policy.name.clone(),
+                field: "pii_redaction.patterns".to_string(),
+                reason: "every pattern needs a label".to_string(),
+            });
+        }
+        for (field, name) in [
+            ("default_llm", &policy.default_llm),
+            ("default_model", &policy.default_model),
+        ] {
+            if let Some(name) = name
+                .as_ref()
+                .filter(|name| policy.llms.iter().all(|llm| llm.name != **name))
+            {
+                return Err(ConfigError::InvalidPolicyField {
+                    policy: policy.name.clone(),
+                    field: field.to_string(),
+                    reason: format!("unknown LLM '{}'", name),
+                });
+            }
+        }
+        if !policy.split.is_empty() {
+            let total: f64 = policy.split.iter().map(|arm| arm.percent).sum();
+            let reason = if let Some(arm) = policy
+                .split
+                .iter()
+                .find(|arm| policy.llms.iter().all(|llm| llm.name != arm.llm))
+            {
+                Some(format!("unknown LLM '{}'", arm.llm))
+            } else if let Some(arm) = policy.split.iter().enumerate().find_map(|(index, arm)| {
+                policy.split[..index]
+                    .iter()
+                    .any(|other| other.llm == arm.llm)
+                    .then_some(arm)
+            }) {
+                Some(format!("LLM '{}' has more than one arm", arm.llm))
+            } else if policy
+                .split
+                .iter()
+                .any(|arm| arm.percent.is_nan() || arm.percent < 0.0)
+            {
+                Some("percentages must not be negative".to_string())
+            } else if (total - 100.0).abs() > 1e-6 {
+                Some(format!("percentages add up to {} instead of 100", total))
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(ConfigError::InvalidPolicyField {
+                    policy: policy.name.clone(),
+                    field: "split".to_string(),
+                    reason,
+                });
+            }
+        }
+        if let Some(name) = policy
+            .labels
+            .iter()
+            .find(|name| policy.llms.iter().all(|llm| llm.name != **name))
+        {
+            return Err(ConfigError::InvalidPolicyField {
+                policy: policy.name.clone(),
+                field: "labels".to_string(),
+                reason: format!("unknown LLM '{}'", name),
+            });
+        }
+        if let Some(name) = policy
+            .low_confidence_llm
+            .as_ref()
+            .filter(|name| policy.llms.iter().all(|llm| llm.name != **name))
+        {
+            return Err(ConfigError::InvalidPolicyField {
+                policy: policy.name.clone(),
+                field: "low_confidence_llm".to_string(),
+                reason: format!("unknown LLM '{}'", name),
+            });
+        }
+        match policy.local_classifier() {
+            Some(local) if local.path.is_none() && local.repo.is_none() => {
+                return Err(ConfigError::MissingPolicyField {
+                    policy: policy.name.clone(),
+                    field: "classifier.path".to_string(),
+                });
+            }
+            Some(local) if local.max_tokens == 0 => {
+                return Err(ConfigError::InvalidPolicyField {
+                    policy: policy.name.clone(),
+                    field: "classifier.max_tokens".to_string(),
+                    reason: "must be greater than zero".to_string(),
+                });
+            }
+            _ => {}
+        }
+        if let Some(grpc) = &policy.triton_grpc {
+            let missing = if grpc.url.is_empty() {
+                Some("triton_grpc.url")
+            } else if grpc.model_name.is_empty() {
+                Some("triton_grpc.model_name")
+            } else {
+                None
+            };
+            if let Some(field) = missing {
+                return Err(ConfigError::MissingPolicyField {
+                    policy: policy.name.clone(),
+                    field: field.to_string(),
+                });
+            }
+        }
+        if let Some(rule) = policy
+            .rules
+            .iter()
+            .find(|rule| policy.llms.iter().all(|llm| llm.name != rule.llm))
+        {
+            return Err(ConfigError::InvalidPolicyField {
+                policy: policy.name.clone(),
+                field: "rules".to_string(),
+                reason: format!("unknown LLM '{}' for `{}`", rule.llm, rule.when.source()),
+            });
+        }
+
+        for llm in &policy.llms {
+            // Mocked LLMs are never called, so need no address or key.
+            if llm.api_base.is_empty() && llm.mock.is_none() {
+                return Err(ConfigError::MissingLlmField {
+                    llm: llm.name.clone(),
+                    field: "api_base".to_string(),
+                });
+            }
+            if llm.model.is_empty() {
+                return Err(ConfigError::MissingLlmField {
+                    llm: llm.name.clone(),
+                    field: "model".to_string(),
+                });
+            }
+            // Bedrock requests are signed with AWS credentials instead, and
+            // local servers usually need none.
+            if llm.api_key.is_empty()
+                && llm.api_key_file.is_none()
+                && llm.mock.is_none()
+                && !matches!(llm.provider(), Provider::Bedrock | Provider::Local)
+            {
+                return Err(ConfigError::MissingLlmField {
+                    llm: llm.name.clone(),
+                    field: "api_key".to_string(),
+                });
+            }
+            if llm.max_concurrent_requests == Some(0) {
+                return Err(ConfigError::InvalidLlmField {
+                    llm: llm.name.clone(),
+                    field: "max_concurrent_requests".to_string(),
+                    reason: "must be at least 1".to_string(),
+                });
+            }
+            if let Some(limits) = &llm.limits {
+                let reason = if limits.max_output_tokens == Some(0) {
+                    Some("max_output_tokens must be at least 1")
+                } else if limits
+                    .min_temperature
+                    .into_iter()
+                    .chain(limits.max_temperature)
+                    .any(|temperature| !(0.0..=2.0).contains(&temperature))
+                {
+                    Some("temperatures must be between 0 and 2")
+                } else if let (Some(min), Some(max)) =
+                    (limits.min_temperature, limits.max_temperature)
+                {
+                    (min > max).then_some("min_temperature must not exceed max_temperature")
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    return Err(ConfigError::InvalidLlmField {
+                        llm: llm.name.clone(),
+                        field: "limits".to_string(),
+                        reason: reason.to_string(),
+                    });
+                }
+            }
+            if llm
+                .tokenizer
+                .as_ref()
+                .is_some_and(|tokenizer| tokenizer.path.is_none() && tokenizer.repo.is_none())
+            {
+                return Err(ConfigError::InvalidLlmField {
+                    llm: llm.name.clone(),
+                    field: "tokenizer".to_string(),
+                    reason: "needs a path or a repo".to_string(),
+                });
+            }
+            if let Some(reference) = VaultRef::parse(&llm.api_key) {
+                let reason = if config.vault.is_none() {
+                    Some("references Vault, which requires the top-level vault section")
+                } else if reference.path.is_empty() || reference.key.is_empty() {
+                    Some("must be written vault:<path>#<key>")
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    return Err(ConfigError::InvalidLlmField {
+                        llm: llm.name.clone(),
+                        field: "api_key".to_string(),
+                        reason: reason.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
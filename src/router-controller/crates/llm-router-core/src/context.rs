@@ -0,0 +1,290 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Context
+//!
+//! Context-window aware routing: the prompt plus the requested completion
+//! must fit the LLM's declared `max_context`. A policy's `context_overflow`
+//! decides what happens to requests that do not.
+use crate::config::{ContextOverflow, Llm, Policy};
+use crate::cost;
+use crate::error::GatewayApiError;
+use crate::metrics::CONTEXT_OVERFLOWS;
+#[cfg(feature = "local-models")]
+use crate::tokenizer;
+use http::StatusCode;
+use log::{info, warn};
+use serde_json::{json, Value};
+
+fn completion_tokens(json: &Value) -> u64 {
+    json.get("max_tokens")
+        .or_else(|| json.get("max_completion_tokens"))
+        .and_then(Value::as_u64)
+        .unwrap_or(0)
+}
+
+/// Estimated tokens the request needs: the prompt plus the completion it
+/// asks for.
+pub fn required_tokens(json: &Value) -> u64 {
+    cost::estimate_prompt_tokens(json) + completion_tokens(json)
+}
+
+/// Prompt tokens of `json` on `llm`, counted with the LLM's tokenizer once
+/// it is loaded and estimated otherwise.
+#[cfg(feature = "local-models")]
+fn prompt_tokens(llm: &Llm, json: &Value) -> u64 {
+    llm.tokenizer
+        .as_ref()
+        .and_then(|config| tokenizer::prompt_tokens(config, json))
+        .unwrap_or_else(|| cost::estimate_prompt_tokens(json))
+}
+
+/// Estimated prompt tokens of `json`; tokenizers need `local-models`.
+#[cfg(not(feature = "local-models"))]
+fn prompt_tokens(_llm: &Llm, json: &Value) -> u64 {
+    cost::estimate_prompt_tokens(json)
+}
+
+/// Tokens `json` needs on `llm`: the prompt plus the completion it asks for.
+fn llm_required_tokens(llm: &Llm, json: &Value) -> u64 {
+    prompt_tokens(llm, json) + completion_tokens(json)
+}
+
+/// Picks the LLM with the smallest declared context window that fits
+/// `required` tokens, or the one with the largest window when none fits.
+/// Ties go to the LLM listed first. Returns `None` when no LLM in the policy
+/// declares `max_context`.
+pub fn select(policy: &Policy, required: u64) -> Option<usize> {
+    let declared = policy
+        .llms
+        .iter()
+        .enumerate()
+        .filter_map(|(index, llm)| llm.max_context.map(|window| (index, window)));
+    let fitting = declared
+        .clone()
+        .filter(|(_, window)| *window >= required)
+        .min_by_key(|(index, window)| (*window, *index));
+    fitting
+        .or_else(|| declared.max_by_key(|(index, window)| (*window, usize::MAX - index)))
+        .map(|(index, _)| index)
+}
+
+/// Keeps `index` when that LLM's window fits (or is undeclared), otherwise
+/// moves the request to the best fitting LLM of the policy.
+pub fn reroute(policy: &Policy, index: usize, required: u64) -> usize {
+    match policy.llms.get(index).and_then(|llm| llm.max_context) {
+        Some(window) if window < required => {
+            let Some(other) = select(policy, required)
+                .filter(|&other| policy.llms[other].max_context.unwrap_or(0) > window)
+            else {
+                return index;
+            };
+            info!(
+                "rerouting {} estimated tokens from {} ({} token window) to {}",
+                required, policy.llms[index].name, window, policy.llms[other].name
+            );
+            other
+        }
+        _ => index,
+    }
+}
+
+/// The LLM serving a request for the LLM at `index`: with the `reroute`
+/// action, the best fitting LLM of the policy when the request does not fit
+/// the one at `index`.
+pub fn fit(policy: &Policy, index: usize, json: &Value) -> usize {
+    let (Some(ContextOverflow::Reroute), Some(llm)) =
+        (policy.context_overflow, policy.llms.get(index))
+    else {
+        return index;
+    };
+    let rerouted = reroute(policy, index, llm_required_tokens(llm, json));
+    if rerouted != index {
+        CONTEXT_OVERFLOWS
+            .with_label_values(&[&policy.name, &llm.name, "rerouted"])
+            .inc();
+    }
+    rerouted
+}
+
+fn exceeded(policy: &Policy, llm: &Llm, window: u64, required: u64) -> GatewayApiError {
+    warn!(
+        "request rejected: policy={} llm={} required_tokens={} max_context={}",
+        policy.name, llm.name, required, window
+    );
+    CONTEXT_OVERFLOWS
+        .with_label_values(&[&policy.name, &llm.name, "rejected"])
+        .inc();
+    GatewayApiError::client_error(
+        StatusCode::BAD_REQUEST,
+        format!(
+            "Request needs about {} tokens, prompt plus max_tokens, over the {} token \
+             context window of LLM '{}'",
+            required, window, llm.name
+        ),
+        "context_length_exceeded",
+    )
+}
+
+/// Drops the oldest messages of `json` until `excess` tokens are gone.
+/// System messages and the last message are kept, and tool results go with
+/// the message that called the tool. `None` when that is not enough.
+fn truncate(llm: &Llm, json: &mut Value, excess: u64) -> Option<usize> {
+    let messages = json.get_mut("messages")?.as_array_mut()?;
+    let last = messages.len().checked_sub(1)?;
+    let mut keep = vec![true; messages.len()];
+    let mut left = excess;
+    let mut index = 0;
+    while left > 0 && index < last {
+        let role = messages[index]["role"].as_str();
+        if matches!(role, Some("system") | Some("tool")) {
+            index += 1;
+            continue;
+        }
+        loop {
+            keep[index] = false;
+            let tokens = prompt_tokens(llm, &json!({ "messages": [&messages[index]] }));
+            left = left.saturating_sub(tokens);
+            index += 1;
+            if index >= last || messages[index]["role"] != "tool" {
+                break;
+            }
+        }
+    }
+    if left > 0 {
+        return None;
+    }
+    let mut kept = keep.iter();
+    messages.retain(|_| kept.next().copied().unwrap_or(true));
+    Some(keep.iter().filter(|&&kept| !kept).count())
+}
+
+/// Enforces `llm`'s `max_context` on `json` as the policy's
+/// `context_overflow` says. With `truncate` the oldest messages are dropped
+/// until the request fits; otherwise, or when it cannot be made to fit, the
+/// request is rejected. Requests are passed on as they are when the policy
+/// sets no action or the LLM declares no window.
+pub fn enforce_window(
+    mut json: Value,
+    policy: &Policy,
+    llm: &Llm,
+) -> Result<Value, GatewayApiError> {
+    let (Some(action), Some(window)) = (policy.context_overflow, llm.max_context) else {
+        return Ok(json);
+    };
+    let required = llm_required_tokens(llm, &json);
+    if required <= window {
+        return Ok(json);
+    }
+    if action != ContextOverflow::Truncate {
+        return Err(exceeded(policy, llm, window, required));
+    }
+    let Some(dropped) = truncate(llm, &mut json, required - window) else {
+        return Err(exceeded(policy, llm, window, required));
+    };
+    info!(
+        "messages truncated: policy={} llm={} required_tokens={} max_context={} dropped={}",
+        policy.name, llm.name, required, window, dropped
+    );
+    CONTEXT_OVERFLOWS
+        .with_label_values(&[&policy.name, &llm.name, "truncated"])
+        .inc();
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Llm;
+    use serde_json::json;
+
+    fn policy(windows: &[Option<u64>]) -> Policy {
+        Policy {
+            name: "context".to_string(),
+            llms: windows
+                .iter()
+                .enumerate()
+                .map(|(i, window)| Llm {
+                    name: format!("llm{}", i),
+                    max_context: *window,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_select_and_reroute() {
+        let policy = policy(&[Some(8_000), None, Some(128_000), Some(32_000)]);
+        assert_eq!(select(&policy, 1_000), Some(0));
+        assert_eq!(select(&policy, 20_000), Some(3));
+        assert_eq!(select(&policy, 500_000), Some(2));
+        assert_eq!(select(&self::policy(&[None, None]), 10), None);
+
+        assert_eq!(reroute(&policy, 0, 20_000), 3);
+        assert_eq!(reroute(&policy, 1, 20_000), 1);
+        assert_eq!(reroute(&policy, 2, 20_000), 2);
+
+        let request = json!({
+            "messages": [{"role": "user", "content": "a".repeat(400)}],
+            "max_tokens": 100
+        });
+        assert_eq!(required_tokens(&request), 204);
+    }
+
+    #[test]
+    fn test_enforce_window() {
+        let mut policy = policy(&[Some(100), Some(1_000)]);
+        let message = |role: &str| json!({"role": role, "content": "a".repeat(120)});
+        let request = json!({
+            "messages": [
+                message("system"),
+                message("user"),
+                message("assistant"),
+                message("tool"),
+                message("user"),
+            ],
+            "max_tokens": 10
+        });
+        let small = policy.llms[0].clone();
+        assert!(enforce_window(request.clone(), &policy, &small).is_ok());
+        assert_eq!(fit(&policy, 0, &request), 0);
+
+        policy.context_overflow = Some(ContextOverflow::Reject);
+        let error = enforce_window(request.clone(), &policy, &small).unwrap_err();
+        assert!(error.to_string().contains("context window of LLM 'llm0'"));
+        assert!(enforce_window(request.clone(), &policy, &policy.llms[1]).is_ok());
+
+        policy.context_overflow = Some(ContextOverflow::Reroute);
+        assert_eq!(fit(&policy, 0, &request), 1);
+
+        policy.context_overflow = Some(ContextOverflow::Truncate);
+        let truncated = enforce_window(request.clone(), &policy, &small).unwrap();
+        let roles: Vec<_> = truncated["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["system", "user"]);
+
+        let tiny = Llm {
+            max_context: Some(20),
+            ..small
+        };
+        assert!(enforce_window(request, &policy, &tiny).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cost
+use crate::config::{CostLimitAction, Llm, Policy, Pricing};
+use crate::error::GatewayApiError;
+use http::StatusCode;
+use log::{info, warn};
+use serde_json::{json, Value};
+
+/// Rough characters per token, used until the prompt is actually tokenized.
+pub const CHARS_PER_TOKEN: usize = 4;
+/// Tokens added per message for role and formatting tokens.
+pub const TOKENS_PER_MESSAGE: u64 = 4;
+
+fn content_chars(content: &Value) -> usize {
+    match content {
+        Value::String(text) => text.chars().count(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .map(|text| text.chars().count())
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Estimated prompt tokens of an OpenAI chat completion body, or of the
+/// `prompt` of a legacy completion.
+pub fn estimate_prompt_tokens(json: &Value) -> u64 {
+    if let Some(prompt) = json.get("prompt") {
+        let chars: usize = match prompt {
+            Value::Array(prompts) => prompts.iter().map(content_chars).sum(),
+            prompt => content_chars(prompt),
+        };
+        return chars.div_ceil(CHARS_PER_TOKEN) as u64;
+    }
+    let Some(messages) = json.get("messages").and_then(Value::as_array) else {
+        return 0;
+    };
+    messages
+        .iter()
+        .map(|message| {
+            let chars = message.get("content").map_or(0, content_chars);
+            chars.div_ceil(CHARS_PER_TOKEN) as u64 + TOKENS_PER_MESSAGE
+        })
+        .sum()
+}
+
+pub fn cost_usd(pricing: &Pricing, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+    (prompt_tokens as f64 * pricing.input_per_million
+        + completion_tokens as f64 * pricing.output_per_million)
+        / 1_000_000.0
+}
+
+/// Cost of the `usage` block of a completion, `None` when it reports no
+/// tokens.
+pub fn usage_cost_usd(pricing: &Pricing, usage: &Value) -> Option<f64> {
+    let prompt_tokens = usage.get("prompt_tokens").and_then(Value::as_u64);
+    let completion_tokens = usage.get("completion_tokens").and_then(Value::as_u64);
+    if prompt_tokens.is_none() && completion_tokens.is_none() {
+        return None;
+    }
+    Some(cost_usd(
+        pricing,
+        prompt_tokens.unwrap_or(0),
+        completion_tokens.unwrap_or(0),
+    ))
+}
+
+fn over_limit(policy: &Policy, llm: &Llm, estimate: f64, limit: f64) -> GatewayApiError {
+    warn!(
+        "request rejected: policy={} llm={} estimated_cost_usd={:.6} max_cost_per_request_usd={}",
+        policy.name, llm.name, estimate, limit
+    );
+    GatewayApiError::client_error(
+        StatusCode::BAD_REQUEST,
+        format!(
+            "Estimated cost ${:.6} exceeds the ${} per request limit of policy '{}'",
+            estimate, limit, policy.name
+        ),
+        "max_cost_exceeded",
+    )
+}
+
+/// Enforces the policy's `max_cost_per_request_usd` against the estimated
+/// cost of `json` on `llm` (prompt tokens plus `max_tokens` at the LLM's
+/// pricing). Over the limit the request is rejected, or with `clamp` its
+/// `max_tokens` is lowered to what the limit still affords. LLMs without
+/// pricing are not limited.
+pub fn enforce_limit(
+    mut json: Value,
+    policy: &Policy,
+    llm: &Llm,
+) -> Result<Value, GatewayApiError> {
+    let (Some(limit), Some(pricing)) = (policy.max_cost_per_request_usd, &llm.pricing) else {
+        return Ok(json);
+    };
+    let action = policy.max_cost_action.unwrap_or_default();
+    let prompt_tokens = estimate_prompt_tokens(&json);
+    let max_tokens = json.get("max_tokens").and_then(Value::as_u64);
+
+    let prompt_cost = cost_usd(pricing, prompt_tokens, 0);
+    let estimate = cost_usd(pricing, prompt_tokens, max_tokens.unwrap_or(0));
+    let needs_clamp = action == CostLimitAction::Clamp && max_tokens.is_none();
+    if estimate <= limit && !needs_clamp {
+        return Ok(json);
+    }
+    if action == CostLimitAction::Reject || prompt_cost >= limit {
+        return Err(over_limit(policy, llm, estimate, limit));
+    }
+
+    if pricing.output_per_million <= 0.0 {
+        // Completion tokens are free, so the prompt alone decides.
+        return Ok(json);
+    }
+    let affordable =
+        ((limit - prompt_cost) * 1_000_000.0 / pricing.output_per_million).floor() as u64;
+    let clamped = max_tokens.map_or(affordable, |m| m.min(affordable));
+    if clamped == 0 {
+        return Err(over_limit(policy, llm, estimate, limit));
+    }
+    if Some(clamped) != max_tokens {
+        info!(
+            "max_tokens clamped: policy={} llm={} requested={} clamped={}",
+            policy.name,
+            llm.name,
+            max_tokens.map_or("unset".to_string(), |m| m.to_string()),
+            clamped
+        );
+        json["max_tokens"] = json!(clamped);
+    }
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn priced_llm() -> Llm {
+        Llm {
+            name: "priced".to_string(),
+            pricing: Some(Pricing {
+                input_per_million: 1.0,
+                output_per_million: 10.0,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn policy(action: CostLimitAction) -> Policy {
+        Policy {
+            name: "capped".to_string(),
+            max_cost_per_request_usd: Some(0.01),
+            max_cost_action: Some(action),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_usage_cost_usd() {
+        let pricing = priced_llm().pricing.unwrap();
+        let usage = json!({"prompt_tokens": 1000, "completion_tokens": 500, "total_tokens": 1500});
+        let cost = usage_cost_usd(&pricing, &usage).unwrap();
+        assert!((cost - 0.006).abs() < 1e-12);
+        assert_eq!(usage_cost_usd(&pricing, &json!({})), None);
+    }
+
+    #[test]
+    fn test_reject_and_clamp() {
+        // 4 prompt tokens, so $0.000004 before completion tokens.
+        let body = json!({ "messages": [{"role": "user", "content": ""}], "max_tokens": 5000 });
+
+        let rejected = enforce_limit(
+            body.clone(),
+            &policy(CostLimitAction::Reject),
+            &priced_llm(),
+        );
+        assert!(rejected.is_err());
+
+        let clamped = enforce_limit(body, &policy(CostLimitAction::Clamp), &priced_llm()).unwrap();
+        assert_eq!(clamped["max_tokens"], 999);
+
+        let within = json!({ "messages": [], "max_tokens": 100 });
+        let unchanged = enforce_limit(
+            within.clone(),
+            &policy(CostLimitAction::Reject),
+            &priced_llm(),
+        )
+        .unwrap();
+        assert_eq!(unchanged, within);
+
+        let free_output = Llm {
+            pricing: Some(Pricing {
+                input_per_million: 1.0,
+                output_per_million: 0.0,
+            }),
+            ..priced_llm()
+        };
+        let unset = json!({ "messages": [] });
+        let unchanged =
+            enforce_limit(unset.clone(), &policy(CostLimitAction::Clamp), &free_output).unwrap();
+        assert_eq!(unchanged, unset);
+
+        let legacy = json!({ "prompt": ["a".repeat(40), "b".repeat(2)] });
+        assert_eq!(estimate_prompt_tokens(&legacy), 11);
+    }
+}
@@ -0,0 +1,130 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decision Cache
+//!
+//! Short lived cache of raw Triton scores keyed by a hash of the classifier
+//! input, so retries and follow-ups with the same last message skip the
+//! Triton round trip.
+use crate::config::{ClassifierCacheConfig, Policy};
+use crate::metrics::{CLASSIFIER_CACHE_ENTRIES, CLASSIFIER_CACHE_REQUESTS};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Entry {
+    scores: Vec<f64>,
+    expires_at: Instant,
+}
+
+lazy_static! {
+    static ref CACHE: Mutex<HashMap<String, Entry>> = Mutex::new(HashMap::new());
+}
+
+/// Scores depend on the model as well as the text, so the policy's Triton
+/// URL or local model is part of the key.
+pub fn key(policy: &Policy, text: &str) -> String {
+    let source = policy.classifier_source();
+    let mut input = Vec::with_capacity(source.len() + text.len() + 1);
+    input.extend_from_slice(source.as_bytes());
+    input.push(0);
+    input.extend_from_slice(text.as_bytes());
+    openssl::sha::sha256(&input)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Cached scores for `key`, counting the lookup as a hit or miss.
+pub fn lookup(policy: &str, key: &str) -> Option<Vec<f64>> {
+    let cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let scores = cache
+        .get(key)
+        .filter(|entry| entry.expires_at > Instant::now())
+        .map(|entry| entry.scores.clone());
+    let outcome = if scores.is_some() { "hit" } else { "miss" };
+    CLASSIFIER_CACHE_REQUESTS
+        .with_label_values(&[policy, outcome])
+        .inc();
+    scores
+}
+
+/// Scores last stored for `key`, even past their TTL. Used as the
+/// last-known-good decision while the classifier is unavailable.
+pub fn last_known(key: &str) -> Option<Vec<f64>> {
+    let cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.get(key).map(|entry| entry.scores.clone())
+}
+
+/// Expired entries are purged when the cache is full, then the entry
+/// closest to expiry is evicted.
+pub fn store(config: &ClassifierCacheConfig, key: String, scores: Vec<f64>) {
+    if config.max_entries == 0 {
+        return;
+    }
+    let now = Instant::now();
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.len() >= config.max_entries {
+        cache.retain(|_, entry| entry.expires_at > now);
+    }
+    if cache.len() >= config.max_entries {
+        if let Some(oldest) = cache
+            .iter()
+            .min_by_key(|(_, entry)| entry.expires_at)
+            .map(|(k, _)| k.clone())
+        {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(
+        key,
+        Entry {
+            scores,
+            expires_at: now + Duration::from_secs(config.ttl_seconds),
+        },
+    );
+    CLASSIFIER_CACHE_ENTRIES.set(cache.len() as i64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_and_eviction() {
+        let policy = Policy {
+            name: "decision_cache_test".to_string(),
+            url: "http://triton:8000/v2/models/task_router/infer".to_string(),
+            ..Default::default()
+        };
+        let config = ClassifierCacheConfig::default();
+        let first = key(&policy, "decision cache test prompt");
+        assert_ne!(first, key(&policy, "another prompt"));
+        assert_eq!(lookup(&policy.name, &first), None);
+
+        store(&config, first.clone(), vec![0.2, 0.8]);
+        assert_eq!(lookup(&policy.name, &first), Some(vec![0.2, 0.8]));
+
+        let expired = ClassifierCacheConfig {
+            ttl_seconds: 0,
+            ..Default::default()
+        };
+        let second = key(&policy, "decision cache expired prompt");
+        store(&expired, second.clone(), vec![1.0]);
+        assert_eq!(lookup(&policy.name, &second), None);
+        assert_eq!(last_known(&second), Some(vec![1.0]));
+    }
+}
@@ -13,14 +13,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "server")]
+use bytes::Bytes;
 use http::header::InvalidHeaderValue;
-use http::{Response, StatusCode};
+#[cfg(feature = "server")]
+use http::Response;
+use http::StatusCode;
+#[cfg(feature = "server")]
 use http_body_util::{combinators::BoxBody, BodyExt, Full};
-use hyper::body::Bytes;
-use serde_json::{json, Value};
+#[cfg(feature = "server")]
+use serde_json::json;
+use serde_json::Value;
 use std::convert::Infallible;
 use thiserror::Error;
 
+#[cfg(feature = "server")]
 pub trait IntoResponse {
     fn into_response(self) -> Response<BoxBody<Bytes, GatewayApiError>>;
 }
@@ -83,6 +90,7 @@ pub enum GatewayApiError {
     #[error(transparent)]
     Http(#[from] http::Error),
 
+    #[cfg(feature = "server")]
     #[error(transparent)]
     Hyper(#[from] hyper::Error),
 
@@ -103,6 +111,16 @@ pub enum GatewayApiError {
 
     #[error("No policy specified in nim-llm-router params")]
     MissingPolicy,
+
+    #[error("Quota exceeded: {message}")]
+    QuotaExceeded {
+        message: String,
+        limit: u64,
+        used: u64,
+        period: String,
+        /// Unix time in seconds at which the quota resets.
+        resets_at: u64,
+    },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -111,6 +129,66 @@ pub enum ConfigError {
     MissingPolicyField { policy: String, field: String },
     #[error("Missing field '{field}' in LLM '{llm}'")]
     MissingLlmField { llm: String, field: String },
+    #[error("Invalid field '{field}' in LLM '{llm}': {reason}")]
+    InvalidLlmField {
+        llm: String,
+        field: String,
+        reason: String,
+    },
+    #[error("Invalid field '{field}' in policy '{policy}': {reason}")]
+    InvalidPolicyField {
+        policy: String,
+        field: String,
+        reason: String,
+    },
+    #[error("Field '{field}' names unknown policy '{policy}'")]
+    UnknownPolicy { field: String, policy: String },
+    #[error("Missing field '{field}' in admin section")]
+    MissingAdminField { field: String },
+    #[error("Invalid field '{field}' in admin section: {reason}")]
+    InvalidAdminField { field: String, reason: String },
+    #[error("Invalid custom metric label '{label}': {reason}")]
+    InvalidMetricLabel { label: String, reason: String },
+    #[error("Invalid field '{field}' in listener '{listener}': {reason}")]
+    InvalidListenerField {
+        listener: String,
+        field: String,
+        reason: String,
+    },
+    #[error("Invalid field '{field}' in state_store section: {reason}")]
+    InvalidStateStoreField { field: String, reason: String },
+    #[error("Invalid field '{field}' in admission section: {reason}")]
+    InvalidAdmissionField { field: String, reason: String },
+    #[error("Invalid field '{field}' in fault_injection section: {reason}")]
+    InvalidFaultField { field: String, reason: String },
+    #[error("Invalid field '{field}' in tenant '{tenant}': {reason}")]
+    InvalidTenantField {
+        tenant: String,
+        field: String,
+        reason: String,
+    },
+    #[error("Invalid field '{field}' in route to policy '{policy}': {reason}")]
+    InvalidRouteField {
+        policy: String,
+        field: String,
+        reason: String,
+    },
+    #[error("Invalid field '{field}' in virtual model '{model}': {reason}")]
+    InvalidVirtualModelField {
+        model: String,
+        field: String,
+        reason: String,
+    },
+    #[error("Invalid field '{field}' in spend alert '{alert}': {reason}")]
+    InvalidSpendAlertField {
+        alert: String,
+        field: String,
+        reason: String,
+    },
+    #[error("Invalid field '{field}' in vault section: {reason}")]
+    InvalidVaultField { field: String, reason: String },
+    #[error("Environment variable '{name}' referenced by the configuration is not set")]
+    MissingEnvVar { name: String },
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
@@ -126,13 +204,25 @@ pub enum RoutingErrorType {
     TritonUnavailable,
 }
 
+impl RoutingErrorType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PolicyNotFound => "policy_not_found",
+            Self::ModelNotFound => "model_not_found",
+            Self::NoRoutingStrategy => "no_routing_strategy",
+            Self::InvalidConfiguration => "invalid_configuration",
+            Self::TritonUnavailable => "triton_unavailable",
+        }
+    }
+}
+
 impl GatewayApiError {
     pub fn error_source(&self) -> ErrorSource {
         match self {
             Self::TritonError { .. } => ErrorSource::Triton,
             Self::LlmServiceError { .. } => ErrorSource::LlmProvider,
             Self::RoutingError { .. } => ErrorSource::Router,
-            Self::ClientError { .. } => ErrorSource::Client,
+            Self::ClientError { .. } | Self::QuotaExceeded { .. } => ErrorSource::Client,
             _ => ErrorSource::Infrastructure,
         }
     }
@@ -144,6 +234,7 @@ impl GatewayApiError {
             }
             Self::LlmServiceError { status, .. } => *status,
             Self::ClientError { status, .. } => *status,
+            Self::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
             Self::RoutingError { error_type, .. } => match error_type {
                 RoutingErrorType::PolicyNotFound => StatusCode::BAD_REQUEST,
                 RoutingErrorType::ModelNotFound => StatusCode::NOT_FOUND,
@@ -155,6 +246,7 @@ impl GatewayApiError {
         }
     }
 
+    #[cfg(feature = "server")]
     pub fn to_response(&self) -> Result<Response<BoxBody<Bytes, Self>>, Self> {
         let error_response = match self {
             Self::LlmServiceError {
@@ -190,7 +282,7 @@ impl GatewayApiError {
                 error_type,
             } => json!({
                 "error": {
-                    "type": format!("routing_error_{:?}", error_type).to_lowercase(),
+                    "type": format!("routing_error_{}", error_type.as_str()),
                     "message": message,
                     "status": self.status_code().as_u16(),
                     "source": "router"
@@ -208,6 +300,26 @@ impl GatewayApiError {
                     "source": "client"
                 }
             }),
+            Self::QuotaExceeded {
+                message,
+                limit,
+                used,
+                period,
+                resets_at,
+            } => json!({
+                "error": {
+                    "type": "quota_exceeded",
+                    "message": message,
+                    "status": self.status_code().as_u16(),
+                    "quota": {
+                        "limit": limit,
+                        "used": used,
+                        "period": period,
+                        "resets_at": resets_at
+                    },
+                    "source": "client"
+                }
+            }),
             _ => json!({
                 "error": {
                     "type": "internal_error",
@@ -271,6 +383,7 @@ impl GatewayApiError {
     }
 }
 
+#[cfg(feature = "server")]
 impl From<reqwest::Error> for GatewayApiError {
     fn from(error: reqwest::Error) -> Self {
         if let Some(status) = error.status() {
@@ -281,6 +394,19 @@ impl From<reqwest::Error> for GatewayApiError {
     }
 }
 
+impl From<ConfigError> for GatewayApiError {
+    fn from(err: ConfigError) -> Self {
+        match err {
+            ConfigError::Io(_) | ConfigError::Yaml(_) => Self::Infrastructure(err.to_string()),
+            _ => Self::client_error(
+                StatusCode::BAD_REQUEST,
+                err.to_string(),
+                "invalid_configuration",
+            ),
+        }
+    }
+}
+
 impl From<Infallible> for GatewayApiError {
     fn from(err: Infallible) -> Self {
         match err {}
@@ -295,8 +421,15 @@ impl From<InvalidHeaderValue> for GatewayApiError {
     }
 }
 
+#[cfg(feature = "server")]
 impl IntoResponse for GatewayApiError {
     fn into_response(self) -> Response<BoxBody<Bytes, GatewayApiError>> {
+        // Quota refusals carry a body clients act on.
+        if matches!(self, GatewayApiError::QuotaExceeded { .. }) {
+            if let Ok(response) = self.to_response() {
+                return response;
+            }
+        }
         let (status, message) = match &self {
             GatewayApiError::InvalidRequest { message } => {
                 (StatusCode::BAD_REQUEST, message.clone())
@@ -346,7 +479,7 @@ impl From<()> for GatewayApiError {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "server"))]
 mod tests {
     use super::*;
 
@@ -0,0 +1,681 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Expr
+//!
+//! Conditions such as `tokens > 4000 && has_tools`, used by the `rules`
+//! routing strategy and LLM guards. Conditions are parsed and type checked
+//! when the config is loaded, so mistakes are reported with their column
+//! rather than at request time.
+//!
+//! ```text
+//! or      := and ("||" and)*
+//! and     := unary ("&&" unary)*
+//! unary   := "!" unary | compare
+//! compare := primary (("==" | "!=" | "<" | "<=" | ">" | ">=" | "contains" | "matches") primary)?
+//! primary := number | string | "true" | "false" | variable | "(" or ")"
+//! ```
+use crate::context;
+use crate::cost;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Variables a condition can refer to. `tags.<name>` reads a request tag and
+/// is the empty string when the tag is absent.
+pub const VARIABLES: &[(&str, Type)] = &[
+    ("tokens", Type::Number),
+    ("prompt_tokens", Type::Number),
+    ("max_tokens", Type::Number),
+    ("messages", Type::Number),
+    ("has_tools", Type::Bool),
+    ("has_images", Type::Bool),
+    ("stream", Type::Bool),
+    // Last user message, or the `prompt` of a legacy completion.
+    ("last_message", Type::String),
+    ("last_message_length", Type::Number),
+    ("model", Type::String),
+];
+
+const TAG_PREFIX: &str = "tags.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    Number,
+    String,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Type::Bool => "boolean",
+            Type::Number => "number",
+            Type::String => "string",
+        })
+    }
+}
+
+/// A parse or type error, with the 1-based column it was found at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    And,
+    Or,
+    Not,
+    Op(Op),
+    LParen,
+    RParen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    Matches,
+}
+
+impl Op {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "contains",
+            Op::Matches => "matches",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Literal),
+    Var(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, Op, Box<Expr>),
+    Matches(Box<Expr>, Regex),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Bool(bool),
+    Number(f64),
+    Str(String),
+}
+
+fn error(column: usize, message: impl Into<String>) -> ParseError {
+    ParseError {
+        column,
+        message: message.into(),
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let two: String = chars[i..chars.len().min(i + 2)].iter().collect();
+        let (token, len) = match (c, two.as_str()) {
+            (_, "&&") => (Token::And, 2),
+            (_, "||") => (Token::Or, 2),
+            (_, "==") => (Token::Op(Op::Eq), 2),
+            (_, "!=") => (Token::Op(Op::Ne), 2),
+            (_, "<=") => (Token::Op(Op::Le), 2),
+            (_, ">=") => (Token::Op(Op::Ge), 2),
+            ('<', _) => (Token::Op(Op::Lt), 1),
+            ('>', _) => (Token::Op(Op::Gt), 1),
+            ('!', _) => (Token::Not, 1),
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('"', _) | ('\'', _) => {
+                let mut value = String::new();
+                let mut end = i + 1;
+                loop {
+                    match chars.get(end) {
+                        None => return Err(error(column, "unterminated string")),
+                        Some(&q) if q == c => break,
+                        Some('\\') if chars.get(end + 1).is_some() => {
+                            value.push(chars[end + 1]);
+                            end += 2;
+                        }
+                        Some(&other) => {
+                            value.push(other);
+                            end += 1;
+                        }
+                    }
+                }
+                (Token::Str(value), end + 1 - i)
+            }
+            _ if c.is_ascii_digit() => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit() || **c == '.' || **c == '_')
+                    .count();
+                let text: String = chars[i..i + len].iter().filter(|c| **c != '_').collect();
+                let value = text
+                    .parse()
+                    .map_err(|_| error(column, format!("invalid number '{}'", text)))?;
+                (Token::Number(value), len)
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_' || **c == '.' || **c == '-')
+                    .count();
+                let word: String = chars[i..i + len].iter().collect();
+                let token = match word.as_str() {
+                    "contains" => Token::Op(Op::Contains),
+                    "matches" => Token::Op(Op::Matches),
+                    _ => Token::Ident(word),
+                };
+                (token, len)
+            }
+            _ => return Err(error(column, format!("unexpected character '{}'", c))),
+        };
+        tokens.push((column, token));
+        i += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    end_column: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end_column, |(column, _)| *column)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(_, token)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn expect_type(&self, column: usize, expr: &Expr, expected: Type) -> Result<(), ParseError> {
+        let actual = type_of(expr);
+        if actual != expected {
+            return Err(error(
+                column,
+                format!("expected a {} but found a {}", expected, actual),
+            ));
+        }
+        Ok(())
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let column = self.column();
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.expect_type(column, &left, Type::Bool)?;
+            self.next();
+            let right_column = self.column();
+            let right = self.and()?;
+            self.expect_type(right_column, &right, Type::Bool)?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let column = self.column();
+        let mut left = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.expect_type(column, &left, Type::Bool)?;
+            self.next();
+            let right_column = self.column();
+            let right = self.unary()?;
+            self.expect_type(right_column, &right, Type::Bool)?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            let column = self.column();
+            let operand = self.unary()?;
+            self.expect_type(column, &operand, Type::Bool)?;
+            return Ok(Expr::Not(Box::new(operand)));
+        }
+        self.compare()
+    }
+
+    fn compare(&mut self) -> Result<Expr, ParseError> {
+        let left_column = self.column();
+        let left = self.primary()?;
+        let Some(Token::Op(op)) = self.peek().cloned() else {
+            return Ok(left);
+        };
+        let op_column = self.column();
+        self.next();
+        let right_column = self.column();
+        let right = self.primary()?;
+        let (left_type, right_type) = (type_of(&left), type_of(&right));
+        match op {
+            Op::Lt | Op::Le | Op::Gt | Op::Ge => {
+                self.expect_type(left_column, &left, Type::Number)?;
+                self.expect_type(right_column, &right, Type::Number)?;
+            }
+            Op::Eq | Op::Ne if left_type != right_type => {
+                return Err(error(
+                    op_column,
+                    format!(
+                        "cannot compare a {} with a {} using '{}'",
+                        left_type,
+                        right_type,
+                        op.as_str()
+                    ),
+                ));
+            }
+            Op::Eq | Op::Ne => {}
+            Op::Contains => {
+                self.expect_type(left_column, &left, Type::String)?;
+                self.expect_type(right_column, &right, Type::String)?;
+            }
+            Op::Matches => {
+                self.expect_type(left_column, &left, Type::String)?;
+                let Expr::Literal(Literal::Str(pattern)) = &right else {
+                    return Err(error(
+                        right_column,
+                        "'matches' needs a string literal pattern",
+                    ));
+                };
+                let regex = Regex::new(pattern)
+                    .map_err(|e| error(right_column, format!("invalid regex: {}", e)))?;
+                return Ok(Expr::Matches(Box::new(left), regex));
+            }
+        }
+        Ok(Expr::Compare(Box::new(left), op, Box::new(right)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let column = self.column();
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Literal(Literal::Number(value))),
+            Some(Token::Str(value)) => Ok(Expr::Literal(Literal::Str(value))),
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Literal::Bool(true))),
+                "false" => Ok(Expr::Literal(Literal::Bool(false))),
+                _ if variable_type(&name).is_some() => Ok(Expr::Var(name)),
+                _ => Err(error(column, format!("unknown variable '{}'", name))),
+            },
+            Some(Token::LParen) => {
+                let inner = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => Err(error(self.column_of_previous(), "expected ')'")),
+                }
+            }
+            Some(token) => Err(error(column, format!("unexpected {}", describe(&token)))),
+            None => Err(error(column, "unexpected end of condition")),
+        }
+    }
+
+    fn column_of_previous(&self) -> usize {
+        self.tokens
+            .get(self.position - 1)
+            .map_or(self.end_column, |(column, _)| *column)
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(value) => format!("number {}", value),
+        Token::Str(value) => format!("string \"{}\"", value),
+        Token::Ident(name) => format!("'{}'", name),
+        Token::And => "'&&'".to_string(),
+        Token::Or => "'||'".to_string(),
+        Token::Not => "'!'".to_string(),
+        Token::Op(op) => format!("'{}'", op.as_str()),
+        Token::LParen => "'('".to_string(),
+        Token::RParen => "')'".to_string(),
+    }
+}
+
+fn variable_type(name: &str) -> Option<Type> {
+    if name
+        .strip_prefix(TAG_PREFIX)
+        .is_some_and(|tag| !tag.is_empty())
+    {
+        return Some(Type::String);
+    }
+    VARIABLES
+        .iter()
+        .find(|(variable, _)| *variable == name)
+        .map(|(_, kind)| *kind)
+}
+
+fn type_of(expr: &Expr) -> Type {
+    match expr {
+        Expr::Literal(Literal::Bool(_)) => Type::Bool,
+        Expr::Literal(Literal::Number(_)) => Type::Number,
+        Expr::Literal(Literal::Str(_)) => Type::String,
+        Expr::Var(name) => variable_type(name).unwrap_or(Type::String),
+        Expr::Not(_) | Expr::And(..) | Expr::Or(..) | Expr::Compare(..) | Expr::Matches(..) => {
+            Type::Bool
+        }
+    }
+}
+
+/// Request attributes conditions are evaluated against.
+#[derive(Debug, Clone, Default)]
+pub struct Facts {
+    pub tokens: u64,
+    pub prompt_tokens: u64,
+    pub max_tokens: u64,
+    pub messages: u64,
+    pub has_tools: bool,
+    pub has_images: bool,
+    pub stream: bool,
+    pub last_message: String,
+    /// Characters in `last_message`.
+    pub last_message_length: u64,
+    pub model: String,
+    pub tags: BTreeMap<String, String>,
+}
+
+impl Facts {
+    pub fn from_request(json: &Value, tags: &BTreeMap<String, String>) -> Self {
+        let messages = json
+            .get("messages")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let has_images = messages.iter().any(|message| {
+            message
+                .get("content")
+                .and_then(Value::as_array)
+                .is_some_and(|parts| parts.iter().any(|part| part["type"] == "image_url"))
+        });
+        let last_message = match json.get("prompt") {
+            Some(Value::Array(prompts)) => prompts
+                .iter()
+                .map(content_text)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Some(prompt) => content_text(prompt),
+            None => messages
+                .iter()
+                .rev()
+                .find(|message| message["role"] == "user")
+                .map(|message| content_text(&message["content"]))
+                .unwrap_or_default(),
+        };
+        let non_empty = |key: &str| {
+            json.get(key)
+                .and_then(Value::as_array)
+                .is_some_and(|items| !items.is_empty())
+        };
+        Self {
+            tokens: context::required_tokens(json),
+            prompt_tokens: cost::estimate_prompt_tokens(json),
+            max_tokens: json
+                .get("max_tokens")
+                .or_else(|| json.get("max_completion_tokens"))
+                .and_then(Value::as_u64)
+                .unwrap_or(0),
+            messages: messages.len() as u64,
+            has_tools: non_empty("tools") || non_empty("functions"),
+            has_images,
+            stream: json["stream"].as_bool().unwrap_or(false),
+            last_message_length: last_message.chars().count() as u64,
+            last_message,
+            model: json["model"].as_str().unwrap_or_default().to_string(),
+            tags: tags.clone(),
+        }
+    }
+}
+
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Evaluated<'a> {
+    Bool(bool),
+    Number(f64),
+    Str(&'a str),
+}
+
+fn variable<'a>(name: &str, facts: &'a Facts) -> Evaluated<'a> {
+    if let Some(tag) = name.strip_prefix(TAG_PREFIX) {
+        return Evaluated::Str(facts.tags.get(tag).map_or("", String::as_str));
+    }
+    match name {
+        "tokens" => Evaluated::Number(facts.tokens as f64),
+        "prompt_tokens" => Evaluated::Number(facts.prompt_tokens as f64),
+        "max_tokens" => Evaluated::Number(facts.max_tokens as f64),
+        "messages" => Evaluated::Number(facts.messages as f64),
+        "has_tools" => Evaluated::Bool(facts.has_tools),
+        "has_images" => Evaluated::Bool(facts.has_images),
+        "stream" => Evaluated::Bool(facts.stream),
+        "last_message" => Evaluated::Str(&facts.last_message),
+        "last_message_length" => Evaluated::Number(facts.last_message_length as f64),
+        "model" => Evaluated::Str(&facts.model),
+        // Rejected by the parser.
+        _ => Evaluated::Str(""),
+    }
+}
+
+fn evaluate<'a>(expr: &'a Expr, facts: &'a Facts) -> Evaluated<'a> {
+    let truthy = |expr| evaluate(expr, facts) == Evaluated::Bool(true);
+    match expr {
+        Expr::Literal(Literal::Bool(value)) => Evaluated::Bool(*value),
+        Expr::Literal(Literal::Number(value)) => Evaluated::Number(*value),
+        Expr::Literal(Literal::Str(value)) => Evaluated::Str(value),
+        Expr::Var(name) => variable(name, facts),
+        Expr::Not(operand) => Evaluated::Bool(!truthy(operand)),
+        Expr::And(left, right) => Evaluated::Bool(truthy(left) && truthy(right)),
+        Expr::Or(left, right) => Evaluated::Bool(truthy(left) || truthy(right)),
+        Expr::Matches(left, regex) => match evaluate(left, facts) {
+            Evaluated::Str(text) => Evaluated::Bool(regex.is_match(text)),
+            _ => Evaluated::Bool(false),
+        },
+        Expr::Compare(left, op, right) => {
+            let result = match (evaluate(left, facts), evaluate(right, facts)) {
+                (Evaluated::Number(a), Evaluated::Number(b)) => match op {
+                    Op::Eq => a == b,
+                    Op::Ne => a != b,
+                    Op::Lt => a < b,
+                    Op::Le => a <= b,
+                    Op::Gt => a > b,
+                    Op::Ge => a >= b,
+                    Op::Contains | Op::Matches => false,
+                },
+                (Evaluated::Str(a), Evaluated::Str(b)) => match op {
+                    Op::Eq => a == b,
+                    Op::Ne => a != b,
+                    Op::Contains => a.contains(b),
+                    _ => false,
+                },
+                (Evaluated::Bool(a), Evaluated::Bool(b)) => match op {
+                    Op::Eq => a == b,
+                    Op::Ne => a != b,
+                    _ => false,
+                },
+                _ => false,
+            };
+            Evaluated::Bool(result)
+        }
+    }
+}
+
+/// A parsed and type checked condition. (De)serializes as its source text.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let tokens = tokenize(source)?;
+        if tokens.is_empty() {
+            return Err(error(1, "empty condition"));
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            end_column: source.chars().count() + 1,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(error(
+                parser.column(),
+                format!("unexpected {}", describe(token)),
+            ));
+        }
+        parser.expect_type(1, &expr, Type::Bool)?;
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn evaluate(&self, facts: &Facts) -> bool {
+        evaluate(&self.expr, facts) == Evaluated::Bool(true)
+    }
+}
+
+impl PartialEq for Condition {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Serialize for Condition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Condition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Condition::parse(&source)
+            .map_err(|e| serde::de::Error::custom(format!("invalid condition `{}`: {}", source, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_and_evaluate() {
+        let request = json!({
+            "model": "",
+            "messages": [{"role": "user", "content": "fix this ```rust\nfn main() {}```"}],
+            "tools": [{"type": "function"}],
+            "max_tokens": 5000
+        });
+        let mut tags = BTreeMap::new();
+        tags.insert("team".to_string(), "search".to_string());
+        let facts = Facts::from_request(&request, &tags);
+
+        let matches = |source: &str| Condition::parse(source).unwrap().evaluate(&facts);
+        assert!(matches("tokens > 4000 && has_tools"));
+        assert!(!matches("tokens > 4000 && !has_tools"));
+        assert!(matches("(stream || messages >= 1) && max_tokens == 5_000"));
+        assert!(matches(
+            "last_message matches '```' && last_message contains \"rust\""
+        ));
+        assert!(matches("tags.team == 'search' && tags.missing == ''"));
+        assert!(matches("last_message_length == 32"));
+        assert!(matches("last_message matches '(?i)(RUST|go)'"));
+
+        let error = |source: &str| Condition::parse(source).unwrap_err();
+        assert_eq!(error("tokens > && has_tools").column, 10);
+        assert_eq!(
+            error("tokens && has_tools").message,
+            "expected a boolean but found a number"
+        );
+        assert_eq!(error("has_tool").message, "unknown variable 'has_tool'");
+        assert_eq!(error("tokens").column, 1);
+        assert_eq!(error("(has_tools").message, "expected ')'");
+        assert!(error("model matches '('")
+            .message
+            .starts_with("invalid regex"));
+        assert_eq!(error("has_tools == 1").column, 11);
+
+        let yaml: std::result::Result<Condition, _> = serde_yaml::from_str("\"tokens >\"");
+        assert!(yaml
+            .unwrap_err()
+            .to_string()
+            .contains("invalid condition `tokens >`: unexpected end of condition at column 9"));
+    }
+}
@@ -0,0 +1,48 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lib
+//!
+//! Routing, policy, configuration and metrics types shared by the gateway
+//! and the client crates. Error responses for the hyper server and the
+//! modules hashing caller data are behind the `server` feature, and the
+//! candle classifier and Hugging Face tokenizers behind `local-models`; only
+//! the gateway enables them.
+
+#[cfg(feature = "server")]
+pub mod caller;
+pub mod config;
+pub mod context;
+pub mod cost;
+#[cfg(feature = "server")]
+pub mod decision_cache;
+pub mod error;
+pub mod expr;
+pub mod limits;
+#[cfg(feature = "local-models")]
+pub mod local_classifier;
+pub mod metrics;
+pub mod pii;
+#[cfg(feature = "server")]
+pub mod privacy;
+pub mod reasoning;
+pub mod request_context;
+pub mod retryability;
+pub mod stats;
+#[cfg(feature = "server")]
+pub mod tags;
+#[cfg(feature = "local-models")]
+pub mod tokenizer;
+pub mod triton;
@@ -0,0 +1,163 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Limits
+//!
+//! Per-LLM bounds on `max_tokens` and `temperature`, for models that fail
+//! or degrade outside them. Requests are brought within the bounds, or
+//! rejected, before they are forwarded.
+use crate::config::{Llm, LlmLimitAction, Policy};
+use crate::error::GatewayApiError;
+use crate::metrics::PARAMETER_LIMITS;
+use http::StatusCode;
+use log::{info, warn};
+use serde_json::{json, Value};
+
+fn out_of_range(policy: &Policy, llm: &Llm, parameter: &str, message: String) -> GatewayApiError {
+    warn!(
+        "request rejected: policy={} llm={} parameter={}",
+        policy.name, llm.name, parameter
+    );
+    PARAMETER_LIMITS
+        .with_label_values(&[&policy.name, &llm.name, parameter, "rejected"])
+        .inc();
+    GatewayApiError::client_error(StatusCode::BAD_REQUEST, message, "llm_limit_exceeded")
+}
+
+fn clamped(policy: &Policy, llm: &Llm, parameter: &str, requested: &Value, value: &Value) {
+    info!(
+        "{} clamped: policy={} llm={} requested={} clamped={}",
+        parameter, policy.name, llm.name, requested, value
+    );
+    PARAMETER_LIMITS
+        .with_label_values(&[&policy.name, &llm.name, parameter, "clamped"])
+        .inc();
+}
+
+/// Enforces the `limits` of `llm` on `json`. `max_tokens` (or
+/// `max_completion_tokens`) over `max_output_tokens` and a `temperature`
+/// outside `min_temperature`..`max_temperature` are clamped to the bound,
+/// or rejected with the `reject` action. Requests without `max_tokens` are
+/// given `max_output_tokens`.
+pub fn enforce(mut json: Value, policy: &Policy, llm: &Llm) -> Result<Value, GatewayApiError> {
+    let Some(limits) = llm.limits.filter(|_| json.is_object()) else {
+        return Ok(json);
+    };
+    let reject = limits.action == LlmLimitAction::Reject;
+
+    if let Some(limit) = limits.max_output_tokens {
+        let parameter = ["max_tokens", "max_completion_tokens"]
+            .into_iter()
+            .find(|parameter| json.get(parameter).is_some_and(|value| !value.is_null()))
+            .unwrap_or("max_tokens");
+        match json.get(parameter).and_then(Value::as_u64) {
+            Some(requested) if requested > limit && reject => {
+                let message = format!(
+                    "{} {} exceeds the limit of {} of LLM '{}'",
+                    parameter, requested, limit, llm.name
+                );
+                return Err(out_of_range(policy, llm, parameter, message));
+            }
+            Some(requested) if requested > limit => {
+                clamped(policy, llm, parameter, &json!(requested), &json!(limit));
+                json[parameter] = json!(limit);
+            }
+            Some(_) => {}
+            None => json[parameter] = json!(limit),
+        }
+    }
+
+    if let Some(requested) = json.get("temperature").and_then(Value::as_f64) {
+        let min = limits.min_temperature.unwrap_or(f64::MIN);
+        let max = limits.max_temperature.unwrap_or(f64::MAX);
+        if !(min..=max).contains(&requested) {
+            if reject {
+                let message = format!(
+                    "temperature {} is outside the range {} to {} of LLM '{}'",
+                    requested,
+                    limits.min_temperature.unwrap_or(0.0),
+                    limits.max_temperature.unwrap_or(2.0),
+                    llm.name
+                );
+                return Err(out_of_range(policy, llm, "temperature", message));
+            }
+            let temperature = requested.clamp(min, max);
+            clamped(
+                policy,
+                llm,
+                "temperature",
+                &json!(requested),
+                &json!(temperature),
+            );
+            json["temperature"] = json!(temperature);
+        }
+    }
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LlmLimits;
+
+    fn llm(action: LlmLimitAction) -> Llm {
+        Llm {
+            name: "Chatbot".to_string(),
+            limits: Some(LlmLimits {
+                max_output_tokens: Some(1_000),
+                min_temperature: Some(0.2),
+                max_temperature: Some(1.0),
+                action,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_clamp() {
+        let policy = Policy::default();
+        let llm = llm(LlmLimitAction::Clamp);
+        let json = enforce(
+            json!({"max_tokens": 4_000, "temperature": 1.5}),
+            &policy,
+            &llm,
+        )
+        .unwrap();
+        assert_eq!(json, json!({"max_tokens": 1_000, "temperature": 1.0}));
+
+        let json = enforce(json!({"temperature": 0.0}), &policy, &llm).unwrap();
+        assert_eq!(json, json!({"max_tokens": 1_000, "temperature": 0.2}));
+
+        let json = enforce(json!({"max_completion_tokens": 500}), &policy, &llm).unwrap();
+        assert_eq!(json, json!({"max_completion_tokens": 500}));
+
+        let unlimited = Llm::default();
+        let json = enforce(json!({"max_tokens": 4_000}), &policy, &unlimited).unwrap();
+        assert_eq!(json["max_tokens"], 4_000);
+    }
+
+    #[test]
+    fn test_reject() {
+        let policy = Policy::default();
+        let llm = llm(LlmLimitAction::Reject);
+        let error = enforce(json!({"max_tokens": 4_000}), &policy, &llm).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("max_tokens 4000 exceeds the limit of 1000"));
+        let error = enforce(json!({"temperature": 1.5}), &policy, &llm).unwrap_err();
+        assert!(error.to_string().contains("outside the range 0.2 to 1"));
+        assert!(enforce(json!({"max_tokens": 10, "temperature": 0.5}), &policy, &llm).is_ok());
+    }
+}
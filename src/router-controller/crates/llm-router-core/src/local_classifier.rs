@@ -0,0 +1,248 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local Classifier
+//!
+//! Runs a BERT-style sequence classification model in process with candle,
+//! for edge deployments without Triton. Models are read as `transformers`
+//! saves them: `config.json`, `tokenizer.json` and `model.safetensors`,
+//! with the head in the `bert.pooler.dense` and `classifier` weights.
+//! Each model is loaded once, on first use or by `preload`, and runs on
+//! the CPU in a blocking task.
+use crate::config::{ClassifierConfig, Policy};
+use crate::error::GatewayApiError;
+use candle_core::{DType, Device, IndexOp, Tensor, D};
+use candle_nn::{linear, Linear, Module, VarBuilder};
+use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+use hf_hub::api::sync::Api;
+use hf_hub::{Repo, RepoType};
+use lazy_static::lazy_static;
+use log::{error, info};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokenizers::{Tokenizer, TruncationParams};
+
+lazy_static! {
+    /// Loaded models by location and input limit.
+    static ref MODELS: Mutex<HashMap<String, Arc<LocalModel>>> = Mutex::new(HashMap::new());
+}
+
+struct LocalModel {
+    tokenizer: Tokenizer,
+    bert: BertModel,
+    pooler: Linear,
+    classifier: Linear,
+    /// Multi-label models score each class independently.
+    sigmoid: bool,
+    device: Device,
+}
+
+fn fail(context: &str, e: impl Display) -> String {
+    format!("{}: {}", context, e)
+}
+
+/// `config.json`, `tokenizer.json` and `model.safetensors`, downloading
+/// them from the hub when no local path is configured.
+fn model_files(config: &ClassifierConfig) -> Result<[PathBuf; 3], String> {
+    const FILES: [&str; 3] = ["config.json", "tokenizer.json", "model.safetensors"];
+    if let Some(path) = &config.path {
+        return Ok(FILES.map(|file| Path::new(path).join(file)));
+    }
+    let repo = config
+        .repo
+        .as_ref()
+        .ok_or_else(|| "classifier has neither a path nor a repo".to_string())?;
+    let api = Api::new().map_err(|e| fail("Hugging Face hub", e))?;
+    let repo = api.repo(Repo::with_revision(
+        repo.clone(),
+        RepoType::Model,
+        config.revision.clone(),
+    ));
+    let mut paths = Vec::with_capacity(FILES.len());
+    for file in FILES {
+        paths.push(repo.get(file).map_err(|e| fail(file, e))?);
+    }
+    paths
+        .try_into()
+        .map_err(|_| "unexpected number of model files".to_string())
+}
+
+fn load(config: &ClassifierConfig) -> Result<LocalModel, String> {
+    let [config_file, tokenizer_file, weights_file] = model_files(config)?;
+    let raw: Value = std::fs::read_to_string(&config_file)
+        .map_err(|e| fail("config.json", e))
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| fail("config.json", e)))?;
+    let bert_config: BertConfig =
+        serde_json::from_value(raw.clone()).map_err(|e| fail("config.json", e))?;
+    let hidden_size = raw["hidden_size"].as_u64().unwrap_or(0) as usize;
+    let num_labels = raw["id2label"].as_object().map_or(0, |labels| labels.len());
+    if hidden_size == 0 || num_labels == 0 {
+        return Err("config.json needs hidden_size and id2label".to_string());
+    }
+
+    let mut tokenizer =
+        Tokenizer::from_file(&tokenizer_file).map_err(|e| fail("tokenizer.json", e))?;
+    tokenizer
+        .with_truncation(Some(TruncationParams {
+            max_length: config.max_tokens,
+            ..Default::default()
+        }))
+        .map_err(|e| fail("tokenizer.json", e))?;
+
+    let device = Device::Cpu;
+    let weights = std::fs::read(&weights_file).map_err(|e| fail("model.safetensors", e))?;
+    let vb = VarBuilder::from_buffered_safetensors(weights, DType::F32, &device)
+        .map_err(|e| fail("model.safetensors", e))?;
+    let bert =
+        BertModel::load(vb.clone(), &bert_config).map_err(|e| fail("model.safetensors", e))?;
+    let pooler = linear(hidden_size, hidden_size, vb.pp("bert.pooler.dense"))
+        .map_err(|e| fail("model.safetensors", e))?;
+    let classifier = linear(hidden_size, num_labels, vb.pp("classifier"))
+        .map_err(|e| fail("model.safetensors", e))?;
+    Ok(LocalModel {
+        tokenizer,
+        bert,
+        pooler,
+        classifier,
+        sigmoid: raw["problem_type"] == "multi_label_classification",
+        device,
+    })
+}
+
+impl LocalModel {
+    fn scores(&self, text: &str) -> Result<Vec<f64>, String> {
+        let encoding = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(|e| fail("tokenizer", e))?;
+        let tensor = |values: &[u32]| {
+            Tensor::new(values, &self.device).and_then(|tensor| tensor.unsqueeze(0))
+        };
+        let scores = (|| {
+            let input_ids = tensor(encoding.get_ids())?;
+            let type_ids = tensor(encoding.get_type_ids())?;
+            let attention_mask = tensor(encoding.get_attention_mask())?;
+            let hidden = self
+                .bert
+                .forward(&input_ids, &type_ids, Some(&attention_mask))?;
+            let pooled = self.pooler.forward(&hidden.i((.., 0))?)?.tanh()?;
+            let logits = self.classifier.forward(&pooled)?;
+            let scores = if self.sigmoid {
+                candle_nn::ops::sigmoid(&logits)?
+            } else {
+                candle_nn::ops::softmax(&logits, D::Minus1)?
+            };
+            scores.squeeze(0)?.to_dtype(DType::F64)?.to_vec1::<f64>()
+        })();
+        scores.map_err(|e| fail("inference", e))
+    }
+}
+
+fn model_key(config: &ClassifierConfig) -> String {
+    format!(
+        "{}@{}:{}",
+        config
+            .path
+            .as_deref()
+            .or(config.repo.as_deref())
+            .unwrap_or_default(),
+        config.revision,
+        config.max_tokens
+    )
+}
+
+/// The model for `config`, loading it on first use. Loads are serialized so
+/// concurrent first requests do not load a model twice.
+fn model(config: &ClassifierConfig) -> Result<Arc<LocalModel>, String> {
+    let key = model_key(config);
+    let mut models = MODELS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(model) = models.get(&key) {
+        return Ok(model.clone());
+    }
+    info!("Loading local classifier {}", key);
+    let model = Arc::new(load(config)?);
+    models.insert(key, model.clone());
+    Ok(model)
+}
+
+/// Class probabilities for `text_input` from the policy's local model.
+pub async fn infer(
+    config: &ClassifierConfig,
+    text_input: &str,
+) -> Result<Vec<f64>, GatewayApiError> {
+    let config = config.clone();
+    let text_input = text_input.to_string();
+    let scores = tokio::task::spawn_blocking(move || model(&config)?.scores(&text_input))
+        .await
+        .map_err(|e| fail("inference task", e))
+        .and_then(|scores| scores)
+        .map_err(|message| {
+            error!("Local classifier failed: {}", message);
+            GatewayApiError::TritonServiceError {
+                status_code: 500,
+                message: format!("Local classifier error: {}", message),
+            }
+        })?;
+    info!("Local classifier scores: {:?}", scores);
+    Ok(scores)
+}
+
+/// Loads the local models of `policies` ahead of their first request, so
+/// hub downloads do not hold up traffic. Failures are logged and retried on
+/// first use.
+pub fn preload(policies: &[Policy]) {
+    for policy in policies {
+        let Some(config) = policy.local_classifier().cloned() else {
+            continue;
+        };
+        let name = policy.name.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = model(&config) {
+                error!(
+                    "Failed to load local classifier of policy '{}': {}",
+                    name, e
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClassifierType;
+
+    #[tokio::test]
+    async fn test_missing_model_is_a_classifier_error() {
+        let config = ClassifierConfig {
+            kind: ClassifierType::Local,
+            path: Some("/nonexistent/router-model".to_string()),
+            ..Default::default()
+        };
+        match infer(&config, "Hello world!").await {
+            Err(GatewayApiError::TritonServiceError {
+                status_code,
+                message,
+            }) => {
+                assert_eq!(status_code, 500);
+                assert!(message.contains("config.json"));
+            }
+            other => panic!("expected a classifier error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,710 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use lazy_static::lazy_static;
+use prometheus::{
+    register_counter_vec, register_gauge_vec, register_histogram_vec, register_int_counter_vec,
+    register_int_gauge, register_int_gauge_vec, CounterVec, GaugeVec, HistogramVec, IntCounterVec,
+    IntGauge, IntGaugeVec,
+};
+use serde_json::Value;
+use std::sync::{OnceLock, RwLock};
+
+/// Labels shared by every per-request metric so series can be joined in
+/// Grafana on `policy`, `model`, `strategy` and `tenant`. Configured custom
+/// labels follow them.
+const BASE_REQUEST_LABELS: &[&str] = &["policy", "model", "strategy", "tenant"];
+
+pub const UNKNOWN_LABEL: &str = "unknown";
+
+static CUSTOM_LABELS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Declares the `custom_metric_labels` added to every per-request metric.
+/// Label names are fixed once the metrics are registered, so this must run
+/// at startup; returns `false` if the names were already set.
+pub fn set_custom_labels(names: Vec<String>) -> bool {
+    CUSTOM_LABELS.set(names).is_ok()
+}
+
+/// Names of the custom labels per-request metrics were registered with.
+pub fn custom_labels() -> &'static [String] {
+    CUSTOM_LABELS.get().map_or(&[], Vec::as_slice)
+}
+
+/// Label names of a per-request metric: the shared labels, the custom
+/// labels, then the metric's own.
+fn request_labels(extra: &[&'static str]) -> Vec<&'static str> {
+    BASE_REQUEST_LABELS
+        .iter()
+        .copied()
+        .chain(custom_labels().iter().map(String::as_str))
+        .chain(extra.iter().copied())
+        .collect()
+}
+
+lazy_static! {
+    pub static ref NUM_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "num_requests",
+        "Total number of requests",
+        &request_labels(&[])
+    )
+    .expect("Failed to create num_requests counter vector");
+
+    pub static ref REQUESTS_PER_POLICY: IntCounterVec = register_int_counter_vec!(
+        "requests_per_policy",
+        "Total number of requests per policy",
+        &request_labels(&[])
+    )
+    .expect("Failed to create requests_per_policy counter vector");
+
+    pub static ref REQUESTS_PER_MODEL: IntCounterVec = register_int_counter_vec!(
+        "requests_per_model",
+        "Total number of requests per model",
+        &request_labels(&[])
+    )
+    .expect("Failed to create requests_per_model counter vector");
+
+    pub static ref REQUEST_LATENCY: HistogramVec = register_histogram_vec!(
+        "request_latency_seconds",
+        "Latency of processing requests in seconds",
+        &request_labels(&[])
+    )
+    .expect("Failed to create request_latency histogram vector");
+
+    pub static ref REQUEST_SUCCESS: IntCounterVec = register_int_counter_vec!(
+        "request_success_total",
+        "Total successful requests",
+        &request_labels(&[])
+    )
+    .expect("Failed to create request_success counter vector");
+
+    pub static ref REQUEST_FAILURE: IntCounterVec = register_int_counter_vec!(
+        "request_failure_total",
+        "Total failed requests, broken down by error type (4XX, 5XX, other)",
+        &request_labels(&["error_type"])
+    )
+    .expect("Failed to create request_failure counter vector");
+
+    pub static ref ROUTING_POLICY_USAGE: IntCounterVec = register_int_counter_vec!(
+        "routing_policy_usage",
+        "Number of times each routing policy was used",
+        &request_labels(&[])
+    )
+    .expect("Failed to create routing_policy_usage counter vector");
+
+    pub static ref MODEL_SELECTION_TIME: HistogramVec = register_histogram_vec!(
+        "model_selection_time_seconds",
+        "Time (in seconds) taken for model selection (e.g., by Triton)",
+        &request_labels(&[])
+    )
+    .expect("Failed to create model_selection_time histogram vector");
+
+    pub static ref LLM_RESPONSE_TIME: HistogramVec = register_histogram_vec!(
+        "llm_response_time_seconds",
+        "Response time (in seconds) for each LLM",
+        &request_labels(&[])
+    )
+    .expect("Failed to create llm_response_time histogram vector");
+
+    pub static ref TIME_TO_FIRST_TOKEN: HistogramVec = register_histogram_vec!(
+        "llm_time_to_first_token_seconds",
+        "Time (in seconds) from receiving a streamed request to the first completion text",
+        &request_labels(&[]),
+        vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0]
+    )
+    .expect("Failed to create llm_time_to_first_token_seconds histogram vector");
+
+    pub static ref STREAM_TOKENS_PER_SECOND: HistogramVec = register_histogram_vec!(
+        "llm_stream_tokens_per_second",
+        "Completion tokens per second of a streamed response, after its first token",
+        &request_labels(&[]),
+        vec![5.0, 10.0, 20.0, 40.0, 60.0, 80.0, 100.0, 150.0, 200.0, 400.0]
+    )
+    .expect("Failed to create llm_stream_tokens_per_second histogram vector");
+
+    pub static ref TOKEN_USAGE: IntCounterVec = register_int_counter_vec!(
+        "llm_token_usage",
+        "Token usage per LLM category, split by streaming and finish reason",
+        &request_labels(&["category", "stream", "finish_reason"])
+    )
+    .expect("Failed to create llm_token_usage counter vector");
+
+    pub static ref LLM_COST_USD: CounterVec = register_counter_vec!(
+        "llm_cost_usd_total",
+        "Spend in USD on LLMs with pricing, from the usage of each completion",
+        &["policy", "model", "tenant"]
+    )
+    .expect("Failed to create llm_cost_usd_total counter vector");
+
+    pub static ref SPEND_ALERTS: IntCounterVec = register_int_counter_vec!(
+        "llm_spend_alerts_total",
+        "Spend alerts whose daily limit was crossed",
+        &["alert"]
+    )
+    .expect("Failed to create llm_spend_alerts_total counter vector");
+
+    pub static ref SPEND_DOWNGRADES: IntCounterVec = register_int_counter_vec!(
+        "llm_spend_downgrades_total",
+        "Requests served by the downgrade_to LLM of a spend alert over its limit",
+        &["alert"]
+    )
+    .expect("Failed to create llm_spend_downgrades_total counter vector");
+
+    pub static ref CONTEXT_OVERFLOWS: IntCounterVec = register_int_counter_vec!(
+        "llm_context_overflows_total",
+        "Requests over the max_context of the chosen LLM, by the context_overflow action taken",
+        &["policy", "llm", "action"]
+    )
+    .expect("Failed to create llm_context_overflows_total counter vector");
+
+    pub static ref PARAMETER_LIMITS: IntCounterVec = register_int_counter_vec!(
+        "llm_parameter_limits_total",
+        "Request parameters outside the limits of the chosen LLM, by whether they were clamped or rejected",
+        &["policy", "llm", "parameter", "action"]
+    )
+    .expect("Failed to create llm_parameter_limits_total counter vector");
+
+    pub static ref PROXY_OVERHEAD_LATENCY: HistogramVec = register_histogram_vec!(
+        "proxy_overhead_latency_seconds",
+        "Overhead latency of the proxy, calculated as overall latency minus model selection and LLM response time",
+        &request_labels(&[])
+    )
+    .expect("Failed to create proxy_overhead_latency histogram vector");
+
+    pub static ref UPSTREAM_RETRIES: IntCounterVec = register_int_counter_vec!(
+        "llm_upstream_retries_total",
+        "Upstream LLM calls retried, by the status code or error that triggered the retry",
+        &request_labels(&["reason"])
+    )
+    .expect("Failed to create llm_upstream_retries_total counter vector");
+
+    pub static ref CLIENT_DISCONNECTS: IntCounterVec = register_int_counter_vec!(
+        "llm_client_disconnects_total",
+        "Requests whose client disconnected before the response was complete, by the stage the request was cancelled in",
+        &request_labels(&["stage"])
+    )
+    .expect("Failed to create llm_client_disconnects_total counter vector");
+
+    pub static ref CIRCUIT_BREAKER_STATE: IntGaugeVec = register_int_gauge_vec!(
+        "llm_circuit_breaker_state",
+        "Circuit breaker state per upstream LLM (0 closed, 1 half-open, 2 open)",
+        &["policy", "model"]
+    )
+    .expect("Failed to create llm_circuit_breaker_state gauge vector");
+
+    pub static ref EVENT_SPOOL_BYTES: IntGauge = register_int_gauge!(
+        "event_spool_bytes",
+        "Size in bytes of usage and audit events spooled to disk awaiting delivery"
+    )
+    .expect("Failed to create event_spool_bytes gauge");
+
+    pub static ref EVENT_SPOOL_SEGMENTS: IntGauge = register_int_gauge!(
+        "event_spool_segments",
+        "Number of event batches spooled to disk awaiting delivery"
+    )
+    .expect("Failed to create event_spool_segments gauge");
+
+    pub static ref EVENTS_DROPPED: IntCounterVec = register_int_counter_vec!(
+        "events_dropped_total",
+        "Usage and audit events dropped before delivery, by reason",
+        &["reason"]
+    )
+    .expect("Failed to create events_dropped_total counter vector");
+
+    pub static ref UPSTREAM_DNS_TIME: HistogramVec = register_histogram_vec!(
+        "upstream_dns_resolution_seconds",
+        "Time (in seconds) to resolve an upstream host",
+        &["host"]
+    )
+    .expect("Failed to create upstream_dns_resolution_seconds histogram vector");
+
+    pub static ref UPSTREAM_CONNECT_TIME: HistogramVec = register_histogram_vec!(
+        "upstream_connect_seconds",
+        "Time (in seconds) to open a new upstream connection, TCP connect plus TLS handshake, excluding DNS",
+        &["host"]
+    )
+    .expect("Failed to create upstream_connect_seconds histogram vector");
+
+    pub static ref UPSTREAM_NEW_CONNECTIONS: IntCounterVec = register_int_counter_vec!(
+        "upstream_new_connections_total",
+        "New upstream connections opened (requests reusing a pooled connection are not counted)",
+        &["host", "outcome"]
+    )
+    .expect("Failed to create upstream_new_connections_total counter vector");
+
+    pub static ref UPSTREAM_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "upstream_requests_total",
+        "Upstream responses by HTTP version; requests beyond upstream_new_connections_total reused a connection",
+        &["host", "version"]
+    )
+    .expect("Failed to create upstream_requests_total counter vector");
+
+    pub static ref UPSTREAM_ACTIVE_STREAMS: IntGaugeVec = register_int_gauge_vec!(
+        "upstream_active_streams",
+        "Upstream requests in flight, streams included, until their response is dropped",
+        &["host"]
+    )
+    .expect("Failed to create upstream_active_streams gauge vector");
+
+    pub static ref RATE_LIMITED_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "llm_rate_limited_requests_total",
+        "Requests refused by the per-caller rate limit",
+        &["key_by"]
+    )
+    .expect("Failed to create llm_rate_limited_requests_total counter vector");
+
+    pub static ref ADMISSION_SHED: IntCounterVec = register_int_counter_vec!(
+        "llm_admission_shed_total",
+        "Requests refused with 503 because the gateway was at its in-flight limit",
+        &["reason", "priority"]
+    )
+    .expect("Failed to create llm_admission_shed_total counter vector");
+
+    pub static ref ADMISSION_IN_FLIGHT: IntGauge = register_int_gauge!(
+        "llm_admission_in_flight",
+        "Completion requests admitted and not yet answered"
+    )
+    .expect("Failed to create llm_admission_in_flight gauge");
+
+    pub static ref ADMISSION_QUEUE_DEPTH: IntGauge = register_int_gauge!(
+        "llm_admission_queue_depth",
+        "Completion requests waiting to be admitted"
+    )
+    .expect("Failed to create llm_admission_queue_depth gauge");
+
+    pub static ref TOKEN_BUDGET_EXCEEDED: IntCounterVec = register_int_counter_vec!(
+        "llm_token_budget_exceeded_total",
+        "Requests from callers over their token budget",
+        &["action"]
+    )
+    .expect("Failed to create llm_token_budget_exceeded_total counter vector");
+
+    pub static ref CLASSIFIER_CACHE_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "llm_classifier_cache_requests_total",
+        "Classifier cache lookups",
+        &["policy", "outcome"]
+    )
+    .expect("Failed to create llm_classifier_cache_requests_total counter vector");
+
+    pub static ref CLASSIFIER_CACHE_ENTRIES: IntGauge = register_int_gauge!(
+        "llm_classifier_cache_entries",
+        "Classifications held in the classifier cache"
+    )
+    .expect("Failed to create llm_classifier_cache_entries gauge");
+
+    pub static ref SLO_BURN_RATE: GaugeVec = register_gauge_vec!(
+        "llm_slo_burn_rate",
+        "Error budget burn rate per policy objective and window",
+        &["policy", "objective", "window_seconds"]
+    )
+    .expect("Failed to create llm_slo_burn_rate gauge vector");
+
+    pub static ref SPECULATIVE_FALLBACKS: IntCounterVec = register_int_counter_vec!(
+        "llm_speculative_fallbacks_total",
+        "Speculative fallbacks started after a primary missed its soft deadline",
+        &["policy", "winner"]
+    )
+    .expect("Failed to create llm_speculative_fallbacks_total counter vector");
+
+    pub static ref UPSTREAM_RATE_LIMITS: IntCounterVec = register_int_counter_vec!(
+        "llm_upstream_rate_limits_total",
+        "Upstream responses with status 429, by the LLM that sent them",
+        &["policy", "llm"]
+    )
+    .expect("Failed to create llm_upstream_rate_limits_total counter vector");
+
+    pub static ref DEGRADED_ROUTING: IntCounterVec = register_int_counter_vec!(
+        "llm_degraded_routing_total",
+        "Routing decisions made without the classifier while it was unavailable",
+        &["policy", "source"]
+    )
+    .expect("Failed to create llm_degraded_routing_total counter vector");
+
+    pub static ref LOW_CONFIDENCE_ROUTES: IntCounterVec = register_int_counter_vec!(
+        "llm_low_confidence_routes_total",
+        "Classifier decisions whose winning score was below the request threshold",
+        &["policy", "source"]
+    )
+    .expect("Failed to create llm_low_confidence_routes_total counter vector");
+
+    pub static ref CANARY_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "llm_canary_requests_total",
+        "Requests routed to an LLM that were sent to its canary instead",
+        &["policy", "llm", "canary"]
+    )
+    .expect("Failed to create llm_canary_requests_total counter vector");
+
+    pub static ref SPLIT_ASSIGNMENTS: IntCounterVec = register_int_counter_vec!(
+        "llm_split_assignments_total",
+        "Requests assigned to each arm of a policy's traffic split",
+        &["policy", "arm"]
+    )
+    .expect("Failed to create llm_split_assignments_total counter vector");
+
+    pub static ref PII_MASKED: IntCounterVec = register_int_counter_vec!(
+        "llm_pii_masked_total",
+        "Sensitive values masked in prompts before they were sent on, by recognizer",
+        &["policy", "label"]
+    )
+    .expect("Failed to create llm_pii_masked_total counter vector");
+
+    pub static ref MODERATION_RESULTS: IntCounterVec = register_int_counter_vec!(
+        "llm_moderation_results_total",
+        "Requests checked by a policy's moderation endpoint, by outcome",
+        &["policy", "result"]
+    )
+    .expect("Failed to create llm_moderation_results_total counter vector");
+
+    pub static ref FAULTS_INJECTED: IntCounterVec = register_int_counter_vec!(
+        "llm_faults_injected_total",
+        "Upstream calls delayed, aborted or failed by fault injection",
+        &["policy", "model", "fault"]
+    )
+    .expect("Failed to create llm_faults_injected_total counter vector");
+
+    pub static ref CONFIG_RELOADS: IntCounterVec = register_int_counter_vec!(
+        "config_reloads_total",
+        "Configuration changes picked up from a watched source, by result",
+        &["source", "result"]
+    )
+    .expect("Failed to create config_reloads_total counter vector");
+
+    pub static ref BACKGROUND_TASKS_DROPPED: IntCounterVec = register_int_counter_vec!(
+        "background_tasks_dropped_total",
+        "Post-response tasks dropped because the background queue was full",
+        &["task"]
+    )
+    .expect("Failed to create background_tasks_dropped_total counter vector");
+
+    pub static ref BACKGROUND_TASK_LAG: HistogramVec = register_histogram_vec!(
+        "background_task_lag_seconds",
+        "Time (in seconds) a post-response task waited in the background queue",
+        &["task"]
+    )
+    .expect("Failed to create background_task_lag_seconds histogram vector");
+
+    pub static ref BACKGROUND_QUEUE_DEPTH: IntGauge = register_int_gauge!(
+        "background_queue_depth",
+        "Post-response tasks waiting in the background queue"
+    )
+    .expect("Failed to create background_queue_depth gauge");
+
+    pub static ref STATE_STORE_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "state_store_errors_total",
+        "State store operations that failed; the feature relying on them fails open",
+        &["backend", "operation"]
+    )
+    .expect("Failed to create state_store_errors_total counter vector");
+
+    pub static ref TAGGED_TOKEN_USAGE: IntCounterVec = register_int_counter_vec!(
+        "llm_tagged_token_usage",
+        "Token usage per request tag listed in request_tags.metric_tags",
+        &["policy", "tag", "value", "category"]
+    )
+    .expect("Failed to create llm_tagged_token_usage counter vector");
+
+    // Recording takes the read side and a reset takes the write side, so a
+    // reset never lands in the middle of a request's set of updates.
+    static ref RESET_LOCK: RwLock<()> = RwLock::new(());
+}
+
+/// The label set a single request is recorded under. Fields stay `None` until
+/// the request gets far enough to resolve them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestLabels {
+    pub policy: Option<String>,
+    pub model: Option<String>,
+    pub strategy: Option<String>,
+    /// Tenant resolved from the `tenants` section.
+    pub tenant: Option<String>,
+    /// Request tags recorded in `llm_tagged_token_usage`.
+    pub tags: Vec<(String, String)>,
+    /// Values of the custom labels, in `custom_labels()` order.
+    pub custom: Vec<String>,
+}
+
+impl RequestLabels {
+    /// Values of the shared and custom labels, in registration order.
+    pub fn values(&self) -> Vec<&str> {
+        let mut values = vec![
+            self.policy.as_deref().unwrap_or(UNKNOWN_LABEL),
+            self.model.as_deref().unwrap_or(UNKNOWN_LABEL),
+            self.strategy.as_deref().unwrap_or(UNKNOWN_LABEL),
+            self.tenant.as_deref().unwrap_or(UNKNOWN_LABEL),
+        ];
+        values.extend(
+            (0..custom_labels().len())
+                .map(|i| self.custom.get(i).map_or(UNKNOWN_LABEL, String::as_str)),
+        );
+        values
+    }
+
+    fn values_with<'a>(&'a self, extra: &[&'a str]) -> Vec<&'a str> {
+        let mut values = self.values();
+        values.extend_from_slice(extra);
+        values
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestTimings {
+    pub overall: f64,
+    pub model_selection: Option<f64>,
+    pub llm_response: Option<f64>,
+}
+
+/// Records every per-request metric for a finished request under one label
+/// set. `error_type` is `None` for successful requests.
+pub fn record_request(labels: &RequestLabels, timings: &RequestTimings, error_type: Option<&str>) {
+    let _guard = RESET_LOCK.read().unwrap_or_else(|e| e.into_inner());
+    let values = labels.values();
+
+    NUM_REQUESTS.with_label_values(&values).inc();
+    if labels.policy.is_some() {
+        REQUESTS_PER_POLICY.with_label_values(&values).inc();
+    }
+    if labels.strategy.is_some() {
+        ROUTING_POLICY_USAGE.with_label_values(&values).inc();
+    }
+    if labels.model.is_some() {
+        REQUESTS_PER_MODEL.with_label_values(&values).inc();
+    }
+
+    REQUEST_LATENCY
+        .with_label_values(&values)
+        .observe(timings.overall);
+    if let Some(selection) = timings.model_selection {
+        MODEL_SELECTION_TIME
+            .with_label_values(&values)
+            .observe(selection);
+    }
+    if let Some(llm_response) = timings.llm_response {
+        LLM_RESPONSE_TIME
+            .with_label_values(&values)
+            .observe(llm_response);
+    }
+    let proxy_overhead = timings.overall
+        - timings.model_selection.unwrap_or(0.0)
+        - timings.llm_response.unwrap_or(0.0);
+    PROXY_OVERHEAD_LATENCY
+        .with_label_values(&values)
+        .observe(proxy_overhead);
+
+    match error_type {
+        None => REQUEST_SUCCESS.with_label_values(&values).inc(),
+        Some(error_type) => REQUEST_FAILURE
+            .with_label_values(&labels.values_with(&[error_type]))
+            .inc(),
+    }
+}
+
+pub fn record_time_to_first_token(labels: &RequestLabels, seconds: f64) {
+    let _guard = RESET_LOCK.read().unwrap_or_else(|e| e.into_inner());
+    TIME_TO_FIRST_TOKEN
+        .with_label_values(&labels.values())
+        .observe(seconds);
+}
+
+pub fn record_stream_throughput(labels: &RequestLabels, tokens_per_second: f64) {
+    let _guard = RESET_LOCK.read().unwrap_or_else(|e| e.into_inner());
+    STREAM_TOKENS_PER_SECOND
+        .with_label_values(&labels.values())
+        .observe(tokens_per_second);
+}
+
+pub fn record_retry(labels: &RequestLabels, reason: &str) {
+    let _guard = RESET_LOCK.read().unwrap_or_else(|e| e.into_inner());
+    UPSTREAM_RETRIES
+        .with_label_values(&labels.values_with(&[reason]))
+        .inc();
+}
+
+pub fn record_client_disconnect(labels: &RequestLabels, stage: &str) {
+    let _guard = RESET_LOCK.read().unwrap_or_else(|e| e.into_inner());
+    CLIENT_DISCONNECTS
+        .with_label_values(&labels.values_with(&[stage]))
+        .inc();
+}
+
+/// Counts the `usage` block of a completion. `finish_reason` is the reason
+/// the generation ended, taken from the response's first choice when `None`.
+pub fn track_token_usage(
+    json: &Value,
+    labels: &RequestLabels,
+    stream: bool,
+    finish_reason: Option<&str>,
+) {
+    let _guard = RESET_LOCK.read().unwrap_or_else(|e| e.into_inner());
+    let Some(usage) = json.get("usage") else {
+        return;
+    };
+    let finish_reason = finish_reason
+        .or_else(|| json["choices"][0]["finish_reason"].as_str())
+        .unwrap_or(UNKNOWN_LABEL);
+    let policy = labels.values()[0];
+    let stream = if stream { "true" } else { "false" };
+    for (category, field) in [
+        ("prompt", "/prompt_tokens"),
+        ("completion", "/completion_tokens"),
+        ("total", "/total_tokens"),
+        ("reasoning", "/completion_tokens_details/reasoning_tokens"),
+    ] {
+        if let Some(tokens) = usage.pointer(field).and_then(Value::as_u64) {
+            TOKEN_USAGE
+                .with_label_values(&labels.values_with(&[category, stream, finish_reason]))
+                .inc_by(tokens);
+            for (tag, value) in &labels.tags {
+                TAGGED_TOKEN_USAGE
+                    .with_label_values(&[policy, tag, value, category])
+                    .inc_by(tokens);
+            }
+        }
+    }
+}
+
+/// Counts the spend of a completion, priced by `cost::usage_cost_usd`.
+pub fn track_cost(labels: &RequestLabels, cost_usd: f64) {
+    let _guard = RESET_LOCK.read().unwrap_or_else(|e| e.into_inner());
+    let values = labels.values();
+    LLM_COST_USD
+        .with_label_values(&[values[0], values[1], values[3]])
+        .inc_by(cost_usd);
+}
+
+/// Clears every gateway metric. Intended for test environments, where it is
+/// exposed through `POST /admin/metrics/reset`.
+pub fn reset_metrics() {
+    let _guard = RESET_LOCK.write().unwrap_or_else(|e| e.into_inner());
+    NUM_REQUESTS.reset();
+    REQUESTS_PER_POLICY.reset();
+    REQUESTS_PER_MODEL.reset();
+    REQUEST_LATENCY.reset();
+    REQUEST_SUCCESS.reset();
+    REQUEST_FAILURE.reset();
+    ROUTING_POLICY_USAGE.reset();
+    MODEL_SELECTION_TIME.reset();
+    LLM_RESPONSE_TIME.reset();
+    TIME_TO_FIRST_TOKEN.reset();
+    STREAM_TOKENS_PER_SECOND.reset();
+    TOKEN_USAGE.reset();
+    LLM_COST_USD.reset();
+    SPEND_ALERTS.reset();
+    SPEND_DOWNGRADES.reset();
+    CONTEXT_OVERFLOWS.reset();
+    PARAMETER_LIMITS.reset();
+    PROXY_OVERHEAD_LATENCY.reset();
+    UPSTREAM_RETRIES.reset();
+    CLIENT_DISCONNECTS.reset();
+    CIRCUIT_BREAKER_STATE.reset();
+    EVENTS_DROPPED.reset();
+    UPSTREAM_DNS_TIME.reset();
+    UPSTREAM_CONNECT_TIME.reset();
+    UPSTREAM_NEW_CONNECTIONS.reset();
+    UPSTREAM_REQUESTS.reset();
+    RATE_LIMITED_REQUESTS.reset();
+    ADMISSION_SHED.reset();
+    TOKEN_BUDGET_EXCEEDED.reset();
+    CLASSIFIER_CACHE_REQUESTS.reset();
+    SLO_BURN_RATE.reset();
+    SPECULATIVE_FALLBACKS.reset();
+    UPSTREAM_RATE_LIMITS.reset();
+    DEGRADED_ROUTING.reset();
+    LOW_CONFIDENCE_ROUTES.reset();
+    SPLIT_ASSIGNMENTS.reset();
+    CANARY_REQUESTS.reset();
+    PII_MASKED.reset();
+    MODERATION_RESULTS.reset();
+    FAULTS_INJECTED.reset();
+    CONFIG_RELOADS.reset();
+    BACKGROUND_TASKS_DROPPED.reset();
+    BACKGROUND_TASK_LAG.reset();
+    STATE_STORE_ERRORS.reset();
+    TAGGED_TOKEN_USAGE.reset();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_labels_are_consistent_and_reset_clears_them() {
+        let labels = RequestLabels {
+            policy: Some("metrics_test_policy".to_string()),
+            model: Some("metrics_test_model".to_string()),
+            strategy: Some("manual".to_string()),
+            tags: vec![("team".to_string(), "search".to_string())],
+            ..Default::default()
+        };
+        let timings = RequestTimings {
+            overall: 0.5,
+            model_selection: None,
+            llm_response: Some(0.25),
+        };
+        record_request(&labels, &timings, None);
+        track_token_usage(
+            &json!({
+                "choices": [{ "finish_reason": "length" }],
+                "usage": {
+                    "total_tokens": 7,
+                    "completion_tokens_details": { "reasoning_tokens": 3 }
+                }
+            }),
+            &labels,
+            false,
+            None,
+        );
+
+        let values = labels.values();
+        assert_eq!(REQUEST_SUCCESS.with_label_values(&values).get(), 1);
+        assert_eq!(
+            TOKEN_USAGE
+                .with_label_values(&[
+                    "metrics_test_policy",
+                    "metrics_test_model",
+                    "manual",
+                    "unknown",
+                    "total",
+                    "false",
+                    "length"
+                ])
+                .get(),
+            7
+        );
+        assert_eq!(
+            TOKEN_USAGE
+                .with_label_values(&[
+                    "metrics_test_policy",
+                    "metrics_test_model",
+                    "manual",
+                    "unknown",
+                    "reasoning",
+                    "false",
+                    "length"
+                ])
+                .get(),
+            3
+        );
+        assert_eq!(
+            TAGGED_TOKEN_USAGE
+                .with_label_values(&["metrics_test_policy", "team", "search", "total"])
+                .get(),
+            7
+        );
+
+        reset_metrics();
+        assert_eq!(REQUEST_SUCCESS.with_label_values(&values).get(), 0);
+    }
+}
@@ -0,0 +1,337 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! PII
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::Range;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    Phone,
+    CreditCard,
+    Ssn,
+    IpAddress,
+}
+
+impl PiiKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "EMAIL",
+            Self::Phone => "PHONE",
+            Self::CreditCard => "CREDIT_CARD",
+            Self::Ssn => "SSN",
+            Self::IpAddress => "IP_ADDRESS",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionMode {
+    /// Replace each span with a stable hash so equal values stay equal.
+    #[default]
+    Hash,
+    /// Remove each span entirely.
+    Strip,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiiSpan {
+    pub kind: PiiKind,
+    pub range: Range<usize>,
+}
+
+lazy_static! {
+    static ref RECOGNIZERS: Vec<(PiiKind, Regex)> = vec![
+        (
+            PiiKind::Email,
+            Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}")
+                .expect("valid email regex"),
+        ),
+        (
+            PiiKind::CreditCard,
+            Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").expect("valid credit card regex"),
+        ),
+        (
+            PiiKind::Ssn,
+            Regex::new(r"\b\d{3}-\d{2}-\d{4}\b").expect("valid ssn regex"),
+        ),
+        (
+            PiiKind::Phone,
+            Regex::new(r"(?:\+\d{1,3}[ .-]?)?\(?\b\d{3}\)?[ .-]?\d{3}[ .-]?\d{4}\b")
+                .expect("valid phone regex"),
+        ),
+        (
+            PiiKind::IpAddress,
+            Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b").expect("valid ip regex"),
+        ),
+    ];
+}
+
+fn passes_luhn(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Finds sensitive spans in `text`. Spans never overlap; when two recognizers
+/// match the same text the one listed first in `RECOGNIZERS` wins.
+pub fn detect(text: &str) -> Vec<PiiSpan> {
+    let mut spans: Vec<PiiSpan> = Vec::new();
+    for (kind, regex) in RECOGNIZERS.iter() {
+        for m in regex.find_iter(text) {
+            if *kind == PiiKind::CreditCard && !passes_luhn(m.as_str()) {
+                continue;
+            }
+            let overlaps = spans
+                .iter()
+                .any(|s| s.range.start < m.end() && m.start() < s.range.end);
+            if !overlaps {
+                spans.push(PiiSpan {
+                    kind: *kind,
+                    range: m.range(),
+                });
+            }
+        }
+    }
+    spans.sort_by_key(|s| s.range.start);
+    spans
+}
+
+/// Short, stable digest of a sensitive value.
+#[cfg(feature = "server")]
+pub fn hash_value(value: &str) -> String {
+    openssl::sha::sha256(value.as_bytes())
+        .iter()
+        .take(4)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Returns `text` with every detected span hashed or stripped.
+#[cfg(feature = "server")]
+pub fn redact(text: &str, mode: RedactionMode) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for span in detect(text) {
+        redacted.push_str(&text[cursor..span.range.start]);
+        if mode == RedactionMode::Hash {
+            let value = &text[span.range.clone()];
+            redacted.push_str(&format!("[{}:{}]", span.kind.as_str(), hash_value(value)));
+        }
+        cursor = span.range.end;
+    }
+    redacted.push_str(&text[cursor..]);
+    redacted
+}
+
+/// A custom recognizer's regex. (De)serializes as its source text.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Self, regex::Error> {
+        Regex::new(source).map(Self)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Pattern::new(&source)
+            .map_err(|e| serde::de::Error::custom(format!("invalid pattern `{}`: {}", source, e)))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PiiPattern {
+    /// Names the placeholders of its matches, e.g. `EMPLOYEE_ID`.
+    pub label: String,
+    pub regex: Pattern,
+}
+
+/// Spans found by the built-in recognizers of `kinds`, all of them when
+/// empty, then by `patterns`. Earlier recognizers win overlaps.
+fn find<'a>(
+    text: &str,
+    kinds: &[PiiKind],
+    patterns: &'a [PiiPattern],
+) -> Vec<(&'a str, Range<usize>)> {
+    let builtin = RECOGNIZERS
+        .iter()
+        .filter(|(kind, _)| kinds.is_empty() || kinds.contains(kind))
+        .map(|(kind, regex)| (kind.as_str(), regex, *kind == PiiKind::CreditCard));
+    let custom = patterns
+        .iter()
+        .map(|pattern| (pattern.label.as_str(), &pattern.regex.0, false));
+    let mut spans: Vec<(&str, Range<usize>)> = Vec::new();
+    for (label, regex, luhn) in builtin.chain(custom) {
+        for m in regex.find_iter(text) {
+            if luhn && !passes_luhn(m.as_str()) {
+                continue;
+            }
+            let overlaps = spans
+                .iter()
+                .any(|(_, range)| range.start < m.end() && m.start() < range.end);
+            if !overlaps {
+                spans.push((label, m.range()));
+            }
+        }
+    }
+    spans.sort_by_key(|(_, range)| range.start);
+    spans
+}
+
+/// Values masked in one request, by placeholder. A value seen twice keeps
+/// its placeholder, so the LLM can still tell values apart, and the
+/// response can be restored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Vault {
+    entries: Vec<(String, String)>,
+}
+
+impl Vault {
+    /// `text` with every span found replaced by a placeholder such as
+    /// `[EMAIL_1]`. Returns the labels of the values masked.
+    pub fn mask<'a>(
+        &mut self,
+        text: &str,
+        kinds: &[PiiKind],
+        patterns: &'a [PiiPattern],
+    ) -> (String, Vec<&'a str>) {
+        let mut masked = String::with_capacity(text.len());
+        let mut labels = Vec::new();
+        let mut cursor = 0;
+        for (label, range) in find(text, kinds, patterns) {
+            masked.push_str(&text[cursor..range.start]);
+            masked.push_str(&self.placeholder(label, &text[range.clone()]));
+            labels.push(label);
+            cursor = range.end;
+        }
+        masked.push_str(&text[cursor..]);
+        (masked, labels)
+    }
+
+    fn placeholder(&mut self, label: &str, value: &str) -> String {
+        if let Some((placeholder, _)) = self.entries.iter().find(|(_, v)| v == value) {
+            return placeholder.clone();
+        }
+        let prefix = format!("[{}_", label);
+        let n = self
+            .entries
+            .iter()
+            .filter(|(placeholder, _)| placeholder.starts_with(&prefix))
+            .count()
+            + 1;
+        let placeholder = format!("{}{}]", prefix, n);
+        self.entries.push((placeholder.clone(), value.to_string()));
+        placeholder
+    }
+
+    /// `text` with the placeholders handed out put back to their values.
+    pub fn restore(&self, text: &str) -> String {
+        self.entries
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, value)| {
+                text.replace(placeholder, value)
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let text = "mail jane@example.com or call 555-123-4567, card 4111 1111 1111 1111";
+        let kinds: Vec<PiiKind> = detect(text).into_iter().map(|s| s.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![PiiKind::Email, PiiKind::Phone, PiiKind::CreditCard]
+        );
+        // Not a valid card number, so it is left alone.
+        assert!(detect("order 1234 5678 9012 3456").is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_redact() {
+        let text = "contact jane@example.com today";
+        let hashed = redact(text, RedactionMode::Hash);
+        assert!(hashed.starts_with("contact [EMAIL:"));
+        assert!(!hashed.contains("jane"));
+        assert_eq!(hashed, redact(text, RedactionMode::Hash));
+        assert_eq!(redact(text, RedactionMode::Strip), "contact  today");
+    }
+
+    #[test]
+    fn test_mask_and_restore() {
+        let patterns = vec![PiiPattern {
+            label: "EMPLOYEE_ID".to_string(),
+            regex: Pattern::new(r"\bE-\d{6}\b").unwrap(),
+        }];
+        let mut vault = Vault::default();
+        let text = "jane@example.com (E-123456) cc bob@example.com, jane@example.com";
+        let (masked, labels) = vault.mask(text, &[PiiKind::Email], &patterns);
+        assert_eq!(
+            masked,
+            "[EMAIL_1] ([EMPLOYEE_ID_1]) cc [EMAIL_2], [EMAIL_1]"
+        );
+        assert_eq!(labels, vec!["EMAIL", "EMPLOYEE_ID", "EMAIL", "EMAIL"]);
+        assert_eq!(vault.restore(&masked), text);
+
+        // Phone numbers are not among the kinds asked for.
+        let (masked, _) = vault.mask("call 555-123-4567", &[PiiKind::Email], &[]);
+        assert_eq!(masked, "call 555-123-4567");
+        assert!(serde_json::from_value::<Pattern>(serde_json::json!("(")).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Privacy
+//!
+//! `privacy_mode`: one switch that keeps user identifiers and request
+//! metadata out of everything the router persists or exports. Identifiers
+//! are replaced by stable pseudonyms so events can still be correlated.
+use crate::request_context::RequestContext;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Headers kept on exported request contexts: they affect routing and
+/// identify nobody.
+const EXPORTED_HEADERS: &[&str] = &["accept", "content-type", "x-data-residency"];
+
+/// Request body fields that identify the end user.
+pub const PRIVATE_FIELDS: &[&str] = &["user", "metadata"];
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Stable pseudonym for an identifier: the same input always maps to the
+/// same value, which cannot be read back.
+pub fn pseudonymize(value: &str) -> String {
+    let digest = openssl::sha::sha256(format!("llm-router-privacy\0{}", value).as_bytes());
+    let hex: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("anon-{}", hex)
+}
+
+/// The context as it may be exported: unchanged unless privacy mode is on.
+pub fn export(context: &RequestContext) -> RequestContext {
+    let mut exported = context.clone();
+    if enabled() {
+        scrub(&mut exported);
+    }
+    exported
+}
+
+fn scrub(context: &mut RequestContext) {
+    context
+        .headers
+        .retain(|name, _| EXPORTED_HEADERS.contains(&name.as_str()));
+    context.subject = context.subject.as_deref().map(pseudonymize);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub() {
+        let mut context = RequestContext {
+            subject: Some("alice@example.com".to_string()),
+            tenant: Some("acme".to_string()),
+            ..Default::default()
+        };
+        context
+            .headers
+            .insert("x-forwarded-for".to_string(), "10.1.2.3".to_string());
+        context
+            .headers
+            .insert("x-data-residency".to_string(), "eu".to_string());
+        scrub(&mut context);
+
+        assert_eq!(context.headers.len(), 1);
+        assert_eq!(context.headers["x-data-residency"], "eu");
+        let subject = context.subject.unwrap();
+        assert!(subject.starts_with("anon-") && !subject.contains("alice"));
+        assert_eq!(subject, pseudonymize("alice@example.com"));
+        assert_eq!(context.tenant.as_deref(), Some("acme"));
+    }
+}
@@ -0,0 +1,86 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reasoning
+//!
+//! Rewrites requests for o1-style reasoning models, which reject the
+//! sampling parameters and `max_tokens` accepted by standard chat models.
+use crate::config::Llm;
+use log::debug;
+use serde_json::Value;
+
+/// Parameters reasoning models refuse.
+const UNSUPPORTED_PARAMS: &[&str] = &[
+    "temperature",
+    "top_p",
+    "presence_penalty",
+    "frequency_penalty",
+    "logprobs",
+    "top_logprobs",
+    "logit_bias",
+];
+
+/// Maps `max_tokens` to `max_completion_tokens` and strips unsupported
+/// parameters when `llm` is a reasoning model.
+pub fn apply(mut json: Value, llm: &Llm) -> Value {
+    if !llm.reasoning {
+        return json;
+    }
+    let Some(body) = json.as_object_mut() else {
+        return json;
+    };
+    if let Some(max_tokens) = body.remove("max_tokens") {
+        body.entry("max_completion_tokens").or_insert(max_tokens);
+    }
+    for param in UNSUPPORTED_PARAMS {
+        if body.remove(*param).is_some() {
+            debug!("Dropped '{}' for reasoning LLM '{}'", param, llm.name);
+        }
+    }
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply() {
+        let body = json!({
+            "model": "o1",
+            "messages": [],
+            "max_tokens": 512,
+            "temperature": 0.2,
+            "top_p": 0.9
+        });
+        let standard = Llm::default();
+        assert_eq!(apply(body.clone(), &standard), body);
+
+        let reasoning = Llm {
+            reasoning: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            apply(body, &reasoning),
+            json!({ "model": "o1", "messages": [], "max_completion_tokens": 512 })
+        );
+        let explicit = json!({ "max_tokens": 10, "max_completion_tokens": 20 });
+        assert_eq!(
+            apply(explicit, &reasoning),
+            json!({ "max_completion_tokens": 20 })
+        );
+    }
+}
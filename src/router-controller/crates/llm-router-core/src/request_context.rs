@@ -0,0 +1,113 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Request Context
+//!
+//! The one view of a routed request handed to every extension point (the
+//! event sink and audit events), so each of them sees the same fields.
+use http::HeaderMap;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Headers that carry credentials and are never exposed.
+const REDACTED_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "api-key",
+    "x-api-key",
+];
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ContextTimings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_selection_seconds: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm_response_seconds: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RequestContext {
+    /// Request headers without credentials. Repeated headers are joined
+    /// with `, `.
+    pub headers: BTreeMap<String, String>,
+    /// `sub` of the caller's validated JWT.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    /// Caller supplied tags for cost attribution.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    /// Classifier scores after adjustment, one per LLM of the policy.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub scores: Vec<f64>,
+    /// LLM picked by the routing strategy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chosen_model: Option<String>,
+    /// LLM the request is being sent to, which differs from `chosen_model`
+    /// after a fallback.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub served_by: Option<String>,
+    pub timings: ContextTimings,
+}
+
+impl RequestContext {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut exposed: BTreeMap<String, String> = BTreeMap::new();
+        for (name, value) in headers {
+            if REDACTED_HEADERS.contains(&name.as_str()) {
+                continue;
+            }
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            exposed
+                .entry(name.as_str().to_string())
+                .and_modify(|joined| {
+                    joined.push_str(", ");
+                    joined.push_str(value);
+                })
+                .or_insert_with(|| value.to_string());
+        }
+        Self {
+            headers: exposed,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers.insert("x-api-key", HeaderValue::from_static("secret"));
+        headers.append("x-data-residency", HeaderValue::from_static("eu"));
+        headers.append("x-data-residency", HeaderValue::from_static("us"));
+
+        let context = RequestContext::from_headers(&headers);
+        assert_eq!(context.headers.len(), 1);
+        assert_eq!(context.headers["x-data-residency"], "eu, us");
+    }
+}
@@ -0,0 +1,200 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retryability
+//!
+//! Per-provider table of upstream error codes, so retries and fallbacks are
+//! not spent on errors that cannot succeed.
+use crate::config::Provider;
+use serde_json::Value;
+
+/// What an upstream error allows the router to do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Transient; the same LLM may be tried again.
+    Retryable,
+    /// The LLM cannot serve the request (e.g. its quota is exhausted), but
+    /// another LLM may.
+    Failover,
+    /// The request itself was refused and is returned to the caller.
+    Terminal,
+}
+
+struct Rule {
+    provider: Provider,
+    status: u16,
+    /// Matched against the error's `code` or `type`.
+    code: &'static str,
+    class: ErrorClass,
+}
+
+const RULES: &[Rule] = &[
+    Rule {
+        provider: Provider::OpenAi,
+        status: 429,
+        code: "insufficient_quota",
+        class: ErrorClass::Failover,
+    },
+    Rule {
+        provider: Provider::OpenAi,
+        status: 429,
+        code: "rate_limit_exceeded",
+        class: ErrorClass::Retryable,
+    },
+    Rule {
+        provider: Provider::OpenAi,
+        status: 401,
+        code: "invalid_api_key",
+        class: ErrorClass::Failover,
+    },
+    Rule {
+        provider: Provider::OpenAi,
+        status: 400,
+        code: "context_length_exceeded",
+        class: ErrorClass::Terminal,
+    },
+    Rule {
+        provider: Provider::OpenAi,
+        status: 500,
+        code: "server_error",
+        class: ErrorClass::Retryable,
+    },
+    Rule {
+        provider: Provider::Anthropic,
+        status: 529,
+        code: "overloaded_error",
+        class: ErrorClass::Retryable,
+    },
+    Rule {
+        provider: Provider::Anthropic,
+        status: 429,
+        code: "rate_limit_error",
+        class: ErrorClass::Retryable,
+    },
+    Rule {
+        provider: Provider::Anthropic,
+        status: 500,
+        code: "api_error",
+        class: ErrorClass::Retryable,
+    },
+    Rule {
+        provider: Provider::Anthropic,
+        status: 401,
+        code: "authentication_error",
+        class: ErrorClass::Failover,
+    },
+    Rule {
+        provider: Provider::Anthropic,
+        status: 403,
+        code: "permission_error",
+        class: ErrorClass::Failover,
+    },
+    Rule {
+        provider: Provider::Anthropic,
+        status: 400,
+        code: "invalid_request_error",
+        class: ErrorClass::Terminal,
+    },
+    Rule {
+        provider: Provider::Gemini,
+        status: 429,
+        code: "RESOURCE_EXHAUSTED",
+        class: ErrorClass::Retryable,
+    },
+    Rule {
+        provider: Provider::Gemini,
+        status: 503,
+        code: "UNAVAILABLE",
+        class: ErrorClass::Retryable,
+    },
+    Rule {
+        provider: Provider::Gemini,
+        status: 403,
+        code: "PERMISSION_DENIED",
+        class: ErrorClass::Failover,
+    },
+    Rule {
+        provider: Provider::Gemini,
+        status: 400,
+        code: "INVALID_ARGUMENT",
+        class: ErrorClass::Terminal,
+    },
+];
+
+/// `error.code` and `error.type` of an OpenAI or Anthropic error body, or
+/// the `error.status` of a Gemini one.
+fn error_codes(body: &[u8]) -> Vec<String> {
+    let Ok(json) = serde_json::from_slice::<Value>(body) else {
+        return Vec::new();
+    };
+    ["code", "type", "status"]
+        .iter()
+        .filter_map(|field| json["error"][field].as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Looks up an error response in the table. `None` means the table has no
+/// opinion and status based defaults apply.
+pub fn classify(provider: Provider, status: u16, body: &[u8]) -> Option<ErrorClass> {
+    // Azure OpenAI and local servers return OpenAI shaped errors.
+    let provider = match provider {
+        Provider::Azure | Provider::Local => Provider::OpenAi,
+        provider => provider,
+    };
+    let codes = error_codes(body);
+    RULES
+        .iter()
+        .find(|rule| {
+            rule.provider == provider
+                && rule.status == status
+                && codes.iter().any(|code| code == rule.code)
+        })
+        .map(|rule| rule.class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let quota = br#"{"error":{"message":"quota","type":"insufficient_quota","code":"insufficient_quota"}}"#;
+        let limited =
+            br#"{"error":{"message":"slow down","type":"requests","code":"rate_limit_exceeded"}}"#;
+        let overloaded =
+            br#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        assert_eq!(
+            classify(Provider::OpenAi, 429, quota),
+            Some(ErrorClass::Failover)
+        );
+        assert_eq!(
+            classify(Provider::OpenAi, 429, limited),
+            Some(ErrorClass::Retryable)
+        );
+        assert_eq!(
+            classify(Provider::Anthropic, 529, overloaded),
+            Some(ErrorClass::Retryable)
+        );
+        let exhausted =
+            br#"{"error":{"code":429,"message":"Quota","status":"RESOURCE_EXHAUSTED"}}"#;
+        assert_eq!(
+            classify(Provider::Gemini, 429, exhausted),
+            Some(ErrorClass::Retryable)
+        );
+        assert_eq!(classify(Provider::Anthropic, 429, quota), None);
+        assert_eq!(classify(Provider::OpenAi, 503, b"upstream down"), None);
+    }
+}
@@ -0,0 +1,97 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stats
+//!
+//! Live, in-process view of each upstream LLM (requests in flight and a
+//! moving average of response time) used by routing decisions.
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Weight of the newest sample in the latency moving average.
+const LATENCY_EWMA_ALPHA: f64 = 0.2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UpstreamStats {
+    pub in_flight: usize,
+    pub ewma_latency: Option<f64>,
+}
+
+lazy_static! {
+    static ref UPSTREAMS: Mutex<HashMap<String, UpstreamStats>> = Mutex::new(HashMap::new());
+}
+
+pub fn upstream_key(policy: &str, llm: &str) -> String {
+    format!("{}/{}", policy, llm)
+}
+
+pub fn snapshot(key: &str) -> UpstreamStats {
+    let upstreams = UPSTREAMS.lock().unwrap_or_else(|e| e.into_inner());
+    upstreams.get(key).copied().unwrap_or_default()
+}
+
+pub fn observe_latency(key: &str, seconds: f64) {
+    let mut upstreams = UPSTREAMS.lock().unwrap_or_else(|e| e.into_inner());
+    let stats = upstreams.entry(key.to_string()).or_default();
+    stats.ewma_latency = Some(match stats.ewma_latency {
+        Some(previous) => LATENCY_EWMA_ALPHA * seconds + (1.0 - LATENCY_EWMA_ALPHA) * previous,
+        None => seconds,
+    });
+}
+
+/// Counts a request as in flight against an upstream until dropped.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    key: String,
+}
+
+pub fn begin(key: &str) -> InFlightGuard {
+    let mut upstreams = UPSTREAMS.lock().unwrap_or_else(|e| e.into_inner());
+    upstreams.entry(key.to_string()).or_default().in_flight += 1;
+    InFlightGuard {
+        key: key.to_string(),
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut upstreams = UPSTREAMS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(stats) = upstreams.get_mut(&self.key) {
+            stats.in_flight = stats.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_flight_and_latency() {
+        let key = upstream_key("stats_test", "llm");
+        {
+            let _first = begin(&key);
+            let _second = begin(&key);
+            assert_eq!(snapshot(&key).in_flight, 2);
+        }
+        assert_eq!(snapshot(&key).in_flight, 0);
+
+        observe_latency(&key, 1.0);
+        observe_latency(&key, 2.0);
+        let latency = snapshot(&key).ewma_latency.unwrap();
+        assert!((latency - 1.2).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,225 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tags
+//!
+//! Free-form key/value request tags for cost attribution, carried in the
+//! request context of usage and audit events.
+use crate::config::{CustomMetricLabel, RequestTagsConfig};
+use crate::metrics;
+use crate::privacy;
+use http::HeaderMap;
+use lazy_static::lazy_static;
+use log::warn;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+
+/// `key=value` pairs separated by commas.
+pub const REQUEST_TAGS_HEADER: &str = "X-Request-Tags";
+const MAX_TAGS: usize = 16;
+const MAX_KEY_LEN: usize = 64;
+const MAX_VALUE_LEN: usize = 256;
+/// Metric label value of tag values past `max_metric_values`.
+const OTHER_VALUE: &str = "other";
+
+lazy_static! {
+    static ref METRIC_VALUES: Mutex<HashMap<String, HashSet<String>>> = Mutex::new(HashMap::new());
+}
+
+fn valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= MAX_KEY_LEN
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Tags of a request: the `X-Request-Tags` header merged with the body's
+/// `nim-llm-router.tags`, which wins on conflicts. Malformed and excess tags
+/// are dropped with a warning.
+pub fn parse(
+    headers: &HeaderMap,
+    body_tags: Option<&BTreeMap<String, String>>,
+) -> BTreeMap<String, String> {
+    let from_header = headers
+        .get_all(REQUEST_TAGS_HEADER)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter(|pair| !pair.trim().is_empty())
+        .filter_map(|pair| match pair.split_once('=') {
+            Some((key, value)) => Some((key.trim().to_string(), value.trim().to_string())),
+            None => {
+                warn!("Ignoring request tag without a value: '{}'", pair.trim());
+                None
+            }
+        });
+    let from_body = body_tags
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| (key.clone(), value.clone()));
+
+    let mut tags = BTreeMap::new();
+    for (key, value) in from_header.chain(from_body) {
+        if !valid_key(&key) || value.len() > MAX_VALUE_LEN {
+            warn!("Ignoring malformed request tag '{}'", key);
+            continue;
+        }
+        if tags.len() >= MAX_TAGS && !tags.contains_key(&key) {
+            warn!(
+                "Ignoring request tag '{}' past the limit of {}",
+                key, MAX_TAGS
+            );
+            continue;
+        }
+        tags.insert(key, value);
+    }
+    tags
+}
+
+/// The configured metric tags of a request, with values beyond the
+/// per-tag cardinality limit replaced by `other`. Values are pseudonymized
+/// in privacy mode.
+pub fn metric_labels(
+    config: &RequestTagsConfig,
+    tags: &BTreeMap<String, String>,
+) -> Vec<(String, String)> {
+    let mut seen = METRIC_VALUES.lock().unwrap_or_else(|e| e.into_inner());
+    config
+        .metric_tags
+        .iter()
+        .filter_map(|tag| {
+            let value = tags.get(tag)?;
+            let value = if privacy::enabled() {
+                privacy::pseudonymize(value)
+            } else {
+                value.clone()
+            };
+            let values = seen.entry(tag.clone()).or_default();
+            let known = values.contains(&value);
+            if known || values.len() < config.max_metric_values {
+                if !known {
+                    values.insert(value.clone());
+                }
+                Some((tag.clone(), value))
+            } else {
+                Some((tag.clone(), OTHER_VALUE.to_string()))
+            }
+        })
+        .collect()
+}
+
+/// Values of the registered custom metric labels for a request, looked up
+/// in `labels` by name so a reloaded config cannot shift them. Values are
+/// strictly allowlisted to bound cardinality.
+pub fn custom_metric_labels(
+    labels: &[CustomMetricLabel],
+    headers: &HeaderMap,
+    json: &Value,
+) -> Vec<String> {
+    metrics::custom_labels()
+        .iter()
+        .map(|name| {
+            let Some(label) = labels.iter().find(|label| &label.name == name) else {
+                return metrics::UNKNOWN_LABEL.to_string();
+            };
+            custom_label_value(label, headers, json)
+        })
+        .collect()
+}
+
+fn custom_label_value(label: &CustomMetricLabel, headers: &HeaderMap, json: &Value) -> String {
+    let value = match (&label.header, &label.field) {
+        (Some(header), _) => headers.get(header).and_then(|v| v.to_str().ok()),
+        (None, Some(field)) => json["nim-llm-router"][field].as_str(),
+        (None, None) => None,
+    };
+    match value.map(str::trim) {
+        None | Some("") => metrics::UNKNOWN_LABEL.to_string(),
+        Some(value) if label.allowed_values.iter().any(|v| v == value) => value.to_string(),
+        Some(_) => OTHER_VALUE.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_parse_and_metric_labels() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            REQUEST_TAGS_HEADER,
+            HeaderValue::from_static("team=search, campaign = spring,bad key=x,novalue"),
+        );
+        let body = BTreeMap::from([("campaign".to_string(), "summer".to_string())]);
+        let tags = parse(&headers, Some(&body));
+        assert_eq!(
+            tags,
+            BTreeMap::from([
+                ("campaign".to_string(), "summer".to_string()),
+                ("team".to_string(), "search".to_string()),
+            ])
+        );
+
+        let config = RequestTagsConfig {
+            metric_tags: vec!["tags_test_team".to_string()],
+            max_metric_values: 1,
+        };
+        let tagged =
+            |team: &str| BTreeMap::from([("tags_test_team".to_string(), team.to_string())]);
+        assert_eq!(
+            metric_labels(&config, &tagged("search")),
+            vec![("tags_test_team".to_string(), "search".to_string())]
+        );
+        assert_eq!(
+            metric_labels(&config, &tagged("ads")),
+            vec![("tags_test_team".to_string(), "other".to_string())]
+        );
+        assert!(metric_labels(&config, &BTreeMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_custom_label_value() {
+        let label = CustomMetricLabel {
+            name: "env".to_string(),
+            header: Some("X-Env".to_string()),
+            allowed_values: vec!["prod".to_string(), "staging".to_string()],
+            ..Default::default()
+        };
+        let request = |env: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("X-Env", HeaderValue::from_static(env));
+            custom_label_value(&label, &headers, &Value::Null)
+        };
+        assert_eq!(request("prod"), "prod");
+        assert_eq!(request("dev-1234"), "other");
+        assert_eq!(
+            custom_label_value(&label, &HeaderMap::new(), &Value::Null),
+            "unknown"
+        );
+
+        let app = CustomMetricLabel {
+            name: "app".to_string(),
+            field: Some("app".to_string()),
+            allowed_values: vec!["search".to_string()],
+            ..Default::default()
+        };
+        let body = serde_json::json!({"nim-llm-router": {"policy": "p", "app": "search"}});
+        assert_eq!(custom_label_value(&app, &HeaderMap::new(), &body), "search");
+    }
+}
@@ -0,0 +1,254 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tokenizer
+//!
+//! Token counts for responses that leave out `usage`, as self-hosted
+//! servers and streams often do. An LLM names the Hugging Face tokenizer of
+//! its model, and its prompts and completions are counted with it so token
+//! metrics and spend stay close to what the provider would have reported.
+//!
+//! Tokenizers are loaded by `preload`, never while counting, so hub
+//! downloads do not hold up usage accounting. Usage of an LLM whose
+//! tokenizer is not loaded goes uncounted.
+use crate::config::{Policy, TokenizerConfig};
+use crate::cost::TOKENS_PER_MESSAGE;
+use hf_hub::api::sync::Api;
+use hf_hub::{Repo, RepoType};
+use lazy_static::lazy_static;
+use log::{error, info};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokenizers::Tokenizer;
+
+lazy_static! {
+    /// Loaded tokenizers by location.
+    static ref TOKENIZERS: Mutex<HashMap<String, Arc<Tokenizer>>> = Mutex::new(HashMap::new());
+}
+
+fn key(config: &TokenizerConfig) -> String {
+    format!(
+        "{}@{}",
+        config
+            .path
+            .as_deref()
+            .or(config.repo.as_deref())
+            .unwrap_or_default(),
+        config.revision
+    )
+}
+
+/// `tokenizer.json`, downloading it from the hub when no local path is
+/// configured.
+fn tokenizer_file(config: &TokenizerConfig) -> Result<PathBuf, String> {
+    if let Some(path) = &config.path {
+        return Ok(PathBuf::from(path));
+    }
+    let repo = config
+        .repo
+        .as_ref()
+        .ok_or_else(|| "tokenizer has neither a path nor a repo".to_string())?;
+    let api = Api::new().map_err(|e| format!("Hugging Face hub: {}", e))?;
+    api.repo(Repo::with_revision(
+        repo.clone(),
+        RepoType::Model,
+        config.revision.clone(),
+    ))
+    .get("tokenizer.json")
+    .map_err(|e| format!("tokenizer.json: {}", e))
+}
+
+fn load(config: &TokenizerConfig) -> Result<(), String> {
+    let key = key(config);
+    if TOKENIZERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains_key(&key)
+    {
+        return Ok(());
+    }
+    let file = tokenizer_file(config)?;
+    let tokenizer = Tokenizer::from_file(&file).map_err(|e| format!("tokenizer.json: {}", e))?;
+    info!("Loaded tokenizer {}", key);
+    TOKENIZERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(key, Arc::new(tokenizer));
+    Ok(())
+}
+
+fn loaded(config: &TokenizerConfig) -> Option<Arc<Tokenizer>> {
+    TOKENIZERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&key(config))
+        .cloned()
+}
+
+/// Loads the tokenizers of the LLMs of `policies`. Failures are logged and
+/// retried on the next configuration change.
+pub fn preload(policies: &[Policy]) {
+    let mut configs: Vec<(String, TokenizerConfig)> = Vec::new();
+    for llm in policies.iter().flat_map(|policy| &policy.llms) {
+        let Some(config) = &llm.tokenizer else {
+            continue;
+        };
+        if !configs.iter().any(|(_, known)| key(known) == key(config)) {
+            configs.push((llm.name.clone(), config.clone()));
+        }
+    }
+    for (name, config) in configs {
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = load(&config) {
+                error!("Failed to load tokenizer of LLM '{}': {}", name, e);
+            }
+        });
+    }
+}
+
+fn count(tokenizer: &Tokenizer, text: &str) -> u64 {
+    if text.is_empty() {
+        return 0;
+    }
+    tokenizer
+        .encode(text, false)
+        .map_or(0, |encoding| encoding.len() as u64)
+}
+
+/// Text of a message `content`, a string or a list of parts.
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn count_prompt(tokenizer: &Tokenizer, request: &Value) -> u64 {
+    if let Some(prompt) = request.get("prompt") {
+        return match prompt {
+            Value::Array(prompts) => prompts
+                .iter()
+                .map(|prompt| count(tokenizer, &content_text(prompt)))
+                .sum(),
+            prompt => count(tokenizer, &content_text(prompt)),
+        };
+    }
+    request["messages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|message| count(tokenizer, &content_text(&message["content"])) + TOKENS_PER_MESSAGE)
+        .sum()
+}
+
+/// Prompt tokens of an OpenAI chat completion body, or of the `prompt` of
+/// a legacy completion, counted with the tokenizer of `config`. `None`
+/// until the tokenizer is loaded.
+pub fn prompt_tokens(config: &TokenizerConfig, request: &Value) -> Option<u64> {
+    let tokenizer = loaded(config)?;
+    Some(count_prompt(&tokenizer, request))
+}
+
+/// Completion text of a chat or legacy completion response.
+pub fn completion_text(response: &Value) -> String {
+    response["choices"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|choice| {
+            choice["message"]["content"]
+                .as_str()
+                .or_else(|| choice["text"].as_str())
+        })
+        .collect()
+}
+
+/// A `usage` object for `request` and its `completion` counted with the
+/// tokenizer of `config`, `None` until the tokenizer is loaded.
+pub fn usage(config: &TokenizerConfig, request: &Value, completion: &str) -> Option<Value> {
+    let tokenizer = loaded(config)?;
+    let prompt_tokens = count_prompt(&tokenizer, request);
+    let completion_tokens = count(&tokenizer, completion);
+    Some(json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORD_LEVEL: &str = r#"{
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": {"type": "Whitespace"},
+        "post_processor": null,
+        "decoder": null,
+        "model": {
+            "type": "WordLevel",
+            "vocab": {"[UNK]": 0, "hello": 1, "world": 2},
+            "unk_token": "[UNK]"
+        }
+    }"#;
+
+    #[test]
+    fn test_usage() {
+        let path = std::env::temp_dir().join(format!(
+            "llm-router-tokenizer-test-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, WORD_LEVEL).unwrap();
+        let config = TokenizerConfig {
+            path: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let request = json!({"messages": [
+            {"role": "system", "content": "hello"},
+            {"role": "user", "content": [{"type": "text", "text": "hello world"}]},
+        ]});
+        assert_eq!(usage(&config, &request, "hello"), None);
+
+        load(&config).unwrap();
+        let response = json!({"choices": [{"message": {"content": "hello there world"}}]});
+        let completion = completion_text(&response);
+        assert_eq!(completion, "hello there world");
+        let usage = usage(&config, &request, &completion).unwrap();
+        assert_eq!(usage["prompt_tokens"], 11);
+        assert_eq!(usage["completion_tokens"], 3);
+        assert_eq!(usage["total_tokens"], 14);
+
+        let legacy = json!({"prompt": "hello world"});
+        assert_eq!(prompt_tokens(&config, &legacy), Some(2));
+        std::fs::remove_file(path).ok();
+
+        let missing = TokenizerConfig {
+            path: Some("/nonexistent/tokenizer.json".to_string()),
+            ..Default::default()
+        };
+        assert!(load(&missing).is_err());
+    }
+}
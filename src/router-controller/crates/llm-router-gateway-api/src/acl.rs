@@ -0,0 +1,104 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ACL
+use crate::config::RouterConfig;
+use crate::error::GatewayApiError;
+use http::StatusCode;
+use hyper::Request;
+use log::warn;
+use std::net::SocketAddr;
+
+/// Peer address of the connection a request arrived on, attached as a
+/// request extension by the listener.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClientAddr(pub SocketAddr);
+
+fn path_matches(path: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim_end_matches('/');
+    path == pattern
+        || path
+            .strip_prefix(pattern)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Refuses requests to a path covered by `network_acls` unless the peer
+/// address falls in one of the rule's allowed networks. The first rule
+/// listing the path applies; paths no rule lists are unrestricted. Requests
+/// without a known peer address are refused on restricted paths.
+pub fn check<B>(req: &Request<B>, config: &RouterConfig) -> Result<(), GatewayApiError> {
+    let path = req.uri().path();
+    let Some(rule) = config
+        .network_acls
+        .iter()
+        .find(|rule| rule.paths.iter().any(|p| path_matches(path, p)))
+    else {
+        return Ok(());
+    };
+
+    let peer = req
+        .extensions()
+        .get::<ClientAddr>()
+        .map(|addr| addr.0.ip().to_canonical());
+    if peer.is_some_and(|ip| rule.allow.iter().any(|net| net.contains(&ip))) {
+        return Ok(());
+    }
+
+    warn!(
+        "network ACL refusal: path={} peer={}",
+        path,
+        peer.map_or("unknown".to_string(), |ip| ip.to_string())
+    );
+    Err(GatewayApiError::client_error(
+        StatusCode::FORBIDDEN,
+        format!("Access to '{}' is not allowed from this network", path),
+        "network_not_allowed",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NetworkAcl;
+
+    fn request_from(path: &str, peer: &str) -> Request<()> {
+        let mut req = Request::get(path).body(()).unwrap();
+        req.extensions_mut()
+            .insert(ClientAddr(peer.parse().unwrap()));
+        req
+    }
+
+    #[test]
+    fn test_check() {
+        let config = RouterConfig {
+            network_acls: vec![NetworkAcl {
+                paths: vec!["/config".to_string(), "/metrics".to_string()],
+                allow: vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()],
+            }],
+            ..Default::default()
+        };
+        assert!(check(&request_from("/config", "10.1.2.3:5000"), &config).is_ok());
+        assert!(check(&request_from("/metrics", "[::1]:5000"), &config).is_ok());
+        assert!(check(&request_from("/metrics", "[::ffff:10.0.0.1]:5000"), &config).is_ok());
+        assert!(check(&request_from("/config", "203.0.113.9:5000"), &config).is_err());
+        assert!(check(&request_from("/configs", "203.0.113.9:5000"), &config).is_ok());
+        assert!(check(
+            &request_from("/v1/chat/completions", "203.0.113.9:5000"),
+            &config
+        )
+        .is_ok());
+        assert!(check(&Request::get("/config").body(()).unwrap(), &config).is_err());
+    }
+}
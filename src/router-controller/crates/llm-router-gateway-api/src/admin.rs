@@ -0,0 +1,859 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Admin
+use crate::config::{
+    expand_vars, AdminConfig, AdminRole, FaultInjectionConfig, Llm, Policy, RouterConfig,
+    SharedConfig, REDACTED,
+};
+use crate::controller;
+use crate::conversation;
+use crate::error::{GatewayApiError, IntoResponse};
+use crate::jwt;
+use crate::logging;
+use crate::metrics;
+use crate::proxy::json_response;
+use bytes::Bytes;
+use http::StatusCode;
+use http_body_util::{combinators::BoxBody, BodyExt};
+use hyper::body::Body;
+use hyper::{Method, Request, Response};
+use log::{info, warn};
+use percent_encoding::percent_decode_str;
+use reqwest::header::AUTHORIZATION;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub const POLICIES_PATH: &str = "/admin/policies";
+pub const CONVERSATIONS_PATH: &str = "/admin/conversations/";
+pub const LOG_LEVEL_PATH: &str = "/admin/loglevel";
+pub const FAULTS_PATH: &str = "/admin/faults";
+
+fn role_name(role: AdminRole) -> &'static str {
+    match role {
+        AdminRole::Viewer => "viewer",
+        AdminRole::Operator => "operator",
+        AdminRole::Admin => "admin",
+    }
+}
+
+fn token_eq(token: &str, expected: &str) -> bool {
+    token.len() == expected.len() && openssl::memcmp::eq(token.as_bytes(), expected.as_bytes())
+}
+
+/// The role a bearer token grants and who it was granted to: the admin
+/// key, a configured token, or a JWT whose role claim is bound in
+/// `jwt_roles`.
+async fn role(
+    token: &str,
+    admin: &AdminConfig,
+    config: &RouterConfig,
+) -> Option<(AdminRole, String)> {
+    if token.is_empty() {
+        return None;
+    }
+    if token_eq(token, &admin.api_key) {
+        return Some((AdminRole::Admin, "api_key".to_string()));
+    }
+    if let Some(granted) = admin.tokens.iter().find(|t| token_eq(token, &t.token)) {
+        return Some((granted.role, granted.name.clone()));
+    }
+    let (Some(jwt_roles), Some(jwt_config)) = (&admin.jwt_roles, &config.jwt) else {
+        return None;
+    };
+    let claims = jwt::claims(token, jwt_config).await.ok()?;
+    let values: Vec<&str> = match &claims.get(&jwt_roles.claim) {
+        Some(Value::String(value)) => vec![value.as_str()],
+        Some(Value::Array(values)) => values.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let role = values
+        .iter()
+        .filter_map(|value| jwt_roles.bindings.get(*value))
+        .max()?;
+    let subject = claims.get("sub").and_then(Value::as_str).unwrap_or("jwt");
+    Some((*role, subject.to_string()))
+}
+
+/// Checks that the request's bearer token grants at least `required`.
+async fn authorize<B>(
+    req: &Request<B>,
+    config: &RouterConfig,
+    required: AdminRole,
+) -> Result<(), GatewayApiError> {
+    let admin = config.admin.as_ref().ok_or_else(|| {
+        GatewayApiError::client_error(
+            StatusCode::NOT_FOUND,
+            "Admin API is not enabled",
+            "admin_disabled",
+        )
+    })?;
+
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+
+    match role(token, admin, config).await {
+        Some((role, _)) if role >= required => Ok(()),
+        Some((role, principal)) => {
+            warn!(
+                target: "audit",
+                "Refused {} {} to {} with role {}: requires {}",
+                req.method(),
+                req.uri().path(),
+                principal,
+                role_name(role),
+                role_name(required)
+            );
+            Err(GatewayApiError::client_error(
+                StatusCode::FORBIDDEN,
+                format!(
+                    "{} {} requires the {} role",
+                    req.method(),
+                    req.uri().path(),
+                    role_name(required)
+                ),
+                "insufficient_role",
+            ))
+        }
+        None => {
+            warn!(
+                "Rejected unauthorized admin request to {}",
+                req.uri().path()
+            );
+            Err(GatewayApiError::client_error(
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid admin API key",
+                "unauthorized",
+            ))
+        }
+    }
+}
+
+/// Requires the `viewer` role for read endpoints such as `/config` when
+/// `admin.protect_read_endpoints` is set.
+pub async fn authorize_read<B>(
+    req: &Request<B>,
+    config: &RouterConfig,
+) -> Result<(), GatewayApiError> {
+    match &config.admin {
+        Some(admin) if admin.protect_read_endpoints => {
+            authorize(req, config, AdminRole::Viewer).await
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `viewer` for `GET` requests, `role` for changes.
+fn read_or<B>(req: &Request<B>, role: AdminRole) -> AdminRole {
+    if req.method() == Method::GET {
+        AdminRole::Viewer
+    } else {
+        role
+    }
+}
+
+fn require_method<B>(req: &Request<B>, method: Method) -> Result<(), GatewayApiError> {
+    if req.method() == method {
+        Ok(())
+    } else {
+        Err(GatewayApiError::client_error(
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("{} requires {}", req.uri().path(), method),
+            "method_not_allowed",
+        ))
+    }
+}
+
+pub async fn reset_metrics<B>(
+    req: &Request<B>,
+    config: &RouterConfig,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
+    let authorized = authorize(req, config, AdminRole::Operator).await;
+    if let Err(error) = authorized.and_then(|_| require_method(req, Method::POST)) {
+        return Ok(error.into_response());
+    }
+
+    metrics::reset_metrics();
+    info!("/admin/metrics/reset: all metrics cleared");
+    json_response(StatusCode::OK, &json!({ "status": "OK" }))
+}
+
+/// `/admin/conversations/{caller}/{session_id}`: `GET` returns the
+/// conversation's token totals, `DELETE` resets them.
+pub async fn conversations<B>(
+    req: &Request<B>,
+    config: &RouterConfig,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
+    if let Err(error) = authorize(req, config, read_or(req, AdminRole::Operator)).await {
+        return Ok(error.into_response());
+    }
+
+    let path = req
+        .uri()
+        .path()
+        .strip_prefix(CONVERSATIONS_PATH)
+        .unwrap_or("");
+    let (caller, id) = path.split_once('/').unwrap_or((path, ""));
+    let caller = percent_decode_str(caller).decode_utf8_lossy();
+    let id = percent_decode_str(id).decode_utf8_lossy();
+    let name = format!("{}/{}", caller, id);
+    match *req.method() {
+        Method::GET => match conversation::totals(&caller, &id) {
+            Some(totals) => json_response(StatusCode::OK, &serde_json::to_value(totals)?),
+            None => Ok(not_found("Conversation", &name).into_response()),
+        },
+        Method::DELETE => {
+            if conversation::remove(&caller, &id) {
+                info!("/admin/conversations: reset {}", name);
+                json_response(StatusCode::OK, &json!({ "status": "OK" }))
+            } else {
+                Ok(not_found("Conversation", &name).into_response())
+            }
+        }
+        _ => Ok(GatewayApiError::client_error(
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("{} {} is not supported", req.method(), CONVERSATIONS_PATH),
+            "method_not_allowed",
+        )
+        .into_response()),
+    }
+}
+
+#[derive(Deserialize)]
+struct LogLevelRequest {
+    filter: String,
+    #[serde(default)]
+    revert_after_seconds: Option<u64>,
+}
+
+/// `/admin/loglevel`: `GET` returns the active log filter, `PUT` replaces it.
+pub async fn log_level<B>(
+    req: Request<B>,
+    config: &RouterConfig,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError>
+where
+    B: Body<Data = Bytes>,
+    GatewayApiError: From<B::Error>,
+{
+    if let Err(error) = authorize(&req, config, read_or(&req, AdminRole::Operator)).await {
+        return Ok(error.into_response());
+    }
+
+    match *req.method() {
+        Method::GET => {}
+        Method::PUT => {
+            let body = req.into_body().collect().await?.to_bytes();
+            let request: LogLevelRequest = match parse_body(&body) {
+                Ok(request) => request,
+                Err(error) => return Ok(error.into_response()),
+            };
+            let revert_after = request.revert_after_seconds.map(Duration::from_secs);
+            if let Err(reason) = logging::set_filter(&request.filter, revert_after) {
+                return Ok(GatewayApiError::client_error(
+                    StatusCode::BAD_REQUEST,
+                    reason,
+                    "invalid_log_filter",
+                )
+                .into_response());
+            }
+            warn!(
+                "{}: log filter set to '{}' (revert after {:?})",
+                LOG_LEVEL_PATH, request.filter, revert_after
+            );
+        }
+        _ => {
+            return Ok(GatewayApiError::client_error(
+                StatusCode::METHOD_NOT_ALLOWED,
+                format!("{} {} is not supported", req.method(), LOG_LEVEL_PATH),
+                "method_not_allowed",
+            )
+            .into_response())
+        }
+    }
+    json_response(StatusCode::OK, &json!({ "filter": logging::current() }))
+}
+
+#[derive(Deserialize)]
+struct FaultToggle {
+    enabled: bool,
+}
+
+/// `/admin/faults`: `GET` returns the fault injection settings, `PUT`
+/// replaces them, `PATCH` with `{"enabled": ...}` switches them on or off
+/// and `DELETE` removes them.
+pub async fn faults<B>(
+    req: Request<B>,
+    shared: &SharedConfig,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError>
+where
+    B: Body<Data = Bytes>,
+    GatewayApiError: From<B::Error>,
+{
+    let config = shared.snapshot();
+    if let Err(error) = authorize(&req, &config, read_or(&req, AdminRole::Admin)).await {
+        return Ok(error.into_response());
+    }
+
+    let method = req.method().clone();
+    if method != Method::GET {
+        if let Err(error) = check_persistable(shared, &config) {
+            return Ok(error.into_response());
+        }
+    }
+    let body = req.into_body().collect().await?.to_bytes();
+    let updated = match method {
+        Method::GET => Ok(config),
+        Method::PUT => parse_body::<FaultInjectionConfig>(&body).and_then(|faults| {
+            shared.update(|config| {
+                config.fault_injection = Some(faults);
+                Ok(())
+            })
+        }),
+        Method::PATCH => parse_body::<FaultToggle>(&body).and_then(|toggle| {
+            shared.update(|config| {
+                let faults = config.fault_injection.get_or_insert_with(Default::default);
+                faults.enabled = toggle.enabled;
+                Ok(())
+            })
+        }),
+        Method::DELETE => shared.update(|config| {
+            config.fault_injection = None;
+            Ok(())
+        }),
+        _ => Err(GatewayApiError::client_error(
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("{} {} is not supported", method, FAULTS_PATH),
+            "method_not_allowed",
+        )),
+    };
+    let updated = match updated {
+        Ok(updated) => updated,
+        Err(error) => return Ok(error.into_response()),
+    };
+
+    if method != Method::GET {
+        persist(shared, &updated)?;
+        warn!(
+            "{} {}: fault injection is now {:?}",
+            method, FAULTS_PATH, updated.fault_injection
+        );
+    }
+    let faults = updated.fault_injection.unwrap_or(FaultInjectionConfig {
+        enabled: false,
+        faults: Vec::new(),
+    });
+    json_response(StatusCode::OK, &serde_json::to_value(faults)?)
+}
+
+fn not_found(what: &str, name: &str) -> GatewayApiError {
+    GatewayApiError::client_error(
+        StatusCode::NOT_FOUND,
+        format!("{} '{}' not found", what, name),
+        format!("{}_not_found", what.to_lowercase()),
+    )
+}
+
+fn conflict(what: &str, name: &str) -> GatewayApiError {
+    GatewayApiError::client_error(
+        StatusCode::CONFLICT,
+        format!("{} '{}' already exists", what, name),
+        format!("{}_exists", what.to_lowercase()),
+    )
+}
+
+fn parse_body<T: DeserializeOwned>(body: &Bytes) -> Result<T, GatewayApiError> {
+    serde_json::from_slice(body).map_err(|e| {
+        GatewayApiError::client_error(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid request body: {}", e),
+            "invalid_request_body",
+        )
+    })
+}
+
+/// Parses a body addressed by name in the path; the path name always wins.
+fn parse_named_body<T: DeserializeOwned>(body: &Bytes, name: &str) -> Result<T, GatewayApiError> {
+    let mut value: serde_json::Value = parse_body(body)?;
+    if let Some(object) = value.as_object_mut() {
+        object.insert("name".to_string(), json!(name));
+    }
+    serde_json::from_value(value).map_err(|e| {
+        GatewayApiError::client_error(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid request body: {}", e),
+            "invalid_request_body",
+        )
+    })
+}
+
+/// Keeps the stored key when a client echoes back the redacted placeholder
+/// it received from a GET.
+fn keep_redacted_key(mut llm: Llm, existing: Option<&Llm>) -> Llm {
+    if llm.api_key == REDACTED {
+        if let Some(existing) = existing {
+            llm.api_key = existing.api_key.clone();
+        }
+    }
+    llm
+}
+
+fn find_policy<'a>(
+    config: &'a mut RouterConfig,
+    name: &str,
+) -> Result<&'a mut Policy, GatewayApiError> {
+    config
+        .policies
+        .iter_mut()
+        .find(|policy| policy.name == name)
+        .ok_or_else(|| not_found("Policy", name))
+}
+
+/// Refuses changes `persist` would save while the configuration file has
+/// `${NAME}` references: saving writes out their values, putting the
+/// secrets they keep out of the file into it.
+fn check_persistable(shared: &SharedConfig, config: &RouterConfig) -> Result<(), GatewayApiError> {
+    let enabled = config.admin.as_ref().is_some_and(|admin| admin.persist);
+    let Some(path) = shared.path().filter(|_| enabled) else {
+        return Ok(());
+    };
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Ok(());
+    };
+    // Only a reference can fail to expand.
+    if expand_vars(&content, |_| None).is_ok() {
+        return Ok(());
+    }
+    Err(GatewayApiError::client_error(
+        StatusCode::CONFLICT,
+        format!(
+            "{} has ${{NAME}} references, which admin.persist would save expanded",
+            path
+        ),
+        "persist_unsupported",
+    ))
+}
+
+/// Saves `updated` to the configuration file when `admin.persist` is set,
+/// leaving out the policies of `RouterPolicy` resources.
+fn persist(shared: &SharedConfig, updated: &RouterConfig) -> Result<(), GatewayApiError> {
+    let enabled = updated.admin.as_ref().is_some_and(|admin| admin.persist);
+    if let (true, Some(path)) = (enabled, shared.path()) {
+        controller::unmerged(updated).save_config(path)?;
+        info!("Persisted admin change to {}", path);
+    }
+    Ok(())
+}
+
+/// `/admin/policies[/{policy}[/llms[/{llm}]]]`
+pub async fn policies<B>(
+    req: Request<B>,
+    shared: &SharedConfig,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError>
+where
+    B: Body<Data = Bytes>,
+    GatewayApiError: From<B::Error>,
+{
+    let config = shared.snapshot();
+    if let Err(error) = authorize(&req, &config, read_or(&req, AdminRole::Admin)).await {
+        return Ok(error.into_response());
+    }
+
+    let segments: Vec<String> = req
+        .uri()
+        .path()
+        .strip_prefix(POLICIES_PATH)
+        .unwrap_or("")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        .collect();
+    let method = req.method().clone();
+    if method != Method::GET {
+        if let Err(error) = check_persistable(shared, &config) {
+            return Ok(error.into_response());
+        }
+    }
+    let body = req.into_body().collect().await?.to_bytes();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    match route_policies(shared, config, &method, &segments, &body) {
+        Ok(response) => Ok(response),
+        Err(error) => Ok(error.into_response()),
+    }
+}
+
+fn route_policies(
+    shared: &SharedConfig,
+    config: RouterConfig,
+    method: &Method,
+    segments: &[&str],
+    body: &Bytes,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
+    let updated = match (method, segments) {
+        (&Method::GET, []) => {
+            let policies: Vec<Policy> = config.policies.iter().map(Policy::sanitized).collect();
+            return json_response(StatusCode::OK, &serde_json::to_value(policies)?);
+        }
+        (&Method::GET, [name]) => {
+            let policy = config
+                .get_policy_by_name(name)
+                .ok_or_else(|| not_found("Policy", name))?;
+            return json_response(StatusCode::OK, &serde_json::to_value(policy.sanitized())?);
+        }
+        (&Method::GET, [name, "llms"]) => {
+            let policy = config
+                .get_policy_by_name(name)
+                .ok_or_else(|| not_found("Policy", name))?;
+            let llms: Vec<Llm> = policy.llms.iter().map(Llm::sanitized).collect();
+            return json_response(StatusCode::OK, &serde_json::to_value(llms)?);
+        }
+        (&Method::GET, [name, "llms", llm_name]) => {
+            let policy = config
+                .get_policy_by_name(name)
+                .ok_or_else(|| not_found("Policy", name))?;
+            let llm = policy
+                .get_llm_by_name(llm_name)
+                .ok_or_else(|| not_found("Llm", llm_name))?;
+            return json_response(StatusCode::OK, &serde_json::to_value(llm.sanitized())?);
+        }
+        (&Method::POST, []) => {
+            let policy: Policy = parse_body(body)?;
+            shared.update(|config| {
+                if config.policies.iter().any(|p| p.name == policy.name) {
+                    return Err(conflict("Policy", &policy.name));
+                }
+                config.policies.push(policy);
+                Ok(())
+            })?
+        }
+        (&Method::PUT, [name]) => {
+            let mut policy: Policy = parse_named_body(body, name)?;
+            shared.update(|config| {
+                let existing = find_policy(config, name)?;
+                policy.llms = policy
+                    .llms
+                    .into_iter()
+                    .map(|llm| {
+                        let previous = existing.llms.iter().find(|l| l.name == llm.name);
+                        keep_redacted_key(llm, previous)
+                    })
+                    .collect();
+                *existing = policy;
+                Ok(())
+            })?
+        }
+        (&Method::DELETE, [name]) => shared.update(|config| {
+            find_policy(config, name)?;
+            config.policies.retain(|policy| policy.name != *name);
+            Ok(())
+        })?,
+        (&Method::POST, [name, "llms"]) => {
+            let llm: Llm = parse_body(body)?;
+            shared.update(|config| {
+                let policy = find_policy(config, name)?;
+                if policy.llms.iter().any(|l| l.name == llm.name) {
+                    return Err(conflict("Llm", &llm.name));
+                }
+                policy.llms.push(llm);
+                Ok(())
+            })?
+        }
+        (&Method::PUT, [name, "llms", llm_name]) => {
+            let llm: Llm = parse_named_body(body, llm_name)?;
+            shared.update(|config| {
+                let policy = find_policy(config, name)?;
+                let existing = policy
+                    .llms
+                    .iter_mut()
+                    .find(|l| l.name == *llm_name)
+                    .ok_or_else(|| not_found("Llm", llm_name))?;
+                *existing = keep_redacted_key(llm, Some(existing));
+                Ok(())
+            })?
+        }
+        (&Method::DELETE, [name, "llms", llm_name]) => shared.update(|config| {
+            let policy = find_policy(config, name)?;
+            if policy.get_llm_by_name(llm_name).is_none() {
+                return Err(not_found("Llm", llm_name));
+            }
+            policy.llms.retain(|llm| llm.name != *llm_name);
+            Ok(())
+        })?,
+        _ => {
+            return Err(GatewayApiError::client_error(
+                StatusCode::METHOD_NOT_ALLOWED,
+                format!("{} {} is not supported", method, POLICIES_PATH),
+                "method_not_allowed",
+            ))
+        }
+    };
+
+    persist(shared, &updated)?;
+    info!("{} {}: configuration updated", method, POLICIES_PATH);
+    let policies: Vec<Policy> = updated.policies.iter().map(Policy::sanitized).collect();
+    json_response(StatusCode::OK, &serde_json::to_value(policies)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AdminToken;
+    use http_body_util::Full;
+
+    fn admin_config() -> RouterConfig {
+        RouterConfig {
+            admin: Some(AdminConfig {
+                api_key: "secret".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn admin_request(method: Method, path: &str, body: serde_json::Value) -> Request<Full<Bytes>> {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .header(AUTHORIZATION, "Bearer secret")
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_admin_disabled_without_config() {
+        let req = Request::post("/admin/metrics/reset").body(()).unwrap();
+        let response = reset_metrics(&req, &RouterConfig::default()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_admin_rejects_wrong_key() {
+        let req = Request::post("/admin/metrics/reset")
+            .header(AUTHORIZATION, "Bearer wrong")
+            .body(())
+            .unwrap();
+        let response = reset_metrics(&req, &admin_config()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_roles() {
+        let mut config = admin_config();
+        if let Some(admin) = config.admin.as_mut() {
+            admin.tokens = vec![AdminToken {
+                name: "dashboard".to_string(),
+                token: "viewer-token".to_string(),
+                role: AdminRole::Viewer,
+            }];
+            admin.protect_read_endpoints = true;
+        }
+        let shared = SharedConfig::new(config.clone(), None);
+        let viewer = |method: Method, path: &str| {
+            Request::builder()
+                .method(method)
+                .uri(path)
+                .header(AUTHORIZATION, "Bearer viewer-token")
+                .body(Full::new(Bytes::from("{}")))
+                .unwrap()
+        };
+
+        let response = policies(viewer(Method::GET, "/admin/policies"), &shared)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = policies(viewer(Method::DELETE, "/admin/policies/any"), &shared)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = conversations(
+            &viewer(Method::DELETE, "/admin/conversations/ip:unknown/s1"),
+            &config,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let anonymous = Request::get("/config").body(()).unwrap();
+        assert!(authorize_read(&anonymous, &config).await.is_err());
+        assert!(authorize_read(&viewer(Method::GET, "/config"), &config)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_policy_crud() {
+        let shared = SharedConfig::new(admin_config(), None);
+        let policy = json!({
+            "name": "new_policy",
+            "url": "http://triton:8000",
+            "llms": [{
+                "name": "Chatbot",
+                "api_base": "https://integrate.api.nvidia.com",
+                "api_key": "key",
+                "model": "meta/llama-3.1-8b-instruct"
+            }]
+        });
+
+        let response = policies(
+            admin_request(Method::POST, "/admin/policies", policy),
+            &shared,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(shared.snapshot().get_policy_by_name("new_policy").is_some());
+
+        // Echoing back the redacted key keeps the stored one.
+        let llm = json!({
+            "api_base": "https://integrate.api.nvidia.com",
+            "api_key": REDACTED,
+            "model": "meta/llama-3.1-70b-instruct"
+        });
+        let path = "/admin/policies/new_policy/llms/Chatbot";
+        let response = policies(admin_request(Method::PUT, path, llm), &shared)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let updated = shared
+            .snapshot()
+            .get_policy_by_name("new_policy")
+            .and_then(|p| p.get_llm_by_name("Chatbot"))
+            .unwrap();
+        assert_eq!(updated.model, "meta/llama-3.1-70b-instruct");
+        assert_eq!(updated.api_key, "key");
+
+        // Invalid LLMs are rejected without touching the live config.
+        let invalid = json!({ "name": "Broken", "api_base": "", "api_key": "k", "model": "m" });
+        let path = "/admin/policies/new_policy/llms";
+        let response = policies(admin_request(Method::POST, path, invalid), &shared)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let path = "/admin/policies/new_policy";
+        let response = policies(admin_request(Method::DELETE, path, json!({})), &shared)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(shared.snapshot().policies.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_faults() {
+        let mut config = admin_config();
+        config.policies = vec![Policy {
+            name: "chaos".to_string(),
+            url: "http://triton:8000".to_string(),
+            llms: vec![Llm {
+                name: "flaky".to_string(),
+                api_base: "https://integrate.api.nvidia.com".to_string(),
+                api_key: "key".to_string(),
+                model: "meta/llama-3.1-8b-instruct".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        let shared = SharedConfig::new(config, None);
+
+        let fault = json!({"faults": [{"llm": "flaky", "delay_percent": 50, "delay_ms": 2000}]});
+        let response = faults(admin_request(Method::PUT, FAULTS_PATH, fault), &shared)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let installed = shared.snapshot().fault_injection.unwrap();
+        assert!(installed.enabled);
+        assert_eq!(installed.faults[0].delay_ms, 2000);
+
+        let toggle = json!({"enabled": false});
+        let response = faults(admin_request(Method::PATCH, FAULTS_PATH, toggle), &shared)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let installed = shared.snapshot().fault_injection.unwrap();
+        assert!(!installed.enabled);
+        assert_eq!(installed.faults.len(), 1);
+
+        // Invalid faults leave the installed ones in place.
+        for invalid in [
+            json!({"faults": [{"abort_percent": 150}]}),
+            json!({"faults": [{"llm": "steady", "error_percent": 10}]}),
+        ] {
+            let response = faults(admin_request(Method::PUT, FAULTS_PATH, invalid), &shared)
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(shared.snapshot().fault_injection.unwrap().faults.len(), 1);
+
+        let response = faults(
+            admin_request(Method::DELETE, FAULTS_PATH, json!({})),
+            &shared,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(shared.snapshot().fault_injection.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_persist_refused_with_references() {
+        let path = std::env::temp_dir().join(format!(
+            "llm-router-persist-test-{}.yaml",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            "admin:\n  api_key: ${ADMIN_API_KEY}\n  persist: true\n",
+        )
+        .unwrap();
+        let mut config = admin_config();
+        if let Some(admin) = config.admin.as_mut() {
+            admin.persist = true;
+        }
+        let shared = SharedConfig::new(config, Some(path.to_string_lossy().into_owned()));
+
+        let toggle = json!({"enabled": true});
+        let response = faults(admin_request(Method::PATCH, FAULTS_PATH, toggle), &shared)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert!(shared.snapshot().fault_injection.is_none());
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("${ADMIN_API_KEY}"));
+        let response = policies(
+            admin_request(Method::GET, POLICIES_PATH, json!({})),
+            &shared,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        std::fs::write(&path, "admin:\n  api_key: secret\n  persist: true\n").unwrap();
+        let response = policies(
+            admin_request(Method::DELETE, "/admin/policies/missing", json!({})),
+            &shared,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,322 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Admission
+//!
+//! A gateway-wide limit on completion requests in flight. Requests over it
+//! wait in a bounded queue and are shed with a `503` when the queue is full
+//! or their wait runs out, before their bodies are read, so a traffic spike
+//! cannot exhaust the router's memory.
+//!
+//! The queue is ordered by priority: freed slots go to the highest priority
+//! waiting, and a full queue makes room for a request by shedding the
+//! newest one of a lower priority.
+use crate::config::{AdmissionConfig, Policy, Priority, Tenant};
+use crate::error::{GatewayApiError, IntoResponse};
+use crate::metrics::{ADMISSION_IN_FLIGHT, ADMISSION_QUEUE_DEPTH, ADMISSION_SHED};
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue, StatusCode};
+use http_body_util::combinators::BoxBody;
+use hyper::Response;
+use lazy_static::lazy_static;
+use log::warn;
+use reqwest::header::RETRY_AFTER;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Place in the queue: highest priority first, then oldest first.
+type Place = (Reverse<Priority>, u64);
+
+#[derive(Default)]
+struct Gate {
+    limit: usize,
+    in_flight: usize,
+    arrivals: u64,
+    waiting: BTreeMap<Place, oneshot::Sender<Result<(), Shed>>>,
+}
+
+impl Gate {
+    /// Hands free slots to the requests first in the queue.
+    fn grant(&mut self) {
+        while self.in_flight < self.limit {
+            let Some((_, waiter)) = self.waiting.pop_first() else {
+                break;
+            };
+            if waiter.send(Ok(())).is_ok() {
+                self.in_flight += 1;
+            }
+        }
+        self.report();
+    }
+
+    fn report(&self) {
+        ADMISSION_IN_FLIGHT.set(self.in_flight as i64);
+        ADMISSION_QUEUE_DEPTH.set(self.waiting.len() as i64);
+    }
+}
+
+lazy_static! {
+    static ref GATE: Mutex<Gate> = Mutex::new(Gate::default());
+}
+
+fn gate() -> MutexGuard<'static, Gate> {
+    GATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn release() {
+    let mut gate = gate();
+    gate.in_flight = gate.in_flight.saturating_sub(1);
+    gate.grant();
+}
+
+/// Why a request was shed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shed {
+    QueueFull,
+    QueueTimeout,
+    /// Displaced from a full queue by a request of a higher priority.
+    Preempted,
+}
+
+impl Shed {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::QueueFull => "queue_full",
+            Self::QueueTimeout => "queue_timeout",
+            Self::Preempted => "preempted",
+        }
+    }
+}
+
+/// A request's slot, given back when it is dropped.
+pub struct Admitted(());
+
+impl Drop for Admitted {
+    fn drop(&mut self) {
+        release();
+    }
+}
+
+/// A place in the queue, left when dropped, including when the client
+/// disconnects while waiting.
+struct Ticket {
+    place: Place,
+    answer: oneshot::Receiver<Result<(), Shed>>,
+    answered: bool,
+}
+
+impl Ticket {
+    /// Leaves the queue, returning the answer given meanwhile, if any.
+    fn leave(&mut self) -> Option<Result<(), Shed>> {
+        self.answered = true;
+        let mut gate = gate();
+        if gate.waiting.remove(&self.place).is_some() {
+            gate.report();
+            return None;
+        }
+        self.answer.try_recv().ok()
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if !self.answered && self.leave() == Some(Ok(())) {
+            release();
+        }
+    }
+}
+
+/// The priority of a request: its tenant's, else that of the policy it was
+/// routed to. The `priority_header` can only lower it, as anyone can send
+/// the header.
+pub fn priority(
+    config: &AdmissionConfig,
+    tenant: Option<&Tenant>,
+    headers: &HeaderMap,
+    policy: Option<&Policy>,
+) -> Priority {
+    let requested = config
+        .priority_header
+        .as_ref()
+        .and_then(|header| headers.get(header.as_str()))
+        .and_then(|value| value.to_str().ok())
+        .and_then(Priority::parse);
+    let granted = tenant
+        .and_then(|tenant| tenant.priority)
+        .or_else(|| policy.and_then(|policy| policy.priority))
+        .unwrap_or_default();
+    requested.map_or(granted, |requested| requested.min(granted))
+}
+
+/// Takes a slot, waiting in the queue for up to `queue_timeout_ms` when
+/// every slot is taken.
+pub async fn admit(config: &AdmissionConfig, priority: Priority) -> Result<Admitted, Shed> {
+    let mut ticket = {
+        let mut gate = gate();
+        gate.limit = config.max_in_flight;
+        gate.grant();
+        if gate.in_flight < gate.limit {
+            gate.in_flight += 1;
+            gate.report();
+            return Ok(Admitted(()));
+        }
+        if gate.waiting.len() >= config.max_queue {
+            match gate.waiting.last_key_value() {
+                Some(((Reverse(last), _), _)) if *last < priority => {
+                    if let Some((_, displaced)) = gate.waiting.pop_last() {
+                        let _ = displaced.send(Err(Shed::Preempted));
+                    }
+                }
+                _ => return Err(Shed::QueueFull),
+            }
+        }
+        gate.arrivals += 1;
+        let place = (Reverse(priority), gate.arrivals);
+        let (waiter, answer) = oneshot::channel();
+        gate.waiting.insert(place, waiter);
+        gate.report();
+        Ticket {
+            place,
+            answer,
+            answered: false,
+        }
+    };
+    let wait = Duration::from_millis(config.queue_timeout_ms);
+    let answer = match tokio::time::timeout(wait, &mut ticket.answer).await {
+        Ok(answer) => {
+            ticket.answered = true;
+            answer.unwrap_or(Err(Shed::QueueTimeout))
+        }
+        Err(_) => ticket.leave().unwrap_or(Err(Shed::QueueTimeout)),
+    };
+    answer.map(|()| Admitted(()))
+}
+
+/// `503` `overloaded` carrying `Retry-After`.
+pub fn shed_response(shed: Shed, priority: Priority) -> Response<BoxBody<Bytes, GatewayApiError>> {
+    warn!(
+        "Shed {} priority request: {}",
+        priority.as_str(),
+        shed.as_str()
+    );
+    ADMISSION_SHED
+        .with_label_values(&[shed.as_str(), priority.as_str()])
+        .inc();
+    let error = GatewayApiError::client_error(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "The gateway is overloaded, retry later",
+        "overloaded",
+    );
+    let mut response = match error.to_response() {
+        Ok(response) => response,
+        Err(error) => error.into_response(),
+    };
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from_static("1"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued() -> usize {
+        gate().waiting.len()
+    }
+
+    #[test]
+    fn test_priority() {
+        let config = AdmissionConfig {
+            priority_header: Some("x-priority".to_string()),
+            ..Default::default()
+        };
+        let tenant = Tenant {
+            priority: Some(Priority::High),
+            ..Default::default()
+        };
+        let policy = Policy {
+            priority: Some(Priority::Low),
+            ..Default::default()
+        };
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            priority(&config, Some(&tenant), &headers, Some(&policy)),
+            Priority::High
+        );
+        assert_eq!(
+            priority(&config, None, &headers, Some(&policy)),
+            Priority::Low
+        );
+        headers.insert("x-priority", HeaderValue::from_static("Normal"));
+        assert_eq!(
+            priority(&config, Some(&tenant), &headers, Some(&policy)),
+            Priority::Normal
+        );
+        // The header cannot raise a request above its policy's priority.
+        assert_eq!(
+            priority(&config, None, &headers, Some(&policy)),
+            Priority::Low
+        );
+        headers.insert("x-priority", HeaderValue::from_static("high"));
+        assert_eq!(priority(&config, None, &headers, None), Priority::Normal);
+        headers.insert("x-priority", HeaderValue::from_static("urgent"));
+        assert_eq!(priority(&config, None, &headers, None), Priority::Normal);
+    }
+
+    #[tokio::test]
+    async fn test_admit() {
+        let config = AdmissionConfig {
+            max_in_flight: 1,
+            max_queue: 1,
+            queue_timeout_ms: 50,
+            ..Default::default()
+        };
+        let first = admit(&config, Priority::Normal).await.unwrap();
+        assert_eq!(
+            admit(&config, Priority::Normal).await.err(),
+            Some(Shed::QueueTimeout)
+        );
+
+        let patient = AdmissionConfig {
+            queue_timeout_ms: 5_000,
+            ..config.clone()
+        };
+        let low = tokio::spawn({
+            let patient = patient.clone();
+            async move { admit(&patient, Priority::Low).await.err() }
+        });
+        while queued() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(
+            admit(&config, Priority::Low).await.err(),
+            Some(Shed::QueueFull)
+        );
+
+        let high = tokio::spawn(async move { admit(&patient, Priority::High).await.is_ok() });
+        assert_eq!(low.await.unwrap(), Some(Shed::Preempted));
+        drop(first);
+        assert!(high.await.unwrap());
+        assert_eq!(queued(), 0);
+
+        let response = shed_response(Shed::QueueFull, Priority::Low);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[RETRY_AFTER], "1");
+    }
+}
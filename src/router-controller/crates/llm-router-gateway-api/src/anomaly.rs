@@ -0,0 +1,256 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Anomaly
+//!
+//! Background detection of routing regressions: a sudden shift in the class
+//! distribution, a spike in low-confidence decisions, or a single LLM
+//! absorbing all of a policy's traffic.
+use crate::classifier::Classification;
+use crate::config::{AnomalyDetectionConfig, Policy, SharedConfig};
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default)]
+struct WindowCounts {
+    per_llm: HashMap<String, u64>,
+    total: u64,
+    low_confidence: u64,
+}
+
+#[derive(Debug, Default)]
+struct PolicyWindow {
+    llm_count: usize,
+    current: WindowCounts,
+    previous: Option<WindowCounts>,
+}
+
+lazy_static! {
+    static ref WINDOWS: Mutex<HashMap<String, PolicyWindow>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    ClassDistributionShift,
+    LowConfidenceSpike,
+    SingleModelSaturation,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    pub policy: String,
+    pub message: String,
+    pub value: f64,
+}
+
+/// Counts one classifier decision towards the policy's current window.
+pub fn record_decision(
+    config: &AnomalyDetectionConfig,
+    policy: &Policy,
+    classification: &Classification,
+) {
+    let Some(llm_name) = policy.get_llm_name_by_index(classification.index) else {
+        return;
+    };
+    let mut windows = WINDOWS.lock().unwrap_or_else(|e| e.into_inner());
+    let window = windows.entry(policy.name.clone()).or_default();
+    window.llm_count = policy.llms.len();
+    *window.current.per_llm.entry(llm_name).or_default() += 1;
+    window.current.total += 1;
+    if classification
+        .confidence()
+        .is_some_and(|c| c < config.low_confidence_threshold)
+    {
+        window.current.low_confidence += 1;
+    }
+}
+
+fn share(counts: &WindowCounts, llm: &str) -> f64 {
+    if counts.total == 0 {
+        return 0.0;
+    }
+    *counts.per_llm.get(llm).unwrap_or(&0) as f64 / counts.total as f64
+}
+
+/// Total variation distance between two class distributions.
+fn distribution_shift(current: &WindowCounts, previous: &WindowCounts) -> f64 {
+    let llms: HashSet<&String> = current
+        .per_llm
+        .keys()
+        .chain(previous.per_llm.keys())
+        .collect();
+    llms.into_iter()
+        .map(|llm| (share(current, llm) - share(previous, llm)).abs())
+        .sum::<f64>()
+        / 2.0
+}
+
+fn evaluate(config: &AnomalyDetectionConfig, policy: &str, window: &PolicyWindow) -> Vec<Anomaly> {
+    let current = &window.current;
+    let mut anomalies = Vec::new();
+
+    if let Some(previous) = &window.previous {
+        let shift = distribution_shift(current, previous);
+        if shift >= config.distribution_shift_threshold {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::ClassDistributionShift,
+                policy: policy.to_string(),
+                message: format!(
+                    "class distribution shifted by {:.2} since the previous window",
+                    shift
+                ),
+                value: shift,
+            });
+        }
+    }
+
+    let low_confidence = current.low_confidence as f64 / current.total as f64;
+    if low_confidence >= config.low_confidence_ratio {
+        anomalies.push(Anomaly {
+            kind: AnomalyKind::LowConfidenceSpike,
+            policy: policy.to_string(),
+            message: format!(
+                "{:.0}% of decisions scored below {}",
+                low_confidence * 100.0,
+                config.low_confidence_threshold
+            ),
+            value: low_confidence,
+        });
+    }
+
+    if window.llm_count > 1 {
+        if let Some((llm, &count)) = current.per_llm.iter().find(|(_, &c)| c == current.total) {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::SingleModelSaturation,
+                policy: policy.to_string(),
+                message: format!("'{}' received all {} routed requests", llm, count),
+                value: 1.0,
+            });
+        }
+    }
+
+    anomalies
+}
+
+/// Evaluates and rotates every policy window that has enough decisions.
+fn check_windows(config: &AnomalyDetectionConfig) -> Vec<Anomaly> {
+    let mut windows = WINDOWS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut anomalies = Vec::new();
+    for (policy, window) in windows.iter_mut() {
+        if window.current.total < config.min_requests {
+            continue;
+        }
+        anomalies.extend(evaluate(config, policy, window));
+        window.previous = Some(std::mem::take(&mut window.current));
+    }
+    anomalies
+}
+
+async fn notify(client: &reqwest::Client, webhook_url: &str, anomaly: &Anomaly) {
+    let result = client
+        .post(webhook_url)
+        .timeout(Duration::from_secs(5))
+        .json(anomaly)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = result {
+        error!("Failed to deliver anomaly webhook: {:?}", e);
+    }
+}
+
+/// Starts the detector if `anomaly_detection` is configured.
+pub fn spawn(config: SharedConfig) {
+    let Some(initial) = config.snapshot().anomaly_detection else {
+        return;
+    };
+    info!(
+        "Routing anomaly detection enabled every {}s",
+        initial.interval_seconds
+    );
+
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(initial.interval_seconds.max(1)));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let Some(detection) = config.snapshot().anomaly_detection else {
+                continue;
+            };
+            for anomaly in check_windows(&detection) {
+                warn!(
+                    target: "anomaly",
+                    "routing anomaly: policy={} kind={:?} {}",
+                    anomaly.policy,
+                    anomaly.kind,
+                    anomaly.message
+                );
+                if let Some(webhook_url) = &detection.webhook_url {
+                    notify(&client, webhook_url, &anomaly).await;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(per_llm: &[(&str, u64)], low_confidence: u64) -> WindowCounts {
+        WindowCounts {
+            per_llm: per_llm.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+            total: per_llm.iter().map(|(_, v)| v).sum(),
+            low_confidence,
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let config = AnomalyDetectionConfig::default();
+        let healthy = PolicyWindow {
+            llm_count: 2,
+            current: counts(&[("a", 12), ("b", 8)], 1),
+            previous: Some(counts(&[("a", 10), ("b", 10)], 0)),
+        };
+        assert!(evaluate(&config, "p", &healthy).is_empty());
+
+        let regressed = PolicyWindow {
+            llm_count: 2,
+            current: counts(&[("a", 20)], 10),
+            previous: Some(counts(&[("a", 10), ("b", 10)], 0)),
+        };
+        let kinds: Vec<AnomalyKind> = evaluate(&config, "p", &regressed)
+            .into_iter()
+            .map(|a| a.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                AnomalyKind::ClassDistributionShift,
+                AnomalyKind::LowConfidenceSpike,
+                AnomalyKind::SingleModelSaturation
+            ]
+        );
+    }
+}
@@ -0,0 +1,417 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Audit
+//!
+//! One JSON lines record per completion request: who sent it, the policy and
+//! LLM that served it, its status, latency and token counts, and the prompt
+//! and response as the redaction settings allow. Records go to stdout or a
+//! file rotated by size. A streamed request is recorded when its stream
+//! ends, once its usage is known.
+use crate::background;
+use crate::config::{AuditContent, AuditLogConfig};
+use crate::metrics::RequestLabels;
+use crate::pii::{self, RedactionMode};
+use crate::privacy;
+use crate::request_context::RequestContext;
+use bytes::Bytes;
+use http::StatusCode;
+use log::{error, info, warn};
+use serde_json::{json, Map, Value};
+use std::io;
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// Records buffered in memory before new ones are dropped.
+const CHANNEL_CAPACITY: usize = 10_000;
+
+static AUDIT: OnceLock<(AuditLogConfig, mpsc::Sender<Value>)> = OnceLock::new();
+
+fn timestamp_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis())
+}
+
+/// Strings of a `content`, `prompt` or `input` field: the string itself, or
+/// the strings and text parts of an array.
+fn text_parts(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(text) => vec![text.as_str()],
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str().or_else(|| item["text"].as_str()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Every message of a chat request, or the `prompt` or `input` of a legacy
+/// completion or embeddings request.
+fn prompt_text(json: &Value) -> String {
+    let parts = match json["messages"].as_array() {
+        Some(messages) => messages
+            .iter()
+            .flat_map(|message| text_parts(&message["content"]))
+            .collect(),
+        None if !json["prompt"].is_null() => text_parts(&json["prompt"]),
+        None => text_parts(&json["input"]),
+    };
+    parts.join("\n")
+}
+
+/// Text of the choices of a non-streaming response.
+fn response_text(json: &Value) -> String {
+    json["choices"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|choice| {
+            choice["message"]["content"]
+                .as_str()
+                .or_else(|| choice["text"].as_str())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `text` as `mode` allows. Privacy mode only allows hashes.
+fn content(text: &str, mode: AuditContent, max_chars: usize) -> Option<String> {
+    let mode = match mode {
+        AuditContent::Redacted if privacy::enabled() => AuditContent::Hash,
+        mode => mode,
+    };
+    match mode {
+        AuditContent::Omit => None,
+        AuditContent::Hash => Some(
+            openssl::sha::sha256(text.as_bytes())
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+        ),
+        AuditContent::Redacted => Some(
+            pii::redact(text, RedactionMode::Hash)
+                .chars()
+                .take(max_chars)
+                .collect(),
+        ),
+    }
+}
+
+/// A request's record, filled in as the request is served.
+#[derive(Debug)]
+pub struct Record {
+    timestamp_ms: u128,
+    start: Instant,
+    path: String,
+    stream: bool,
+    subject: Option<String>,
+    tenant: Option<String>,
+    prompt: String,
+    status: Option<u16>,
+    response: Option<Bytes>,
+}
+
+/// Starts the record of a request received at `start`. `None` when the
+/// audit log is not configured.
+pub fn begin(
+    path: &str,
+    context: &RequestContext,
+    json: &Value,
+    stream: bool,
+    start: Instant,
+) -> Option<Record> {
+    AUDIT.get()?;
+    let context = privacy::export(context);
+    Some(Record {
+        timestamp_ms: timestamp_ms(),
+        start,
+        path: path.to_string(),
+        stream,
+        subject: context.subject,
+        tenant: context.tenant,
+        prompt: prompt_text(json),
+        status: None,
+        response: None,
+    })
+}
+
+impl Record {
+    pub fn set_status(&mut self, status: StatusCode) {
+        self.status = Some(status.as_u16());
+    }
+
+    /// Body of a non-streaming response, for its usage and text.
+    pub fn set_response(&mut self, body: Bytes) {
+        self.response = Some(body);
+    }
+
+    fn to_json(
+        &self,
+        config: &AuditLogConfig,
+        labels: &RequestLabels,
+        usage: Option<Value>,
+    ) -> Value {
+        let response = self
+            .response
+            .as_deref()
+            .and_then(|body| serde_json::from_slice::<Value>(body).ok());
+        let usage = usage
+            .or_else(|| response.as_ref().map(|json| json["usage"].clone()))
+            .unwrap_or(Value::Null);
+        let mut record = Map::new();
+        record.insert("timestamp_ms".to_string(), json!(self.timestamp_ms));
+        record.insert("path".to_string(), json!(self.path));
+        record.insert("subject".to_string(), json!(self.subject));
+        record.insert("tenant".to_string(), json!(self.tenant));
+        record.insert("policy".to_string(), json!(labels.policy));
+        record.insert("strategy".to_string(), json!(labels.strategy));
+        record.insert("model".to_string(), json!(labels.model));
+        record.insert("status".to_string(), json!(self.status));
+        record.insert("stream".to_string(), json!(self.stream));
+        record.insert(
+            "latency_seconds".to_string(),
+            json!(self.start.elapsed().as_secs_f64()),
+        );
+        record.insert(
+            "usage".to_string(),
+            json!({
+                "prompt_tokens": usage["prompt_tokens"].as_u64(),
+                "completion_tokens": usage["completion_tokens"].as_u64(),
+                "total_tokens": usage["total_tokens"].as_u64(),
+            }),
+        );
+        if let Some(prompt) = content(&self.prompt, config.prompt, config.max_chars) {
+            record.insert("prompt".to_string(), json!(prompt));
+        }
+        if let Some(text) = response.as_ref().map(response_text) {
+            if let Some(text) = content(&text, config.response, config.max_chars) {
+                record.insert("response".to_string(), json!(text));
+            }
+        }
+        Value::Object(record)
+    }
+
+    /// Queues the record, under the labels the request was served with.
+    /// `usage` is the usage block of a streamed response; that of a
+    /// non-streaming one is read from its body.
+    pub fn finish(self, labels: &RequestLabels, usage: Option<&Value>) {
+        let Some((config, sender)) = AUDIT.get() else {
+            return;
+        };
+        let labels = labels.clone();
+        let usage = usage.cloned();
+        background::submit("audit", move || {
+            let record = self.to_json(config, &labels, usage);
+            if sender.try_send(record).is_err() {
+                warn!("Audit log queue full, dropping record");
+            }
+        });
+    }
+}
+
+enum Output {
+    Stdout(tokio::io::Stdout),
+    File(tokio::fs::File),
+}
+
+/// Appends records to stdout or `path`, rotating the file once it would
+/// grow past `max_file_bytes`.
+struct Writer {
+    config: AuditLogConfig,
+    output: Output,
+    written: u64,
+}
+
+async fn open_file(path: &str) -> io::Result<(tokio::fs::File, u64)> {
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    let written = file.metadata().await?.len();
+    Ok((file, written))
+}
+
+/// Shifts `<path>.1` to `<path>.2` and so on, dropping the oldest, then
+/// moves `path` to `<path>.1`.
+async fn rotate(path: &str, max_files: usize) -> io::Result<()> {
+    if max_files == 0 {
+        return tokio::fs::remove_file(path).await;
+    }
+    for n in (1..max_files).rev() {
+        match tokio::fs::rename(format!("{}.{}", path, n), format!("{}.{}", path, n + 1)).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    tokio::fs::rename(path, format!("{}.1", path)).await
+}
+
+impl Writer {
+    async fn open(config: &AuditLogConfig) -> io::Result<Self> {
+        let (output, written) = match &config.path {
+            Some(path) => {
+                let (file, written) = open_file(path).await?;
+                (Output::File(file), written)
+            }
+            None => (Output::Stdout(tokio::io::stdout()), 0),
+        };
+        Ok(Self {
+            config: config.clone(),
+            output,
+            written,
+        })
+    }
+
+    async fn write(&mut self, line: &[u8]) -> io::Result<()> {
+        let len = line.len() as u64;
+        if let (Some(path), Output::File(_)) = (&self.config.path, &self.output) {
+            if self.written > 0 && self.written + len > self.config.max_file_bytes {
+                rotate(path, self.config.max_files).await?;
+                let (file, written) = open_file(path).await?;
+                self.output = Output::File(file);
+                self.written = written;
+            }
+        }
+        match &mut self.output {
+            Output::Stdout(stdout) => {
+                stdout.write_all(line).await?;
+                stdout.flush().await?;
+            }
+            Output::File(file) => {
+                file.write_all(line).await?;
+                file.flush().await?;
+            }
+        }
+        self.written += len;
+        Ok(())
+    }
+}
+
+/// Starts the audit writer if `audit_log` is configured.
+pub fn spawn(config: &AuditLogConfig) {
+    let (sender, mut receiver) = mpsc::channel::<Value>(CHANNEL_CAPACITY);
+    if AUDIT.set((config.clone(), sender)).is_err() {
+        return;
+    }
+    info!(
+        "Writing audit records to {}",
+        config.path.as_deref().unwrap_or("stdout")
+    );
+    let config = config.clone();
+
+    tokio::spawn(async move {
+        let mut writer = match Writer::open(&config).await {
+            Ok(writer) => writer,
+            Err(e) => {
+                error!("Failed to open audit log {:?}: {:?}", config.path, e);
+                return;
+            }
+        };
+        while let Some(record) = receiver.recv().await {
+            let line = format!("{}\n", record);
+            if let Err(e) = writer.write(line.as_bytes()).await {
+                error!("Failed to write audit record: {:?}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_redaction() {
+        let request = json!({
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": [{"type": "text", "text": "Mail jane@example.com"}]}
+            ]
+        });
+        let mut record = Record {
+            timestamp_ms: 0,
+            start: Instant::now(),
+            path: "/v1/chat/completions".to_string(),
+            stream: false,
+            subject: None,
+            tenant: None,
+            prompt: prompt_text(&request),
+            status: None,
+            response: None,
+        };
+        record.set_status(StatusCode::OK);
+        record.set_response(Bytes::from(
+            json!({
+                "choices": [{"message": {"content": "Done, jane@example.com"}}],
+                "usage": {"prompt_tokens": 9, "completion_tokens": 4, "total_tokens": 13}
+            })
+            .to_string(),
+        ));
+        let labels = RequestLabels {
+            policy: Some("audit_test".to_string()),
+            model: Some("small".to_string()),
+            ..Default::default()
+        };
+        let config = AuditLogConfig {
+            response: AuditContent::Redacted,
+            max_chars: 10,
+            ..Default::default()
+        };
+
+        let json = record.to_json(&config, &labels, None);
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["policy"], "audit_test");
+        assert_eq!(json["usage"]["total_tokens"], 13);
+        assert_eq!(json["prompt"].as_str().unwrap().len(), 64);
+        assert_eq!(json["response"], "Done, [EMA");
+
+        let omitted = AuditLogConfig {
+            prompt: AuditContent::Omit,
+            ..Default::default()
+        };
+        let json = record.to_json(&omitted, &labels, None);
+        assert!(json.get("prompt").is_none() && json.get("response").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rotation() {
+        let dir =
+            std::env::temp_dir().join(format!("llm-router-audit-test-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("audit.jsonl").to_string_lossy().into_owned();
+        let config = AuditLogConfig {
+            path: Some(path.clone()),
+            max_file_bytes: 10,
+            max_files: 2,
+            ..Default::default()
+        };
+        let mut writer = Writer::open(&config).await.unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            writer.write(line.as_bytes()).await.unwrap();
+        }
+        drop(writer);
+
+        let read = |suffix: &str| std::fs::read_to_string(format!("{}{}", path, suffix)).unwrap();
+        assert_eq!(read(""), "fourth\n");
+        assert_eq!(read(".1"), "third\n");
+        assert_eq!(read(".2"), "second\n");
+        assert!(std::fs::metadata(format!("{}.3", path)).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
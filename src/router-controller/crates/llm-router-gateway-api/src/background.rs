@@ -0,0 +1,95 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Background
+//!
+//! Bounded queue for work done after a response is produced, such as usage
+//! accounting and traffic capture, so slow sinks never add to the latency
+//! a client observes. Tasks run in submission order on a single worker;
+//! when the queue is full new tasks are dropped and counted.
+use crate::config::BackgroundTasksConfig;
+use crate::metrics::{BACKGROUND_QUEUE_DEPTH, BACKGROUND_TASKS_DROPPED, BACKGROUND_TASK_LAG};
+use log::{info, warn};
+use std::sync::OnceLock;
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+struct Task {
+    kind: &'static str,
+    queued_at: Instant,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+static QUEUE: OnceLock<(mpsc::Sender<Task>, usize)> = OnceLock::new();
+
+fn publish_depth(sender: &mpsc::Sender<Task>, capacity: usize) {
+    BACKGROUND_QUEUE_DEPTH.set((capacity - sender.capacity()) as i64);
+}
+
+/// Queues `run` to execute after the current request. Runs it inline when
+/// the queue was never started, as in tests and tools.
+pub fn submit(kind: &'static str, run: impl FnOnce() + Send + 'static) {
+    let Some((sender, capacity)) = QUEUE.get() else {
+        run();
+        return;
+    };
+    let task = Task {
+        kind,
+        queued_at: Instant::now(),
+        run: Box::new(run),
+    };
+    if sender.try_send(task).is_err() {
+        BACKGROUND_TASKS_DROPPED.with_label_values(&[kind]).inc();
+        warn!("Background queue full, dropping {} task", kind);
+    }
+    publish_depth(sender, *capacity);
+}
+
+/// Starts the worker. Later calls are ignored.
+pub fn spawn(config: &BackgroundTasksConfig) {
+    let capacity = config.queue_capacity.max(1);
+    let (sender, mut receiver) = mpsc::channel::<Task>(capacity);
+    if QUEUE.set((sender.clone(), capacity)).is_err() {
+        return;
+    }
+    info!("Background task queue started with capacity {}", capacity);
+
+    tokio::spawn(async move {
+        while let Some(task) = receiver.recv().await {
+            BACKGROUND_TASK_LAG
+                .with_label_values(&[task.kind])
+                .observe(task.queued_at.elapsed().as_secs_f64());
+            (task.run)();
+            publish_depth(&sender, capacity);
+            // Lets request handling on this thread run between tasks.
+            tokio::task::yield_now().await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_submit_without_queue_runs_inline() {
+        let ran = Arc::new(AtomicBool::new(false));
+        let flag = ran.clone();
+        submit("test", move || flag.store(true, Ordering::SeqCst));
+        assert!(ran.load(Ordering::SeqCst));
+    }
+}
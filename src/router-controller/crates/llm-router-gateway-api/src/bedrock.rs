@@ -0,0 +1,543 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bedrock
+//!
+//! AWS Bedrock through the Converse API: OpenAI chat completion requests
+//! are translated to Converse requests, and Converse responses and
+//! `converse-stream` event streams back to OpenAI responses and SSE chunks.
+use crate::config::{BedrockConfig, Llm};
+use crate::error::GatewayApiError;
+use crate::sigv4::{self, SigningRequest};
+use bytes::{Buf, Bytes, BytesMut};
+use futures_util::Stream;
+use http::{HeaderValue, StatusCode};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::Rng;
+use serde_json::{json, Map, Value};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const SERVICE: &str = "bedrock";
+
+fn region(llm: &Llm, config: &BedrockConfig) -> Option<String> {
+    config.region.clone().or_else(|| {
+        let host = llm.api_base.split("://").nth(1)?.split('/').next()?;
+        host.strip_prefix("bedrock-runtime.")?
+            .split('.')
+            .next()
+            .map(str::to_string)
+    })
+}
+
+fn text_blocks(content: &Value) -> Vec<Value> {
+    match content {
+        Value::String(text) => vec![json!({"text": text})],
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .map(|text| json!({"text": text}))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Converse request body for an OpenAI chat completion body. System
+/// messages become `system` blocks and consecutive messages of the same
+/// role are merged, as Converse requires alternating turns.
+pub fn converse_request(json: &Value) -> Value {
+    let mut system = Vec::new();
+    let mut messages: Vec<Value> = Vec::new();
+    for message in json["messages"].as_array().into_iter().flatten() {
+        let blocks = text_blocks(&message["content"]);
+        let role = match message["role"].as_str() {
+            Some("system") | Some("developer") => {
+                system.extend(blocks);
+                continue;
+            }
+            Some("assistant") => "assistant",
+            _ => "user",
+        };
+        match messages.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(content) = last["content"].as_array_mut() {
+                    content.extend(blocks);
+                }
+            }
+            _ => messages.push(json!({"role": role, "content": blocks})),
+        }
+    }
+
+    let mut inference = Map::new();
+    let max_tokens = json
+        .get("max_tokens")
+        .or_else(|| json.get("max_completion_tokens"));
+    for (field, value) in [
+        ("maxTokens", max_tokens),
+        ("temperature", json.get("temperature")),
+        ("topP", json.get("top_p")),
+    ] {
+        if let Some(value) = value.filter(|v| !v.is_null()) {
+            inference.insert(field.to_string(), value.clone());
+        }
+    }
+    match &json["stop"] {
+        Value::String(stop) => {
+            inference.insert("stopSequences".to_string(), json!([stop]));
+        }
+        Value::Array(stops) => {
+            inference.insert("stopSequences".to_string(), json!(stops));
+        }
+        _ => {}
+    }
+
+    let mut request = json!({ "messages": messages });
+    if !system.is_empty() {
+        request["system"] = json!(system);
+    }
+    if !inference.is_empty() {
+        request["inferenceConfig"] = Value::Object(inference);
+    }
+    request
+}
+
+fn finish_reason(stop_reason: &str) -> &'static str {
+    match stop_reason {
+        "max_tokens" => "length",
+        "tool_use" => "tool_calls",
+        "content_filtered" | "guardrail_intervened" => "content_filter",
+        _ => "stop",
+    }
+}
+
+fn usage(usage: &Value) -> Value {
+    json!({
+        "prompt_tokens": usage["inputTokens"].as_u64().unwrap_or(0),
+        "completion_tokens": usage["outputTokens"].as_u64().unwrap_or(0),
+        "total_tokens": usage["totalTokens"].as_u64().unwrap_or(0),
+    })
+}
+
+fn completion_id() -> String {
+    format!("chatcmpl-{:016x}", rand::thread_rng().gen::<u64>())
+}
+
+/// OpenAI chat completion for a Converse response.
+pub fn chat_completion(response: &Value, model: &str) -> Value {
+    let content: String = response["output"]["message"]["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|block| block["text"].as_str())
+        .collect();
+    json!({
+        "id": completion_id(),
+        "object": "chat.completion",
+        "created": sigv4::now(),
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": finish_reason(response["stopReason"].as_str().unwrap_or_default()),
+        }],
+        "usage": usage(&response["usage"]),
+    })
+}
+
+/// The signed upstream request for an OpenAI chat completion body.
+pub async fn request(
+    client: &reqwest::Client,
+    llm: &Llm,
+    json: &Value,
+) -> Result<reqwest::RequestBuilder, GatewayApiError> {
+    let config = llm.bedrock.clone().unwrap_or_default();
+    let region = region(llm, &config).ok_or_else(|| GatewayApiError::LlmServiceError {
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+        message: format!("No Bedrock region configured for LLM '{}'", llm.name),
+        provider: llm.name.clone(),
+        details: None,
+    })?;
+    let credentials = sigv4::credentials(&config).await?;
+
+    let base =
+        reqwest::Url::parse(&llm.api_base).map_err(|e| GatewayApiError::LlmServiceError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: format!("Invalid api_base for LLM '{}': {}", llm.name, e),
+            provider: llm.name.clone(),
+            details: None,
+        })?;
+    let host = match base.port() {
+        Some(port) => format!("{}:{}", base.host_str().unwrap_or_default(), port),
+        None => base.host_str().unwrap_or_default().to_string(),
+    };
+    let operation = if json["stream"].as_bool().unwrap_or(false) {
+        "converse-stream"
+    } else {
+        "converse"
+    };
+    let path = format!(
+        "{}/model/{}/{}",
+        base.path().trim_end_matches('/'),
+        utf8_percent_encode(&llm.model, NON_ALPHANUMERIC),
+        operation
+    );
+    let body = serde_json::to_vec(&converse_request(json))?;
+    let signing = SigningRequest {
+        method: "POST",
+        host: &host,
+        path: &path,
+        query: "",
+        headers: &[("content-type", "application/json")],
+        body: &body,
+    };
+    let signed = sigv4::sign(&signing, &credentials, &region, SERVICE, sigv4::now());
+
+    let url = format!("{}://{}{}", base.scheme(), host, path);
+    let mut request = client
+        .post(url)
+        .header("content-type", "application/json")
+        .body(body);
+    for (name, value) in signed {
+        let mut value = HeaderValue::from_str(&value)?;
+        value.set_sensitive(matches!(name, "authorization" | "x-amz-security-token"));
+        request = request.header(name, value);
+    }
+    Ok(request)
+}
+
+/// One frame of an `application/vnd.amazon.eventstream` body.
+#[derive(Debug, PartialEq)]
+struct Frame {
+    message_type: String,
+    event_type: String,
+    payload: Value,
+}
+
+/// Prelude (total and header lengths, CRC) plus the trailing message CRC.
+const FRAME_OVERHEAD: usize = 16;
+
+/// Reads string headers; other header types are skipped.
+fn headers(mut bytes: &[u8]) -> Vec<(String, String)> {
+    let mut headers = Vec::new();
+    while bytes.len() > 2 {
+        let name_len = bytes[0] as usize;
+        if bytes.len() < 2 + name_len {
+            break;
+        }
+        let name = String::from_utf8_lossy(&bytes[1..1 + name_len]).into_owned();
+        let kind = bytes[1 + name_len];
+        bytes = &bytes[2 + name_len..];
+        let value_len = match kind {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 if bytes.len() >= 2 => 2 + u16::from_be_bytes([bytes[0], bytes[1]]) as usize,
+            _ => break,
+        };
+        if bytes.len() < value_len {
+            break;
+        }
+        if kind == 7 {
+            headers.push((
+                name,
+                String::from_utf8_lossy(&bytes[2..value_len]).into_owned(),
+            ));
+        }
+        bytes = &bytes[value_len..];
+    }
+    headers
+}
+
+/// Takes the next complete frame off `buffer`, if one has arrived.
+fn next_frame(buffer: &mut BytesMut) -> Option<Frame> {
+    if buffer.len() < 12 {
+        return None;
+    }
+    let total = u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+    let headers_len = u32::from_be_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]) as usize;
+    if total < FRAME_OVERHEAD.saturating_add(headers_len) {
+        // Nothing after a frame of no usable length can be framed again.
+        buffer.clear();
+        return Some(Frame {
+            message_type: "exception".to_string(),
+            event_type: String::new(),
+            payload: json!({"message": "malformed event stream frame"}),
+        });
+    }
+    if buffer.len() < total {
+        return None;
+    }
+    let frame = buffer.split_to(total);
+    let headers = headers(&frame[12..12 + headers_len]);
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    };
+    Some(Frame {
+        message_type: header(":message-type"),
+        event_type: match header(":event-type") {
+            event if event.is_empty() => header(":exception-type"),
+            event => event,
+        },
+        payload: serde_json::from_slice(&frame[12 + headers_len..total - 4]).unwrap_or(Value::Null),
+    })
+}
+
+fn sse(data: &Value) -> String {
+    format!("data: {}\n\n", data)
+}
+
+/// SSE for one Converse stream event: OpenAI chunks, a final usage chunk
+/// and `[DONE]`.
+fn translate(frame: &Frame, id: &str, model: &str) -> String {
+    let chunk = |delta: Value, finish_reason: Value| {
+        json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": sigv4::now(),
+            "model": model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+        })
+    };
+    if frame.message_type != "event" {
+        let error = json!({"error": {
+            "message": frame.payload["message"].as_str().unwrap_or("Bedrock stream error"),
+            "type": frame.event_type,
+        }});
+        return sse(&error) + "data: [DONE]\n\n";
+    }
+    match frame.event_type.as_str() {
+        "messageStart" => sse(&chunk(json!({"role": "assistant"}), Value::Null)),
+        "contentBlockDelta" => match frame.payload["delta"]["text"].as_str() {
+            Some(text) => sse(&chunk(json!({"content": text}), Value::Null)),
+            None => String::new(),
+        },
+        "messageStop" => {
+            let reason = finish_reason(frame.payload["stopReason"].as_str().unwrap_or_default());
+            sse(&chunk(json!({}), json!(reason)))
+        }
+        "metadata" => {
+            let usage = json!({
+                "id": id,
+                "object": "chat.completion.chunk",
+                "created": sigv4::now(),
+                "model": model,
+                "choices": [],
+                "usage": usage(&frame.payload["usage"]),
+            });
+            sse(&usage) + "data: [DONE]\n\n"
+        }
+        _ => String::new(),
+    }
+}
+
+type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send + Sync>>;
+
+/// Converts a `converse-stream` body into OpenAI style SSE.
+pub struct EventStream {
+    inner: ByteStream,
+    buffer: BytesMut,
+    id: String,
+    model: String,
+    /// Set once an error frame ended the stream.
+    done: bool,
+}
+
+impl EventStream {
+    pub fn new(inner: ByteStream, model: &str) -> Self {
+        Self {
+            inner,
+            buffer: BytesMut::new(),
+            id: completion_id(),
+            model: model.to_string(),
+            done: false,
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<Bytes, reqwest::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let this = &mut *self;
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let mut out = String::new();
+            while let Some(frame) = next_frame(&mut this.buffer) {
+                out.push_str(&translate(&frame, &this.id, &this.model));
+                // Error frames end with `[DONE]`, so nothing may follow.
+                if frame.message_type != "event" {
+                    this.done = true;
+                    this.buffer.clear();
+                    break;
+                }
+            }
+            if !out.is_empty() {
+                return Poll::Ready(Some(Ok(Bytes::from(out))));
+            }
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(mut chunk))) => {
+                    while chunk.has_remaining() {
+                        let bytes = chunk.chunk().to_vec();
+                        chunk.advance(bytes.len());
+                        this.buffer.extend_from_slice(&bytes);
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    fn frame(event_type: &str, payload: &Value) -> Vec<u8> {
+        let mut headers = Vec::new();
+        for (name, value) in [(":message-type", "event"), (":event-type", event_type)] {
+            headers.push(name.len() as u8);
+            headers.extend_from_slice(name.as_bytes());
+            headers.push(7);
+            headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            headers.extend_from_slice(value.as_bytes());
+        }
+        let payload = payload.to_string().into_bytes();
+        let total = FRAME_OVERHEAD + headers.len() + payload.len();
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&(total as u32).to_be_bytes());
+        frame.extend_from_slice(&(headers.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0; 4]);
+        frame.extend_from_slice(&headers);
+        frame.extend_from_slice(&payload);
+        frame.extend_from_slice(&[0; 4]);
+        frame
+    }
+
+    #[test]
+    fn test_converse_mapping() {
+        let request = converse_request(&json!({
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Hi"},
+                {"role": "user", "content": [{"type": "text", "text": "there"}]},
+                {"role": "assistant", "content": "Hello"}
+            ],
+            "max_tokens": 64,
+            "stop": "END"
+        }));
+        assert_eq!(
+            request,
+            json!({
+                "system": [{"text": "Be brief."}],
+                "messages": [
+                    {"role": "user", "content": [{"text": "Hi"}, {"text": "there"}]},
+                    {"role": "assistant", "content": [{"text": "Hello"}]}
+                ],
+                "inferenceConfig": {"maxTokens": 64, "stopSequences": ["END"]}
+            })
+        );
+
+        let completion = chat_completion(
+            &json!({
+                "output": {"message": {"role": "assistant", "content": [{"text": "Hey"}]}},
+                "stopReason": "max_tokens",
+                "usage": {"inputTokens": 5, "outputTokens": 1, "totalTokens": 6}
+            }),
+            "anthropic.claude-3-haiku",
+        );
+        assert_eq!(completion["choices"][0]["message"]["content"], "Hey");
+        assert_eq!(completion["choices"][0]["finish_reason"], "length");
+        assert_eq!(completion["usage"]["total_tokens"], 6);
+
+        let llm = Llm {
+            api_base: "https://bedrock-runtime.eu-west-1.amazonaws.com".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            region(&llm, &BedrockConfig::default()).as_deref(),
+            Some("eu-west-1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_stream() {
+        let mut body = frame("messageStart", &json!({"role": "assistant"}));
+        body.extend(frame(
+            "contentBlockDelta",
+            &json!({"delta": {"text": "Hel"}}),
+        ));
+        body.extend(frame(
+            "contentBlockDelta",
+            &json!({"delta": {"text": "lo"}}),
+        ));
+        body.extend(frame("messageStop", &json!({"stopReason": "end_turn"})));
+        body.extend(frame(
+            "metadata",
+            &json!({"usage": {"inputTokens": 3, "outputTokens": 2, "totalTokens": 5}}),
+        ));
+        // Frames split across network chunks.
+        let chunks: Vec<Result<Bytes, reqwest::Error>> = body
+            .chunks(7)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        let stream = EventStream::new(Box::pin(futures_util::stream::iter(chunks)), "m");
+        let sse: String = stream
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .await
+            .concat();
+        let events: Vec<&str> = sse.split("\n\n").filter(|e| !e.is_empty()).collect();
+        assert_eq!(events.len(), 6);
+        let data = |i: usize| -> Value { serde_json::from_str(&events[i][6..]).unwrap() };
+        assert_eq!(data(1)["choices"][0]["delta"]["content"], "Hel");
+        assert_eq!(data(3)["choices"][0]["finish_reason"], "stop");
+        assert_eq!(data(4)["usage"]["completion_tokens"], 2);
+        assert_eq!(events[5], "data: [DONE]");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_frame_ends_stream() {
+        let mut body = frame("messageStart", &json!({"role": "assistant"}));
+        // A prelude whose total length cannot hold the prelude itself.
+        body.extend_from_slice(&[0; 12]);
+        body.extend(frame(
+            "contentBlockDelta",
+            &json!({"delta": {"text": "lost"}}),
+        ));
+        let chunks: Vec<Result<Bytes, reqwest::Error>> = vec![Ok(Bytes::from(body))];
+        let stream = EventStream::new(Box::pin(futures_util::stream::iter(chunks)), "m");
+        let collected = stream
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect::<Vec<_>>();
+        let sse = tokio::time::timeout(std::time::Duration::from_secs(5), collected)
+            .await
+            .unwrap()
+            .concat();
+        let events: Vec<&str> = sse.split("\n\n").filter(|e| !e.is_empty()).collect();
+        assert_eq!(events.len(), 3);
+        assert!(events[1].contains("malformed event stream frame"));
+        assert_eq!(events[2], "data: [DONE]");
+    }
+}
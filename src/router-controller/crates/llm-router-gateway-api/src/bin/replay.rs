@@ -0,0 +1,120 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Replay
+//!
+//! Sends requests recorded by `traffic_capture` to another router, such as
+//! a staging deployment, at a fixed rate.
+use clap::Parser;
+use llm_router_gateway_api::capture;
+use llm_router_gateway_api::config::AnonymizeConfig;
+use log::{error, info, warn};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::time::MissedTickBehavior;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Capture file written by `traffic_capture`.
+    #[arg(long)]
+    capture: String,
+    /// Base URL of the router to replay against, e.g. http://staging:8084.
+    #[arg(long)]
+    target: String,
+    /// Requests sent per second.
+    #[arg(long, default_value_t = 1.0)]
+    rate: f64,
+    /// Requests in flight at once.
+    #[arg(long, default_value_t = 16)]
+    concurrency: usize,
+    /// Bearer key sent with every request.
+    #[arg(long)]
+    api_key: Option<String>,
+    /// Anonymize bodies again with the default settings, for captures
+    /// recorded with anonymization turned off.
+    #[arg(long)]
+    anonymize: bool,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+    let reader = std::io::BufReader::new(std::fs::File::open(&args.capture)?);
+    let client = reqwest::Client::new();
+    let in_flight = Arc::new(Semaphore::new(args.concurrency.max(1)));
+    let statuses = Arc::new(Mutex::new(BTreeMap::<String, u64>::new()));
+    let mut ticks = tokio::time::interval(Duration::from_secs_f64(1.0 / args.rate.max(0.001)));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let target = args.target.trim_end_matches('/').to_string();
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let record: Value = match serde_json::from_str(&line) {
+            Ok(record) => record,
+            Err(e) => {
+                warn!("Skipping line {} of {}: {}", number + 1, args.capture, e);
+                continue;
+            }
+        };
+        let path = record["path"].as_str().unwrap_or("/v1/chat/completions");
+        let mut body = record["body"].clone();
+        if args.anonymize {
+            body = capture::anonymize(body, &AnonymizeConfig::default());
+        }
+        let mut request = client.post(format!("{}{}", target, path)).json(&body);
+        if let Some(api_key) = &args.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        ticks.tick().await;
+        let permit = in_flight.clone().acquire_owned().await?;
+        let statuses = statuses.clone();
+        tokio::spawn(async move {
+            let outcome = match request.send().await {
+                Ok(response) => {
+                    let status = response.status().as_u16().to_string();
+                    // Drain the body so streamed responses complete.
+                    let _ = response.bytes().await;
+                    status
+                }
+                Err(e) => {
+                    error!("Replay request failed: {:?}", e);
+                    "error".to_string()
+                }
+            };
+            *statuses
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(outcome)
+                .or_default() += 1;
+            drop(permit);
+        });
+    }
+
+    // Wait for the requests still in flight.
+    let _ = in_flight
+        .acquire_many(args.concurrency.max(1) as u32)
+        .await?;
+    let statuses = statuses.lock().unwrap_or_else(|e| e.into_inner());
+    info!("Replay finished: {:?}", *statuses);
+    println!("{}", serde_json::to_string(&*statuses)?);
+    Ok(())
+}
@@ -0,0 +1,237 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Breaker
+//!
+//! Per-upstream circuit breakers. A breaker opens when the failure rate over
+//! the last `window_size` calls reaches the threshold, rejects calls while
+//! open, then lets a single probe through to decide whether to close again.
+use crate::config::CircuitBreakerConfig;
+use crate::metrics::CIRCUIT_BREAKER_STATE;
+use crate::store::{self, StateStore};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Times are wall clock milliseconds, so replicas sharing a state store
+/// agree on them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum State {
+    Closed,
+    Open { until_ms: u64 },
+    HalfOpen { probe_started_ms: u64 },
+}
+
+impl State {
+    fn gauge_value(&self) -> i64 {
+        match self {
+            Self::Closed => 0,
+            Self::HalfOpen { .. } => 1,
+            Self::Open { .. } => 2,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn state_key(policy: &str, llm: &str) -> String {
+    format!("breaker:state:{}:{}", policy, llm)
+}
+
+/// Outcomes of the most recent calls, `1` for a failure, newest last.
+fn outcomes_key(policy: &str, llm: &str) -> String {
+    format!("breaker:outcomes:{}:{}", policy, llm)
+}
+
+/// The stored state and its raw value, for a later compare-and-set.
+fn load(store: &dyn StateStore, policy: &str, llm: &str) -> Option<(Option<Vec<u8>>, State)> {
+    let raw = store::report("circuit_breaker", store.get(&state_key(policy, llm)))?;
+    let state = raw
+        .as_deref()
+        .and_then(|value| serde_json::from_slice(value).ok())
+        .unwrap_or(State::Closed);
+    Some((raw, state))
+}
+
+/// Moves the breaker from `raw` to `next`. Loses to a concurrent
+/// transition, which then stands.
+fn transition(
+    store: &dyn StateStore,
+    policy: &str,
+    llm: &str,
+    raw: Option<&[u8]>,
+    next: &State,
+) -> bool {
+    let swapped = store.compare_and_set(&state_key(policy, llm), raw, &next.encode(), None);
+    store::report("circuit_breaker", swapped).unwrap_or(false)
+}
+
+fn publish(policy: &str, llm: &str, state: &State) {
+    CIRCUIT_BREAKER_STATE
+        .with_label_values(&[policy, llm])
+        .set(state.gauge_value());
+}
+
+/// Whether a call to the upstream may go ahead. Moves an open breaker to
+/// half-open once its cool-down has passed, admitting one probe. Calls are
+/// allowed when the state store fails.
+pub fn allow(policy: &str, llm: &str, config: &CircuitBreakerConfig) -> bool {
+    let store = store::get();
+    let Some((raw, state)) = load(store, policy, llm) else {
+        return true;
+    };
+    let now = now_ms();
+    let probe_timeout_ms = config.open_seconds.saturating_mul(1000);
+    let probe = State::HalfOpen {
+        probe_started_ms: now,
+    };
+    let (allowed, state) = match state {
+        State::Closed => (true, state),
+        State::Open { until_ms } if now >= until_ms => {
+            if transition(store, policy, llm, raw.as_deref(), &probe) {
+                info!("Circuit breaker half-open: policy={} llm={}", policy, llm);
+                (true, probe)
+            } else {
+                (false, state)
+            }
+        }
+        State::Open { .. } => (false, state),
+        // A probe that never reported back does not hold the breaker forever.
+        State::HalfOpen { probe_started_ms }
+            if now >= probe_started_ms.saturating_add(probe_timeout_ms) =>
+        {
+            if transition(store, policy, llm, raw.as_deref(), &probe) {
+                (true, probe)
+            } else {
+                (false, state)
+            }
+        }
+        State::HalfOpen { .. } => (false, state),
+    };
+    publish(policy, llm, &state);
+    allowed
+}
+
+/// Whether the breaker is open and still cooling down, without admitting a
+/// probe. Breakers are taken as closed when the state store fails.
+pub fn is_open(policy: &str, llm: &str) -> bool {
+    matches!(
+        load(store::get(), policy, llm),
+        Some((_, State::Open { until_ms })) if now_ms() < until_ms
+    )
+}
+
+/// Records the outcome of a call admitted by `allow`.
+pub fn record(policy: &str, llm: &str, config: &CircuitBreakerConfig, failed: bool) {
+    let store = store::get();
+    let Some((raw, state)) = load(store, policy, llm) else {
+        return;
+    };
+    let open = State::Open {
+        until_ms: now_ms().saturating_add(config.open_seconds.saturating_mul(1000)),
+    };
+
+    let state = match state {
+        State::HalfOpen { .. } if failed => {
+            if transition(store, policy, llm, raw.as_deref(), &open) {
+                warn!("Circuit breaker re-opened: policy={} llm={}", policy, llm);
+                open
+            } else {
+                state
+            }
+        }
+        State::HalfOpen { .. } => {
+            if transition(store, policy, llm, raw.as_deref(), &State::Closed) {
+                info!("Circuit breaker closed: policy={} llm={}", policy, llm);
+                let cleared = store.delete(&outcomes_key(policy, llm));
+                store::report("circuit_breaker", cleared);
+                State::Closed
+            } else {
+                state
+            }
+        }
+        State::Open { .. } => state,
+        State::Closed => {
+            let key = outcomes_key(policy, llm);
+            let outcome: &[u8] = if failed { b"1" } else { b"0" };
+            let pushed = store.push(&key, outcome, config.window_size.max(1), None);
+            let Some(outcomes) =
+                store::report("circuit_breaker", pushed.and_then(|()| store.list(&key)))
+            else {
+                return;
+            };
+            let calls = outcomes.len();
+            let failures = outcomes.iter().filter(|o| o.as_slice() == b"1").count();
+            let failure_rate = failures as f64 / calls.max(1) as f64;
+            if calls >= config.min_requests
+                && failure_rate >= config.failure_rate_threshold
+                && transition(store, policy, llm, raw.as_deref(), &open)
+            {
+                warn!(
+                    "Circuit breaker opened: policy={} llm={} failure_rate={:.2} over {} calls",
+                    policy, llm, failure_rate, calls
+                );
+                let cleared = store.delete(&key);
+                store::report("circuit_breaker", cleared);
+                open
+            } else {
+                state
+            }
+        }
+    };
+    publish(policy, llm, &state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_probes_and_closes() {
+        let config = CircuitBreakerConfig {
+            window_size: 4,
+            min_requests: 4,
+            failure_rate_threshold: 0.5,
+            open_seconds: 0,
+        };
+        let (policy, llm) = ("breaker_test", "flaky");
+
+        for failed in [false, true, false] {
+            assert!(allow(policy, llm, &config));
+            record(policy, llm, &config, failed);
+        }
+        assert!(allow(policy, llm, &config));
+        record(policy, llm, &config, true);
+
+        // Open with no cool-down: the next call is the half-open probe and
+        // others wait for its outcome.
+        assert!(allow(policy, llm, &config));
+        let (_, state) = load(store::get(), policy, llm).unwrap();
+        assert!(matches!(state, State::HalfOpen { .. }));
+        record(policy, llm, &config, false);
+        let (_, state) = load(store::get(), policy, llm).unwrap();
+        assert_eq!(state, State::Closed);
+    }
+}
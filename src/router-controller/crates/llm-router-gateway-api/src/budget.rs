@@ -0,0 +1,322 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Budget
+//!
+//! Token budgets per caller, reset every UTC day or month. Usage is added
+//! once a response reports it, so a request that starts under the budget is
+//! always served in full. Counters live in the state store, one per caller
+//! and period, and expire when the period ends.
+use crate::caller::Caller;
+use crate::config::{BudgetAction, BudgetPeriod, Policy, TokenBudgetConfig};
+use crate::error::GatewayApiError;
+use crate::metrics::TOKEN_BUDGET_EXCEEDED;
+use crate::store;
+use log::warn;
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The budget a request's usage is added to.
+#[derive(Debug, Clone)]
+pub struct Account {
+    key: String,
+    period: BudgetPeriod,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Days since the epoch of the first day of `month` (1-12) in `year`.
+fn days_from_civil(year: i64, month: u64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Year and month (1-12) of a day counted from the epoch.
+fn civil_from_days(days: i64) -> (i64, u64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u64;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month)
+}
+
+/// Start and end, in Unix seconds, of the period containing `now`.
+fn period_bounds(period: BudgetPeriod, now: u64) -> (u64, u64) {
+    let day = now / SECONDS_PER_DAY;
+    match period {
+        BudgetPeriod::Daily => (day * SECONDS_PER_DAY, (day + 1) * SECONDS_PER_DAY),
+        BudgetPeriod::Monthly => {
+            let (year, month) = civil_from_days(day as i64);
+            let start = days_from_civil(year, month);
+            let end = if month == 12 {
+                days_from_civil(year + 1, 1)
+            } else {
+                days_from_civil(year, month + 1)
+            };
+            (start as u64 * SECONDS_PER_DAY, end as u64 * SECONDS_PER_DAY)
+        }
+    }
+}
+
+fn period_name(period: BudgetPeriod) -> &'static str {
+    match period {
+        BudgetPeriod::Daily => "daily",
+        BudgetPeriod::Monthly => "monthly",
+    }
+}
+
+fn usage_key(key: &str, period_start: u64) -> String {
+    format!("budget:{}:{}", key, period_start)
+}
+
+/// Tokens `key` has used in the current period, or none when the store
+/// cannot tell.
+fn used(key: &str, period_start: u64) -> u64 {
+    let used = store::get().get(&usage_key(key, period_start));
+    store::report("token_budget", used)
+        .flatten()
+        .and_then(|value| String::from_utf8(value).ok()?.parse().ok())
+        .unwrap_or(0)
+}
+
+fn evaluate(
+    config: &TokenBudgetConfig,
+    caller: &Caller,
+    policy: &Policy,
+    now: u64,
+) -> Result<(Option<usize>, Account), GatewayApiError> {
+    let known_keys = config.overrides.iter().map(|o| o.key.as_str());
+    let (identity, key) = caller.identify(config.key_by, known_keys);
+    let limit = config
+        .overrides
+        .iter()
+        .find(|o| o.key == identity)
+        .map_or(config.max_tokens, |o| o.max_tokens);
+    let (period_start, resets_at) = period_bounds(config.period, now);
+    let used = used(&key, period_start);
+    let account = Account {
+        key,
+        period: config.period,
+    };
+    if used < limit {
+        return Ok((None, account));
+    }
+
+    let downgrade = match config.action {
+        BudgetAction::Reject => None,
+        BudgetAction::Downgrade => config
+            .downgrade_to
+            .as_ref()
+            .and_then(|name| policy.llms.iter().position(|llm| &llm.name == name)),
+    };
+    let action = if downgrade.is_some() {
+        "downgrade"
+    } else {
+        "reject"
+    };
+    warn!(
+        "token budget exhausted: key_by={} subject={} ip={:?} policy={} used={} limit={} action={}",
+        config.key_by.as_str(),
+        caller.subject.unwrap_or("unknown"),
+        caller.ip,
+        policy.name,
+        used,
+        limit,
+        action
+    );
+    TOKEN_BUDGET_EXCEEDED.with_label_values(&[action]).inc();
+    match downgrade {
+        Some(index) => Ok((Some(index), account)),
+        None => Err(GatewayApiError::QuotaExceeded {
+            message: format!(
+                "{} token budget of {} tokens exhausted",
+                period_name(config.period),
+                limit
+            ),
+            limit,
+            used,
+            period: period_name(config.period).to_string(),
+            resets_at,
+        }),
+    }
+}
+
+/// Checks the caller's budget. Returns the account to add usage to, and the
+/// index of the policy's `downgrade_to` LLM when the budget is exhausted and
+/// requests are downgraded rather than refused.
+pub fn check(
+    config: &TokenBudgetConfig,
+    caller: &Caller,
+    policy: &Policy,
+) -> Result<(Option<usize>, Account), GatewayApiError> {
+    evaluate(config, caller, policy, now_secs())
+}
+
+impl Account {
+    fn add(&self, tokens: u64, now: u64) {
+        let (period_start, period_end) = period_bounds(self.period, now);
+        // Counters of earlier periods are dead weight once a new one starts.
+        let ttl = Duration::from_secs(period_end.saturating_sub(now).max(1));
+        let added = store::get().incr(
+            &usage_key(&self.key, period_start),
+            i64::try_from(tokens).unwrap_or(i64::MAX),
+            Some(ttl),
+        );
+        store::report("token_budget", added);
+    }
+
+    /// Adds the tokens of an OpenAI style `usage` object.
+    pub fn record(&self, usage: &Value) {
+        let tokens = usage["total_tokens"].as_u64().unwrap_or_else(|| {
+            usage["prompt_tokens"].as_u64().unwrap_or(0)
+                + usage["completion_tokens"].as_u64().unwrap_or(0)
+        });
+        self.add(tokens, now_secs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CallerKey, Llm, TokenBudgetOverride};
+    use http::{HeaderMap, HeaderValue};
+    use reqwest::header::AUTHORIZATION;
+
+    #[test]
+    fn test_period_bounds() {
+        // 2024-02-29T12:00:00Z
+        let now = 1_709_208_000;
+        assert_eq!(
+            period_bounds(BudgetPeriod::Daily, now),
+            (1_709_164_800, 1_709_251_200)
+        );
+        // 2024-02-01 to 2024-03-01, and December rolls into the next year.
+        assert_eq!(
+            period_bounds(BudgetPeriod::Monthly, now),
+            (1_706_745_600, 1_709_251_200)
+        );
+        assert_eq!(
+            period_bounds(BudgetPeriod::Monthly, 1_734_998_400),
+            (1_733_011_200, 1_735_689_600)
+        );
+    }
+
+    #[test]
+    fn test_budget_per_key() {
+        let mut config = TokenBudgetConfig {
+            max_tokens: 100,
+            downgrade_to: Some("small".to_string()),
+            key_by: CallerKey::ApiKey,
+            overrides: vec![TokenBudgetOverride {
+                key: "budget-test-vip".to_string(),
+                max_tokens: 1000,
+            }],
+            ..Default::default()
+        };
+        let policy = Policy {
+            name: "budget_test".to_string(),
+            llms: ["large", "small"]
+                .iter()
+                .map(|name| Llm {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Bearer budget-test-key"),
+        );
+        let caller = Caller::new(&headers, None, Some("198.51.100.8".parse().unwrap()));
+        let now = 1_709_208_000;
+
+        let (downgrade, account) = evaluate(&config, &caller, &policy, now).unwrap();
+        assert_eq!(downgrade, None);
+        account.add(120, now);
+        match evaluate(&config, &caller, &policy, now) {
+            Err(GatewayApiError::QuotaExceeded {
+                used, resets_at, ..
+            }) => {
+                assert_eq!(used, 120);
+                assert_eq!(resets_at, 1_709_251_200);
+            }
+            other => panic!("expected quota exceeded, got {:?}", other.map(|r| r.0)),
+        }
+        config.action = BudgetAction::Downgrade;
+        assert_eq!(evaluate(&config, &caller, &policy, now).unwrap().0, Some(1));
+        // The next day starts from zero.
+        assert_eq!(
+            evaluate(&config, &caller, &policy, now + SECONDS_PER_DAY)
+                .unwrap()
+                .0,
+            None
+        );
+
+        let mut vip = HeaderMap::new();
+        vip.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Bearer budget-test-vip"),
+        );
+        let vip = Caller::new(&vip, None, None);
+        let (_, account) = evaluate(&config, &vip, &policy, now).unwrap();
+        account.add(500, now);
+        assert_eq!(evaluate(&config, &vip, &policy, now).unwrap().0, None);
+    }
+
+    #[test]
+    fn test_unverified_keys_share_a_budget() {
+        let config = TokenBudgetConfig {
+            max_tokens: 100,
+            key_by: CallerKey::ApiKey,
+            ..Default::default()
+        };
+        let policy = Policy {
+            name: "budget_test".to_string(),
+            ..Default::default()
+        };
+        let ip = Some("198.51.100.9".parse().unwrap());
+        let now = 1_709_208_000;
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer made-up-1"));
+        let first = Caller::new(&headers, None, ip);
+        let (_, account) = evaluate(&config, &first, &policy, now).unwrap();
+        assert_eq!(account.key, "ip:198.51.100.9");
+        account.add(120, now);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer made-up-2"));
+        let second = Caller::new(&headers, None, ip);
+        assert!(evaluate(&config, &second, &policy, now).is_err());
+    }
+}
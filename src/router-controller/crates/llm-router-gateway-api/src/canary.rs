@@ -0,0 +1,93 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Canary
+//!
+//! Gradual rollout of a new model version: an LLM with a `canary` hands
+//! `percent` of the requests routed to it to the canary LLM. Canary
+//! requests are reported under the canary's name like any other LLM, so
+//! its error rate and latency can be compared with the stable one.
+use crate::config::Policy;
+use crate::metrics::CANARY_REQUESTS;
+use log::info;
+use rand::Rng;
+
+/// The LLM serving a request routed to the LLM at `index`: its canary for a
+/// random `percent` of requests, otherwise the LLM itself.
+pub fn route(policy: &Policy, index: usize) -> usize {
+    let Some(llm) = policy.llms.get(index) else {
+        return index;
+    };
+    let Some(canary) = &llm.canary else {
+        return index;
+    };
+    if rand::thread_rng().gen_range(0.0..100.0) >= canary.percent {
+        return index;
+    }
+    let Some(canary_index) = policy
+        .llms
+        .iter()
+        .position(|other| other.name == canary.llm)
+    else {
+        return index;
+    };
+    info!(
+        "Canary routing: policy={} llm={} canary={}",
+        policy.name, llm.name, canary.llm
+    );
+    CANARY_REQUESTS
+        .with_label_values(&[&policy.name, &llm.name, &canary.llm])
+        .inc();
+    canary_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CanaryConfig, Llm};
+
+    #[test]
+    fn test_canary_share() {
+        let canary = |percent: f64| Policy {
+            name: "canary_test".to_string(),
+            llms: vec![
+                Llm {
+                    name: "stable".to_string(),
+                    canary: Some(CanaryConfig {
+                        llm: "next".to_string(),
+                        percent,
+                    }),
+                    ..Default::default()
+                },
+                Llm {
+                    name: "next".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let never = canary(0.0);
+        let always = canary(100.0);
+        for _ in 0..100 {
+            assert_eq!(route(&never, 0), 0);
+            assert_eq!(route(&always, 0), 1);
+            // The canary itself has none.
+            assert_eq!(route(&always, 1), 1);
+        }
+        let tenth = canary(10.0);
+        let canaried = (0..10_000).filter(|_| route(&tenth, 0) == 1).count();
+        assert!((800..1200).contains(&canaried), "{}", canaried);
+    }
+}
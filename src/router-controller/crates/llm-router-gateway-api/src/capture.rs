@@ -0,0 +1,190 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Capture
+//!
+//! Records production request bodies to a JSON lines file so they can be
+//! replayed against a staging router with the `replay` binary. Captures are
+//! anonymized before they are written.
+use crate::background;
+use crate::config::{AnonymizeConfig, TrafficCaptureConfig};
+use crate::pii::{self, RedactionMode};
+use crate::privacy;
+use crate::request_context::RequestContext;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use rand::Rng;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// Records buffered in memory before new ones are dropped.
+const CHANNEL_CAPACITY: usize = 10_000;
+
+static CAPTURE: OnceLock<(TrafficCaptureConfig, mpsc::Sender<Value>)> = OnceLock::new();
+
+lazy_static! {
+    /// Credentials pasted into prompts or request fields.
+    static ref API_KEY: Regex =
+        Regex::new(r"\b(?:sk|nvapi|sk-ant|key)-[A-Za-z0-9_\-]{16,}|\bBearer\s+[A-Za-z0-9._~+/\-]{16,}=*")
+            .expect("valid api key regex");
+}
+
+/// Top level fields left as captured: they drive routing, not content.
+const ROUTING_FIELDS: &[&str] = &["model", "nim-llm-router", "stream", "max_tokens"];
+
+fn anonymize_value(value: &mut Value, config: &AnonymizeConfig) {
+    match value {
+        Value::String(text) => {
+            let stripped = API_KEY.replace_all(text, "[API_KEY]");
+            *text = match config.pii {
+                Some(mode) => pii::redact(&stripped, mode),
+                None => stripped.into_owned(),
+            };
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| anonymize_value(item, config)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|field| anonymize_value(field, config)),
+        _ => {}
+    }
+}
+
+/// Drops `strip_fields`, replaces anything that looks like an API key and
+/// scrubs PII from every string outside the routing fields.
+pub fn anonymize(mut body: Value, config: &AnonymizeConfig) -> Value {
+    let Some(fields) = body.as_object_mut() else {
+        return body;
+    };
+    for field in &config.strip_fields {
+        fields.remove(field);
+    }
+    for (name, value) in fields.iter_mut() {
+        if !ROUTING_FIELDS.contains(&name.as_str()) {
+            anonymize_value(value, config);
+        }
+    }
+    body
+}
+
+fn timestamp_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis())
+}
+
+/// Queues a sampled request for the capture file. A no-op when capture is
+/// not configured; never blocks the caller.
+pub fn record(path: &str, context: &RequestContext, body: &Value) {
+    let Some((config, sender)) = CAPTURE.get() else {
+        return;
+    };
+    if config.sample_rate < 1.0 && rand::thread_rng().gen::<f64>() >= config.sample_rate {
+        return;
+    }
+    let mut anonymize_config = config.anonymize.clone();
+    if privacy::enabled() {
+        anonymize_config.pii.get_or_insert(RedactionMode::Hash);
+        for field in privacy::PRIVATE_FIELDS {
+            if !anonymize_config.strip_fields.iter().any(|f| f == field) {
+                anonymize_config.strip_fields.push(field.to_string());
+            }
+        }
+    }
+    // Credential headers are already removed from the context.
+    let timestamp_ms = timestamp_ms();
+    let path = path.to_string();
+    let headers = privacy::export(context).headers;
+    let body = body.clone();
+    background::submit("capture", move || {
+        let record = json!({
+            "timestamp_ms": timestamp_ms,
+            "path": path,
+            "headers": headers,
+            "body": anonymize(body, &anonymize_config),
+        });
+        if sender.try_send(record).is_err() {
+            warn!("Traffic capture queue full, dropping request");
+        }
+    });
+}
+
+/// Starts the capture writer if `traffic_capture` is configured.
+pub fn spawn(config: &TrafficCaptureConfig) {
+    let (sender, mut receiver) = mpsc::channel::<Value>(CHANNEL_CAPACITY);
+    if CAPTURE.set((config.clone(), sender)).is_err() {
+        return;
+    }
+    let path = config.path.clone();
+    info!("Capturing sampled traffic to {}", path);
+
+    tokio::spawn(async move {
+        let mut file = match tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+        {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to open traffic capture file {}: {:?}", path, e);
+                return;
+            }
+        };
+        while let Some(record) = receiver.recv().await {
+            let line = format!("{}\n", record);
+            if let Err(e) = file.write_all(line.as_bytes()).await {
+                error!("Failed to write traffic capture: {:?}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pii::RedactionMode;
+
+    #[test]
+    fn test_anonymize() {
+        let body = json!({
+            "model": "",
+            "user": "jane@example.com",
+            "messages": [
+                { "role": "user", "content": "I am jane@example.com, my key is sk-abcdefghijklmnop1234" }
+            ],
+            "nim-llm-router": { "policy": "task_router" }
+        });
+        let config = AnonymizeConfig {
+            pii: Some(RedactionMode::Strip),
+            strip_fields: vec!["user".to_string()],
+        };
+        assert_eq!(
+            anonymize(body, &config),
+            json!({
+                "model": "",
+                "messages": [
+                    { "role": "user", "content": "I am , my key is [API_KEY]" }
+                ],
+                "nim-llm-router": { "policy": "task_router" }
+            })
+        );
+    }
+}
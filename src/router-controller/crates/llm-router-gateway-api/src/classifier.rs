@@ -0,0 +1,522 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Classifier
+use crate::config::{
+    CandidateSelection, ClassifierCacheConfig, MultiLabelConfig, Policy, SyntheticClassifierConfig,
+    SyntheticScores,
+};
+use crate::decision_cache;
+use crate::error::GatewayApiError;
+use crate::local_classifier;
+use crate::metrics::LOW_CONFIDENCE_ROUTES;
+use crate::stats;
+use crate::triton::{InferInputTensor, InferInputs, Output};
+use crate::triton_grpc;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use rand::Rng;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+lazy_static! {
+    /// Next LLM index per policy for round robin synthetic scores.
+    static ref ROUND_ROBIN: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+}
+
+/// Turns classifier class scores into one score per LLM of the policy,
+/// through its `labels`. An LLM named by several classes takes the highest
+/// of their scores, and one named by none scores `0`. Without `labels`
+/// classes map to `llms` by position.
+pub fn label_scores(policy: &Policy, raw: &[f64]) -> Vec<f64> {
+    if policy.labels.is_empty() {
+        return raw.to_vec();
+    }
+    if raw.len() != policy.labels.len() {
+        warn!(
+            "Classifier returned {} scores for the {} labels of policy {}",
+            raw.len(),
+            policy.labels.len(),
+            policy.name
+        );
+    }
+    let mut scores = vec![0.0_f64; policy.llms.len()];
+    for (label, &score) in policy.labels.iter().zip(raw) {
+        if let Some(index) = policy.llms.iter().position(|llm| llm.name == *label) {
+            scores[index] = scores[index].max(score);
+        }
+    }
+    scores
+}
+
+/// Applies each LLM's `score_adjustment` to scores positionally aligned
+/// with the policy's `llms`.
+pub fn adjust_scores(policy: &Policy, scores: &[f64]) -> Vec<f64> {
+    scores
+        .iter()
+        .enumerate()
+        .map(|(index, &score)| match policy.llms.get(index) {
+            Some(llm) => match &llm.score_adjustment {
+                Some(adjustment) => score * adjustment.multiplier + adjustment.bias,
+                None => score,
+            },
+            None => score,
+        })
+        .collect()
+}
+
+fn argmax(scores: &[f64]) -> Option<usize> {
+    scores
+        .iter()
+        .enumerate()
+        .max_by(|&(_, a), &(_, b)| a.total_cmp(b))
+        .map(|(idx, _)| idx)
+}
+
+/// Indices of the (at most `top_k`) LLMs scoring at or above the multi-label
+/// threshold, best first.
+pub fn candidates(policy: &Policy, scores: &[f64], multi_label: &MultiLabelConfig) -> Vec<usize> {
+    let mut candidates: Vec<usize> = scores
+        .iter()
+        .enumerate()
+        .filter(|&(index, &score)| index < policy.llms.len() && score >= multi_label.threshold)
+        .map(|(index, _)| index)
+        .collect();
+    candidates.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+    candidates.truncate(multi_label.top_k.max(1));
+    candidates
+}
+
+fn selection_cost(policy: &Policy, index: usize, selection: &CandidateSelection) -> f64 {
+    let llm = &policy.llms[index];
+    match selection {
+        CandidateSelection::Cost => llm
+            .pricing
+            .as_ref()
+            .map(|p| p.input_per_million + p.output_per_million)
+            .unwrap_or(f64::INFINITY),
+        // Unmeasured upstreams get a chance to report a latency.
+        CandidateSelection::Latency => {
+            stats::snapshot(&stats::upstream_key(&policy.name, &llm.name))
+                .ewma_latency
+                .unwrap_or(0.0)
+        }
+        CandidateSelection::Load => {
+            stats::snapshot(&stats::upstream_key(&policy.name, &llm.name)).in_flight as f64
+        }
+    }
+}
+
+/// Picks the LLM index for a set of (adjusted) scores. Policies with
+/// `multi_label` choose among the candidates above threshold using the
+/// configured secondary selection, falling back to the highest score when no
+/// class clears the threshold.
+pub fn select_index(policy: &Policy, scores: &[f64]) -> Option<usize> {
+    let Some(multi_label) = &policy.multi_label else {
+        return argmax(scores);
+    };
+    let candidates = candidates(policy, scores, multi_label);
+    info!("Multi-label candidates: {:?}", &candidates);
+    candidates
+        .into_iter()
+        .min_by(|&a, &b| {
+            selection_cost(policy, a, &multi_label.selection)
+                .total_cmp(&selection_cost(policy, b, &multi_label.selection))
+                .then(scores[b].total_cmp(&scores[a]))
+        })
+        .or_else(|| argmax(scores))
+}
+
+/// Moves a decision whose score is below `threshold` to the policy's
+/// `low_confidence_llm`, keeping it when there is none.
+pub fn apply_threshold(policy: &Policy, index: usize, scores: &[f64], threshold: f64) -> usize {
+    if scores.get(index).is_none_or(|&score| score >= threshold) {
+        return index;
+    }
+    let fallback = policy
+        .low_confidence_llm
+        .as_ref()
+        .and_then(|name| policy.llms.iter().position(|llm| llm.name == *name));
+    let source = if fallback.is_some() {
+        "low_confidence_llm"
+    } else {
+        "classifier"
+    };
+    LOW_CONFIDENCE_ROUTES
+        .with_label_values(&[&policy.name, source])
+        .inc();
+    info!(
+        "Classifier score {:.3} is below threshold {} for policy {}, routing by {}",
+        scores[index], threshold, policy.name, source
+    );
+    fallback.unwrap_or(index)
+}
+
+/// Result of classifying a prompt: the chosen LLM index and the adjusted
+/// scores it was chosen from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub index: usize,
+    pub scores: Vec<f64>,
+}
+
+impl Classification {
+    /// Score of the chosen class.
+    pub fn confidence(&self) -> Option<f64> {
+        self.scores.get(self.index).copied()
+    }
+}
+
+/// Raw class probabilities for `text_input` from the policy's Triton model.
+async fn triton_scores(
+    policy: &Policy,
+    client: &reqwest::Client,
+    text_input: &str,
+) -> Result<Vec<f64>, GatewayApiError> {
+    info!("Using policy: {}", &policy.name);
+    info!("Triton input text: {:#?}", &text_input);
+    let request_timeout = policy
+        .classifier_timeouts
+        .and_then(|timeouts| timeouts.request_ms)
+        .map(Duration::from_millis);
+    let timed_out = |timeout: Duration| {
+        error!("Triton did not respond within {:?}", timeout);
+        GatewayApiError::TritonServiceError {
+            status_code: 504,
+            message: format!("Triton did not respond within {} ms", timeout.as_millis()),
+        }
+    };
+    if let Some(grpc) = &policy.triton_grpc {
+        match triton_grpc::infer(grpc, text_input, request_timeout).await {
+            Ok(scores) => return Ok(scores),
+            Err(e) if grpc.fallback_to_http => {
+                warn!("Triton gRPC call failed, retrying over HTTP: {}", e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let text_tensor = InferInputTensor {
+        name: "INPUT".to_string(),
+        datatype: "BYTES".to_string(),
+        shape: vec![1, 1],
+        data: vec![vec![text_input.to_string()]],
+    };
+
+    let data = InferInputs {
+        inputs: vec![text_tensor],
+    };
+
+    let url = policy.url.clone();
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+    let request = client.post(url).headers(headers).json(&data);
+    let request = match request_timeout {
+        Some(timeout) => request.timeout(timeout),
+        None => request,
+    };
+    let response = request.send().await.map_err(|e| match request_timeout {
+        Some(timeout) if e.is_timeout() && !e.is_connect() => timed_out(timeout),
+        _ => {
+            error!("Failed to reach Triton server: {:?}", e);
+            GatewayApiError::TritonServiceError {
+                status_code: if e.is_timeout() { 504 } else { 503 },
+                message: "Triton server is unreachable".to_string(),
+            }
+        }
+    })?;
+    info!("Triton classification response: {:#?}", response);
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_body = response.bytes().await?;
+        error!(
+            "Triton error response: {}",
+            String::from_utf8_lossy(&error_body)
+        );
+
+        return Err(GatewayApiError::TritonServiceError {
+            status_code: status.as_u16(),
+            message: format!(
+                "Triton service error: {}",
+                String::from_utf8_lossy(&error_body)
+            ),
+        });
+    }
+
+    // Parse successful response
+    let response: Output = response.json().await.map_err(|e| {
+        error!("Failed to parse Triton response: {:?}", e);
+        GatewayApiError::TritonServiceError {
+            status_code: 500,
+            message: format!("Invalid Triton response: {}", e),
+        }
+    })?;
+
+    info!("Triton Output: {:#?}", response);
+
+    let output_tensor =
+        response
+            .outputs
+            .first()
+            .ok_or_else(|| GatewayApiError::TritonServiceError {
+                status_code: 500,
+                message: "No outputs returned from the Triton response".to_string(),
+            })?;
+
+    Ok(output_tensor.data.clone())
+}
+
+/// Raw class probabilities from the policy's local model, or from Triton.
+async fn raw_scores(
+    policy: &Policy,
+    client: &reqwest::Client,
+    text_input: &str,
+) -> Result<Vec<f64>, GatewayApiError> {
+    match policy.local_classifier() {
+        Some(local) => local_classifier::infer(local, text_input).await,
+        None => triton_scores(policy, client, text_input).await,
+    }
+}
+
+/// Classifies `text_input` with the policy's classifier, Triton unless a
+/// local one is configured. With `cache` set, raw scores for
+/// text seen recently are reused; adjustment and selection always run, so
+/// cached decisions still follow live upstream stats. Decisions scoring
+/// below `threshold` go to the policy's `low_confidence_llm`.
+pub async fn choose_model(
+    policy: &Policy,
+    client: &reqwest::Client,
+    text_input: &str,
+    threshold: f64,
+    cache: Option<&ClassifierCacheConfig>,
+) -> Result<Classification, GatewayApiError> {
+    let cache_key = cache.map(|_| decision_cache::key(policy, text_input));
+    let cached = cache_key
+        .as_deref()
+        .and_then(|key| decision_cache::lookup(&policy.name, key));
+    let raw = match cached {
+        Some(raw) => raw,
+        None => {
+            let raw = raw_scores(policy, client, text_input).await?;
+            if let (Some(cache), Some(key)) = (cache, cache_key) {
+                decision_cache::store(cache, key, raw.clone());
+            }
+            raw
+        }
+    };
+
+    let scores = adjust_scores(policy, &label_scores(policy, &raw));
+    info!("Adjusted classifier scores: {:?}", &scores);
+
+    let model_index = select_index(policy, &scores).ok_or_else(|| {
+        error!("Invalid probability distribution from Triton");
+        GatewayApiError::TritonServiceError {
+            status_code: 500,
+            message: "Could not determine model selection from probability distribution"
+                .to_string(),
+        }
+    })?;
+
+    let model_index = apply_threshold(policy, model_index, &scores, threshold);
+
+    info!("model_index chosen by classifier: {:#?}", model_index);
+    Ok(Classification {
+        index: model_index,
+        scores,
+    })
+}
+
+fn synthetic_scores(policy: &Policy, scores: SyntheticScores) -> Vec<f64> {
+    let count = policy.llms.len();
+    match scores {
+        SyntheticScores::RoundRobin => {
+            let mut next = ROUND_ROBIN.lock().unwrap_or_else(|e| e.into_inner());
+            let index = next.entry(policy.name.clone()).or_default();
+            let chosen = *index % count.max(1);
+            *index = chosen + 1;
+            (0..count)
+                .map(|i| if i == chosen { 1.0 } else { 0.0 })
+                .collect()
+        }
+        SyntheticScores::Random => {
+            let mut rng = rand::thread_rng();
+            let raw: Vec<f64> = (0..count).map(|_| rng.gen_range(0.0..1.0)).collect();
+            let total: f64 = raw.iter().sum();
+            raw.iter()
+                .map(|score| score / total.max(f64::MIN_POSITIVE))
+                .collect()
+        }
+    }
+}
+
+/// Stands in for `choose_model` without calling Triton. The generated
+/// scores go through the same adjustment and selection as real ones.
+pub async fn choose_synthetic(
+    policy: &Policy,
+    config: &SyntheticClassifierConfig,
+) -> Result<Classification, GatewayApiError> {
+    if config.latency_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(config.latency_ms)).await;
+    }
+    let scores = adjust_scores(policy, &synthetic_scores(policy, config.scores));
+    info!("Synthetic classifier scores: {:?}", &scores);
+    let index =
+        select_index(policy, &scores).ok_or_else(|| GatewayApiError::TritonServiceError {
+            status_code: 500,
+            message: format!("Policy '{}' has no LLMs to classify into", policy.name),
+        })?;
+    Ok(Classification { index, scores })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Llm, Pricing, ScoreAdjustment};
+
+    #[test]
+    fn test_adjust_scores() {
+        let policy = Policy {
+            llms: vec![
+                Llm {
+                    name: "cheap".to_string(),
+                    score_adjustment: Some(ScoreAdjustment {
+                        multiplier: 1.0,
+                        bias: 0.3,
+                    }),
+                    ..Default::default()
+                },
+                Llm {
+                    name: "expensive".to_string(),
+                    score_adjustment: Some(ScoreAdjustment {
+                        multiplier: 0.5,
+                        bias: 0.0,
+                    }),
+                    ..Default::default()
+                },
+                Llm {
+                    name: "plain".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            adjust_scores(&policy, &[0.2, 0.8, 0.1, 0.4]),
+            vec![0.5, 0.4, 0.1, 0.4]
+        );
+    }
+
+    #[test]
+    fn test_label_scores() {
+        let named = |name: &str| Llm {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let mut policy = Policy {
+            llms: vec![named("general"), named("code"), named("math")],
+            ..Default::default()
+        };
+        let raw = [0.1, 0.2, 0.3, 0.4];
+        assert_eq!(label_scores(&policy, &raw), raw.to_vec());
+
+        policy.labels = vec![
+            "code".to_string(),
+            "general".to_string(),
+            "code".to_string(),
+            "general".to_string(),
+        ];
+        assert_eq!(label_scores(&policy, &raw), vec![0.4, 0.3, 0.0]);
+        // Reordering `llms` keeps each class on the same LLM.
+        policy.llms.reverse();
+        assert_eq!(label_scores(&policy, &raw), vec![0.0, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn test_multi_label_by_cost() {
+        let priced = |name: &str, price: f64| Llm {
+            name: name.to_string(),
+            pricing: Some(Pricing {
+                input_per_million: price,
+                output_per_million: price,
+            }),
+            ..Default::default()
+        };
+        let mut policy = Policy {
+            name: "multi_label_test".to_string(),
+            llms: vec![
+                priced("large", 10.0),
+                priced("small", 0.5),
+                priced("medium", 2.0),
+            ],
+            ..Default::default()
+        };
+        let scores = [0.9, 0.6, 0.2];
+        assert_eq!(select_index(&policy, &scores), Some(0));
+
+        policy.multi_label = Some(MultiLabelConfig {
+            threshold: 0.5,
+            top_k: 3,
+            selection: CandidateSelection::Cost,
+        });
+        assert_eq!(select_index(&policy, &scores), Some(1));
+        // Nothing clears the threshold, so the top score still wins.
+        assert_eq!(select_index(&policy, &[0.1, 0.3, 0.2]), Some(1));
+    }
+
+    #[test]
+    fn test_low_confidence_llm() {
+        let named = |name: &str| Llm {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let mut policy = Policy {
+            name: "low_confidence_test".to_string(),
+            llms: vec![named("small"), named("large"), named("general")],
+            ..Default::default()
+        };
+        let scores = [0.45, 0.35, 0.2];
+        assert_eq!(apply_threshold(&policy, 0, &scores, 0.5), 0);
+
+        policy.low_confidence_llm = Some("general".to_string());
+        assert_eq!(apply_threshold(&policy, 0, &scores, 0.5), 2);
+        assert_eq!(apply_threshold(&policy, 0, &scores, 0.4), 0);
+    }
+
+    #[tokio::test]
+    async fn test_synthetic_classifier() {
+        let policy = Policy {
+            name: "synthetic_test".to_string(),
+            llms: vec![Llm::default(), Llm::default(), Llm::default()],
+            ..Default::default()
+        };
+        let round_robin = SyntheticClassifierConfig::default();
+        let mut picks = Vec::new();
+        for _ in 0..4 {
+            picks.push(choose_synthetic(&policy, &round_robin).await.unwrap().index);
+        }
+        assert_eq!(picks, vec![0, 1, 2, 0]);
+
+        let random = SyntheticClassifierConfig {
+            scores: SyntheticScores::Random,
+            latency_ms: 0,
+        };
+        let classification = choose_synthetic(&policy, &random).await.unwrap();
+        assert_eq!(classification.scores.len(), 3);
+        assert!((classification.scores.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+}
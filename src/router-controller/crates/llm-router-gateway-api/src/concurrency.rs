@@ -0,0 +1,78 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Concurrency
+//!
+//! Per-LLM limits on requests in flight, for self-hosted NIMs that fall
+//! over under unbounded parallel load. A request holds its slot until its
+//! response, streamed or not, has been passed on.
+use crate::config::Llm;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+lazy_static! {
+    /// Slots of the LLMs with a limit, by policy and LLM, with the limit
+    /// they were made for.
+    static ref SLOTS: Mutex<HashMap<String, (usize, Arc<Semaphore>)>> = Mutex::new(HashMap::new());
+}
+
+/// A slot of `llm` in `policy`, `None` when it has no limit. Fails with
+/// the limit when every slot is taken.
+///
+/// A changed limit starts a new set of slots; requests holding slots of
+/// the old limit are not counted against it.
+pub fn try_acquire(policy: &str, llm: &Llm) -> Result<Option<OwnedSemaphorePermit>, usize> {
+    let Some(limit) = llm.max_concurrent_requests else {
+        return Ok(None);
+    };
+    let semaphore = {
+        let mut slots = SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = slots
+            .entry(format!("{}/{}", policy, llm.name))
+            .or_insert_with(|| (limit, Arc::new(Semaphore::new(limit))));
+        if entry.0 != limit {
+            *entry = (limit, Arc::new(Semaphore::new(limit)));
+        }
+        entry.1.clone()
+    };
+    semaphore.try_acquire_owned().map(Some).map_err(|_| limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_acquire() {
+        let mut llm = Llm {
+            name: "Chatbot".to_string(),
+            ..Default::default()
+        };
+        assert!(try_acquire("concurrency_test", &llm).unwrap().is_none());
+
+        llm.max_concurrent_requests = Some(2);
+        let first = try_acquire("concurrency_test", &llm).unwrap();
+        let second = try_acquire("concurrency_test", &llm).unwrap();
+        assert!(first.is_some() && second.is_some());
+        assert_eq!(try_acquire("concurrency_test", &llm).unwrap_err(), 2);
+        drop(first);
+        assert!(try_acquire("concurrency_test", &llm).is_ok());
+
+        llm.max_concurrent_requests = Some(3);
+        assert!(try_acquire("concurrency_test", &llm).is_ok());
+    }
+}
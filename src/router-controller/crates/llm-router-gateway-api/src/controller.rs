@@ -0,0 +1,288 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Controller
+//!
+//! Policies managed as `RouterPolicy` custom resources: each resource's
+//! `spec` is a policy named after the resource, and the controller keeps
+//! the live configuration in step with the resources as they are created,
+//! changed and deleted. Policies of the configuration itself take
+//! precedence over resources of the same name.
+use crate::config::{expand_env, Policy, RouterConfig, SharedConfig};
+use crate::metrics::CONFIG_RELOADS;
+use crate::reload::{RESULT_APPLIED, RESULT_REJECTED};
+use futures_util::StreamExt;
+use kube::api::{ApiResource, DynamicObject, GroupVersionKind};
+use kube::runtime::watcher::{self, Event};
+use kube::runtime::WatchStreamExt;
+use kube::{Api, Client, ResourceExt};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Mutex, RwLock};
+
+pub const GROUP: &str = "llm-router.nvidia.com";
+pub const VERSION: &str = "v1alpha1";
+pub const KIND: &str = "RouterPolicy";
+
+/// `source` label of policies reconciled from resources.
+const SOURCE: &str = "routerpolicy";
+
+lazy_static! {
+    /// Valid policies of the watched resources, by name.
+    static ref POLICIES: RwLock<BTreeMap<String, Policy>> = RwLock::new(BTreeMap::new());
+    /// Names of the policies last merged into the live configuration.
+    static ref MERGED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+}
+
+/// Resolves `${NAME}` references in the strings of `value` from the
+/// environment.
+fn expand(value: &mut Value) -> Result<(), String> {
+    match value {
+        Value::String(text) => *text = expand_env(text).map_err(|e| e.to_string())?,
+        Value::Array(values) => values.iter_mut().try_for_each(expand)?,
+        Value::Object(fields) => fields.values_mut().try_for_each(expand)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The policy a resource describes, validated on its own.
+fn policy(resource: &DynamicObject) -> Result<Policy, String> {
+    let mut spec = resource.data.get("spec").cloned().unwrap_or(Value::Null);
+    let fields = spec.as_object_mut().ok_or("has no spec")?;
+    fields.insert("name".to_string(), Value::String(resource.name_any()));
+    expand(&mut spec)?;
+    let policy: Policy = serde_json::from_value(spec).map_err(|e| e.to_string())?;
+    RouterConfig {
+        policies: vec![policy.clone()],
+        ..Default::default()
+    }
+    .validate()
+    .map_err(|e| e.to_string())?;
+    Ok(policy)
+}
+
+/// Adds the policies of the resources to `config`, except those whose
+/// name a policy of `config` already has.
+pub fn merge(config: &mut RouterConfig) {
+    let policies = POLICIES.read().unwrap_or_else(|e| e.into_inner());
+    let mut merged = MERGED.lock().unwrap_or_else(|e| e.into_inner());
+    merged.clear();
+    for policy in policies.values() {
+        if config.policies.iter().any(|p| p.name == policy.name) {
+            warn!(
+                "{} '{}' is shadowed by a policy of the configuration",
+                KIND, policy.name
+            );
+            continue;
+        }
+        merged.insert(policy.name.clone());
+        config.policies.push(policy.clone());
+    }
+}
+
+/// `config` without the policies merged from resources, as it is saved.
+pub fn unmerged(config: &RouterConfig) -> RouterConfig {
+    let merged = MERGED.lock().unwrap_or_else(|e| e.into_inner());
+    RouterConfig {
+        policies: config
+            .policies
+            .iter()
+            .filter(|policy| !merged.contains(&policy.name))
+            .cloned()
+            .collect(),
+        ..config.clone()
+    }
+}
+
+/// Replaces the policies of the resources with those of `resources`, and
+/// the live configuration's with them.
+fn reconcile(resources: &BTreeMap<String, DynamicObject>, shared: &SharedConfig) {
+    let mut policies = BTreeMap::new();
+    for (key, resource) in resources {
+        match policy(resource) {
+            Ok(policy) if policies.contains_key(&policy.name) => {
+                warn!("Ignored {} {}: its name is already taken", KIND, key);
+            }
+            Ok(policy) => {
+                policies.insert(policy.name.clone(), policy);
+            }
+            Err(e) => {
+                warn!("Ignored invalid {} {}: {}", KIND, key, e);
+                CONFIG_RELOADS
+                    .with_label_values(&[SOURCE, RESULT_REJECTED])
+                    .inc();
+            }
+        }
+    }
+    *POLICIES.write().unwrap_or_else(|e| e.into_inner()) = policies;
+
+    let previous = MERGED.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let updated = shared.update(|config| {
+        config
+            .policies
+            .retain(|policy| !previous.contains(&policy.name));
+        merge(config);
+        Ok(())
+    });
+    match updated {
+        Ok(config) => {
+            info!(
+                "Reconciled {} resources: {} policies in use",
+                KIND,
+                config.policies.len()
+            );
+            CONFIG_RELOADS
+                .with_label_values(&[SOURCE, RESULT_APPLIED])
+                .inc();
+        }
+        Err(e) => {
+            warn!(
+                "Kept the running policies; {} resources do not apply: {}",
+                KIND, e
+            );
+            CONFIG_RELOADS
+                .with_label_values(&[SOURCE, RESULT_REJECTED])
+                .inc();
+        }
+    }
+}
+
+fn key(resource: &DynamicObject) -> String {
+    format!(
+        "{}/{}",
+        resource.namespace().unwrap_or_default(),
+        resource.name_any()
+    )
+}
+
+/// Watches `RouterPolicy` resources in `namespace`, or in every namespace,
+/// and reconciles `shared` with them.
+pub async fn spawn(namespace: Option<String>, shared: SharedConfig) -> Result<(), String> {
+    let client = Client::try_default()
+        .await
+        .map_err(|e| format!("Kubernetes client setup failed: {}", e))?;
+    let resource = ApiResource::from_gvk(&GroupVersionKind::gvk(GROUP, VERSION, KIND));
+    let api: Api<DynamicObject> = match &namespace {
+        Some(namespace) => Api::namespaced_with(client, namespace, &resource),
+        None => Api::all_with(client, &resource),
+    };
+    let mut events = watcher::watcher(api, watcher::Config::default())
+        .default_backoff()
+        .boxed();
+
+    tokio::spawn(async move {
+        info!(
+            "Watching {} resources in {}",
+            KIND,
+            namespace.as_deref().unwrap_or("all namespaces")
+        );
+        let mut resources = BTreeMap::new();
+        // Resources of a listing in progress, applied once it completes.
+        let mut listing: Option<BTreeMap<String, DynamicObject>> = None;
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(Event::Init) => listing = Some(BTreeMap::new()),
+                Ok(Event::InitApply(resource)) => {
+                    if let Some(listing) = listing.as_mut() {
+                        listing.insert(key(&resource), resource);
+                    }
+                }
+                Ok(Event::InitDone) => {
+                    resources = listing.take().unwrap_or_default();
+                    reconcile(&resources, &shared);
+                }
+                Ok(Event::Apply(resource)) => {
+                    resources.insert(key(&resource), resource);
+                    reconcile(&resources, &shared);
+                }
+                Ok(Event::Delete(resource)) => {
+                    resources.remove(&key(&resource));
+                    reconcile(&resources, &shared);
+                }
+                Err(e) => warn!("{} watch failed, retrying: {}", KIND, e),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Llm;
+    use serde_json::json;
+
+    fn resource(name: &str, spec: Value) -> DynamicObject {
+        let resource = ApiResource::from_gvk(&GroupVersionKind::gvk(GROUP, VERSION, KIND));
+        DynamicObject::new(name, &resource)
+            .within("llm-router")
+            .data(json!({ "spec": spec }))
+    }
+
+    #[test]
+    fn test_reconcile() {
+        std::env::set_var("CONTROLLER_TEST_KEY", "secret");
+        let spec = json!({
+            "url": "http://triton:8000",
+            "llms": [{
+                "name": "Chatbot",
+                "api_base": "https://integrate.api.nvidia.com",
+                "api_key": "${CONTROLLER_TEST_KEY}",
+                "model": "meta/llama-3.1-8b-instruct"
+            }]
+        });
+        let file_policy = Policy {
+            name: "from_file".to_string(),
+            llms: vec![Llm {
+                name: "Chatbot".to_string(),
+                api_base: "https://integrate.api.nvidia.com".to_string(),
+                api_key: "key".to_string(),
+                model: "meta/llama-3.1-8b-instruct".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let shared = SharedConfig::new(
+            RouterConfig {
+                policies: vec![file_policy],
+                ..Default::default()
+            },
+            None,
+        );
+
+        let mut resources = BTreeMap::new();
+        for resource in [
+            resource("chat", spec.clone()),
+            resource("from_file", spec.clone()),
+            resource("broken", json!({"url": "http://triton:8000"})),
+        ] {
+            resources.insert(key(&resource), resource);
+        }
+        reconcile(&resources, &shared);
+        let config = shared.snapshot();
+        let names: Vec<&str> = config.policies.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["from_file", "chat"]);
+        assert_eq!(config.policies[0].llms[0].api_key, "key");
+        assert_eq!(config.policies[1].llms[0].api_key, "secret");
+        assert_eq!(unmerged(&config).policies.len(), 1);
+
+        resources.remove("llm-router/chat");
+        reconcile(&resources, &shared);
+        assert_eq!(shared.snapshot().policies.len(), 1);
+    }
+}
@@ -0,0 +1,193 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversation
+//!
+//! Token totals per conversation, keyed by the caller and its
+//! `X-Session-Id` header, kept in the state store until the conversation
+//! has been idle for `idle_ttl_seconds`.
+use crate::caller::Caller;
+use crate::config::{CallerKey, ConversationConfig};
+use crate::error::GatewayApiError;
+use crate::store;
+use http::{HeaderMap, StatusCode};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const SESSION_ID_HEADER: &str = "X-Session-Id";
+/// Longest session id that is tracked; longer ids are ignored.
+const MAX_SESSION_ID_LEN: usize = 256;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ConversationTotals {
+    pub turns: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    pub last_turn_ms: u64,
+}
+
+/// One request's view of its conversation.
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    caller: String,
+    id: String,
+    config: ConversationConfig,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Session ids are chosen by callers, so each caller has its own.
+fn store_key(caller: &str, id: &str) -> String {
+    format!("conversation:{}:{}", caller, id)
+}
+
+/// `caller` is the identity conversations are kept under: `sub:<subject>`,
+/// `key:<sha256 of the bearer key>` or `ip:<address>`.
+pub fn totals(caller: &str, id: &str) -> Option<ConversationTotals> {
+    store::report("conversation", store::get().get(&store_key(caller, id)))
+        .flatten()
+        .and_then(|value| serde_json::from_slice(&value).ok())
+}
+
+/// Forgets a conversation, returning whether it was tracked.
+pub fn remove(caller: &str, id: &str) -> bool {
+    let removed = store::get().delete(&store_key(caller, id));
+    store::report("conversation", removed).unwrap_or(false)
+}
+
+impl Conversation {
+    /// Conversations are kept per JWT subject, bearer key or address. Any
+    /// bearer key counts here: it only separates callers, nothing is charged
+    /// to it.
+    pub fn from_request(
+        headers: &HeaderMap,
+        caller: &Caller,
+        config: &ConversationConfig,
+    ) -> Option<Self> {
+        let id = headers
+            .get(SESSION_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty() && v.len() <= MAX_SESSION_ID_LEN)?;
+        Some(Self {
+            caller: caller.identify(CallerKey::ApiKey, caller.api_key).1,
+            id: id.to_string(),
+            config: config.clone(),
+        })
+    }
+
+    /// Refuses another turn once the conversation has used its budget.
+    pub fn check(&self) -> Result<(), GatewayApiError> {
+        let Some(max_tokens) = self.config.max_tokens else {
+            return Ok(());
+        };
+        let used = totals(&self.caller, &self.id).map_or(0, |totals| totals.total_tokens);
+        if used < max_tokens {
+            return Ok(());
+        }
+        warn!(
+            "conversation budget exhausted: session={} total_tokens={} max_tokens={}",
+            self.id, used, max_tokens
+        );
+        Err(GatewayApiError::client_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!(
+                "Conversation has used {} of its {} token budget",
+                used, max_tokens
+            ),
+            "conversation_budget_exceeded",
+        ))
+    }
+
+    /// Adds one turn's OpenAI `usage` object to the conversation and
+    /// restarts its idle timer.
+    pub fn record(&self, usage: &Value) {
+        let prompt = usage["prompt_tokens"].as_u64().unwrap_or(0);
+        let completion = usage["completion_tokens"].as_u64().unwrap_or(0);
+        let total = usage["total_tokens"]
+            .as_u64()
+            .unwrap_or(prompt + completion);
+
+        let ttl = Duration::from_secs(self.config.idle_ttl_seconds);
+        let recorded = store::update(
+            store::get(),
+            &store_key(&self.caller, &self.id),
+            Some(ttl),
+            |current| {
+                let mut totals: ConversationTotals = current
+                    .and_then(|value| serde_json::from_slice(value).ok())
+                    .unwrap_or_default();
+                totals.turns += 1;
+                totals.prompt_tokens += prompt;
+                totals.completion_tokens += completion;
+                totals.total_tokens += total;
+                totals.last_turn_ms = now_ms();
+                (serde_json::to_vec(&totals).unwrap_or_default(), ())
+            },
+        );
+        store::report("conversation", recorded);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+    use serde_json::json;
+
+    #[test]
+    fn test_totals_and_budget() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            SESSION_ID_HEADER,
+            HeaderValue::from_static("conversation-test"),
+        );
+        let config = ConversationConfig {
+            max_tokens: Some(100),
+            ..Default::default()
+        };
+        let caller = Caller::new(&headers, None, Some("198.51.100.7".parse().unwrap()));
+        let conversation = Conversation::from_request(&headers, &caller, &config).unwrap();
+        assert!(Conversation::from_request(&HeaderMap::new(), &caller, &config).is_none());
+
+        conversation
+            .record(&json!({"prompt_tokens": 30, "completion_tokens": 20, "total_tokens": 50}));
+        assert!(conversation.check().is_ok());
+        conversation.record(&json!({"prompt_tokens": 40, "completion_tokens": 10}));
+
+        let owner = "ip:198.51.100.7";
+        let totals = totals(owner, "conversation-test").unwrap();
+        assert_eq!(totals.turns, 2);
+        assert_eq!(totals.prompt_tokens, 70);
+        assert_eq!(totals.total_tokens, 100);
+        assert!(conversation.check().is_err());
+
+        // Another caller reusing the session id has a conversation of its own.
+        let other = Caller::new(&headers, None, Some("198.51.100.8".parse().unwrap()));
+        let other = Conversation::from_request(&headers, &other, &config).unwrap();
+        assert!(other.check().is_ok());
+
+        assert!(remove(owner, "conversation-test"));
+        assert!(conversation.check().is_ok());
+    }
+}
@@ -0,0 +1,196 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! CORS
+//!
+//! Cross-origin access for browser clients: preflight `OPTIONS` requests are
+//! answered here, and responses to allowed origins carry the headers that
+//! let the page read them.
+use crate::config::CorsConfig;
+use crate::error::{GatewayApiError, IntoResponse};
+use bytes::Bytes;
+use http::header::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::{Request, Response};
+use log::info;
+
+const ANY: &str = "*";
+
+/// The `Access-Control-Allow-Origin` value for the request's origin, if it
+/// is allowed. Credentialed responses may not use `*`, so they name the
+/// origin.
+pub fn allowed_origin(config: &CorsConfig, headers: &HeaderMap) -> Option<HeaderValue> {
+    let origin = headers.get(ORIGIN)?;
+    let listed = config
+        .allowed_origins
+        .iter()
+        .any(|allowed| allowed.as_bytes() == origin.as_bytes());
+    let any = config.allowed_origins.iter().any(|allowed| allowed == ANY);
+    match (listed, any) {
+        (false, false) => None,
+        (false, true) if !config.allow_credentials => Some(HeaderValue::from_static(ANY)),
+        _ => Some(origin.clone()),
+    }
+}
+
+/// Whether the request is a browser's preflight check.
+pub fn is_preflight<B>(req: &Request<B>) -> bool {
+    req.method() == Method::OPTIONS && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+fn join(values: &[String]) -> Option<HeaderValue> {
+    HeaderValue::from_str(&values.join(", ")).ok()
+}
+
+/// Adds the headers letting `origin` read a response.
+pub fn apply(config: &CorsConfig, origin: Option<HeaderValue>, headers: &mut HeaderMap) {
+    let Some(origin) = origin else {
+        return;
+    };
+    if origin != ANY {
+        headers.append(VARY, HeaderValue::from_static("Origin"));
+    }
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    if config.allow_credentials {
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+    if config.expose_headers.is_empty() {
+        return;
+    }
+    if let Some(expose) = join(&config.expose_headers) {
+        headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, expose);
+    }
+}
+
+/// Answers a preflight request. Origins that are not allowed are refused
+/// with a 403.
+pub fn preflight(
+    config: &CorsConfig,
+    origin: Option<HeaderValue>,
+    request_headers: &HeaderMap,
+) -> Response<BoxBody<Bytes, GatewayApiError>> {
+    if origin.is_none() {
+        info!(
+            "CORS preflight refused for origin {:?}",
+            request_headers.get(ORIGIN)
+        );
+        let error = GatewayApiError::client_error(
+            StatusCode::FORBIDDEN,
+            "Origin is not allowed",
+            "cors_origin_not_allowed",
+        );
+        return error.into_response();
+    }
+
+    let empty = Full::from(Bytes::new())
+        .map_err(|never| match never {})
+        .boxed();
+    let mut response = Response::new(empty);
+    *response.status_mut() = StatusCode::NO_CONTENT;
+    let headers = response.headers_mut();
+    apply(config, origin, headers);
+    if let Some(methods) = join(&config.allowed_methods) {
+        headers.insert(ACCESS_CONTROL_ALLOW_METHODS, methods);
+    }
+    // `*` is taken literally on credentialed requests, so the requested
+    // headers are echoed instead.
+    let allowed_headers = if config.allowed_headers.iter().any(|h| h == ANY) {
+        request_headers.get(ACCESS_CONTROL_REQUEST_HEADERS).cloned()
+    } else {
+        join(&config.allowed_headers)
+    };
+    if let Some(allowed_headers) = allowed_headers {
+        headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+    }
+    headers.insert(ACCESS_CONTROL_MAX_AGE, config.max_age_seconds.into());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_headers(origin: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ORIGIN, HeaderValue::from_str(origin).unwrap());
+        headers.insert(
+            ACCESS_CONTROL_REQUEST_METHOD,
+            HeaderValue::from_static("POST"),
+        );
+        headers.insert(
+            ACCESS_CONTROL_REQUEST_HEADERS,
+            HeaderValue::from_static("content-type, x-session-id"),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_allowed_origin() {
+        let listed = CorsConfig {
+            allowed_origins: vec!["https://playground.example.com".to_string()],
+            ..Default::default()
+        };
+        let headers = request_headers("https://playground.example.com");
+        assert_eq!(
+            allowed_origin(&listed, &headers).unwrap(),
+            "https://playground.example.com"
+        );
+        assert!(allowed_origin(&listed, &request_headers("https://evil.example.com")).is_none());
+        assert!(allowed_origin(&listed, &HeaderMap::new()).is_none());
+
+        let mut any = CorsConfig {
+            allowed_origins: vec![ANY.to_string()],
+            ..Default::default()
+        };
+        assert_eq!(allowed_origin(&any, &headers).unwrap(), ANY);
+        any.allow_credentials = true;
+        assert_eq!(
+            allowed_origin(&any, &headers).unwrap(),
+            "https://playground.example.com"
+        );
+    }
+
+    #[test]
+    fn test_preflight() {
+        let config = CorsConfig {
+            allowed_origins: vec!["https://playground.example.com".to_string()],
+            allowed_headers: vec![ANY.to_string()],
+            ..Default::default()
+        };
+        let headers = request_headers("https://playground.example.com");
+        let response = preflight(&config, allowed_origin(&config, &headers), &headers);
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let headers = response.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "GET, POST, OPTIONS");
+        assert_eq!(
+            headers[ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type, x-session-id"
+        );
+        assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(headers[VARY], "Origin");
+
+        let headers = request_headers("https://evil.example.com");
+        let response = preflight(&config, allowed_origin(&config, &headers), &headers);
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,266 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Degraded
+//!
+//! Routing while the classifier is restarting. A policy's classifier is
+//! marked unavailable when Triton fails; until its readiness endpoint
+//! answers again, decisions come from the last-known-good cached scores or
+//! the policy's `default_llm` instead of failing the request.
+use crate::classifier::{adjust_scores, label_scores, select_index, Classification};
+use crate::config::{Policy, SharedConfig};
+use crate::decision_cache;
+use crate::error::GatewayApiError;
+use crate::metrics::DEGRADED_ROUTING;
+use crate::upstream;
+use http::{HeaderMap, HeaderValue};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Response header naming where a degraded decision came from.
+pub const DEGRADED_ROUTING_HEADER: &str = "X-Degraded-Routing";
+
+lazy_static! {
+    /// Classifier URLs that failed and have not passed a readiness check.
+    static ref UNAVAILABLE: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// Last-known-good scores for the same classifier input.
+    Cache,
+    DefaultLlm,
+}
+
+impl Source {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Cache => "cache",
+            Source::DefaultLlm => "default_llm",
+        }
+    }
+}
+
+pub fn is_available(url: &str) -> bool {
+    !UNAVAILABLE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains(url)
+}
+
+fn mark_unavailable(url: &str) {
+    let newly = UNAVAILABLE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(url.to_string());
+    if newly {
+        warn!(
+            "Classifier {} is unavailable, routing in degraded mode",
+            url
+        );
+    }
+}
+
+fn mark_available(url: &str) {
+    UNAVAILABLE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(url);
+}
+
+/// Returned in place of a Triton call while the classifier is unavailable.
+pub fn unavailable(policy: &Policy) -> GatewayApiError {
+    GatewayApiError::TritonServiceError {
+        status_code: 503,
+        message: format!("Classifier of policy '{}' is unavailable", policy.name),
+    }
+}
+
+/// Replaces a failed classification with a degraded decision when one is
+/// available. Classifier errors other than 5xx are returned unchanged.
+pub fn recover(
+    policy: &Policy,
+    text: &str,
+    result: Result<Classification, GatewayApiError>,
+) -> (Result<Classification, GatewayApiError>, Option<Source>) {
+    let error = match result {
+        Err(error @ GatewayApiError::TritonServiceError { status_code, .. })
+            if status_code >= 500 =>
+        {
+            error
+        }
+        result => return (result, None),
+    };
+    // A local model that fails will not recover by itself, so there is
+    // nothing to probe.
+    if policy.local_classifier().is_none() {
+        mark_unavailable(&policy.url);
+    }
+
+    let cached = decision_cache::last_known(&decision_cache::key(policy, text)).and_then(|raw| {
+        let scores = adjust_scores(policy, &label_scores(policy, &raw));
+        select_index(policy, &scores).map(|index| Classification { index, scores })
+    });
+    let decision = cached.map(|c| (c, Source::Cache)).or_else(|| {
+        let name = policy.default_llm.as_ref()?;
+        let index = policy.llms.iter().position(|llm| llm.name == *name)?;
+        Some((
+            Classification {
+                index,
+                scores: Vec::new(),
+            },
+            Source::DefaultLlm,
+        ))
+    });
+    match decision {
+        Some((classification, source)) => {
+            info!(
+                "Degraded routing: policy={} source={} llm={}",
+                policy.name,
+                source.as_str(),
+                policy.llms[classification.index].name
+            );
+            DEGRADED_ROUTING
+                .with_label_values(&[&policy.name, source.as_str()])
+                .inc();
+            (Ok(classification), Some(source))
+        }
+        None => (Err(error), None),
+    }
+}
+
+pub fn insert_header(headers: &mut HeaderMap, source: Option<Source>) {
+    if let Some(source) = source {
+        headers.insert(
+            DEGRADED_ROUTING_HEADER,
+            HeaderValue::from_static(source.as_str()),
+        );
+    }
+}
+
+/// Triton's readiness endpoint for a classifier URL: the model's `ready`
+/// endpoint for `.../infer` URLs, the server's otherwise.
+pub(crate) fn ready_url(url: &str) -> Option<String> {
+    if let Some(model) = url.strip_suffix("/infer") {
+        return Some(format!("{}/ready", model));
+    }
+    let mut parsed = reqwest::Url::parse(url).ok()?;
+    parsed.set_path("/v2/health/ready");
+    parsed.set_query(None);
+    Some(parsed.to_string())
+}
+
+/// Polls unavailable classifiers and resumes normal routing once they are
+/// ready. Does nothing unless `degraded_routing` is configured.
+pub fn spawn(config: SharedConfig) {
+    let Some(initial) = config.snapshot().degraded_routing else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(initial.probe_interval_seconds.max(1)));
+        loop {
+            interval.tick().await;
+            let unavailable: Vec<String> = UNAVAILABLE
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .cloned()
+                .collect();
+            for url in unavailable {
+                let Some(ready) = ready_url(&url) else {
+                    continue;
+                };
+                let snapshot = config.snapshot();
+                let tls = snapshot
+                    .policies
+                    .iter()
+                    .find(|policy| policy.url == url)
+                    .and_then(|policy| policy.classifier_tls.as_ref());
+                let Ok(client) = upstream::client_for(None, tls) else {
+                    continue;
+                };
+                let healthy = client
+                    .get(&ready)
+                    .timeout(Duration::from_secs(2))
+                    .send()
+                    .await
+                    .is_ok_and(|response| response.status().is_success());
+                if healthy {
+                    info!("Classifier {} is ready, leaving degraded mode", url);
+                    mark_available(&url);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ClassifierCacheConfig, Llm};
+
+    #[test]
+    fn test_recover() {
+        let policy = Policy {
+            name: "degraded_test".to_string(),
+            url: "http://degraded-test:8000/v2/models/task_router/infer".to_string(),
+            llms: vec![
+                Llm {
+                    name: "small".to_string(),
+                    ..Default::default()
+                },
+                Llm {
+                    name: "large".to_string(),
+                    ..Default::default()
+                },
+            ],
+            default_llm: Some("small".to_string()),
+            ..Default::default()
+        };
+        let failed = || Err(unavailable(&policy));
+
+        let (result, source) = recover(&policy, "uncached prompt", failed());
+        assert_eq!(result.unwrap().index, 0);
+        assert_eq!(source, Some(Source::DefaultLlm));
+        assert!(!is_available(&policy.url));
+
+        let key = decision_cache::key(&policy, "cached prompt");
+        decision_cache::store(&ClassifierCacheConfig::default(), key, vec![0.1, 0.9]);
+        let (result, source) = recover(&policy, "cached prompt", failed());
+        assert_eq!(result.unwrap().index, 1);
+        assert_eq!(source, Some(Source::Cache));
+
+        let client_error = Err(GatewayApiError::TritonServiceError {
+            status_code: 400,
+            message: "bad input".to_string(),
+        });
+        assert!(recover(&policy, "uncached prompt", client_error).0.is_err());
+
+        mark_available(&policy.url);
+        assert!(is_available(&policy.url));
+        assert_eq!(
+            ready_url(&policy.url).as_deref(),
+            Some("http://degraded-test:8000/v2/models/task_router/ready")
+        );
+        assert_eq!(
+            ready_url("http://triton:8000/classify?x=1").as_deref(),
+            Some("http://triton:8000/v2/health/ready")
+        );
+    }
+}
@@ -0,0 +1,106 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Disconnect
+//!
+//! Work abandoned by clients. When a client disconnects, the listener drops
+//! its request's future, and with it the pending classifier call and
+//! upstream request; a streamed body the client stopped reading is dropped
+//! too, closing the upstream connection mid-generation. Nothing is spawned
+//! on a request's behalf, so nothing keeps running after it. The guard here
+//! reports where a request was when that happened.
+use crate::metrics::{record_client_disconnect, RequestLabels};
+use log::info;
+
+/// Choosing the LLM, including the classifier call.
+pub const STAGE_ROUTING: &str = "routing";
+/// Waiting for the upstream LLM's response.
+pub const STAGE_UPSTREAM: &str = "upstream";
+/// Reading a streamed response.
+pub const STAGE_STREAM: &str = "stream";
+
+/// Counts a request whose client went away in `stage`.
+pub fn report(stage: &str, labels: &RequestLabels) {
+    info!(
+        "Client disconnected during {}, cancelling upstream work: policy={} model={}",
+        stage,
+        labels.policy.as_deref().unwrap_or("unknown"),
+        labels.model.as_deref().unwrap_or("unknown")
+    );
+    record_client_disconnect(labels, stage);
+}
+
+/// Reports a disconnect when dropped before `complete`, that is when the
+/// request's future was cancelled.
+#[derive(Debug)]
+pub struct Disconnect {
+    stage: &'static str,
+    labels: RequestLabels,
+    complete: bool,
+}
+
+impl Default for Disconnect {
+    fn default() -> Self {
+        Self {
+            stage: STAGE_ROUTING,
+            labels: RequestLabels::default(),
+            complete: false,
+        }
+    }
+}
+
+impl Disconnect {
+    /// Moves the request to `stage`, reported under `labels`.
+    pub fn enter(&mut self, stage: &'static str, labels: &RequestLabels) {
+        self.stage = stage;
+        self.labels = labels.clone();
+    }
+
+    /// The response was produced; dropping the guard reports nothing.
+    pub fn complete(&mut self) {
+        self.complete = true;
+    }
+
+    fn cancelled(&self) -> Option<&'static str> {
+        (!self.complete).then_some(self.stage)
+    }
+}
+
+impl Drop for Disconnect {
+    fn drop(&mut self) {
+        if let Some(stage) = self.cancelled() {
+            report(stage, &self.labels);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages() {
+        let mut disconnect = Disconnect::default();
+        assert_eq!(disconnect.cancelled(), Some(STAGE_ROUTING));
+        let labels = RequestLabels {
+            policy: Some("disconnect_test".to_string()),
+            ..Default::default()
+        };
+        disconnect.enter(STAGE_UPSTREAM, &labels);
+        assert_eq!(disconnect.cancelled(), Some(STAGE_UPSTREAM));
+        disconnect.complete();
+        assert_eq!(disconnect.cancelled(), None);
+    }
+}
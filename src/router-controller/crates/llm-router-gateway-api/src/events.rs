@@ -0,0 +1,337 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Events
+//!
+//! Delivery of usage and audit events to an external sink. Events are
+//! queued off the request path and posted in batches; batches the sink
+//! cannot take are spooled to a bounded on-disk queue and replayed, oldest
+//! first, once it recovers.
+use crate::config::EventSinkConfig;
+use crate::metrics::{EVENTS_DROPPED, EVENT_SPOOL_BYTES, EVENT_SPOOL_SEGMENTS};
+use crate::privacy;
+use crate::request_context::RequestContext;
+use log::{error, info, warn};
+use serde_json::{json, Value};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+/// Events buffered in memory before new ones are dropped.
+const CHANNEL_CAPACITY: usize = 10_000;
+const MAX_BATCH: usize = 100;
+
+static SENDER: OnceLock<mpsc::Sender<Value>> = OnceLock::new();
+
+fn timestamp_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis())
+}
+
+/// Queues an event for the sink, tagged with its type, time and the
+/// request's context as privacy mode allows. A no-op when no sink is configured; never blocks the
+/// caller.
+pub fn publish(kind: &str, context: &RequestContext, mut event: Value) {
+    let Some(sender) = SENDER.get() else {
+        return;
+    };
+    if let Some(fields) = event.as_object_mut() {
+        fields.insert("type".to_string(), json!(kind));
+        fields.insert("timestamp_ms".to_string(), json!(timestamp_ms()));
+        fields.insert("context".to_string(), json!(privacy::export(context)));
+    }
+    if sender.try_send(event).is_err() {
+        EVENTS_DROPPED.with_label_values(&["queue_full"]).inc();
+    }
+}
+
+pub fn publish_usage(context: &RequestContext, usage: &Value) {
+    publish("usage", context, json!({ "usage": usage }));
+}
+
+/// Bounded on-disk queue of event batches, one JSON lines file per batch.
+#[derive(Debug, Clone)]
+pub struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+static SEGMENT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+impl Spool {
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let spool = Self { dir, max_bytes };
+        spool.publish_size();
+        Ok(spool)
+    }
+
+    /// Segment files, oldest first. Names sort in write order.
+    fn segments(&self) -> Vec<(PathBuf, u64)> {
+        let mut segments: Vec<(PathBuf, u64)> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|e| e == "jsonl"))
+            .map(|entry| {
+                let size = entry.metadata().map_or(0, |m| m.len());
+                (entry.path(), size)
+            })
+            .collect();
+        segments.sort();
+        segments
+    }
+
+    fn publish_size(&self) {
+        let segments = self.segments();
+        EVENT_SPOOL_SEGMENTS.set(segments.len() as i64);
+        EVENT_SPOOL_BYTES.set(segments.iter().map(|(_, size)| *size).sum::<u64>() as i64);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments().is_empty()
+    }
+
+    fn write_segment(&self, contents: &str) -> io::Result<()> {
+        let name = format!(
+            "{:020}-{:010}.jsonl",
+            timestamp_ms(),
+            SEGMENT_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+        );
+        let tmp = self.dir.join(format!("{}.tmp", name));
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, self.dir.join(name))
+    }
+
+    /// Writes `events` as new segments no larger than the size limit, then
+    /// evicts the oldest segments while the spool is over it. An event
+    /// larger than the whole spool is dropped.
+    pub fn append(&self, events: &[Value]) -> io::Result<()> {
+        let mut contents = String::new();
+        for event in events {
+            let line = format!("{}\n", event);
+            if line.len() as u64 > self.max_bytes {
+                EVENTS_DROPPED.with_label_values(&["spool_full"]).inc();
+                warn!("Event spool full, dropped an event of {} bytes", line.len());
+                continue;
+            }
+            if (contents.len() + line.len()) as u64 > self.max_bytes {
+                self.write_segment(&std::mem::take(&mut contents))?;
+            }
+            contents.push_str(&line);
+        }
+        if !contents.is_empty() {
+            self.write_segment(&contents)?;
+        }
+
+        let mut segments = self.segments();
+        let mut total: u64 = segments.iter().map(|(_, size)| *size).sum();
+        while total > self.max_bytes && segments.len() > 1 {
+            let (path, size) = segments.remove(0);
+            let dropped = fs::read_to_string(&path).map_or(0, |c| c.lines().count());
+            fs::remove_file(&path)?;
+            total -= size;
+            EVENTS_DROPPED
+                .with_label_values(&["spool_full"])
+                .inc_by(dropped as u64);
+            warn!("Event spool full, dropped {} oldest events", dropped);
+        }
+        self.publish_size();
+        Ok(())
+    }
+
+    /// The oldest spooled batch, if any.
+    pub fn oldest(&self) -> Option<(PathBuf, Vec<Value>)> {
+        let (path, _) = self.segments().into_iter().next()?;
+        let events = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        Some((path, events))
+    }
+
+    pub fn remove(&self, segment: &Path) -> io::Result<()> {
+        fs::remove_file(segment)?;
+        self.publish_size();
+        Ok(())
+    }
+}
+
+/// Runs `op` on the blocking pool, keeping spool I/O off the runtime's
+/// worker threads.
+async fn on_spool<T: Send + 'static>(
+    spool: &Spool,
+    op: impl FnOnce(&Spool) -> T + Send + 'static,
+) -> io::Result<T> {
+    let spool = spool.clone();
+    tokio::task::spawn_blocking(move || op(&spool))
+        .await
+        .map_err(io::Error::other)
+}
+
+async fn deliver(client: &reqwest::Client, url: &str, events: &[Value]) -> bool {
+    let result = client
+        .post(url)
+        .timeout(Duration::from_secs(10))
+        .json(events)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    match result {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("Event sink unavailable: {:?}", e);
+            false
+        }
+    }
+}
+
+/// Replays spooled batches in order until the spool is empty or the sink
+/// fails. Returns whether the spool was drained.
+async fn replay(client: &reqwest::Client, url: &str, spool: &Spool) -> bool {
+    loop {
+        let (segment, events) = match on_spool(spool, Spool::oldest).await {
+            Ok(Some(oldest)) => oldest,
+            Ok(None) => return true,
+            Err(e) => {
+                error!("Failed to read the event spool: {:?}", e);
+                return false;
+            }
+        };
+        if !events.is_empty() && !deliver(client, url, &events).await {
+            return false;
+        }
+        if let Err(e) = on_spool(spool, move |spool| spool.remove(&segment))
+            .await
+            .and_then(|removed| removed)
+        {
+            error!("Failed to remove replayed spool segment: {:?}", e);
+            return false;
+        }
+        info!("Replayed {} spooled events", events.len());
+    }
+}
+
+async fn spool_batch(spool: &Spool, batch: Vec<Value>) {
+    let count = batch.len();
+    let appended = on_spool(spool, move |spool| spool.append(&batch)).await;
+    if let Err(e) = appended.and_then(|appended| appended) {
+        error!("Failed to spool {} events: {:?}", count, e);
+        EVENTS_DROPPED
+            .with_label_values(&["spool_error"])
+            .inc_by(count as u64);
+    }
+}
+
+/// Starts the delivery worker if `event_sink` is configured.
+pub fn spawn(config: &EventSinkConfig) {
+    let spool = match Spool::open(&config.spool_dir, config.max_spool_bytes) {
+        Ok(spool) => spool,
+        Err(e) => {
+            error!("Failed to open event spool {}: {:?}", config.spool_dir, e);
+            return;
+        }
+    };
+    let (sender, mut receiver) = mpsc::channel(CHANNEL_CAPACITY);
+    if SENDER.set(sender).is_err() {
+        return;
+    }
+    let url = config.webhook_url.clone();
+    let retry_interval = Duration::from_secs(config.replay_interval_seconds.max(1));
+    info!("Publishing usage and audit events to {}", url);
+
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        let mut batch = Vec::with_capacity(MAX_BATCH);
+        loop {
+            batch.clear();
+            let received =
+                tokio::time::timeout(retry_interval, receiver.recv_many(&mut batch, MAX_BATCH))
+                    .await;
+            if matches!(received, Ok(0)) {
+                // Every sender is gone.
+                return;
+            }
+
+            // Spooled events go first so delivery stays in order.
+            let empty = on_spool(&spool, Spool::is_empty).await.unwrap_or(false);
+            let drained = empty || replay(&client, &url, &spool).await;
+            if batch.is_empty() {
+                continue;
+            }
+            if !drained || !deliver(&client, &url, &batch).await {
+                spool_batch(&spool, std::mem::take(&mut batch)).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spool_order_and_bound() {
+        let dir =
+            std::env::temp_dir().join(format!("llm-router-spool-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let spool = Spool::open(&dir, 60).unwrap();
+        assert!(spool.is_empty());
+
+        spool.append(&[json!({"n": 1}), json!({"n": 2})]).unwrap();
+        spool.append(&[json!({"n": 3})]).unwrap();
+        let (segment, events) = spool.oldest().unwrap();
+        assert_eq!(events, vec![json!({"n": 1}), json!({"n": 2})]);
+        spool.remove(&segment).unwrap();
+        assert_eq!(spool.oldest().unwrap().1, vec![json!({"n": 3})]);
+
+        // Over the size limit the oldest segments are evicted.
+        spool.append(&[json!({"padding": "x".repeat(40)})]).unwrap();
+        let (_, events) = spool.oldest().unwrap();
+        assert_eq!(events[0]["padding"].as_str().unwrap().len(), 40);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spool_splits_oversized_batches() {
+        let dir = std::env::temp_dir().join(format!(
+            "llm-router-spool-split-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let spool = Spool::open(&dir, 20).unwrap();
+
+        // Each `{"n":_}` line is 8 bytes; the padded event is larger than
+        // the whole spool.
+        let batch = [
+            json!({"n": 1}),
+            json!({"n": 2}),
+            json!({"padding": "x".repeat(40)}),
+            json!({"n": 3}),
+        ];
+        spool.append(&batch).unwrap();
+        let total: u64 = spool.segments().iter().map(|(_, size)| *size).sum();
+        assert!(total <= 20);
+        assert_eq!(spool.oldest().unwrap().1, vec![json!({"n": 3})]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
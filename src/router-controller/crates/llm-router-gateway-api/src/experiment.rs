@@ -0,0 +1,98 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Experiment
+use crate::config::{Llm, Policy};
+use crate::events;
+use crate::request_context::RequestContext;
+use log::info;
+use serde_json::{json, Value};
+
+/// Pins sampling parameters for policies in experiment mode so comparisons
+/// between models are not confounded by sampling randomness. The seed is only
+/// sent to LLMs that support it. Pinned values are written to the `audit` log
+/// target.
+pub fn apply(mut json: Value, policy: &Policy, llm: &Llm, context: &RequestContext) -> Value {
+    let Some(experiment) = &policy.experiment else {
+        return json;
+    };
+    let Some(body) = json.as_object_mut() else {
+        return json;
+    };
+
+    let seed = experiment
+        .seed
+        .filter(|_| llm.supports_seed.unwrap_or(true));
+    if let Some(seed) = seed {
+        body.insert("seed".to_string(), json!(seed));
+    }
+    if let Some(temperature) = experiment.temperature {
+        body.insert("temperature".to_string(), json!(temperature));
+    }
+
+    info!(
+        target: "audit",
+        "experiment pinning: policy={} llm={} seed={} temperature={}",
+        policy.name,
+        llm.name,
+        seed.map_or("unset".to_string(), |s| s.to_string()),
+        experiment
+            .temperature
+            .map_or("unset".to_string(), |t| t.to_string())
+    );
+    events::publish(
+        "experiment_pinning",
+        context,
+        json!({
+            "policy": policy.name,
+            "llm": llm.name,
+            "seed": seed,
+            "temperature": experiment.temperature,
+        }),
+    );
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ExperimentConfig;
+
+    #[test]
+    fn test_apply_pins_parameters() {
+        let policy = Policy {
+            name: "experiment".to_string(),
+            experiment: Some(ExperimentConfig {
+                seed: Some(42),
+                temperature: Some(0.0),
+            }),
+            ..Default::default()
+        };
+        let body = json!({ "temperature": 0.9, "messages": [] });
+        let context = RequestContext::default();
+
+        let pinned = apply(body.clone(), &policy, &Llm::default(), &context);
+        assert_eq!(pinned["seed"], 42);
+        assert_eq!(pinned["temperature"], 0.0);
+
+        let unseeded = Llm {
+            supports_seed: Some(false),
+            ..Default::default()
+        };
+        let pinned = apply(body, &policy, &unseeded, &context);
+        assert!(pinned.get("seed").is_none());
+        assert_eq!(pinned["temperature"], 0.0);
+    }
+}
@@ -0,0 +1,139 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fault
+//!
+//! Fault injection for chaos tests: upstream calls matching a configured
+//! fault are delayed, fail as if the LLM were unreachable, or are answered
+//! with an error status, so client retries and gateway fallbacks can be
+//! exercised end to end.
+use crate::config::FaultInjectionConfig;
+use crate::error::GatewayApiError;
+use crate::metrics::FAULTS_INJECTED;
+use http::header::CONTENT_TYPE;
+use http::StatusCode;
+use log::info;
+use rand::Rng;
+use serde_json::json;
+use std::time::Duration;
+
+pub const FAULT_DELAY: &str = "delay";
+pub const FAULT_ABORT: &str = "abort";
+pub const FAULT_ERROR: &str = "error";
+
+fn hit(percent: f64) -> bool {
+    percent > 0.0 && rand::thread_rng().gen_range(0.0..100.0) < percent
+}
+
+fn record(policy: &str, llm: &str, fault: &str) {
+    info!("Injecting {} fault: policy={} model={}", fault, policy, llm);
+    FAULTS_INJECTED
+        .with_label_values(&[policy, llm, fault])
+        .inc();
+}
+
+/// An upstream answer with `status` and an OpenAI style error body.
+fn error_response(status: u16) -> reqwest::Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+    let body = json!({
+        "error": {
+            "message": "Injected fault",
+            "type": "fault_injected",
+            "code": status.as_u16(),
+        }
+    });
+    let response = http::Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .expect("fault response is valid");
+    reqwest::Response::from(response)
+}
+
+/// Applies the first fault matching the call of `llm` for `policy`: waits
+/// out a drawn delay, then returns the outcome replacing the upstream call,
+/// if one is drawn.
+pub async fn inject(
+    config: &FaultInjectionConfig,
+    policy: &str,
+    llm: &str,
+) -> Option<Result<reqwest::Response, GatewayApiError>> {
+    if !config.enabled {
+        return None;
+    }
+    let fault = config.faults.iter().find(|f| f.matches(policy, llm))?;
+    if hit(fault.delay_percent) {
+        record(policy, llm, FAULT_DELAY);
+        tokio::time::sleep(Duration::from_millis(fault.delay_ms)).await;
+    }
+    if hit(fault.abort_percent) {
+        record(policy, llm, FAULT_ABORT);
+        return Some(Err(GatewayApiError::LlmServiceError {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            message: "LLM server is unreachable".to_string(),
+            provider: llm.to_string(),
+            details: None,
+        }));
+    }
+    if hit(fault.error_percent) {
+        record(policy, llm, FAULT_ERROR);
+        return Some(Ok(error_response(fault.error_status)));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Fault;
+
+    #[tokio::test]
+    async fn test_inject() {
+        let mut config = FaultInjectionConfig {
+            faults: vec![
+                Fault {
+                    llm: Some("flaky".to_string()),
+                    error_percent: 100.0,
+                    error_status: 429,
+                    ..Default::default()
+                },
+                Fault {
+                    policy: Some("chaos".to_string()),
+                    abort_percent: 100.0,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let response = inject(&config, "chaos", "flaky").await.unwrap().unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let error = inject(&config, "chaos", "steady")
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            GatewayApiError::LlmServiceError {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..
+            }
+        ));
+        assert!(inject(&config, "calm", "steady").await.is_none());
+
+        config.enabled = false;
+        assert!(inject(&config, "chaos", "flaky").await.is_none());
+    }
+}
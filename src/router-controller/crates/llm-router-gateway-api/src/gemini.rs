@@ -0,0 +1,439 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Gemini
+//!
+//! Google Gemini through the `generateContent` API: OpenAI chat completion
+//! requests are translated to Gemini requests, and Gemini responses and
+//! `streamGenerateContent` SSE back to OpenAI responses and chunks.
+use crate::config::Llm;
+use crate::error::GatewayApiError;
+use crate::stream::event_end;
+use bytes::{Bytes, BytesMut};
+use futures_util::Stream;
+use http::header::{HeaderValue, ACCEPT};
+use rand::Rng;
+use serde_json::{json, Map, Value};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const API_KEY_HEADER: &str = "x-goog-api-key";
+
+fn parts(content: &Value) -> Vec<Value> {
+    match content {
+        Value::String(text) => vec![json!({"text": text})],
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item["text"].as_str())
+            .map(|text| json!({"text": text}))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Gemini request body for an OpenAI chat completion body. System messages
+/// become the `systemInstruction`, `assistant` turns the `model` role, and
+/// consecutive turns of the same role are merged.
+pub fn generate_content_request(llm: &Llm, json: &Value) -> Value {
+    let mut system = Vec::new();
+    let mut contents: Vec<Value> = Vec::new();
+    for message in json["messages"].as_array().into_iter().flatten() {
+        let message_parts = parts(&message["content"]);
+        let role = match message["role"].as_str() {
+            Some("system") | Some("developer") => {
+                system.extend(message_parts);
+                continue;
+            }
+            Some("assistant") => "model",
+            _ => "user",
+        };
+        match contents.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(existing) = last["parts"].as_array_mut() {
+                    existing.extend(message_parts);
+                }
+            }
+            _ => contents.push(json!({"role": role, "parts": message_parts})),
+        }
+    }
+
+    let mut generation = Map::new();
+    let max_tokens = json
+        .get("max_tokens")
+        .or_else(|| json.get("max_completion_tokens"));
+    for (field, value) in [
+        ("maxOutputTokens", max_tokens),
+        ("temperature", json.get("temperature")),
+        ("topP", json.get("top_p")),
+        ("candidateCount", json.get("n")),
+        ("seed", json.get("seed")),
+    ] {
+        if let Some(value) = value.filter(|v| !v.is_null()) {
+            generation.insert(field.to_string(), value.clone());
+        }
+    }
+    match &json["stop"] {
+        Value::String(stop) => {
+            generation.insert("stopSequences".to_string(), json!([stop]));
+        }
+        Value::Array(stops) => {
+            generation.insert("stopSequences".to_string(), json!(stops));
+        }
+        _ => {}
+    }
+    if json["response_format"]["type"] == "json_object" {
+        generation.insert("responseMimeType".to_string(), json!("application/json"));
+    }
+
+    let mut request = json!({ "contents": contents });
+    if !system.is_empty() {
+        request["systemInstruction"] = json!({ "parts": system });
+    }
+    if !generation.is_empty() {
+        request["generationConfig"] = Value::Object(generation);
+    }
+    if let Some(gemini) = llm
+        .gemini
+        .as_ref()
+        .filter(|g| !g.safety_settings.is_empty())
+    {
+        request["safetySettings"] = json!(gemini.safety_settings);
+    }
+    request
+}
+
+fn finish_reason(reason: &str) -> Option<&'static str> {
+    match reason {
+        "" | "FINISH_REASON_UNSPECIFIED" => None,
+        "STOP" => Some("stop"),
+        "MAX_TOKENS" => Some("length"),
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => {
+            Some("content_filter")
+        }
+        _ => Some("stop"),
+    }
+}
+
+fn usage(metadata: &Value) -> Value {
+    json!({
+        "prompt_tokens": metadata["promptTokenCount"].as_u64().unwrap_or(0),
+        "completion_tokens": metadata["candidatesTokenCount"].as_u64().unwrap_or(0),
+        "total_tokens": metadata["totalTokenCount"].as_u64().unwrap_or(0),
+    })
+}
+
+fn text(candidate: &Value) -> String {
+    candidate["content"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|part| part["text"].as_str())
+        .collect()
+}
+
+fn completion_id() -> String {
+    format!("chatcmpl-{:016x}", rand::thread_rng().gen::<u64>())
+}
+
+fn created() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// OpenAI chat completion for a Gemini response. A prompt blocked by
+/// safety filters has no candidates and maps to an empty `content_filter`
+/// choice.
+pub fn chat_completion(response: &Value, model: &str) -> Value {
+    let candidates = response["candidates"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let choices: Vec<Value> = if candidates.is_empty() {
+        vec![json!({
+            "index": 0,
+            "message": {"role": "assistant", "content": ""},
+            "finish_reason": "content_filter",
+        })]
+    } else {
+        candidates
+            .iter()
+            .enumerate()
+            .map(|(index, candidate)| {
+                json!({
+                    "index": candidate["index"].as_u64().unwrap_or(index as u64),
+                    "message": {"role": "assistant", "content": text(candidate)},
+                    "finish_reason": finish_reason(candidate["finishReason"].as_str().unwrap_or_default()).unwrap_or("stop"),
+                })
+            })
+            .collect()
+    };
+    json!({
+        "id": completion_id(),
+        "object": "chat.completion",
+        "created": created(),
+        "model": response["modelVersion"].as_str().unwrap_or(model),
+        "choices": choices,
+        "usage": usage(&response["usageMetadata"]),
+    })
+}
+
+/// The upstream request for an OpenAI chat completion body.
+pub fn request(
+    client: &reqwest::Client,
+    llm: &Llm,
+    json: &Value,
+) -> Result<reqwest::RequestBuilder, GatewayApiError> {
+    let operation = if json["stream"].as_bool().unwrap_or(false) {
+        "streamGenerateContent?alt=sse"
+    } else {
+        "generateContent"
+    };
+    let model = llm.model.strip_prefix("models/").unwrap_or(&llm.model);
+    let url = format!(
+        "{}/v1beta/models/{}:{}",
+        llm.api_base.trim_end_matches('/'),
+        model,
+        operation
+    );
+    let mut api_key = HeaderValue::from_str(&llm.api_key)?;
+    api_key.set_sensitive(true);
+    Ok(client
+        .post(url)
+        .header(ACCEPT, HeaderValue::from_static("application/json"))
+        .header(API_KEY_HEADER, api_key)
+        .json(&generate_content_request(llm, json)))
+}
+
+type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send + Sync>>;
+
+/// Converts a `streamGenerateContent?alt=sse` body into OpenAI style SSE.
+/// Gemini repeats cumulative usage on every event, so the last one seen is
+/// sent as a final usage chunk ahead of `[DONE]`.
+pub struct EventStream {
+    inner: ByteStream,
+    buffer: BytesMut,
+    id: String,
+    model: String,
+    started: bool,
+    usage: Option<Value>,
+    done: bool,
+}
+
+impl EventStream {
+    pub fn new(inner: ByteStream, model: &str) -> Self {
+        Self {
+            inner,
+            buffer: BytesMut::new(),
+            id: completion_id(),
+            model: model.to_string(),
+            started: false,
+            usage: None,
+            done: false,
+        }
+    }
+
+    fn chunk(&self, choices: Value) -> String {
+        let chunk = json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": created(),
+            "model": self.model,
+            "choices": choices,
+        });
+        format!("data: {}\n\n", chunk)
+    }
+
+    fn translate(&mut self, event: &Value) -> String {
+        let mut out = String::new();
+        if event["usageMetadata"].is_object() {
+            self.usage = Some(usage(&event["usageMetadata"]));
+        }
+        for candidate in event["candidates"].as_array().into_iter().flatten() {
+            let mut delta = json!({"content": text(candidate)});
+            if !self.started {
+                delta["role"] = json!("assistant");
+                self.started = true;
+            }
+            let finish = finish_reason(candidate["finishReason"].as_str().unwrap_or_default());
+            let index = candidate["index"].as_u64().unwrap_or(0);
+            out.push_str(
+                &self.chunk(json!([{"index": index, "delta": delta, "finish_reason": finish}])),
+            );
+        }
+        if event["candidates"].is_null() && event["promptFeedback"]["blockReason"].is_string() {
+            out.push_str(
+                &self.chunk(json!([{"index": 0, "delta": {}, "finish_reason": "content_filter"}])),
+            );
+        }
+        out
+    }
+
+    /// Takes complete events off the buffer. Gemini separates events with
+    /// either `\n\n` or `\r\n\r\n`.
+    fn drain_events(&mut self) -> String {
+        let mut out = String::new();
+        while let Some((end, delimiter)) = event_end(&self.buffer) {
+            let event = self.buffer.split_to(end + delimiter);
+            let event = String::from_utf8_lossy(&event[..end]);
+            let data: String = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(str::trim_start)
+                .collect();
+            if let Ok(json) = serde_json::from_str::<Value>(&data) {
+                out.push_str(&self.translate(&json));
+            }
+        }
+        out
+    }
+
+    fn finish(&mut self) -> String {
+        let mut out = self.drain_events();
+        if let Some(usage) = self.usage.take() {
+            let chunk = json!({
+                "id": self.id,
+                "object": "chat.completion.chunk",
+                "created": created(),
+                "model": self.model,
+                "choices": [],
+                "usage": usage,
+            });
+            out.push_str(&format!("data: {}\n\n", chunk));
+        }
+        out.push_str("data: [DONE]\n\n");
+        out
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<Bytes, reqwest::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    this.buffer.extend_from_slice(&chunk);
+                    let out = this.drain_events();
+                    if !out.is_empty() {
+                        return Poll::Ready(Some(Ok(Bytes::from(out))));
+                    }
+                }
+                Poll::Ready(None) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Ok(Bytes::from(this.finish()))));
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GeminiConfig, GeminiSafetySetting};
+    use futures_util::StreamExt;
+
+    #[test]
+    fn test_generate_content_mapping() {
+        let llm = Llm {
+            model: "gemini-2.0-flash".to_string(),
+            gemini: Some(GeminiConfig {
+                safety_settings: vec![GeminiSafetySetting {
+                    category: "HARM_CATEGORY_HARASSMENT".to_string(),
+                    threshold: "BLOCK_ONLY_HIGH".to_string(),
+                }],
+            }),
+            ..Default::default()
+        };
+        let request = generate_content_request(
+            &llm,
+            &json!({
+                "messages": [
+                    {"role": "system", "content": "Be brief."},
+                    {"role": "user", "content": "Hi"},
+                    {"role": "assistant", "content": "Hello"},
+                    {"role": "user", "content": "Again"}
+                ],
+                "max_tokens": 32,
+                "temperature": 0.2
+            }),
+        );
+        assert_eq!(
+            request,
+            json!({
+                "systemInstruction": {"parts": [{"text": "Be brief."}]},
+                "contents": [
+                    {"role": "user", "parts": [{"text": "Hi"}]},
+                    {"role": "model", "parts": [{"text": "Hello"}]},
+                    {"role": "user", "parts": [{"text": "Again"}]}
+                ],
+                "generationConfig": {"maxOutputTokens": 32, "temperature": 0.2},
+                "safetySettings": [
+                    {"category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH"}
+                ]
+            })
+        );
+
+        let completion = chat_completion(
+            &json!({
+                "candidates": [{
+                    "content": {"role": "model", "parts": [{"text": "Hey"}]},
+                    "finishReason": "SAFETY"
+                }],
+                "usageMetadata": {"promptTokenCount": 4, "candidatesTokenCount": 1, "totalTokenCount": 5}
+            }),
+            "gemini-2.0-flash",
+        );
+        assert_eq!(completion["choices"][0]["message"]["content"], "Hey");
+        assert_eq!(completion["choices"][0]["finish_reason"], "content_filter");
+        assert_eq!(completion["usage"]["prompt_tokens"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_event_stream() {
+        let body = concat!(
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hel\"}]}}],",
+            "\"usageMetadata\":{\"promptTokenCount\":3,\"totalTokenCount\":4}}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"lo\"}]},\"finishReason\":\"STOP\"}],",
+            "\"usageMetadata\":{\"promptTokenCount\":3,\"candidatesTokenCount\":2,\"totalTokenCount\":5}}\r\n\r\n"
+        );
+        let chunks: Vec<Result<Bytes, reqwest::Error>> = body
+            .as_bytes()
+            .chunks(9)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        let stream = EventStream::new(Box::pin(futures_util::stream::iter(chunks)), "m");
+        let sse: String = stream
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .await
+            .concat();
+        let events: Vec<&str> = sse.split("\n\n").filter(|e| !e.is_empty()).collect();
+        assert_eq!(events.len(), 4);
+        let data = |i: usize| -> Value { serde_json::from_str(&events[i][6..]).unwrap() };
+        assert_eq!(data(0)["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(data(1)["choices"][0]["delta"]["content"], "lo");
+        assert_eq!(data(1)["choices"][0]["finish_reason"], "stop");
+        assert_eq!(data(2)["usage"]["completion_tokens"], 2);
+        assert_eq!(events[3], "data: [DONE]");
+    }
+}
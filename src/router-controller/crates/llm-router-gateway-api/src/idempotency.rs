@@ -0,0 +1,261 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Idempotency
+//!
+//! Final responses of requests carrying an `Idempotency-Key`, kept in the
+//! state store for `ttl_seconds`. An entry is a JSON header (fingerprint,
+//! status and headers) and the raw body, separated by a newline. While a
+//! request is served its key holds a marker entry, so a concurrent retry is
+//! refused rather than sent upstream a second time.
+use crate::caller::Caller;
+use crate::config::{CallerKey, IdempotencyConfig};
+use crate::error::GatewayApiError;
+use crate::store;
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::Response;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+/// Status of the marker entry of a request still being served.
+const IN_FLIGHT_STATUS: u16 = 0;
+
+#[derive(Serialize, Deserialize)]
+struct EntryHeader {
+    fingerprint: [u8; 32],
+    status: u16,
+    headers: Vec<(String, Vec<u8>)>,
+}
+
+fn encode(header: &EntryHeader, body: &Bytes) -> Vec<u8> {
+    // Serialized JSON never contains a raw newline.
+    let mut entry = serde_json::to_vec(header).unwrap_or_default();
+    entry.push(b'\n');
+    entry.extend_from_slice(body);
+    entry
+}
+
+fn decode(entry: &[u8]) -> Option<(EntryHeader, Bytes)> {
+    let split = entry.iter().position(|&b| b == b'\n')?;
+    let header = serde_json::from_slice(&entry[..split]).ok()?;
+    Some((header, Bytes::copy_from_slice(&entry[split + 1..])))
+}
+
+/// Identifies one retried request: the client supplied key, scoped to the
+/// caller so keys cannot collide across clients. Callers without a JWT or
+/// bearer key are told apart by address.
+#[derive(Debug, Clone)]
+pub struct IdempotencyKey {
+    key: String,
+    fingerprint: [u8; 32],
+}
+
+pub enum Lookup {
+    /// No response yet; the key is held by the claim until it is dropped.
+    Miss(Claim),
+    Hit(Response<BoxBody<Bytes, GatewayApiError>>),
+    /// The key was seen before with a different request body.
+    Conflict,
+    /// A request with the key is still being served.
+    InFlight,
+}
+
+impl IdempotencyKey {
+    pub fn from_request(headers: &HeaderMap, caller: &Caller, body: &[u8]) -> Option<Self> {
+        let key = headers
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())?;
+        // Only separates callers, so any bearer key will do.
+        let (_, scope) = caller.identify(CallerKey::ApiKey, caller.api_key);
+        Some(Self {
+            key: format!("{}:{}", scope, key),
+            fingerprint: openssl::sha::sha256(body),
+        })
+    }
+}
+
+fn store_key(key: &IdempotencyKey) -> String {
+    format!("idempotency:{}", key.key)
+}
+
+/// Holds a key while its request is served. Dropped without a stored
+/// response, it frees the key for a retry.
+pub struct Claim {
+    key: IdempotencyKey,
+}
+
+impl Claim {
+    /// Caches a final response for the configured window.
+    pub fn store(
+        &self,
+        config: &IdempotencyConfig,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &Bytes,
+    ) {
+        let header = EntryHeader {
+            fingerprint: self.key.fingerprint,
+            status: status.as_u16(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+                .collect(),
+        };
+        let stored = store::get().set(
+            &store_key(&self.key),
+            &encode(&header, body),
+            Some(Duration::from_secs(config.ttl_seconds)),
+        );
+        store::report("idempotency", stored);
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        let store = store::get();
+        let key = store_key(&self.key);
+        // Only this claim writes over its marker, so it can be deleted as is.
+        let held = store::report("idempotency", store.get(&key))
+            .flatten()
+            .and_then(|entry| decode(&entry))
+            .is_some_and(|(entry, _)| entry.status == IN_FLIGHT_STATUS);
+        if held {
+            store::report("idempotency", store.delete(&key));
+        }
+    }
+}
+
+/// A cached response for the key, or a claim on it when there is none.
+/// The marker of a claim lasts `ttl_seconds` at most, should the router
+/// stop before the request ends. Store failures count as a miss.
+pub fn lookup(key: IdempotencyKey, config: &IdempotencyConfig) -> Lookup {
+    let store = store::get();
+    let marker = EntryHeader {
+        fingerprint: key.fingerprint,
+        status: IN_FLIGHT_STATUS,
+        headers: Vec::new(),
+    };
+    let claimed = store.compare_and_set(
+        &store_key(&key),
+        None,
+        &encode(&marker, &Bytes::new()),
+        Some(Duration::from_secs(config.ttl_seconds)),
+    );
+    if store::report("idempotency", claimed) != Some(false) {
+        return Lookup::Miss(Claim { key });
+    }
+    let Some((entry, body)) = store::report("idempotency", store.get(&store_key(&key)))
+        .flatten()
+        .and_then(|entry| decode(&entry))
+    else {
+        return Lookup::Miss(Claim { key });
+    };
+    if entry.fingerprint != key.fingerprint {
+        return Lookup::Conflict;
+    }
+    if entry.status == IN_FLIGHT_STATUS {
+        return Lookup::InFlight;
+    }
+    let body = Full::from(body).map_err(|never| match never {}).boxed();
+    let mut response = Response::new(body);
+    *response.status_mut() = StatusCode::from_u16(entry.status).unwrap_or(StatusCode::OK);
+    for (name, value) in entry.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_bytes(&value),
+        ) {
+            response.headers_mut().append(name, value);
+        }
+    }
+    response
+        .headers_mut()
+        .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    Lookup::Hit(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anonymous<'a>(headers: &'a HeaderMap, ip: &str) -> Caller<'a> {
+        Caller::new(headers, None, Some(ip.parse().unwrap()))
+    }
+
+    #[test]
+    fn test_replay_and_conflict() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            IDEMPOTENCY_KEY_HEADER,
+            HeaderValue::from_static("test-replay-key"),
+        );
+        let caller = anonymous(&headers, "198.51.100.20");
+        let config = IdempotencyConfig::default();
+        let key = IdempotencyKey::from_request(&headers, &caller, b"{\"a\":1}").unwrap();
+        let Lookup::Miss(claim) = lookup(key.clone(), &config) else {
+            panic!("expected a miss");
+        };
+        assert!(matches!(lookup(key.clone(), &config), Lookup::InFlight));
+
+        claim.store(
+            &config,
+            StatusCode::OK,
+            &HeaderMap::new(),
+            &Bytes::from("done"),
+        );
+        drop(claim);
+        match lookup(key, &config) {
+            Lookup::Hit(response) => {
+                assert_eq!(response.status(), StatusCode::OK);
+                assert_eq!(response.headers()[IDEMPOTENT_REPLAYED_HEADER], "true");
+            }
+            _ => panic!("expected a cached response"),
+        }
+
+        let other_body = IdempotencyKey::from_request(&headers, &caller, b"{\"a\":2}").unwrap();
+        assert!(matches!(lookup(other_body, &config), Lookup::Conflict));
+    }
+
+    #[test]
+    fn test_claims() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            IDEMPOTENCY_KEY_HEADER,
+            HeaderValue::from_static("test-claim-key"),
+        );
+        let config = IdempotencyConfig::default();
+        let key =
+            IdempotencyKey::from_request(&headers, &anonymous(&headers, "198.51.100.21"), b"{}");
+        let key = key.unwrap();
+        let Lookup::Miss(claim) = lookup(key.clone(), &config) else {
+            panic!("expected a miss");
+        };
+
+        // Anonymous callers at other addresses have keys of their own.
+        let other =
+            IdempotencyKey::from_request(&headers, &anonymous(&headers, "198.51.100.22"), b"{}");
+        assert!(matches!(lookup(other.unwrap(), &config), Lookup::Miss(_)));
+
+        // A request that ends without a response frees its key.
+        drop(claim);
+        assert!(matches!(lookup(key, &config), Lookup::Miss(_)));
+    }
+}
@@ -0,0 +1,308 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JWT
+//!
+//! Validation of inbound bearer JWTs against a JWKS endpoint, yielding the
+//! caller identity later stages use for per-tenant metrics and quotas.
+use crate::config::JwtConfig;
+use crate::error::GatewayApiError;
+use http::{HeaderMap, StatusCode};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use lazy_static::lazy_static;
+use log::{error, warn};
+use reqwest::header::AUTHORIZATION;
+use serde_json::{Map, Value};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+
+/// Shortest interval between refetches triggered by an unknown key id.
+const MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The authenticated caller.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Identity {
+    pub subject: Option<String>,
+    pub tenant: Option<String>,
+}
+
+#[derive(Default)]
+struct JwksCache {
+    url: String,
+    keys: Option<JwkSet>,
+    fetched_at: Option<Instant>,
+}
+
+lazy_static! {
+    static ref JWKS: RwLock<JwksCache> = RwLock::new(JwksCache::default());
+    /// Held while the key set is fetched, so concurrent requests needing a
+    /// refresh wait for one fetch rather than each starting their own.
+    static ref REFRESH: Mutex<()> = Mutex::new(());
+    static ref CLIENT: reqwest::Client = reqwest::Client::new();
+}
+
+fn unauthorized(message: impl Into<String>) -> GatewayApiError {
+    GatewayApiError::client_error(StatusCode::UNAUTHORIZED, message, "invalid_token")
+}
+
+async fn fetch(url: &str) -> Result<JwkSet, reqwest::Error> {
+    CLIENT
+        .get(url)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+impl JwksCache {
+    fn needs_refresh(&self, config: &JwtConfig, kid: Option<&str>) -> bool {
+        let age = self.fetched_at.map(|at| at.elapsed());
+        let stale = self.url != config.jwks_url
+            || age.is_none_or(|age| age >= Duration::from_secs(config.jwks_refresh_seconds));
+        let unknown_kid = match (kid, &self.keys) {
+            (Some(kid), Some(keys)) => {
+                keys.find(kid).is_none() && age.is_none_or(|age| age >= MIN_REFETCH_INTERVAL)
+            }
+            _ => false,
+        };
+        stale || unknown_kid
+    }
+
+    fn current(&self, config: &JwtConfig) -> Result<JwkSet, GatewayApiError> {
+        match &self.keys {
+            Some(keys) if self.url == config.jwks_url => Ok(keys.clone()),
+            _ => Err(GatewayApiError::client_error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Token signing keys are unavailable",
+                "jwks_unavailable",
+            )),
+        }
+    }
+}
+
+/// The cached key set, refetched when it is older than
+/// `jwks_refresh_seconds` or does not know `kid`. A failed refetch keeps
+/// serving the previous keys. The cache is not locked during the fetch.
+async fn key_set(config: &JwtConfig, kid: Option<&str>) -> Result<JwkSet, GatewayApiError> {
+    {
+        let cache = JWKS.read().await;
+        if !cache.needs_refresh(config, kid) {
+            return cache.current(config);
+        }
+    }
+    let _refreshing = REFRESH.lock().await;
+    // Another request may have refreshed the keys while this one waited.
+    {
+        let cache = JWKS.read().await;
+        if !cache.needs_refresh(config, kid) {
+            return cache.current(config);
+        }
+    }
+    match fetch(&config.jwks_url).await {
+        Ok(keys) => {
+            let mut cache = JWKS.write().await;
+            cache.url = config.jwks_url.clone();
+            cache.keys = Some(keys);
+            cache.fetched_at = Some(Instant::now());
+        }
+        Err(e) => error!("Failed to fetch JWKS from {}: {:?}", config.jwks_url, e),
+    }
+    JWKS.read().await.current(config)
+}
+
+fn claim_accepted(claim: &Value, accepted: &[String]) -> bool {
+    match claim {
+        Value::String(value) => accepted.contains(value),
+        Value::Array(values) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|value| accepted.iter().any(|a| a == value)),
+        _ => false,
+    }
+}
+
+fn token_kid(token: &str) -> Option<String> {
+    decode_header(token).ok().and_then(|header| header.kid)
+}
+
+/// Checks the signature, expiry, issuer, audience and required claims of
+/// `token` against `keys`.
+pub fn verify(token: &str, keys: &JwkSet, config: &JwtConfig) -> Result<Identity, GatewayApiError> {
+    let claims = verify_claims(token, keys, config)?;
+    let string_claim = |name: &str| claims.get(name).and_then(Value::as_str).map(str::to_string);
+    Ok(Identity {
+        subject: string_claim("sub"),
+        tenant: string_claim(&config.tenant_claim),
+    })
+}
+
+/// The claims of `token` once [`verify`]'s checks pass.
+pub fn verify_claims(
+    token: &str,
+    keys: &JwkSet,
+    config: &JwtConfig,
+) -> Result<Map<String, Value>, GatewayApiError> {
+    let header =
+        decode_header(token).map_err(|e| unauthorized(format!("Malformed token: {}", e)))?;
+    if !config
+        .algorithms
+        .iter()
+        .any(|&alg| Algorithm::from(alg) == header.alg)
+    {
+        return Err(unauthorized(format!(
+            "Token algorithm {:?} is not accepted",
+            header.alg
+        )));
+    }
+    let jwk = match &header.kid {
+        Some(kid) => keys.find(kid),
+        None if keys.keys.len() == 1 => keys.keys.first(),
+        None => None,
+    }
+    .ok_or_else(|| unauthorized("Token signing key is unknown"))?;
+    let key =
+        DecodingKey::from_jwk(jwk).map_err(|e| unauthorized(format!("Unusable key: {}", e)))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.leeway = config.leeway_seconds;
+    if let Some(issuer) = &config.issuer {
+        validation.set_issuer(&[issuer]);
+    }
+    if config.audiences.is_empty() {
+        validation.validate_aud = false;
+    } else {
+        validation.set_audience(&config.audiences);
+    }
+    let claims = decode::<Map<String, Value>>(token, &key, &validation)
+        .map_err(|e| unauthorized(format!("Invalid token: {}", e)))?
+        .claims;
+
+    for (name, accepted) in &config.required_claims {
+        if !claims
+            .get(name)
+            .is_some_and(|claim| claim_accepted(claim, accepted))
+        {
+            warn!(
+                "token refused: sub={} claim={} is not accepted",
+                claims
+                    .get("sub")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown"),
+                name
+            );
+            return Err(GatewayApiError::client_error(
+                StatusCode::FORBIDDEN,
+                format!("Token claim '{}' is not permitted", name),
+                "claim_not_permitted",
+            ));
+        }
+    }
+
+    Ok(claims)
+}
+
+/// The verified claims of a bearer `token`.
+pub async fn claims(
+    token: &str,
+    config: &JwtConfig,
+) -> Result<Map<String, Value>, GatewayApiError> {
+    let keys = key_set(config, token_kid(token).as_deref()).await?;
+    verify_claims(token, &keys, config)
+}
+
+/// Validates the request's `Authorization: Bearer <jwt>` header.
+pub async fn authenticate(
+    headers: &HeaderMap,
+    config: &JwtConfig,
+) -> Result<Identity, GatewayApiError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| unauthorized("Missing bearer token"))?;
+    let keys = key_set(config, token_kid(token).as_deref()).await?;
+    verify(token, &keys, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::JwtAlgorithm;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    const SECRET: &[u8] = b"jwt-test-secret";
+
+    fn token(claims: Value) -> String {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("test".to_string());
+        encode(&header, &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+    }
+
+    #[test]
+    fn test_verify() {
+        let keys: JwkSet = serde_json::from_value(json!({
+            "keys": [{
+                "kty": "oct",
+                "kid": "test",
+                "k": "and0LXRlc3Qtc2VjcmV0"
+            }]
+        }))
+        .unwrap();
+        let config = JwtConfig {
+            issuer: Some("https://idp.example.com".to_string()),
+            audiences: vec!["llm-router".to_string()],
+            algorithms: vec![JwtAlgorithm::HS256],
+            required_claims: BTreeMap::from([(
+                "groups".to_string(),
+                vec!["llm-users".to_string()],
+            )]),
+            ..Default::default()
+        };
+        let exp = jsonwebtoken::get_current_timestamp() + 600;
+        let claims = json!({
+            "sub": "user-1",
+            "org": "acme",
+            "iss": "https://idp.example.com",
+            "aud": "llm-router",
+            "groups": ["staff", "llm-users"],
+            "exp": exp,
+        });
+
+        let identity = verify(&token(claims.clone()), &keys, &config).unwrap();
+        assert_eq!(identity.subject.as_deref(), Some("user-1"));
+        assert_eq!(identity.tenant.as_deref(), Some("acme"));
+
+        let mut wrong_audience = claims.clone();
+        wrong_audience["aud"] = json!("other");
+        assert!(verify(&token(wrong_audience), &keys, &config).is_err());
+
+        let mut outside_group = claims.clone();
+        outside_group["groups"] = json!(["staff"]);
+        assert!(verify(&token(outside_group), &keys, &config).is_err());
+
+        let rs256_only = JwtConfig {
+            algorithms: vec![JwtAlgorithm::RS256],
+            ..config
+        };
+        assert!(verify(&token(claims), &keys, &rs256_only).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Kubernetes
+//!
+//! Configuration kept in a ConfigMap rather than a mounted file: `${NAME}`
+//! references are resolved from the keys of Secrets before the environment,
+//! and the configuration is reloaded whenever the ConfigMap or one of the
+//! Secrets changes, so a rollout needs no pod restart.
+use crate::config::{RouterConfig, SharedConfig};
+use crate::reload;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{StreamExt, TryStreamExt};
+use k8s_openapi::api::core::v1::{ConfigMap, Secret};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::{Api, Client};
+use log::{info, warn};
+use std::collections::BTreeMap;
+
+/// `source` label of reloads from a ConfigMap.
+const SOURCE: &str = "configmap";
+
+/// A namespaced object, written `name` or `namespace/name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectRef {
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+impl ObjectRef {
+    pub fn parse(value: &str) -> Self {
+        match value.split_once('/') {
+            Some((namespace, name)) => Self {
+                namespace: Some(namespace.to_string()),
+                name: name.to_string(),
+            },
+            None => Self {
+                namespace: None,
+                name: value.to_string(),
+            },
+        }
+    }
+
+    fn api<K>(&self, client: &Client) -> Api<K>
+    where
+        K: kube::Resource<Scope = k8s_openapi::NamespaceResourceScope>,
+        <K as kube::Resource>::DynamicType: Default,
+    {
+        match &self.namespace {
+            Some(namespace) => Api::namespaced(client.clone(), namespace),
+            None => Api::default_namespaced(client.clone()),
+        }
+    }
+
+    fn watcher_config(&self) -> watcher::Config {
+        watcher::Config::default().fields(&format!("metadata.name={}", self.name))
+    }
+}
+
+/// Where the configuration is read from.
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub configmap: ObjectRef,
+    /// Key of the ConfigMap holding the YAML configuration.
+    pub key: String,
+    /// Secrets resolving `${NAME}` references, earlier ones first.
+    pub secrets: Vec<ObjectRef>,
+}
+
+/// Last seen content of the watched objects.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Contents {
+    config: Option<String>,
+    secrets: Vec<BTreeMap<String, String>>,
+}
+
+impl Contents {
+    fn lookup(&self, name: &str) -> Option<String> {
+        self.secrets
+            .iter()
+            .find_map(|secret| secret.get(name).cloned())
+            .or_else(|| std::env::var(name).ok())
+    }
+}
+
+/// What the watch started by `spawn` picks up from.
+pub struct Watch {
+    client: Client,
+    contents: Contents,
+}
+
+fn configmap_data(configmap: &ConfigMap, key: &str) -> Option<String> {
+    configmap.data.as_ref()?.get(key).cloned()
+}
+
+/// Keys of a Secret whose values are UTF-8.
+fn secret_data(secret: &Secret) -> BTreeMap<String, String> {
+    secret
+        .data
+        .iter()
+        .flatten()
+        .filter_map(|(key, value)| {
+            let value = String::from_utf8(value.0.clone()).ok()?;
+            Some((key.clone(), value))
+        })
+        .collect()
+}
+
+/// Reads the configuration once, for startup.
+pub async fn load(source: &Source) -> Result<(RouterConfig, Watch), String> {
+    let client = Client::try_default()
+        .await
+        .map_err(|e| format!("Kubernetes client setup failed: {}", e))?;
+    let configmap: ConfigMap = source
+        .configmap
+        .api(&client)
+        .get(&source.configmap.name)
+        .await
+        .map_err(|e| format!("ConfigMap '{}': {}", source.configmap.name, e))?;
+    let mut contents = Contents {
+        config: configmap_data(&configmap, &source.key),
+        secrets: Vec::new(),
+    };
+    for secret_ref in &source.secrets {
+        let secret: Secret = secret_ref
+            .api(&client)
+            .get(&secret_ref.name)
+            .await
+            .map_err(|e| format!("Secret '{}': {}", secret_ref.name, e))?;
+        contents.secrets.push(secret_data(&secret));
+    }
+    let content = contents.config.as_deref().ok_or_else(|| {
+        format!(
+            "ConfigMap '{}' has no key '{}'",
+            source.configmap.name, source.key
+        )
+    })?;
+    let config = RouterConfig::from_yaml(content, |name| contents.lookup(name))
+        .map_err(|e| e.to_string())?;
+    Ok((config, Watch { client, contents }))
+}
+
+enum Change {
+    ConfigMap(ConfigMap),
+    Secret(usize, Secret),
+}
+
+/// Watches the ConfigMap and Secrets of `source`, reloading `shared` when
+/// they change. Deleting one keeps the running configuration.
+pub fn spawn(source: Source, watch: Watch, shared: SharedConfig) {
+    let Watch { client, contents } = watch;
+    let configmap = watcher(
+        source.configmap.api(&client),
+        source.configmap.watcher_config(),
+    )
+    .default_backoff()
+    .applied_objects()
+    .map_ok(Change::ConfigMap)
+    .boxed();
+    let secrets = source.secrets.iter().enumerate().map(|(i, secret)| {
+        watcher(secret.api(&client), secret.watcher_config())
+            .default_backoff()
+            .applied_objects()
+            .map_ok(move |secret| Change::Secret(i, secret))
+            .boxed()
+    });
+    let changes: Vec<BoxStream<'static, _>> = std::iter::once(configmap).chain(secrets).collect();
+    let mut changes = stream::select_all(changes);
+
+    tokio::spawn(async move {
+        let mut contents = contents;
+        // Contents last applied, so events that change nothing, such as
+        // the initial listing, do not reload.
+        let mut applied = contents.clone();
+        info!(
+            "Watching ConfigMap '{}' for configuration changes",
+            source.configmap.name
+        );
+        while let Some(change) = changes.next().await {
+            match change {
+                Ok(Change::ConfigMap(configmap)) => {
+                    contents.config = configmap_data(&configmap, &source.key);
+                }
+                Ok(Change::Secret(i, secret)) => contents.secrets[i] = secret_data(&secret),
+                Err(e) => {
+                    warn!("Kubernetes watch failed, retrying: {}", e);
+                    continue;
+                }
+            }
+            if contents == applied {
+                continue;
+            }
+            let Some(content) = &contents.config else {
+                warn!(
+                    "ConfigMap '{}' has no key '{}'; keeping the running configuration",
+                    source.configmap.name, source.key
+                );
+                continue;
+            };
+            reload::apply(&shared, SOURCE, content, |name| contents.lookup(name));
+            applied = contents.clone();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::ByteString;
+
+    #[test]
+    fn test_object_ref() {
+        assert_eq!(
+            ObjectRef::parse("llm-router/router-config"),
+            ObjectRef {
+                namespace: Some("llm-router".to_string()),
+                name: "router-config".to_string(),
+            }
+        );
+        assert_eq!(ObjectRef::parse("router-config").namespace, None);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let secret = Secret {
+            data: Some(BTreeMap::from([
+                ("NIM_KEY".to_string(), ByteString(b"from-secret".to_vec())),
+                ("BINARY".to_string(), ByteString(vec![0xff, 0xfe])),
+            ])),
+            ..Default::default()
+        };
+        let contents = Contents {
+            config: None,
+            secrets: vec![secret_data(&secret)],
+        };
+        assert_eq!(contents.lookup("NIM_KEY").as_deref(), Some("from-secret"));
+        assert_eq!(contents.lookup("BINARY"), None);
+        std::env::set_var("KUBERNETES_TEST_KEY", "from-env");
+        assert_eq!(
+            contents.lookup("KUBERNETES_TEST_KEY").as_deref(),
+            Some("from-env")
+        );
+    }
+}
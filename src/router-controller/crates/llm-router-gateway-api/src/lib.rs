@@ -14,10 +14,68 @@
 // limitations under the License.
 
 //! Lib
+//!
+//! The hyper gateway. Routing, policy, configuration and metrics live in
+//! `llm-router-core` and are re-exported here under their usual paths.
 
-pub mod config;
-pub mod error;
-pub mod metrics;
+pub use llm_router_core::{
+    caller, config, context, cost, decision_cache, error, expr, limits, local_classifier, metrics,
+    pii, privacy, reasoning, request_context, retryability, stats, tags, tokenizer, triton,
+};
+
+pub mod acl;
+pub mod admin;
+pub mod admission;
+pub mod anomaly;
+pub mod audit;
+pub mod background;
+pub mod bedrock;
+pub mod breaker;
+pub mod budget;
+pub mod canary;
+pub mod capture;
+pub mod classifier;
+pub mod concurrency;
+pub mod controller;
+pub mod conversation;
+pub mod cors;
+pub mod degraded;
+pub mod disconnect;
+pub mod events;
+pub mod experiment;
+pub mod fault;
+pub mod gemini;
+pub mod idempotency;
+pub mod jwt;
+pub mod kubernetes;
+pub mod listener;
+pub mod local;
+pub mod logging;
+pub mod masking;
+pub mod mock;
+pub mod moderation;
+pub mod normalize;
+pub mod provider;
 pub mod proxy;
+pub mod ratelimit;
+pub mod readiness;
+pub mod reload;
+pub mod remote;
+pub mod residency;
+pub mod retry;
+pub mod routes;
+pub mod saturation;
+pub mod sigv4;
+pub mod slo;
+pub mod speculative;
+pub mod spend;
+pub mod split;
+pub mod sticky;
+pub mod store;
 pub mod stream;
-pub mod triton;
+pub mod tenant;
+pub mod triton_grpc;
+pub mod upstream;
+pub mod validation;
+pub mod vault;
+pub mod verify;
@@ -0,0 +1,126 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Listener
+//!
+//! Accept loops for the configured listeners, each serving connections
+//! with its own HTTP/1 and HTTP/2 protocol limits.
+use crate::acl::ClientAddr;
+use crate::config::{ListenerConfig, SharedConfig};
+use crate::proxy::handler;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use log::{error, info};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// Connection builder applying the listener's protocol limits.
+pub fn builder(listener: &ListenerConfig) -> Builder<TokioExecutor> {
+    let mut builder = Builder::new(TokioExecutor::new());
+    // A client closing its side ends the connection, cancelling the request
+    // in flight and whatever upstream call it is waiting on.
+    builder
+        .http1()
+        .half_close(false)
+        .timer(TokioTimer::new())
+        .keep_alive(listener.keep_alive)
+        .header_read_timeout(Duration::from_secs(listener.header_read_timeout_seconds));
+    if let Some(max_headers) = listener.max_headers {
+        builder.http1().max_headers(max_headers);
+    }
+    if let Some(max_header_bytes) = listener.max_header_bytes {
+        builder.http1().max_buf_size(max_header_bytes);
+        builder
+            .http2()
+            .max_header_list_size(u32::try_from(max_header_bytes).unwrap_or(u32::MAX));
+    }
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .max_concurrent_streams(listener.max_concurrent_streams)
+        .keep_alive_interval(
+            listener
+                .keep_alive_interval_seconds
+                .map(Duration::from_secs),
+        )
+        .keep_alive_timeout(Duration::from_secs(listener.keep_alive_timeout_seconds));
+    if listener.http1_only {
+        builder = builder.http1_only();
+    }
+    builder
+}
+
+/// Binds the listener and serves connections until the process exits.
+pub async fn serve(listener: ListenerConfig, config: SharedConfig) -> std::io::Result<()> {
+    let addr: SocketAddr = listener
+        .address
+        .parse()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let tcp = TcpListener::bind(addr).await?;
+    let builder = builder(&listener);
+    info!(
+        "Listening on http://{}{}",
+        addr,
+        if listener.http1_only {
+            " (HTTP/1.1 only)"
+        } else {
+            ""
+        }
+    );
+
+    loop {
+        let (stream, peer) = tcp.accept().await?;
+        let io = TokioIo::new(stream);
+
+        let config_clone = config.clone();
+        let builder = builder.clone();
+        tokio::task::spawn(async move {
+            if let Err(err) = builder
+                .serve_connection(
+                    io,
+                    service_fn(move |mut req| {
+                        req.extensions_mut().insert(ClientAddr(peer));
+                        handler(req, config_clone.clone())
+                    }),
+                )
+                .await
+            {
+                error!("Error serving connection: {:?}", err);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_protocols() {
+        let default = builder(&ListenerConfig::default());
+        assert!(default.is_http1_available() && default.is_http2_available());
+
+        let http1_only = builder(&ListenerConfig {
+            http1_only: true,
+            max_concurrent_streams: Some(16),
+            max_header_bytes: Some(16 * 1024),
+            ..Default::default()
+        });
+        assert!(http1_only.is_http1_available());
+        assert!(!http1_only.is_http2_available());
+    }
+}
@@ -0,0 +1,275 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local
+//!
+//! Fills the gaps local OpenAI compatible servers such as Ollama and
+//! llama.cpp leave in their responses: missing `usage`, which would leave
+//! token metrics and budgets short, and streams that end without
+//! `data: [DONE]`.
+use crate::config::LocalConfig;
+use crate::cost::{self, CHARS_PER_TOKEN};
+use bytes::{Bytes, BytesMut};
+use futures_util::Stream;
+use serde_json::{json, Value};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const DONE_EVENT: &str = "data: [DONE]";
+
+fn estimated_usage(prompt_tokens: u64, completion_chars: usize) -> Value {
+    let completion_tokens = completion_chars.div_ceil(CHARS_PER_TOKEN) as u64;
+    json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+        "estimated": true,
+    })
+}
+
+/// Text a choice adds: chat message or delta content, or completion text.
+fn choice_chars(choice: &Value) -> usize {
+    [
+        &choice["message"]["content"],
+        &choice["delta"]["content"],
+        &choice["text"],
+    ]
+    .iter()
+    .filter_map(|text| text.as_str())
+    .map(|text| text.chars().count())
+    .sum()
+}
+
+fn has_usage(json: &Value) -> bool {
+    json["usage"]["total_tokens"]
+        .as_u64()
+        .is_some_and(|t| t > 0)
+}
+
+/// Adds an estimated `usage` block to a response that has none.
+pub fn fill_usage(config: &LocalConfig, request: &Value, body: Bytes) -> Bytes {
+    if !config.estimate_usage {
+        return body;
+    }
+    let Ok(mut response) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+    if has_usage(&response) || !response["choices"].is_array() {
+        return body;
+    }
+    let completion_chars = response["choices"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(choice_chars)
+        .sum();
+    response["usage"] = estimated_usage(cost::estimate_prompt_tokens(request), completion_chars);
+    Bytes::from(response.to_string())
+}
+
+type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send + Sync>>;
+
+/// Passes a local server's SSE through unchanged, adding a usage chunk
+/// before the end of the stream when none was sent, and a final `[DONE]`
+/// when the server closed the stream without one.
+pub struct EventStream {
+    inner: ByteStream,
+    config: LocalConfig,
+    prompt_tokens: u64,
+    // Bytes of an event not yet terminated by a blank line.
+    partial: BytesMut,
+    completion_chars: usize,
+    saw_usage: bool,
+    saw_done: bool,
+    id: Value,
+    model: Value,
+    finished: bool,
+}
+
+impl EventStream {
+    pub fn new(inner: ByteStream, config: LocalConfig, request: &Value) -> Self {
+        Self {
+            inner,
+            config,
+            prompt_tokens: cost::estimate_prompt_tokens(request),
+            partial: BytesMut::new(),
+            completion_chars: 0,
+            saw_usage: false,
+            saw_done: false,
+            id: Value::Null,
+            model: Value::Null,
+            finished: false,
+        }
+    }
+
+    /// Complete events received so far, with the usage chunk inserted
+    /// before `[DONE]` where OpenAI sends it.
+    fn drain_events(&mut self) -> BytesMut {
+        let mut out = BytesMut::new();
+        while let Some(end) = self.partial.windows(2).position(|w| w == b"\n\n") {
+            let event = self.partial.split_to(end + 2);
+            let text = String::from_utf8_lossy(&event);
+            let data = text.trim().strip_prefix("data:").unwrap_or("").trim();
+            if data == "[DONE]" {
+                self.saw_done = true;
+                if let Some(usage) = self.usage_event() {
+                    out.extend_from_slice(usage.as_bytes());
+                }
+            } else if let Ok(json) = serde_json::from_str::<Value>(data) {
+                self.saw_usage |= has_usage(&json);
+                self.completion_chars += json["choices"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(choice_chars)
+                    .sum::<usize>();
+                if self.id.is_null() {
+                    self.id = json["id"].clone();
+                    self.model = json["model"].clone();
+                }
+            }
+            out.extend_from_slice(&event);
+        }
+        out
+    }
+
+    /// The estimated usage chunk, once, if the server sent no usage.
+    fn usage_event(&mut self) -> Option<String> {
+        if !self.config.estimate_usage || self.saw_usage {
+            return None;
+        }
+        self.saw_usage = true;
+        let chunk = json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "model": self.model,
+            "choices": [],
+            "usage": estimated_usage(self.prompt_tokens, self.completion_chars),
+        });
+        Some(format!("data: {}\n\n", chunk))
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<Bytes, reqwest::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if this.finished {
+                return Poll::Ready(None);
+            }
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    this.partial.extend_from_slice(&chunk);
+                    let out = this.drain_events();
+                    if !out.is_empty() {
+                        return Poll::Ready(Some(Ok(out.freeze())));
+                    }
+                }
+                Poll::Ready(None) => {
+                    this.finished = true;
+                    // An unterminated last event is passed on as is.
+                    let mut out = std::mem::take(&mut this.partial);
+                    if !this.saw_done {
+                        if let Some(usage) = this.usage_event() {
+                            out.extend_from_slice(usage.as_bytes());
+                        }
+                        if this.config.terminate_stream {
+                            out.extend_from_slice(DONE_EVENT.as_bytes());
+                            out.extend_from_slice(b"\n\n");
+                        }
+                    }
+                    if out.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(Ok(out.freeze())));
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    async fn collect(body: &'static str, config: LocalConfig) -> String {
+        let chunks: Vec<Result<Bytes, reqwest::Error>> = body
+            .as_bytes()
+            .chunks(11)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        let request = json!({"messages": [{"role": "user", "content": "Hello there"}]});
+        EventStream::new(
+            Box::pin(futures_util::stream::iter(chunks)),
+            config,
+            &request,
+        )
+        .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+        .collect::<Vec<_>>()
+        .await
+        .concat()
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_usage_or_done() {
+        let body = concat!(
+            "data: {\"id\":\"c1\",\"model\":\"llama3\",\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n",
+            "data: {\"id\":\"c1\",\"model\":\"llama3\",\"choices\":[{\"delta\":{\"content\":\" world\"},\"finish_reason\":\"stop\"}]}\n\n"
+        );
+        let sse = collect(body, LocalConfig::default()).await;
+        let events: Vec<&str> = sse.split("\n\n").filter(|e| !e.is_empty()).collect();
+        assert_eq!(events.len(), 4);
+        let usage: Value = serde_json::from_str(&events[2][6..]).unwrap();
+        assert_eq!(usage["usage"]["completion_tokens"], 3);
+        assert_eq!(usage["model"], "llama3");
+        assert_eq!(events[3], DONE_EVENT);
+
+        // Usage goes before a `[DONE]` the server does send.
+        let body = "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\ndata: [DONE]\n\n";
+        let sse = collect(body, LocalConfig::default()).await;
+        let events: Vec<&str> = sse.split("\n\n").filter(|e| !e.is_empty()).collect();
+        assert_eq!(events.len(), 3);
+        assert!(events[1].contains("\"usage\""));
+        assert_eq!(events[2], DONE_EVENT);
+
+        let disabled = LocalConfig {
+            estimate_usage: false,
+            terminate_stream: false,
+        };
+        let body = "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n";
+        assert_eq!(collect(body, disabled).await, body);
+    }
+
+    #[test]
+    fn test_fill_usage() {
+        let request = json!({"messages": [{"role": "user", "content": "Hello there"}]});
+        let body = Bytes::from(r#"{"choices":[{"message":{"role":"assistant","content":"Hi!"}}]}"#);
+        let filled: Value =
+            serde_json::from_slice(&fill_usage(&LocalConfig::default(), &request, body)).unwrap();
+        assert_eq!(filled["usage"]["completion_tokens"], 1);
+        assert_eq!(filled["usage"]["estimated"], true);
+
+        let reported =
+            Bytes::from(r#"{"choices":[],"usage":{"prompt_tokens":1,"total_tokens":2}}"#);
+        assert_eq!(
+            fill_usage(&LocalConfig::default(), &request, reported.clone()),
+            reported
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Logging
+//!
+//! `env_logger` output behind a filter that can be replaced at runtime, so
+//! `RUST_LOG` style directives can be changed without a restart.
+use env_logger::filter::{Builder as FilterBuilder, Filter};
+use lazy_static::lazy_static;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Filter used when `RUST_LOG` is not set, as with `env_logger::init`.
+const DEFAULT_FILTER: &str = "error";
+
+struct ActiveFilter {
+    spec: String,
+    filter: Filter,
+}
+
+lazy_static! {
+    static ref ACTIVE: RwLock<ActiveFilter> = RwLock::new(ActiveFilter {
+        spec: DEFAULT_FILTER.to_string(),
+        filter: FilterBuilder::new().parse(DEFAULT_FILTER).build(),
+    });
+}
+
+/// Bumped on every change so a scheduled revert only undoes its own change.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+struct ReloadableLogger {
+    inner: env_logger::Logger,
+}
+
+impl Log for ReloadableLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let active = ACTIVE.read().unwrap_or_else(|e| e.into_inner());
+        active.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let matches = {
+            let active = ACTIVE.read().unwrap_or_else(|e| e.into_inner());
+            active.filter.matches(record)
+        };
+        if matches {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Installs the logger with the filter from `RUST_LOG`.
+pub fn init() {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(LevelFilter::Trace);
+    if let Ok(style) = std::env::var("RUST_LOG_STYLE") {
+        builder.parse_write_style(&style);
+    }
+    let logger = ReloadableLogger {
+        inner: builder.build(),
+    };
+    let spec = std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_FILTER.to_string());
+    apply(&spec);
+    if log::set_boxed_logger(Box::new(logger)).is_err() {
+        eprintln!("A logger is already installed; runtime log filters are unavailable");
+    }
+}
+
+/// Checks `spec` the way `env_logger` parses it: comma separated `level`,
+/// `module` or `module=level` directives, optionally followed by `/regex`.
+pub fn validate(spec: &str) -> Result<(), String> {
+    let directives = spec.split('/').next().unwrap_or("");
+    for directive in directives.split(',').map(str::trim) {
+        if directive.is_empty() {
+            continue;
+        }
+        let mut parts = directive.splitn(2, '=');
+        let name = parts.next().unwrap_or("");
+        match parts.next() {
+            Some(level) if name.is_empty() || LevelFilter::from_str(level.trim()).is_err() => {
+                return Err(format!("invalid log directive '{}'", directive));
+            }
+            Some(_) => {}
+            None if name.contains(char::is_whitespace) => {
+                return Err(format!("invalid log directive '{}'", directive));
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+fn apply(spec: &str) -> String {
+    let filter = FilterBuilder::new().parse(spec).build();
+    log::set_max_level(filter.filter());
+    let mut active = ACTIVE.write().unwrap_or_else(|e| e.into_inner());
+    let previous = std::mem::replace(&mut active.spec, spec.to_string());
+    active.filter = filter;
+    previous
+}
+
+pub fn current() -> String {
+    ACTIVE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .spec
+        .clone()
+}
+
+/// Replaces the log filter. With `revert_after`, the previous filter is put
+/// back once it elapses unless the filter was changed again meanwhile.
+pub fn set_filter(spec: &str, revert_after: Option<Duration>) -> Result<(), String> {
+    validate(spec)?;
+    let previous = apply(spec);
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    if let Some(delay) = revert_after {
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if GENERATION.load(Ordering::SeqCst) == generation {
+                apply(&previous);
+                log::warn!("Log filter reverted to '{}'", previous);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("info").is_ok());
+        assert!(validate("warn,llm_router_gateway_api::proxy=debug").is_ok());
+        assert!(validate("hyper, reqwest=off/retry").is_ok());
+        assert!(validate("proxy=loud").is_err());
+        assert!(validate("=debug").is_err());
+        assert!(validate("info debug").is_err());
+    }
+}
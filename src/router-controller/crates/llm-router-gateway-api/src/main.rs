@@ -14,55 +14,217 @@
 // limitations under the License.
 
 //! Main
-use clap::{arg, command, Parser};
-use env_logger;
-use hyper::service::service_fn;
-use hyper_util::rt::{TokioExecutor, TokioIo};
-use llm_router_gateway_api::config::RouterConfig;
-use llm_router_gateway_api::proxy::handler;
+use clap::{Parser, Subcommand};
+use llm_router_gateway_api::anomaly;
+use llm_router_gateway_api::audit;
+use llm_router_gateway_api::background;
+use llm_router_gateway_api::capture;
+use llm_router_gateway_api::config::{RouterConfig, SharedConfig};
+use llm_router_gateway_api::controller;
+use llm_router_gateway_api::degraded;
+use llm_router_gateway_api::events;
+use llm_router_gateway_api::kubernetes;
+use llm_router_gateway_api::listener;
+use llm_router_gateway_api::local_classifier;
+use llm_router_gateway_api::logging;
+use llm_router_gateway_api::metrics;
+use llm_router_gateway_api::privacy;
+use llm_router_gateway_api::remote;
+use llm_router_gateway_api::slo;
+use llm_router_gateway_api::store;
+use llm_router_gateway_api::tokenizer;
+use llm_router_gateway_api::upstream;
+use llm_router_gateway_api::vault;
+use llm_router_gateway_api::verify;
 use log::{error, info};
-use std::net::SocketAddr;
-use tokio::net::TcpListener;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
+    /// Configuration file; with `--config-backend`, the fallback used when
+    /// the store cannot be read at startup.
+    #[arg(long, required_unless_present_any = ["configmap", "config_backend"])]
+    config_path: Option<String>,
+    /// Kubernetes ConfigMap holding the configuration, as `name` or
+    /// `namespace/name`, instead of a file. Reloaded when it changes.
+    #[arg(long, conflicts_with = "config_path")]
+    configmap: Option<String>,
+    /// ConfigMap key holding the YAML configuration.
+    #[arg(long, default_value = "config.yaml")]
+    configmap_key: String,
+    /// Secret whose keys resolve `${NAME}` references of the ConfigMap
+    /// before environment variables. Can be repeated.
+    #[arg(long = "secret", requires = "configmap")]
+    secrets: Vec<String>,
+    /// Key-value store holding the configuration, shared by every replica
+    /// and reloaded when it changes.
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with = "configmap",
+        requires = "config_endpoint"
+    )]
+    config_backend: Option<remote::Backend>,
+    /// Base URL of the store's HTTP API, e.g. `http://etcd:2379`.
     #[arg(long)]
-    config_path: String,
+    config_endpoint: Option<String>,
+    /// Prefix of the store key; the configuration is read from
+    /// `<prefix>/config.yaml`.
+    #[arg(long, default_value = "llm-router")]
+    config_prefix: String,
+    /// Also serve the policies of `RouterPolicy` custom resources, kept in
+    /// step with them as they change.
+    #[arg(long)]
+    policy_crds: bool,
+    /// Namespace watched for `RouterPolicy` resources; all namespaces when
+    /// omitted.
+    #[arg(long, requires = "policy_crds")]
+    policy_namespace: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Checks a configuration without serving it, exiting nonzero when it
+    /// is invalid.
+    Validate {
+        #[arg(long)]
+        config_path: String,
+        /// Also check that every classifier is ready and every LLM's
+        /// api_base answers.
+        #[arg(long)]
+        ping: bool,
+        /// Time allowed for each check of `--ping`.
+        #[arg(long, default_value_t = 5000)]
+        timeout_ms: u64,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    env_logger::init();
+    logging::init();
+    let args = Args::parse();
+    if let Some(Command::Validate {
+        config_path,
+        ping,
+        timeout_ms,
+    }) = &args.command
+    {
+        let problems = verify::run(config_path, *ping, Duration::from_millis(*timeout_ms)).await;
+        if problems.is_empty() {
+            println!("{} is valid", config_path);
+            return Ok(());
+        }
+        for problem in &problems {
+            eprintln!("{}", problem);
+        }
+        std::process::exit(1);
+    }
     // cargo run -- --config foobar
     info!("Gateway API is active and running.");
-    let args = Args::parse();
-    let config = match RouterConfig::load_config(&args.config_path) {
-        Ok(config) => config,
-        Err(e) => {
-            error!("Failed to load configuration: {}", e);
-            return Err(e.into());
+    let kubernetes_source = args
+        .configmap
+        .as_deref()
+        .map(|configmap| kubernetes::Source {
+            configmap: kubernetes::ObjectRef::parse(configmap),
+            key: args.configmap_key.clone(),
+            secrets: args
+                .secrets
+                .iter()
+                .map(|secret| kubernetes::ObjectRef::parse(secret))
+                .collect(),
+        });
+    let remote_source = args.config_backend.map(|backend| remote::Source {
+        backend,
+        endpoint: args.config_endpoint.clone().unwrap_or_default(),
+        prefix: args.config_prefix.clone(),
+    });
+    let mut remote_watch = None;
+    let (config, watch) = match (&kubernetes_source, &remote_source) {
+        (Some(source), _) => match kubernetes::load(source).await {
+            Ok((config, watch)) => (config, Some(watch)),
+            Err(e) => {
+                error!("Failed to load configuration: {}", e);
+                return Err(anyhow::anyhow!(e));
+            }
+        },
+        (None, Some(source)) => match remote::load(source, args.config_path.as_deref()).await {
+            Ok((config, watch)) => {
+                remote_watch = Some(watch);
+                (config, None)
+            }
+            Err(e) => {
+                error!("Failed to load configuration: {}", e);
+                return Err(anyhow::anyhow!(e));
+            }
+        },
+        (None, None) => {
+            match RouterConfig::load_config(args.config_path.as_deref().unwrap_or_default()) {
+                Ok(config) => (config, None),
+                Err(e) => {
+                    error!("Failed to load configuration: {}", e);
+                    return Err(e.into());
+                }
+            }
         }
     };
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8084));
-    let listener = TcpListener::bind(addr).await?;
-    info!("Listening on http://{}", addr);
-
-    loop {
-        let (stream, _) = listener.accept().await?;
-        let io = TokioIo::new(stream);
-
-        let config_clone = config.clone();
-        tokio::task::spawn(async move {
-            if let Err(err) = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
-                .serve_connection(
-                    io,
-                    service_fn(move |req| handler(req, config_clone.clone())),
-                )
-                .await
-            {
-                error!("Error serving connection: {:?}", err);
-            }
-        });
+    metrics::set_custom_labels(
+        config
+            .custom_metric_labels
+            .iter()
+            .map(|label| label.name.clone())
+            .collect(),
+    );
+    // Admin changes are not persisted to the fallback of a remote store.
+    let path = args.config_path.clone().filter(|_| remote_source.is_none());
+    let config = SharedConfig::new(config, path);
+    upstream::init(&config.snapshot().upstream_pool.unwrap_or_default());
+    if let Err(e) = store::init(&config.snapshot().state_store.unwrap_or_default()) {
+        error!("Failed to open state store: {}", e);
+        return Err(e.into());
+    }
+    if let Err(e) = vault::resolve(&config.snapshot()).await {
+        error!("Failed to read API keys from Vault: {}", e);
+        return Err(anyhow::anyhow!(e));
+    }
+    vault::spawn(config.clone());
+    privacy::set_enabled(config.snapshot().privacy_mode);
+    local_classifier::preload(&config.snapshot().policies);
+    tokenizer::preload(&config.snapshot().policies);
+    background::spawn(&config.snapshot().background_tasks.unwrap_or_default());
+    anomaly::spawn(config.clone());
+    slo::spawn(config.clone());
+    degraded::spawn(config.clone());
+    if let Some(event_sink) = &config.snapshot().event_sink {
+        events::spawn(event_sink);
+    }
+    if let Some(traffic_capture) = &config.snapshot().traffic_capture {
+        capture::spawn(traffic_capture);
+    }
+    if let Some(audit_log) = &config.snapshot().audit_log {
+        audit::spawn(audit_log);
+    }
+    if let (Some(source), Some(watch)) = (kubernetes_source, watch) {
+        kubernetes::spawn(source, watch, config.clone());
+    }
+    if let (Some(source), Some(watch)) = (remote_source, remote_watch) {
+        remote::spawn(source, watch, config.clone());
+    }
+    if args.policy_crds {
+        if let Err(e) = controller::spawn(args.policy_namespace.clone(), config.clone()).await {
+            error!("Failed to watch {} resources: {}", controller::KIND, e);
+            return Err(anyhow::anyhow!(e));
+        }
+    }
+    let listeners = config
+        .snapshot()
+        .listeners()
+        .into_iter()
+        .map(|listener| tokio::spawn(listener::serve(listener, config.clone())));
+    for result in futures_util::future::try_join_all(listeners).await? {
+        result?;
     }
+    Ok(())
 }
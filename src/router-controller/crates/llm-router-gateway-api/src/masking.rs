@@ -0,0 +1,151 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Masking
+//!
+//! The `pii_redaction` stage: sensitive values in a request's prompt are
+//! replaced with placeholders such as `[EMAIL_1]` before it is routed, so
+//! neither the classifier nor the LLM sees them. With `restore`, the values
+//! are put back in place of the placeholders in non-streaming responses;
+//! streamed ones keep the placeholders, which LLMs tend to split across
+//! chunks.
+use crate::config::PiiRedactionConfig;
+use crate::metrics::PII_MASKED;
+use crate::pii::Vault;
+use bytes::Bytes;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Masks a `content`, `prompt` or `input` field: a string, or an array of
+/// strings and text parts.
+fn mask_field<'a>(
+    value: &mut Value,
+    config: &'a PiiRedactionConfig,
+    vault: &mut Vault,
+    labels: &mut Vec<&'a str>,
+) {
+    let mut mask = |text: &mut String| {
+        let (masked, found) = vault.mask(text, &config.kinds, &config.patterns);
+        *text = masked;
+        labels.extend(found);
+    };
+    match value {
+        Value::String(text) => mask(text),
+        Value::Array(items) => {
+            for item in items {
+                match item {
+                    Value::String(text) => mask(text),
+                    Value::Object(part) => {
+                        if let Some(Value::String(text)) = part.get_mut("text") {
+                            mask(text);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Masks the message contents, `prompt` and `input` of a request. The
+/// returned vault restores the values.
+pub fn mask_request(json: &mut Value, config: &PiiRedactionConfig, policy: &str) -> Vault {
+    let mut vault = Vault::default();
+    let mut labels = Vec::new();
+    if let Some(messages) = json.get_mut("messages").and_then(Value::as_array_mut) {
+        for message in messages {
+            if let Some(content) = message.get_mut("content") {
+                mask_field(content, config, &mut vault, &mut labels);
+            }
+        }
+    }
+    for field in ["prompt", "input"] {
+        if let Some(value) = json.get_mut(field) {
+            mask_field(value, config, &mut vault, &mut labels);
+        }
+    }
+
+    let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+    for label in labels {
+        *counts.entry(label).or_default() += 1;
+    }
+    for (label, count) in counts {
+        PII_MASKED.with_label_values(&[policy, label]).inc_by(count);
+    }
+    vault
+}
+
+fn restore_value(value: &mut Value, vault: &Vault) {
+    match value {
+        Value::String(text) => *text = vault.restore(text),
+        Value::Array(items) => items.iter_mut().for_each(|item| restore_value(item, vault)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|field| restore_value(field, vault)),
+        _ => {}
+    }
+}
+
+/// Puts the masked values back into the strings of a JSON response body.
+/// Other bodies are returned as they are.
+pub fn restore_response(body: Bytes, vault: &Vault) -> Bytes {
+    if vault.is_empty() {
+        return body;
+    }
+    let Ok(mut json) = serde_json::from_slice::<Value>(&body) else {
+        return body;
+    };
+    restore_value(&mut json, vault);
+    serde_json::to_vec(&json).map_or(body, Bytes::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_mask_and_restore_response() {
+        let config = PiiRedactionConfig {
+            restore: true,
+            ..Default::default()
+        };
+        let mut request = json!({
+            "model": "jane@example.com",
+            "messages": [
+                {"role": "user", "content": "Write to jane@example.com"},
+                {"role": "user", "content": [{"type": "text", "text": "and \"jane@example.com\""}]}
+            ]
+        });
+        let vault = mask_request(&mut request, &config, "masking_test");
+        assert_eq!(request["model"], "jane@example.com");
+        assert_eq!(request["messages"][0]["content"], "Write to [EMAIL_1]");
+        assert_eq!(
+            request["messages"][1]["content"][0]["text"],
+            "and \"[EMAIL_1]\""
+        );
+
+        let response = json!({
+            "choices": [{"message": {"content": "Sent to \"[EMAIL_1]\""}}]
+        });
+        let restored = restore_response(Bytes::from(response.to_string()), &vault);
+        let restored: Value = serde_json::from_slice(&restored).unwrap();
+        assert_eq!(
+            restored["choices"][0]["message"]["content"],
+            "Sent to \"jane@example.com\""
+        );
+    }
+}
@@ -0,0 +1,310 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Mock
+//!
+//! Stub upstream for LLMs with a `mock` block: chat completion, completion
+//! and embedding responses are generated in the OpenAI shape, streamed or
+//! not, so the gateway can be load tested without real providers.
+use crate::config::{MockResponse, MockUpstreamConfig};
+use crate::cost::{self, CHARS_PER_TOKEN};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use http::header::CONTENT_TYPE;
+use http::{StatusCode, Uri};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::convert::Infallible;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Host mocked calls are counted under in the upstream metrics.
+const HOST: &str = "mock";
+
+/// Length of generated embeddings.
+const EMBEDDING_DIMENSIONS: usize = 16;
+
+/// Address mocked calls are attributed to.
+pub fn url() -> reqwest::Url {
+    reqwest::Url::parse(&format!("http://{}/", HOST)).expect("mock URL is valid")
+}
+
+fn text_of(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => String::new(),
+    }
+}
+
+/// Text the response carries.
+fn completion_text(config: &MockUpstreamConfig, json: &Value) -> String {
+    match config.response {
+        MockResponse::Canned => config.content.clone(),
+        MockResponse::Echo => match json.get("prompt") {
+            Some(Value::Array(prompts)) => prompts.first().map(text_of).unwrap_or_default(),
+            Some(prompt) => text_of(prompt),
+            None => json["messages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .rev()
+                .find(|message| message["role"] == "user")
+                .map(|message| text_of(&message["content"]))
+                .unwrap_or_default(),
+        },
+    }
+}
+
+fn usage(json: &Value, text: &str) -> Value {
+    let prompt_tokens = cost::estimate_prompt_tokens(json);
+    let completion_tokens = text.chars().count().div_ceil(CHARS_PER_TOKEN) as u64;
+    json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+    })
+}
+
+fn created() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Deterministic unit-range vector for `text`.
+fn embedding(text: &str) -> Vec<f64> {
+    (0..EMBEDDING_DIMENSIONS)
+        .map(|i| {
+            let mut hasher = DefaultHasher::new();
+            (text, i).hash(&mut hasher);
+            (hasher.finish() % 2001) as f64 / 1000.0 - 1.0
+        })
+        .collect()
+}
+
+fn embeddings_body(json: &Value) -> Value {
+    let inputs: Vec<String> = match &json["input"] {
+        Value::Array(inputs) => inputs.iter().map(text_of).collect(),
+        input => vec![text_of(input)],
+    };
+    let prompt_tokens: u64 = inputs
+        .iter()
+        .map(|input| input.chars().count().div_ceil(CHARS_PER_TOKEN) as u64)
+        .sum();
+    let data: Vec<Value> = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            json!({"object": "embedding", "index": index, "embedding": embedding(input)})
+        })
+        .collect();
+    json!({
+        "object": "list",
+        "model": json["model"],
+        "data": data,
+        "usage": {"prompt_tokens": prompt_tokens, "total_tokens": prompt_tokens},
+    })
+}
+
+fn completion_body(chat: bool, json: &Value, text: &str) -> Value {
+    let choice = if chat {
+        json!({
+            "index": 0,
+            "message": {"role": "assistant", "content": text},
+            "finish_reason": "stop",
+        })
+    } else {
+        json!({"index": 0, "text": text, "finish_reason": "stop"})
+    };
+    json!({
+        "id": if chat { "chatcmpl-mock" } else { "cmpl-mock" },
+        "object": if chat { "chat.completion" } else { "text_completion" },
+        "created": created(),
+        "model": json["model"],
+        "choices": [choice],
+        "usage": usage(json, text),
+    })
+}
+
+/// SSE events of a streamed response: one chunk per word, a final chunk
+/// with the finish reason and usage, and `data: [DONE]`.
+fn stream_events(chat: bool, json: &Value, text: &str) -> Vec<String> {
+    let chunk = |choices: Value, usage: Option<Value>| {
+        let mut chunk = json!({
+            "id": if chat { "chatcmpl-mock" } else { "cmpl-mock" },
+            "object": if chat { "chat.completion.chunk" } else { "text_completion" },
+            "created": created(),
+            "model": json["model"],
+            "choices": choices,
+        });
+        if let Some(usage) = usage {
+            chunk["usage"] = usage;
+        }
+        format!("data: {}\n\n", chunk)
+    };
+    let delta = |content: &str| {
+        if chat {
+            json!([{"index": 0, "delta": {"content": content}, "finish_reason": null}])
+        } else {
+            json!([{"index": 0, "text": content, "finish_reason": null}])
+        }
+    };
+    let finish = if chat {
+        json!([{"index": 0, "delta": {}, "finish_reason": "stop"}])
+    } else {
+        json!([{"index": 0, "text": "", "finish_reason": "stop"}])
+    };
+
+    let mut events = Vec::new();
+    if chat {
+        events.push(chunk(
+            json!([{"index": 0, "delta": {"role": "assistant"}, "finish_reason": null}]),
+            None,
+        ));
+    }
+    events.extend(
+        text.split_inclusive(' ')
+            .map(|word| chunk(delta(word), None)),
+    );
+    events.push(chunk(finish, Some(usage(json, text))));
+    events.push("data: [DONE]\n\n".to_string());
+    events
+}
+
+/// The generated response to `json`, received on `forward_uri`, after the
+/// configured latency.
+pub async fn respond(
+    config: &MockUpstreamConfig,
+    forward_uri: &Uri,
+    json: &Value,
+) -> Result<reqwest::Response, reqwest::Error> {
+    if config.latency_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(config.latency_ms)).await;
+    }
+    let path = forward_uri.path();
+    let is_stream = json["stream"].as_bool().unwrap_or(false);
+    let (content_type, body) = if path.ends_with("/embeddings") {
+        (
+            "application/json",
+            reqwest::Body::from(embeddings_body(json).to_string()),
+        )
+    } else {
+        let chat = path.ends_with("/chat/completions");
+        let text = completion_text(config, json);
+        if is_stream {
+            let chunk_delay = Duration::from_millis(config.chunk_delay_ms);
+            let events = stream_events(chat, json, &text);
+            let stream = futures_util::stream::iter(events.into_iter().enumerate()).then(
+                move |(i, event)| async move {
+                    if i > 0 && !chunk_delay.is_zero() {
+                        tokio::time::sleep(chunk_delay).await;
+                    }
+                    Ok::<_, Infallible>(Bytes::from(event))
+                },
+            );
+            ("text/event-stream", reqwest::Body::wrap_stream(stream))
+        } else {
+            let body = completion_body(chat, json, &text);
+            ("application/json", reqwest::Body::from(body.to_string()))
+        }
+    };
+    let response = http::Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, content_type)
+        .body(body)
+        .expect("mock response is valid");
+    Ok(reqwest::Response::from(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat_request(stream: bool) -> Value {
+        json!({
+            "model": "meta/llama",
+            "stream": stream,
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "ping back"}
+            ]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion() {
+        let config = MockUpstreamConfig {
+            response: MockResponse::Echo,
+            ..Default::default()
+        };
+        let uri: Uri = "/v1/chat/completions".parse().unwrap();
+
+        let response = respond(&config, &uri, &chat_request(false)).await.unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["model"], "meta/llama");
+        assert_eq!(body["choices"][0]["message"]["content"], "ping back");
+        assert_eq!(body["usage"]["completion_tokens"], 3);
+
+        let response = respond(&config, &uri, &chat_request(true)).await.unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/event-stream");
+        let body = response.text().await.unwrap();
+        let events: Vec<&str> = body.split("\n\n").filter(|e| !e.is_empty()).collect();
+        assert_eq!(events.len(), 5);
+        assert!(events[1].contains(r#""content":"ping ""#));
+        assert!(events[2].contains(r#""content":"back""#));
+        assert!(events[3].contains(r#""finish_reason":"stop""#));
+        assert_eq!(events[4], "data: [DONE]");
+    }
+
+    #[tokio::test]
+    async fn test_completion_and_embeddings() {
+        let config = MockUpstreamConfig::default();
+
+        let uri: Uri = "/v1/completions".parse().unwrap();
+        let request = json!({"model": "meta/llama", "prompt": "Say something"});
+        let body: Value = respond(&config, &uri, &request)
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["object"], "text_completion");
+        assert_eq!(body["choices"][0]["text"], "This is a mock response.");
+
+        let uri: Uri = "/v1/embeddings".parse().unwrap();
+        let request = json!({"model": "nv-embed", "input": ["a", "b", "a"]});
+        let body: Value = respond(&config, &uri, &request)
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let data = body["data"].as_array().unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(
+            data[0]["embedding"].as_array().unwrap().len(),
+            EMBEDDING_DIMENSIONS
+        );
+        assert_eq!(data[0]["embedding"], data[2]["embedding"]);
+        assert_ne!(data[0]["embedding"], data[1]["embedding"]);
+    }
+}
@@ -0,0 +1,258 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Moderation
+//!
+//! The `moderation` stage: the last user message of a request is checked by
+//! a moderation endpoint before the request is routed, and flagged requests
+//! are refused or sent to the policy's designated LLM.
+use crate::config::{ModerationConfig, ModerationProvider};
+use crate::metrics::MODERATION_RESULTS;
+use crate::triton::{InferInputTensor, InferInputs, Output};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub const RESULT_ALLOWED: &str = "allowed";
+pub const RESULT_REJECTED: &str = "rejected";
+pub const RESULT_REROUTED: &str = "rerouted";
+pub const RESULT_ERROR: &str = "error";
+
+/// What the endpoint made of a text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Verdict {
+    pub flagged: bool,
+    /// Categories or rails that flagged the text.
+    pub categories: Vec<String>,
+}
+
+fn request_body(config: &ModerationConfig, text: &str) -> Value {
+    match config.provider {
+        ModerationProvider::Openai => match &config.model {
+            Some(model) => json!({"model": model, "input": text}),
+            None => json!({"input": text}),
+        },
+        ModerationProvider::NemoGuardrails => {
+            let mut body = json!({
+                "messages": [{"role": "user", "content": text}],
+            });
+            if let Some(model) = &config.model {
+                body["model"] = json!(model);
+            }
+            if let Some(config_id) = &config.config_id {
+                body["guardrails"] = json!({"config_id": config_id});
+            }
+            body
+        }
+        ModerationProvider::Triton => serde_json::to_value(InferInputs {
+            inputs: vec![InferInputTensor {
+                name: "INPUT".to_string(),
+                datatype: "BYTES".to_string(),
+                shape: vec![1, 1],
+                data: vec![vec![text.to_string()]],
+            }],
+        })
+        .unwrap_or_default(),
+    }
+}
+
+fn parse_openai(response: &Value) -> Result<Verdict, String> {
+    let results = response["results"]
+        .as_array()
+        .ok_or("moderation response has no results")?;
+    let mut verdict = Verdict::default();
+    for result in results {
+        verdict.flagged |= result["flagged"].as_bool().unwrap_or(false);
+        if let Some(categories) = result["categories"].as_object() {
+            verdict.categories.extend(
+                categories
+                    .iter()
+                    .filter(|(_, flagged)| flagged.as_bool() == Some(true))
+                    .map(|(category, _)| category.clone()),
+            );
+        }
+    }
+    Ok(verdict)
+}
+
+fn parse_nemo_guardrails(response: &Value) -> Result<Verdict, String> {
+    let status = response["status"]
+        .as_str()
+        .ok_or("guardrail check response has no status")?;
+    let categories = response["rails_status"]
+        .as_object()
+        .map(|rails| {
+            rails
+                .iter()
+                .filter(|(_, rail)| rail["status"].as_str() == Some("blocked"))
+                .map(|(rail, _)| rail.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Verdict {
+        flagged: status == "blocked",
+        categories,
+    })
+}
+
+fn parse_triton(config: &ModerationConfig, response: Value) -> Result<Verdict, String> {
+    let output: Output =
+        serde_json::from_value(response).map_err(|e| format!("invalid Triton response: {}", e))?;
+    let scores = &output
+        .outputs
+        .first()
+        .ok_or("no outputs returned from the Triton response")?
+        .data;
+    let categories: Vec<String> = config
+        .flagged_classes
+        .iter()
+        .filter(|class| {
+            scores
+                .get(**class)
+                .is_some_and(|score| *score >= config.threshold)
+        })
+        .map(|class| format!("class_{}", class))
+        .collect();
+    Ok(Verdict {
+        flagged: !categories.is_empty(),
+        categories,
+    })
+}
+
+/// Asks the moderation endpoint about `text`.
+pub async fn check(
+    config: &ModerationConfig,
+    client: &reqwest::Client,
+    text: &str,
+) -> Result<Verdict, String> {
+    let mut request = client
+        .post(&config.url)
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .json(&request_body(config, text));
+    if !config.api_key.is_empty() {
+        request = request.bearer_auth(&config.api_key);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("moderation endpoint is unreachable: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("moderation endpoint returned {}", status));
+    }
+    let response: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("invalid moderation response: {}", e))?;
+    match config.provider {
+        ModerationProvider::Openai => parse_openai(&response),
+        ModerationProvider::NemoGuardrails => parse_nemo_guardrails(&response),
+        ModerationProvider::Triton => parse_triton(config, response),
+    }
+}
+
+/// Counts a moderation outcome of `policy`.
+pub fn record(policy: &str, result: &str) {
+    MODERATION_RESULTS
+        .with_label_values(&[policy, result])
+        .inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_openai() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer secret"))
+            .and(body_partial_json(json!({"input": "hurtful"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{
+                    "flagged": true,
+                    "categories": {"harassment": true, "violence": false}
+                }]
+            })))
+            .mount(&server)
+            .await;
+        let config = ModerationConfig {
+            url: server.uri(),
+            api_key: "secret".to_string(),
+            ..Default::default()
+        };
+
+        let verdict = check(&config, &reqwest::Client::new(), "hurtful")
+            .await
+            .unwrap();
+        assert_eq!(
+            verdict,
+            Verdict {
+                flagged: true,
+                categories: vec!["harassment".to_string()],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nemo_guardrails() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                json!({"guardrails": {"config_id": "safety"}}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "status": "success",
+                "rails_status": {"content safety check input": {"status": "success"}}
+            })))
+            .mount(&server)
+            .await;
+        let config = ModerationConfig {
+            provider: ModerationProvider::NemoGuardrails,
+            url: server.uri(),
+            config_id: Some("safety".to_string()),
+            ..Default::default()
+        };
+
+        let verdict = check(&config, &reqwest::Client::new(), "hello")
+            .await
+            .unwrap();
+        assert!(!verdict.flagged);
+    }
+
+    #[test]
+    fn test_triton_threshold() {
+        let config = ModerationConfig {
+            provider: ModerationProvider::Triton,
+            flagged_classes: vec![1, 2],
+            threshold: 0.6,
+            ..Default::default()
+        };
+        let response = |scores: Vec<f64>| {
+            json!({
+                "model_name": "safety",
+                "model_version": "1",
+                "parameters": {"sequence_id": 0, "sequence_start": false, "sequence_end": false},
+                "outputs": [{"name": "OUTPUT", "datatype": "FP32", "shape": [1, 3], "data": scores}]
+            })
+        };
+
+        let verdict = parse_triton(&config, response(vec![0.3, 0.1, 0.6])).unwrap();
+        assert_eq!(verdict.categories, vec!["class_2".to_string()]);
+        let verdict = parse_triton(&config, response(vec![0.3, 0.5, 0.2])).unwrap();
+        assert!(!verdict.flagged);
+    }
+}
@@ -0,0 +1,160 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Normalize
+//!
+//! Upstream error bodies rewritten into the OpenAI error schema,
+//! `{"error": {"message", "type", "param", "code"}}`, whatever the
+//! provider, with the original body kept under `details`.
+use bytes::Bytes;
+use http::StatusCode;
+use serde_json::{json, Value};
+
+/// OpenAI error `type` of an upstream status.
+fn error_type(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::FORBIDDEN => "permission_error",
+        StatusCode::NOT_FOUND => "not_found_error",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+        status if status.is_server_error() => "server_error",
+        _ => "invalid_request_error",
+    }
+}
+
+/// The message of an error body: OpenAI, Anthropic and Gemini nest it under
+/// `error`, Bedrock and vLLM put it at the top level, FastAPI servers such
+/// as NIMs under `detail`.
+fn message(body: &Value) -> Option<String> {
+    let error = &body["error"];
+    [
+        &error["message"],
+        error,
+        &body["message"],
+        &body["Message"],
+        &body["detail"],
+    ]
+    .into_iter()
+    .find_map(|value| match value {
+        Value::String(text) if !text.is_empty() => Some(text.clone()),
+        _ => None,
+    })
+}
+
+/// The provider's own error code, such as OpenAI's `rate_limit_exceeded`
+/// or Gemini's `RESOURCE_EXHAUSTED`.
+fn code(body: &Value) -> Value {
+    let error = &body["error"];
+    [&error["code"], &error["status"], &body["code"]]
+        .into_iter()
+        .find(|value| value.is_string())
+        .cloned()
+        .unwrap_or(Value::Null)
+}
+
+/// Whether `message` says the prompt is over the model's context window,
+/// as OpenAI, Anthropic and vLLM word it.
+fn context_exceeded(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    [
+        "context length",
+        "context_length",
+        "context window",
+        "maximum context",
+        "prompt is too long",
+    ]
+    .iter()
+    .any(|phrase| message.contains(phrase))
+}
+
+/// `body` of an upstream response with `status` in the OpenAI error schema.
+pub fn error_body(status: StatusCode, body: &[u8]) -> Bytes {
+    let details = serde_json::from_slice::<Value>(body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()));
+    let message = match &details {
+        Value::String(text) if !text.trim().is_empty() => text.trim().to_string(),
+        details => message(details).unwrap_or_else(|| {
+            status
+                .canonical_reason()
+                .unwrap_or("Upstream error")
+                .to_string()
+        }),
+    };
+    // Providers that give no code for an overlong prompt get OpenAI's.
+    let code = match code(&details) {
+        Value::Null if context_exceeded(&message) => json!("context_length_exceeded"),
+        code => code,
+    };
+    let normalized = json!({
+        "error": {
+            "message": message,
+            "type": error_type(status),
+            "param": details["error"]["param"].as_str(),
+            "code": code,
+            "details": details,
+        }
+    });
+    Bytes::from(serde_json::to_vec(&normalized).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(status: StatusCode, body: &str) -> Value {
+        serde_json::from_slice(&error_body(status, body.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn test_error_body() {
+        let anthropic = normalized(
+            StatusCode::TOO_MANY_REQUESTS,
+            r#"{"type":"error","error":{"type":"rate_limit_error","message":"Slow down"}}"#,
+        );
+        assert_eq!(anthropic["error"]["message"], "Slow down");
+        assert_eq!(anthropic["error"]["type"], "rate_limit_error");
+        assert_eq!(anthropic["error"]["code"], Value::Null);
+        assert_eq!(anthropic["error"]["details"]["type"], "error");
+
+        let gemini = normalized(
+            StatusCode::BAD_REQUEST,
+            r#"{"error":{"code":400,"message":"Bad field","status":"INVALID_ARGUMENT"}}"#,
+        );
+        assert_eq!(gemini["error"]["message"], "Bad field");
+        assert_eq!(gemini["error"]["type"], "invalid_request_error");
+        assert_eq!(gemini["error"]["code"], "INVALID_ARGUMENT");
+
+        let nim = normalized(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            r#"{"detail":"Bad input"}"#,
+        );
+        assert_eq!(nim["error"]["message"], "Bad input");
+        assert_eq!(nim["error"]["code"], Value::Null);
+
+        let vllm = normalized(
+            StatusCode::BAD_REQUEST,
+            r#"{"object":"error","message":"This model's maximum context length is 4096 tokens."}"#,
+        );
+        assert_eq!(vllm["error"]["code"], "context_length_exceeded");
+
+        let text = normalized(StatusCode::BAD_GATEWAY, "upstream connect error");
+        assert_eq!(text["error"]["message"], "upstream connect error");
+        assert_eq!(text["error"]["type"], "server_error");
+        assert_eq!(text["error"]["details"], "upstream connect error");
+
+        let empty = normalized(StatusCode::SERVICE_UNAVAILABLE, "");
+        assert_eq!(empty["error"]["message"], "Service Unavailable");
+    }
+}
@@ -0,0 +1,273 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Provider
+//!
+//! Provider specific shape of an upstream request: where it is sent and how
+//! it authenticates, and for providers that do not speak the OpenAI API,
+//! how their responses are translated back.
+use crate::bedrock;
+use crate::config::{Llm, Provider};
+use crate::error::GatewayApiError;
+use crate::gemini;
+use crate::local;
+use bytes::Bytes;
+use futures_util::Stream;
+use http::header::{HeaderName, ACCEPT, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE};
+use http::{HeaderMap, HeaderValue, StatusCode, Uri};
+use serde_json::Value;
+use std::pin::Pin;
+
+const AZURE_API_KEY_HEADER: &str = "api-key";
+
+/// URL of the upstream call for a request received on `forward_uri`.
+///
+/// Azure OpenAI addresses deployments rather than models, so
+/// `/v1/chat/completions` becomes
+/// `/openai/deployments/{deployment}/chat/completions?api-version=...`.
+pub fn url(llm: &Llm, forward_uri: &Uri) -> String {
+    match llm.provider() {
+        Provider::Azure => {
+            let azure = llm.azure.clone().unwrap_or_default();
+            let deployment = azure.deployment.as_deref().unwrap_or(&llm.model);
+            let path = forward_uri.path();
+            let operation = path.strip_prefix("/v1").unwrap_or(path);
+            let query = match forward_uri.query() {
+                Some(query) => format!("{}&api-version={}", query, azure.api_version),
+                None => format!("api-version={}", azure.api_version),
+            };
+            format!(
+                "{}/openai/deployments/{}{}?{}",
+                llm.api_base.trim_end_matches('/'),
+                deployment,
+                operation,
+                query
+            )
+        }
+        Provider::OpenAi
+        | Provider::Anthropic
+        | Provider::Bedrock
+        | Provider::Gemini
+        | Provider::Local => format!("{}{}", llm.api_base, forward_uri),
+    }
+}
+
+fn auth_header(llm: &Llm) -> Result<(HeaderName, HeaderValue), GatewayApiError> {
+    let (name, mut value) = match llm.provider() {
+        Provider::Azure => (
+            HeaderName::from_static(AZURE_API_KEY_HEADER),
+            HeaderValue::from_str(&llm.api_key)?,
+        ),
+        Provider::OpenAi
+        | Provider::Anthropic
+        | Provider::Bedrock
+        | Provider::Gemini
+        | Provider::Local => (
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", llm.api_key))?,
+        ),
+    };
+    // Keeps the key out of `Debug` output, e.g. in logged requests.
+    value.set_sensitive(true);
+    Ok((name, value))
+}
+
+/// Providers translated from the OpenAI API only support chat completions.
+fn chat_only(llm: &Llm, forward_uri: &Uri, provider: &str) -> Result<(), GatewayApiError> {
+    if forward_uri.path().ends_with("/chat/completions") {
+        return Ok(());
+    }
+    Err(GatewayApiError::client_error(
+        StatusCode::BAD_REQUEST,
+        format!(
+            "LLM '{}' is served by {}, which only supports chat completions",
+            llm.name, provider
+        ),
+        "unsupported_endpoint",
+    ))
+}
+
+/// The upstream request for `json`, ready to send.
+pub async fn request(
+    client: &reqwest::Client,
+    llm: &Llm,
+    forward_uri: &Uri,
+    json: &Value,
+) -> Result<reqwest::RequestBuilder, GatewayApiError> {
+    match llm.provider() {
+        Provider::Bedrock => {
+            chat_only(llm, forward_uri, "AWS Bedrock")?;
+            return bedrock::request(client, llm, json).await;
+        }
+        Provider::Gemini => {
+            chat_only(llm, forward_uri, "Google Gemini")?;
+            return gemini::request(client, llm, json);
+        }
+        Provider::OpenAi | Provider::Anthropic | Provider::Azure | Provider::Local => {}
+    }
+    let mut request = client
+        .post(url(llm, forward_uri))
+        .header(ACCEPT, HeaderValue::from_static("application/json"));
+    // Local servers are often run without authentication.
+    if !(llm.provider() == Provider::Local && llm.api_key.is_empty()) {
+        let (name, value) = auth_header(llm)?;
+        request = request.header(name, value);
+    }
+    Ok(request.json(json))
+}
+
+type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send + Sync>>;
+
+/// Successful non-streaming response body in the OpenAI shape.
+pub fn translate_body(llm: &Llm, json: &Value, body: Bytes) -> Bytes {
+    match llm.provider() {
+        Provider::Bedrock => match serde_json::from_slice::<Value>(&body) {
+            Ok(response) => {
+                Bytes::from(bedrock::chat_completion(&response, &llm.model).to_string())
+            }
+            Err(_) => body,
+        },
+        Provider::Gemini => match serde_json::from_slice::<Value>(&body) {
+            Ok(response) => Bytes::from(gemini::chat_completion(&response, &llm.model).to_string()),
+            Err(_) => body,
+        },
+        Provider::Local => local::fill_usage(&llm.local.clone().unwrap_or_default(), json, body),
+        _ => body,
+    }
+}
+
+/// Successful streaming response body as OpenAI SSE.
+pub fn translate_stream(llm: &Llm, json: &Value, stream: ByteStream) -> ByteStream {
+    match llm.provider() {
+        Provider::Bedrock => Box::pin(bedrock::EventStream::new(stream, &llm.model)),
+        Provider::Gemini => Box::pin(gemini::EventStream::new(stream, &llm.model)),
+        Provider::Local => Box::pin(local::EventStream::new(
+            stream,
+            llm.local.clone().unwrap_or_default(),
+            json,
+        )),
+        _ => stream,
+    }
+}
+
+/// Response headers matching a translated body.
+pub fn translate_headers(llm: &Llm, headers: &mut HeaderMap, is_stream: bool) {
+    if matches!(
+        llm.provider(),
+        Provider::Bedrock | Provider::Gemini | Provider::Local
+    ) {
+        headers.remove(CONTENT_LENGTH);
+        let content_type = if is_stream {
+            "text/event-stream"
+        } else {
+            "application/json"
+        };
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AzureConfig, BedrockConfig};
+
+    #[test]
+    fn test_azure_request() {
+        let llm = Llm {
+            name: "azure".to_string(),
+            api_base: "https://contoso.openai.azure.com/".to_string(),
+            api_key: "azure-key".to_string(),
+            model: "gpt-4o".to_string(),
+            ..Default::default()
+        };
+        let uri: Uri = "/v1/chat/completions".parse().unwrap();
+        assert_eq!(
+            url(&llm, &uri),
+            "https://contoso.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2024-10-21"
+        );
+        let (name, value) = auth_header(&llm).unwrap();
+        assert_eq!(name, AZURE_API_KEY_HEADER);
+        assert_eq!(value, "azure-key");
+
+        let pinned = Llm {
+            api_base: "https://gateway.internal".to_string(),
+            azure: Some(AzureConfig {
+                deployment: Some("prod-gpt4o".to_string()),
+                api_version: "2025-01-01-preview".to_string(),
+            }),
+            ..llm
+        };
+        let uri: Uri = "/v1/embeddings?trace=1".parse().unwrap();
+        assert_eq!(
+            url(&pinned, &uri),
+            "https://gateway.internal/openai/deployments/prod-gpt4o/embeddings?trace=1&api-version=2025-01-01-preview"
+        );
+
+        let openai = Llm {
+            api_base: "https://integrate.api.nvidia.com".to_string(),
+            api_key: "nvapi-key".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            url(&openai, &uri),
+            "https://integrate.api.nvidia.com/v1/embeddings?trace=1"
+        );
+        assert_eq!(auth_header(&openai).unwrap().1, "Bearer nvapi-key");
+    }
+
+    #[tokio::test]
+    async fn test_request_debug_hides_credentials() {
+        let client = reqwest::Client::new();
+        let uri: Uri = "/v1/chat/completions".parse().unwrap();
+        let json = serde_json::json!({"messages": [{"role": "user", "content": "hi"}]});
+        let llms = [
+            Llm {
+                api_base: "https://integrate.api.nvidia.com".to_string(),
+                api_key: "secret-openai-key".to_string(),
+                ..Default::default()
+            },
+            Llm {
+                api_base: "https://contoso.openai.azure.com".to_string(),
+                api_key: "secret-azure-key".to_string(),
+                ..Default::default()
+            },
+            Llm {
+                api_base: "https://generativelanguage.googleapis.com".to_string(),
+                api_key: "secret-gemini-key".to_string(),
+                ..Default::default()
+            },
+            Llm {
+                api_base: "https://bedrock-runtime.us-east-1.amazonaws.com".to_string(),
+                bedrock: Some(BedrockConfig {
+                    access_key_id: Some("AKIDEXAMPLE".to_string()),
+                    secret_access_key: Some("secret-aws-key".to_string()),
+                    session_token: Some("secret-aws-token".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        ];
+        for llm in &llms {
+            let built = request(&client, llm, &uri, &json)
+                .await
+                .unwrap()
+                .build()
+                .unwrap();
+            let debug = format!("{:?}", built);
+            assert!(!debug.contains("secret"), "{}", debug);
+            assert!(!debug.contains("Signature="), "{}", debug);
+        }
+    }
+}
@@ -14,34 +14,91 @@
 // limitations under the License.
 
 //! Proxy
-use crate::config::{Policy, RouterConfig};
+use crate::acl::{self, ClientAddr};
+use crate::admin;
+use crate::admission;
+use crate::anomaly;
+use crate::audit;
+use crate::background;
+use crate::breaker;
+use crate::budget;
+use crate::caller::Caller;
+use crate::canary;
+use crate::capture;
+use crate::classifier::{choose_model, choose_synthetic};
+use crate::concurrency;
+use crate::config::{
+    Llm, ModerationAction, Policy, RetryConfig, RouterConfig, RoutingStrategy, SharedConfig, Tenant,
+};
+use crate::context;
+use crate::conversation::Conversation;
+use crate::cors;
+use crate::cost;
+use crate::degraded;
+use crate::disconnect::{Disconnect, STAGE_ROUTING, STAGE_UPSTREAM};
 use crate::error::{GatewayApiError, IntoResponse};
+use crate::events;
+use crate::experiment;
+use crate::expr::Facts;
+use crate::fault;
+use crate::idempotency::{self, IdempotencyKey, Lookup};
+use crate::jwt;
+use crate::limits;
+use crate::masking;
 use crate::metrics::{
-    track_token_usage, LLM_RESPONSE_TIME, MODEL_SELECTION_TIME, NUM_REQUESTS,
-    PROXY_OVERHEAD_LATENCY, REQUESTS_PER_MODEL, REQUESTS_PER_POLICY, REQUEST_FAILURE,
-    REQUEST_LATENCY, REQUEST_SUCCESS, ROUTING_POLICY_USAGE,
+    record_request, track_cost, track_token_usage, RequestLabels, RequestTimings,
+    SPECULATIVE_FALLBACKS,
+};
+use crate::mock;
+use crate::moderation;
+use crate::normalize;
+use crate::pii;
+use crate::privacy;
+use crate::provider;
+use crate::ratelimit;
+use crate::readiness;
+use crate::reasoning;
+use crate::request_context::RequestContext;
+use crate::residency;
+use crate::retry;
+use crate::retryability::ErrorClass;
+use crate::routes;
+use crate::saturation;
+use crate::slo;
+use crate::speculative::{self, Winner};
+use crate::spend;
+use crate::split;
+use crate::stats;
+use crate::sticky;
+use crate::stream::{
+    first_chunk, ReqwestStreamAdapter, TokenEstimate, UsageReport, COST_HEADER, USAGE_TRAILERS,
 };
-use crate::stream::ReqwestStreamAdapter;
-use crate::triton::{InferInputTensor, InferInputs, Output};
+use crate::tags;
+use crate::tenant;
+use crate::tokenizer;
+use crate::upstream;
+use crate::validation;
+use crate::vault;
 use bytes::Bytes;
+use futures_util::FutureExt;
 use http::StatusCode;
 use http_body_util::{combinators::BoxBody, BodyExt, Full};
-use hyper::body::Incoming;
+use hyper::body::Body;
 use hyper::{Method, Request, Response, Uri};
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use prometheus::{gather, Encoder, TextEncoder};
-use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::Mutex;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::sync::OwnedSemaphorePermit;
 
 fn print_config(config: &RouterConfig) {
     debug!("{:#?}", config);
 }
 
-fn extract_forward_uri_path_and_query(req: &Request<Incoming>) -> Result<Uri, GatewayApiError> {
+fn extract_forward_uri_path_and_query<B>(req: &Request<B>) -> Result<Uri, GatewayApiError> {
     let uri = req
         .uri()
         .path_and_query()
@@ -82,6 +139,15 @@ fn get_last_message_for_triton(messages: &Messages) -> String {
         .unwrap_or_default()
 }
 
+fn get_last_user_message(messages: &Messages) -> String {
+    messages
+        .iter()
+        .rev()
+        .find(|msg| msg.role == "user")
+        .map(|msg| msg.content.clone())
+        .unwrap_or_default()
+}
+
 fn shorten_string(s: &str, max_length: usize) -> String {
     let len = s.len();
     if len <= max_length {
@@ -91,119 +157,21 @@ fn shorten_string(s: &str, max_length: usize) -> String {
     }
 }
 
-async fn choose_model(
-    policy: &Policy,
-    client: &reqwest::Client,
-    text_input: &str,
-    _threshold: f64,
-) -> Result<usize, GatewayApiError> {
-    info!("Using policy: {}", &policy.name);
-    info!("Triton input text: {:#?}", &text_input);
-    let text_tensor = InferInputTensor {
-        name: "INPUT".to_string(),
-        datatype: "BYTES".to_string(),
-        shape: vec![1, 1],
-        data: vec![vec![text_input.to_string()]],
-    };
-
-    let data = InferInputs {
-        inputs: vec![text_tensor],
-    };
-
-    let url = policy.url.clone();
-    let mut headers = HeaderMap::new();
-    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-
-    let response = client
-        .post(url)
-        .headers(headers)
-        .json(&data)
-        .send()
-        .await
-        .map_err(|e| {
-            error!("Failed to reach Triton server: {:?}", e);
-            GatewayApiError::TritonServiceError {
-                status_code: 503,
-                message: "Triton server is unreachable".to_string(),
-            }
-        })?;
-    info!("Triton classification response: {:#?}", response);
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_body = response.bytes().await?;
-        error!(
-            "Triton error response: {}",
-            String::from_utf8_lossy(&error_body)
-        );
-
-        return Err(GatewayApiError::TritonServiceError {
-            status_code: status.as_u16(),
-            message: format!(
-                "Triton service error: {}",
-                String::from_utf8_lossy(&error_body)
-            ),
-        });
-    }
-
-    // Parse successful response
-    let response: Output = response.json().await.map_err(|e| {
-        error!("Failed to parse Triton response: {:?}", e);
-        GatewayApiError::TritonServiceError {
-            status_code: 500,
-            message: format!("Invalid Triton response: {}", e),
-        }
-    })?;
-
-    info!("Triton Output: {:#?}", response);
-
-    let output_tensor =
-        response
-            .outputs
-            .first()
-            .ok_or_else(|| GatewayApiError::TritonServiceError {
-                status_code: 500,
-                message: "No outputs returned from the Triton response".to_string(),
-            })?;
-
-    let model_index = output_tensor
-        .data
-        .iter()
-        .enumerate()
-        .max_by(|&(_, a), &(_, b)| a.partial_cmp(b).unwrap())
-        .map(|(idx, _)| idx)
-        .ok_or_else(|| {
-            error!("Invalid probability distribution from Triton");
-            GatewayApiError::TritonServiceError {
-                status_code: 500,
-                message: "Could not determine model selection from probability distribution"
-                    .to_string(),
-            }
-        })?;
-
-    info!("model_index chosen by classifier: {:#?}", model_index);
-    Ok(model_index)
-}
-
 fn modify_model(value: Value, model: &str) -> Result<Value, GatewayApiError> {
     let mut json = value.clone();
     json["model"] = Value::String(model.to_string());
     Ok(json)
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(rename_all = "lowercase")]
-enum RoutingStrategy {
-    Manual,
-    Triton,
-}
-
 #[derive(Serialize, Deserialize, Debug)]
 struct NimLlmRouterParams {
-    policy: String,
+    #[serde(default)]
+    policy: Option<String>,
     routing_strategy: Option<RoutingStrategy>,
     model: Option<String>,
     threshold: Option<f64>,
+    #[serde(default)]
+    tags: Option<BTreeMap<String, String>>,
 }
 
 fn extract_nim_llm_router_params(value: &Value) -> Option<NimLlmRouterParams> {
@@ -212,6 +180,49 @@ fn extract_nim_llm_router_params(value: &Value) -> Option<NimLlmRouterParams> {
         .and_then(|v| serde_json::from_value(v.clone()).ok())
 }
 
+/// Request headers standing in for fields of the `nim-llm-router` block,
+/// for clients behind gateways that can add headers but not change bodies.
+const PARAM_HEADERS: [(&str, &str); 4] = [
+    ("X-Nim-Llm-Router-Policy", "policy"),
+    ("X-Nim-Llm-Router-Strategy", "routing_strategy"),
+    ("X-Nim-Llm-Router-Model", "model"),
+    ("X-Nim-Llm-Router-Threshold", "threshold"),
+];
+
+/// Adds the routing parameters of `headers` to the `nim-llm-router` block
+/// of `value`. Fields the body sets are kept.
+fn merge_header_params(mut value: Value, headers: &http::HeaderMap) -> Value {
+    for (header, field) in PARAM_HEADERS {
+        let Some(text) = headers.get(header).and_then(|v| v.to_str().ok()) else {
+            continue;
+        };
+        let param = if field == "threshold" {
+            match text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+            {
+                Some(threshold) => Value::Number(threshold),
+                None => {
+                    warn!("Ignored invalid {} header: {}", header, text);
+                    continue;
+                }
+            }
+        } else {
+            Value::String(text.trim().to_string())
+        };
+        let block = value.as_object_mut().map(|body| {
+            body.entry("nim-llm-router")
+                .or_insert_with(|| Value::Object(Default::default()))
+        });
+        if let Some(block) = block.and_then(Value::as_object_mut) {
+            block.entry(field).or_insert(param);
+        }
+    }
+    value
+}
+
 fn remove_nim_llm_router_params(mut value: Value) -> Value {
     value
         .as_object_mut()
@@ -235,6 +246,281 @@ fn remove_nim_llm_router_params(mut value: Value) -> Value {
 //     value
 // }
 
+/// Response header naming the fallback LLM that served the request, when
+/// the chosen LLM failed.
+pub const FALLBACK_LLM_HEADER: &str = "X-Fallback-Llm";
+
+/// Upstream responses that move on to the next LLM in the fallback chain:
+/// `5xx` and `429`, unless the provider's retryability table says
+/// otherwise.
+fn triggers_fallback(response: &reqwest::Response) -> bool {
+    match response.extensions().get::<ErrorClass>() {
+        Some(class) => *class != ErrorClass::Terminal,
+        None => {
+            let status = response.status();
+            status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+        }
+    }
+}
+
+fn insert_fallback_header(headers: &mut http::HeaderMap, fallback_llm: &Option<String>) {
+    if let Some(value) = fallback_llm
+        .as_deref()
+        .and_then(|name| HeaderValue::from_str(name).ok())
+    {
+        headers.insert(FALLBACK_LLM_HEADER, value);
+    }
+}
+
+pub const CHAT_COMPLETIONS_PATH: &str = "/v1/chat/completions";
+pub const EMBEDDINGS_PATH: &str = "/v1/embeddings";
+/// Answers with the routing decision for a chat completion request instead
+/// of forwarding it.
+pub const ROUTE_PATH: &str = "/v1/route";
+pub const COMPLETIONS_PATH: &str = "/v1/completions";
+/// Older clients call the legacy completions API without the version prefix.
+const UNVERSIONED_COMPLETIONS_PATH: &str = "/completions";
+
+/// Text classified for an embeddings `input` or a legacy completion
+/// `prompt`: the string, or its string items one per line.
+fn input_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// The request body sent to `llm`, or why `llm` may not serve it.
+fn prepare_request(
+    json: &Value,
+    policy: &Policy,
+    llm: &Llm,
+    tenant: Option<&Tenant>,
+    headers: &http::HeaderMap,
+    context: &RequestContext,
+    embedding: bool,
+) -> Result<Value, GatewayApiError> {
+    residency::check(policy, llm, tenant, headers, context)?;
+    if let Some(guard) = &llm.guard {
+        if !guard.evaluate(&Facts::from_request(json, &context.tags)) {
+            return Err(GatewayApiError::client_error(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Request does not satisfy the guard `{}` of LLM '{}'",
+                    guard.source(),
+                    llm.name
+                ),
+                "llm_guard_rejected",
+            ));
+        }
+    }
+    if embedding {
+        let model = llm.embedding_model.as_deref().ok_or_else(|| {
+            GatewayApiError::client_error(
+                StatusCode::BAD_REQUEST,
+                format!("LLM '{}' has no embedding_model", llm.name),
+                "embeddings_not_supported",
+            )
+        })?;
+        return modify_model(json.clone(), model);
+    }
+    let json = modify_model(json.clone(), &llm.model)?;
+    let json = experiment::apply(json, policy, llm, context);
+    let json = limits::enforce(json, policy, llm)?;
+    let json = context::enforce_window(json, policy, llm)?;
+    cost::enforce_limit(json, policy, llm).map(|json| reasoning::apply(json, llm))
+}
+
+/// One upstream call, counted as in flight until the response is dropped.
+struct Attempt {
+    result: Result<reqwest::Response, GatewayApiError>,
+    in_flight: stats::InFlightGuard,
+    stream_slot: Option<upstream::StreamSlot>,
+    elapsed: f64,
+}
+
+impl Attempt {
+    /// Keeps `permit`, a concurrency slot of the LLM, until the response is
+    /// dropped.
+    fn hold(mut self, permit: Option<OwnedSemaphorePermit>) -> Self {
+        self.stream_slot = self.stream_slot.map(|slot| slot.hold(permit));
+        self
+    }
+
+    fn usable(&self) -> bool {
+        self.result
+            .as_ref()
+            .is_ok_and(|response| !triggers_fallback(response))
+    }
+}
+
+async fn attempt(
+    client: &reqwest::Client,
+    forward_uri_path_and_query: &Uri,
+    json: &Value,
+    config: &RouterConfig,
+    policy: &Policy,
+    llm: &Llm,
+    labels: &RequestLabels,
+) -> Attempt {
+    let upstream_key = stats::upstream_key(&policy.name, &llm.name);
+    let in_flight = stats::begin(&upstream_key);
+    let start = Instant::now();
+    let injected = match &config.fault_injection {
+        Some(faults) => fault::inject(faults, &policy.name, &llm.name).await,
+        None => None,
+    };
+    let (result, stream_slot) = match injected {
+        Some(result) => (result, None),
+        None => {
+            let retry_config = policy.retry.as_ref().or(config.retry.as_ref());
+            let sent = match vault::resolved(config.vault.as_ref(), llm).await {
+                Ok(llm) => {
+                    send_upstream(
+                        client,
+                        forward_uri_path_and_query,
+                        json,
+                        &llm,
+                        retry_config,
+                        labels,
+                    )
+                    .await
+                }
+                Err(error) => Err(error),
+            };
+            match sent {
+                Ok((response, stream_slot)) => (Ok(response), Some(stream_slot)),
+                Err(error) => (Err(error), None),
+            }
+        }
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+    stats::observe_latency(&upstream_key, elapsed);
+    Attempt {
+        result,
+        in_flight,
+        stream_slot,
+        elapsed,
+    }
+}
+
+async fn send_upstream(
+    client: &reqwest::Client,
+    forward_uri_path_and_query: &Uri,
+    json: &Value,
+    llm: &Llm,
+    retry_config: Option<&RetryConfig>,
+    labels: &RequestLabels,
+) -> Result<(reqwest::Response, upstream::StreamSlot), GatewayApiError> {
+    let timeouts = llm.timeouts.unwrap_or_default();
+    let client = match (timeouts.connect_ms, &llm.tls) {
+        (None, None) => client.clone(),
+        (connect_ms, tls) => upstream::client_for(connect_ms, tls.as_ref()).map_err(|message| {
+            error!("TLS setup of LLM '{}' failed: {}", llm.name, message);
+            GatewayApiError::LlmServiceError {
+                status: StatusCode::BAD_GATEWAY,
+                message: format!("TLS setup failed: {}", message),
+                provider: llm.name.clone(),
+                details: None,
+            }
+        })?,
+    };
+    // Waiting for a slot of a busy host is bounded like the request.
+    let slot_wait = timeouts.request_ms.map(Duration::from_millis);
+    let (stream_slot, sent) = match &llm.mock {
+        Some(mock) => (
+            upstream::acquire(&mock::url(), slot_wait).await,
+            mock::respond(mock, forward_uri_path_and_query, json).boxed(),
+        ),
+        None => {
+            let reqwest_request =
+                provider::request(&client, llm, forward_uri_path_and_query, json).await?;
+
+            // Built to learn its host; sent from the rebuilt builder so it
+            // can still be cloned for retries.
+            let (request_client, request) = reqwest_request.build_split();
+            let request = request?;
+            info!("upstream request: {} {}", request.method(), request.url());
+            let stream_slot = upstream::acquire(request.url(), slot_wait).await;
+            let reqwest_request = reqwest::RequestBuilder::from_parts(request_client, request);
+            let sent = retry::send(reqwest_request, retry_config, labels, llm.provider());
+            (stream_slot, sent.boxed())
+        }
+    };
+    let Some(stream_slot) = stream_slot else {
+        let waited_ms = timeouts.request_ms.unwrap_or_default();
+        error!(
+            "No upstream slot for LLM '{}' freed up within {} ms",
+            llm.name, waited_ms
+        );
+        return Err(GatewayApiError::LlmServiceError {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            message: format!(
+                "Too many requests in flight to the LLM's host for {} ms",
+                waited_ms
+            ),
+            provider: llm.name.clone(),
+            details: None,
+        });
+    };
+    let result = match timeouts.request_ms {
+        Some(timeout_ms) => {
+            match tokio::time::timeout(Duration::from_millis(timeout_ms), sent).await {
+                Ok(result) => result,
+                Err(_) => {
+                    error!(
+                        "LLM '{}' did not respond within {} ms",
+                        llm.name, timeout_ms
+                    );
+                    return Err(GatewayApiError::LlmServiceError {
+                        status: StatusCode::GATEWAY_TIMEOUT,
+                        message: format!("LLM did not respond within {} ms", timeout_ms),
+                        provider: llm.name.clone(),
+                        details: None,
+                    });
+                }
+            }
+        }
+        None => sent.await,
+    };
+    let response = result.map_err(|e| {
+        error!("Failed to reach LLM server: {:?}", e);
+        let (status, message) = if e.is_timeout() {
+            (StatusCode::GATEWAY_TIMEOUT, "LLM server timed out")
+        } else {
+            (StatusCode::SERVICE_UNAVAILABLE, "LLM server is unreachable")
+        };
+        GatewayApiError::LlmServiceError {
+            status,
+            message: message.to_string(),
+            provider: llm.name.clone(),
+            details: None,
+        }
+    })?;
+    upstream::observe(&response);
+    Ok((response, stream_slot))
+}
+
+pub(crate) fn json_response(
+    status: StatusCode,
+    body: &Value,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
+    let body_bytes = Bytes::from(serde_json::to_vec(body)?);
+    let full_body = Full::from(body_bytes)
+        .map_err(|never| match never {})
+        .boxed();
+
+    Ok(Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(full_body)?)
+}
+
 pub fn config(
     config: RouterConfig,
 ) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
@@ -252,21 +538,42 @@ pub fn config(
     Ok(client_res)
 }
 
-pub fn health() -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
+pub fn health(
+    method: &Method,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
     let body = serde_json::json!({ "status": "OK" });
     let json_vec = serde_json::to_vec(&body).expect("Serialization to JSON should succeed.");
-    let body_bytes = Bytes::from(json_vec);
+    let content_length = json_vec.len();
+    // HEAD gets the headers of GET without the body.
+    let body_bytes = if method == Method::HEAD {
+        Bytes::new()
+    } else {
+        Bytes::from(json_vec)
+    };
 
     let full_body = Full::from(body_bytes)
         .map_err(|never| match never {})
         .boxed();
 
-    let client_res = Response::builder().status(200).body(full_body)?;
+    let client_res = Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, "application/json")
+        .header(CONTENT_LENGTH, content_length)
+        .body(full_body)?;
 
     info!("/health: {client_res:#?}");
     Ok(client_res)
 }
 
+/// Empty `200` for load balancer probes, served without logging or
+/// metrics.
+pub fn probe() -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
+    let empty = Full::from(Bytes::new())
+        .map_err(|never| match never {})
+        .boxed();
+    Ok(Response::builder().status(200).body(empty)?)
+}
+
 pub fn metrics() -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
     let encoder = TextEncoder::new();
     let metric_families = gather();
@@ -314,29 +621,113 @@ pub fn unavailable() -> Result<Response<BoxBody<Bytes, GatewayApiError>>, Gatewa
     Ok(client_res)
 }
 
-pub async fn handler(
-    req: Request<Incoming>,
-    cfg: RouterConfig,
-) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
+pub async fn handler<B>(
+    mut req: Request<B>,
+    cfg: SharedConfig,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError>
+where
+    B: Body<Data = Bytes>,
+    GatewayApiError: From<B::Error>,
+{
+    let snapshot = cfg.snapshot();
+    // Prefixed paths are checked as the endpoint they reach.
+    routes::apply(&snapshot.routes, &mut req);
+    if let Err(error) = acl::check(&req, &snapshot) {
+        return Ok(error.into_response());
+    }
+    let Some(cors_config) = &snapshot.cors else {
+        return route(req, cfg, &snapshot).await;
+    };
+    let origin = cors::allowed_origin(cors_config, req.headers());
+    if cors::is_preflight(&req) {
+        return Ok(cors::preflight(cors_config, origin, req.headers()));
+    }
+    let mut response = route(req, cfg, &snapshot).await?;
+    cors::apply(cors_config, origin, response.headers_mut());
+    Ok(response)
+}
+
+async fn route<B>(
+    req: Request<B>,
+    cfg: SharedConfig,
+    snapshot: &RouterConfig,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError>
+where
+    B: Body<Data = Bytes>,
+    GatewayApiError: From<B::Error>,
+{
     let uri_path = req.uri().path();
+    if snapshot.probe_path.as_deref() == Some(uri_path) {
+        return probe();
+    }
     info!("Received request for URI: {}", uri_path);
 
     match uri_path {
         "/config" => {
             info!("Routing to config handler");
-            config(cfg)
+            if let Err(error) = admin::authorize_read(&req, snapshot).await {
+                return Ok(error.into_response());
+            }
+            config(cfg.snapshot())
         }
-        "/health" => {
+        "/health" | "/livez" | "/" => {
             info!("Routing to health handler");
-            health()
+            health(req.method())
+        }
+        "/readyz" => {
+            info!("Routing to readiness handler");
+            let report = readiness::check(snapshot).await;
+            let status = if report.ready {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            };
+            json_response(status, &report.to_json())
         }
         "/metrics" => {
             info!("Routing to metrics handler");
             metrics()
         }
-        "/v1/chat/completions" | "/completions" => {
+        "/slo" => {
+            info!("Routing to SLO handler");
+            if let Err(error) = admin::authorize_read(&req, snapshot).await {
+                return Ok(error.into_response());
+            }
+            json_response(
+                StatusCode::OK,
+                &serde_json::to_value(slo::report(snapshot))?,
+            )
+        }
+        "/admin/metrics/reset" => {
+            info!("Routing to admin metrics reset handler");
+            admin::reset_metrics(&req, &cfg.snapshot()).await
+        }
+        admin::LOG_LEVEL_PATH => {
+            info!("Routing to admin log level handler");
+            admin::log_level(req, &cfg.snapshot()).await
+        }
+        admin::FAULTS_PATH => {
+            info!("Routing to admin fault injection handler");
+            admin::faults(req, &cfg).await
+        }
+        path if path.starts_with(admin::CONVERSATIONS_PATH) => {
+            info!("Routing to admin conversations handler");
+            admin::conversations(&req, &cfg.snapshot()).await
+        }
+        path if path.starts_with(admin::POLICIES_PATH) => {
+            info!("Routing to admin policies handler");
+            admin::policies(req, &cfg).await
+        }
+        CHAT_COMPLETIONS_PATH
+        | COMPLETIONS_PATH
+        | UNVERSIONED_COMPLETIONS_PATH
+        | EMBEDDINGS_PATH => {
             info!("Routing to proxy handler");
-            proxy(req, cfg).await
+            proxy(req, cfg.snapshot()).await
+        }
+        ROUTE_PATH => {
+            info!("Routing to dry run handler");
+            dry_run(req, cfg.snapshot()).await
         }
         _ => {
             info!("Routing to Unavailable Path");
@@ -345,32 +736,150 @@ pub async fn handler(
     }
 }
 
-pub async fn proxy(
-    req: Request<Incoming>,
+pub async fn proxy<B>(
+    req: Request<B>,
     config: RouterConfig,
-) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError> {
-    let overall_start = Instant::now();
-    let mut model_selection_time = 0.0;
-    let llm_resp_time_holder = Arc::new(Mutex::new(0.0));
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError>
+where
+    B: Body<Data = Bytes>,
+    GatewayApiError: From<B::Error>,
+{
+    forward(req, config, false).await
+}
+
+/// Routes a chat completion request as `proxy` would and answers with the
+/// decision: the policy, the chosen LLM, the classifier scores and the URL
+/// the request would be sent to. Nothing is sent upstream, and the request
+/// is left out of the request metrics, captures, audit log and sticky
+/// sessions.
+pub async fn dry_run<B>(
+    req: Request<B>,
+    config: RouterConfig,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError>
+where
+    B: Body<Data = Bytes>,
+    GatewayApiError: From<B::Error>,
+{
+    forward(req, config, true).await
+}
 
-    NUM_REQUESTS.inc();
+async fn forward<B>(
+    req: Request<B>,
+    config: RouterConfig,
+    dry_run: bool,
+) -> Result<Response<BoxBody<Bytes, GatewayApiError>>, GatewayApiError>
+where
+    B: Body<Data = Bytes>,
+    GatewayApiError: From<B::Error>,
+{
+    let overall_start = Instant::now();
+    let mut labels = RequestLabels::default();
+    let mut model_selection_time = None;
+    let mut llm_response_time = None;
+    let mut degraded_routing = None;
+    // Dropped with this future when the client disconnects.
+    let mut disconnect = Disconnect::default();
+    let mut audit_record = None;
 
     let result = (async {
         print_config(&config);
+        // Request bodies and headers identify users, so privacy mode keeps
+        // them out of the logs.
+        let log_payloads = !privacy::enabled();
 
-        let forward_uri_path_and_query = extract_forward_uri_path_and_query(&req)?;
+        let forward_uri_path_and_query = if dry_run {
+            Uri::from_static(CHAT_COMPLETIONS_PATH)
+        } else {
+            extract_forward_uri_path_and_query(&req)?
+        };
         info!("forward_uri_path_and_query: {forward_uri_path_and_query:#?}");
 
         let (parts, body) = req.into_parts();
-        info!("parts: {parts:#?}");
+        if log_payloads {
+            info!("parts: {parts:#?}");
+        }
+        let mut context = RequestContext::from_headers(&parts.headers);
+        if let Some(jwt_config) = &config.jwt {
+            match jwt::authenticate(&parts.headers, jwt_config).await {
+                Ok(identity) => {
+                    context.subject = identity.subject;
+                    context.tenant = identity.tenant;
+                }
+                Err(error) => return Ok(error.into_response()),
+            }
+        }
+        let subject = context.subject.clone();
+        let peer = parts.extensions.get::<ClientAddr>().map(|addr| addr.0.ip());
+        let mut caller = Caller::new(&parts.headers, subject.as_deref(), peer);
+        let tenant = match &config.tenants {
+            Some(tenants) => {
+                match tenant::resolve(tenants, &caller, &parts.headers, context.tenant.as_deref()) {
+                    Ok(tenant) => tenant.cloned(),
+                    Err(error) => return Ok(error.into_response()),
+                }
+            }
+            None => None,
+        };
+        if let Some(tenant) = &tenant {
+            caller.key_verified = caller.api_key.is_some_and(|key| tenant.owns_key(key));
+            context.tenant = Some(tenant.id.clone());
+            labels.tenant = Some(tenant.id.clone());
+        }
+        if let Some(rate_limit) = &config.rate_limit {
+            if let Some(response) = ratelimit::check(rate_limit, &caller) {
+                return Ok(response);
+            }
+        }
+        // Taken before the body is read and held until the response is
+        // produced.
+        let _admitted = match &config.admission {
+            Some(admission) => {
+                // The body is not read yet, so only a policy chosen by its
+                // header, a route or the default can set the priority.
+                let routed = parts
+                    .headers
+                    .get("X-Nim-Llm-Router-Policy")
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string)
+                    .or_else(|| {
+                        let routed = parts.extensions.get::<routes::RoutedPolicy>();
+                        routed.map(|policy| policy.0.clone())
+                    })
+                    .or_else(|| config.default_policy.clone());
+                let policy = routed.as_deref().and_then(|name| {
+                    config
+                        .policies
+                        .iter()
+                        .find(|policy| policy.name.trim() == name.trim())
+                });
+                let priority =
+                    admission::priority(admission, tenant.as_ref(), &parts.headers, policy);
+                match admission::admit(admission, priority).await {
+                    Ok(admitted) => Some(admitted),
+                    Err(shed) => return Ok(admission::shed_response(shed, priority)),
+                }
+            }
+            None => None,
+        };
 
         let body_bytes = body.collect().await?.to_bytes();
-        info!("body_bytes: {body_bytes:#?}");
+        if log_payloads {
+            info!("body_bytes: {body_bytes:#?}");
+        }
 
         let body_str = String::from_utf8_lossy(&body_bytes);
-        info!("body_str: {:#?}", &body_str);
+        if log_payloads {
+            info!("body_str: {:#?}", &body_str);
+        }
         let json: Value = serde_json::from_str(&body_str).unwrap_or(Value::Null);
-        info!("json: {:#?}", &json);
+        let json = merge_header_params(json, &parts.headers);
+        if log_payloads {
+            info!("json: {:#?}", &json);
+        }
+        if !json.is_null() && !dry_run {
+            capture::record(parts.uri.path(), &context, &json);
+        }
+        labels.custom = tags::custom_metric_labels(&config.custom_metric_labels, &parts.headers, &json);
 
         let is_stream = if parts.method == Method::POST
             && parts
@@ -384,19 +893,81 @@ pub async fn proxy(
             false
         };
         info!("is_stream: {is_stream:#?}");
+        if !dry_run {
+            audit_record = audit::begin(parts.uri.path(), &context, &json, is_stream, overall_start);
+        }
+        let is_embedding = parts.uri.path() == EMBEDDINGS_PATH;
+        let is_completion = matches!(
+            parts.uri.path(),
+            COMPLETIONS_PATH | UNVERSIONED_COMPLETIONS_PATH
+        );
 
-        let messages = extract_messages(&json).unwrap_or_default();
-        info!("messages: {:#?}", &messages);
-        let text_input = convert_messages_to_text_input(&messages);
-        info!("text_input: {:#?}", &text_input);
+        let idempotency_key = match &config.idempotency {
+            Some(_) if !is_stream && !dry_run => {
+                IdempotencyKey::from_request(&parts.headers, &caller, &body_bytes)
+            }
+            _ => None,
+        };
+        let mut idempotency_claim = None;
+        if let (Some(key), Some(idempotency)) = (idempotency_key, &config.idempotency) {
+            match idempotency::lookup(key, idempotency) {
+                Lookup::Hit(response) => {
+                    info!("Replaying cached response for idempotency key");
+                    return Ok(response);
+                }
+                Lookup::Conflict => {
+                    let error = GatewayApiError::client_error(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "Idempotency-Key was already used with a different request body",
+                        "idempotency_key_reused",
+                    );
+                    return Ok(error.into_response());
+                }
+                Lookup::InFlight => {
+                    let error = GatewayApiError::client_error(
+                        StatusCode::CONFLICT,
+                        "A request with this Idempotency-Key is still being processed",
+                        "idempotency_key_in_flight",
+                    );
+                    return Ok(error.into_response());
+                }
+                Lookup::Miss(claim) => idempotency_claim = Some(claim),
+            }
+        }
 
-        let client = reqwest::Client::new();
+        let conversation = config
+            .conversations
+            .as_ref()
+            .filter(|_| !dry_run)
+            .and_then(|conversations| {
+                Conversation::from_request(&parts.headers, &caller, conversations)
+            });
+        if let Some(conversation) = &conversation {
+            if let Err(error) = conversation.check() {
+                return Ok(error.into_response());
+            }
+        }
 
-        let policy = if let Some(nim_llm_router_params) = extract_nim_llm_router_params(&json) {
-            match config.get_policy_by_name(nim_llm_router_params.policy.as_str()) {
+        let client = upstream::client();
+
+        let virtual_model = json
+            .get("model")
+            .and_then(Value::as_str)
+            .and_then(|name| config.virtual_model(name))
+            .cloned();
+        let policy_name = extract_nim_llm_router_params(&json)
+            .and_then(|params| params.policy)
+            .or_else(|| virtual_model.as_ref().map(|model| model.policy.clone()))
+            .or_else(|| {
+                let routed = parts.extensions.get::<routes::RoutedPolicy>();
+                routed.map(|policy| policy.0.clone())
+            })
+            .or_else(|| config.default_policy.clone());
+        let policy = if let Some(policy_name) = policy_name {
+            match config.get_policy_by_name(policy_name.as_str()) {
                 Some(policy) => policy,
                 None => {
-                    let error = GatewayApiError::PolicyNotFound(nim_llm_router_params.policy.clone());
+                    let error = GatewayApiError::PolicyNotFound(policy_name);
                     return Ok(error.into_response());
                 }
             }
@@ -407,22 +978,156 @@ pub async fn proxy(
             return Ok(error.into_response());
         };
 
-        REQUESTS_PER_POLICY
-            .with_label_values(&[policy.name.as_str()])
-            .inc();
+        if let Some(tenant) = &tenant {
+            if let Err(error) = tenant::check_policy(tenant, &policy.name) {
+                return Ok(error.into_response());
+            }
+        }
+
+        labels.policy = Some(policy.name.clone());
+        context.policy = labels.policy.clone();
+        context.tags = tags::parse(
+            &parts.headers,
+            extract_nim_llm_router_params(&json)
+                .and_then(|params| params.tags)
+                .as_ref(),
+        );
+        if let Some(request_tags) = &config.request_tags {
+            labels.tags = tags::metric_labels(request_tags, &context.tags);
+        }
 
-        let routing_strategy =
-            extract_nim_llm_router_params(&json).and_then(|params| params.routing_strategy);
+        // Masked before anything downstream sees the prompt.
+        let mut json = json;
+        let restore = policy.pii_redaction.as_ref().and_then(|pii_redaction| {
+            let vault = masking::mask_request(&mut json, pii_redaction, &policy.name);
+            pii_redaction.restore.then_some(vault)
+        });
 
-        let model_index = match routing_strategy {
-            Some(RoutingStrategy::Manual) => {
-                ROUTING_POLICY_USAGE.with_label_values(&["manual"]).inc();
-                if let Some(nim_llm_router_params) = extract_nim_llm_router_params(&json) {
-                    let model = nim_llm_router_params.model.ok_or_else(|| {
-                        GatewayApiError::InvalidRequest {
-                            message: "No model specified for manual routing".to_string(),
+        let messages = extract_messages(&json).unwrap_or_default();
+        if log_payloads {
+            info!("messages: {:#?}", &messages);
+        }
+        let text_input = convert_messages_to_text_input(&messages);
+        if log_payloads {
+            info!("text_input: {:#?}", &text_input);
+        }
+
+        let (downgrade, account) = match &config.token_budget {
+            Some(token_budget) => match budget::check(token_budget, &caller, &policy) {
+                Ok((downgrade, account)) => (downgrade, Some(account)),
+                Err(error) => return Ok(error.into_response()),
+            },
+            None => (None, None),
+        };
+
+        // Flagged requests are refused, or go to the policy's moderation LLM
+        // whatever the routing strategy.
+        let mut moderated = None;
+        if let Some(moderation) = &policy.moderation {
+            disconnect.enter(STAGE_ROUTING, &labels);
+            let moderation_text = if is_embedding {
+                input_text(&json["input"])
+            } else if is_completion {
+                input_text(&json["prompt"])
+            } else {
+                get_last_user_message(&messages)
+            };
+            match moderation::check(moderation, &client, &moderation_text).await {
+                Ok(verdict) if verdict.flagged => {
+                    warn!(
+                        "Moderation flagged request: policy={} categories={:?}",
+                        policy.name, verdict.categories
+                    );
+                    let target = moderation
+                        .llm
+                        .as_ref()
+                        .filter(|_| moderation.action == ModerationAction::Reroute)
+                        .and_then(|name| policy.llms.iter().position(|llm| llm.name == *name));
+                    match target {
+                        Some(index) => {
+                            moderation::record(&policy.name, moderation::RESULT_REROUTED);
+                            moderated = Some(index);
+                        }
+                        None => {
+                            moderation::record(&policy.name, moderation::RESULT_REJECTED);
+                            let message = moderation.message.clone().unwrap_or_else(|| {
+                                format!(
+                                    "Request was flagged by the moderation of policy '{}'",
+                                    policy.name
+                                )
+                            });
+                            let error = GatewayApiError::client_error(
+                                StatusCode::BAD_REQUEST,
+                                message,
+                                "content_flagged",
+                            );
+                            return Ok(error.into_response());
                         }
-                    })?;
+                    }
+                }
+                Ok(_) => moderation::record(&policy.name, moderation::RESULT_ALLOWED),
+                Err(message) => {
+                    moderation::record(&policy.name, moderation::RESULT_ERROR);
+                    if moderation.fail_open {
+                        warn!("Moderation failed, letting the request through: {}", message);
+                    } else {
+                        error!("Moderation failed: {}", message);
+                        let error = GatewayApiError::client_error(
+                            StatusCode::SERVICE_UNAVAILABLE,
+                            format!("Moderation failed: {}", message),
+                            "moderation_unavailable",
+                        );
+                        return Ok(error.into_response());
+                    }
+                }
+            }
+        }
+
+        let routing_strategy = extract_nim_llm_router_params(&json)
+            .and_then(|params| params.routing_strategy)
+            .or(virtual_model.and_then(|model| model.routing_strategy))
+            .or(policy.default_routing_strategy);
+        // Requests matching a rule of the policy skip the classifier.
+        let routing_strategy = match routing_strategy {
+            Some(RoutingStrategy::Triton)
+                if policy.rules_before_classifier
+                    && policy
+                        .matching_rule(&Facts::from_request(&json, &context.tags))
+                        .is_some() =>
+            {
+                Some(RoutingStrategy::Rules)
+            }
+            strategy => strategy,
+        };
+        // Later turns of a conversation go to the LLM of its first turn.
+        let sticky = config
+            .sticky_routing
+            .as_ref()
+            .filter(|_| !is_embedding && !matches!(routing_strategy, Some(RoutingStrategy::Manual)))
+            .and_then(|sticky_routing| {
+                sticky::session_id(&parts.headers, &json).map(|session| (sticky_routing, session))
+            });
+        let remembered = sticky
+            .as_ref()
+            .and_then(|(sticky_routing, session)| sticky::lookup(sticky_routing, &policy, session));
+
+        // Moderation and conversation stickiness override the strategy.
+        let pinned = moderated
+            .map(|index| (index, "moderation"))
+            .or(remembered.map(|index| (index, "sticky")));
+        let model_index = if let Some((index, pinned_by)) = pinned {
+            labels.strategy = Some(pinned_by.to_string());
+            index
+        } else {
+            match routing_strategy {
+                Some(RoutingStrategy::Manual) => {
+                    labels.strategy = Some("manual".to_string());
+                    let model = extract_nim_llm_router_params(&json)
+                        .and_then(|params| params.model)
+                        .or_else(|| policy.default_model.clone())
+                        .ok_or_else(|| GatewayApiError::InvalidRequest {
+                            message: "No model specified for manual routing".to_string(),
+                        })?;
                     match policy.llms.iter().position(|llm| llm.name == model) {
                         Some(index) => index,
                         None => {
@@ -439,130 +1144,523 @@ pub async fn proxy(
                             return Ok(error_response);
                         }
                     }
-                } else {
-                    return Err(GatewayApiError::InvalidRequest {
-                        message: "Manual routing strategy requires nim-llm-router params"
-                            .to_string(),
-                    });
                 }
-            }
-            Some(RoutingStrategy::Triton) => {
-                ROUTING_POLICY_USAGE.with_label_values(&["triton"]).inc();
-                let selection_start = Instant::now();
-                let threshold = extract_nim_llm_router_params(&json)
-                    .and_then(|params| params.threshold)
-                    .unwrap_or(0.5);
-                let triton_text = get_last_message_for_triton(&messages);
-                match choose_model(&policy, &client, &triton_text, threshold).await {
-                    Ok(index) => {
-                        model_selection_time = selection_start.elapsed().as_secs_f64();
-                        MODEL_SELECTION_TIME.observe(model_selection_time);
-                        index
+                Some(RoutingStrategy::Triton) => {
+                    labels.strategy = Some("triton".to_string());
+                    disconnect.enter(STAGE_ROUTING, &labels);
+                    let selection_start = Instant::now();
+                    let threshold = extract_nim_llm_router_params(&json)
+                        .and_then(|params| params.threshold)
+                        .unwrap_or(0.5);
+                    let triton_text = if is_embedding {
+                        input_text(&json["input"])
+                    } else if is_completion {
+                        input_text(&json["prompt"])
+                    } else {
+                        get_last_message_for_triton(&messages)
+                    };
+                    let triton_text = match &policy.classifier_redaction {
+                        Some(redaction) => pii::redact(&triton_text, redaction.mode),
+                        None => triton_text,
+                    };
+                    let degraded_enabled =
+                        config.degraded_routing.is_some() && config.synthetic_classifier.is_none();
+                    let classification = match &config.synthetic_classifier {
+                        Some(synthetic) => choose_synthetic(&policy, synthetic).await,
+                        None if degraded_enabled && !degraded::is_available(&policy.url) => {
+                            Err(degraded::unavailable(&policy))
+                        }
+                        None => {
+                            let cache = config.classifier_cache.as_ref();
+                            let connect_ms = policy.classifier_timeouts.and_then(|t| t.connect_ms);
+                            let tls = policy.classifier_tls.as_ref();
+                            let client = match (connect_ms, tls) {
+                                (None, None) => Ok(client.clone()),
+                                _ => upstream::client_for(connect_ms, tls),
+                            };
+                            match client {
+                                Ok(client) => {
+                                    choose_model(&policy, &client, &triton_text, threshold, cache).await
+                                }
+                                Err(message) => Err(GatewayApiError::TritonServiceError {
+                                    status_code: 502,
+                                    message: format!("Classifier TLS setup failed: {}", message),
+                                }),
+                            }
+                        }
+                    };
+                    let classification = if degraded_enabled {
+                        let (classification, source) =
+                            degraded::recover(&policy, &triton_text, classification);
+                        degraded_routing = source;
+                        classification
+                    } else {
+                        classification
+                    };
+                    match classification {
+                        Ok(classification) => {
+                            model_selection_time = Some(selection_start.elapsed().as_secs_f64());
+                            if let Some(detection) = config
+                                .anomaly_detection
+                                .as_ref()
+                                .filter(|_| degraded_routing.is_none())
+                            {
+                                anomaly::record_decision(detection, &policy, &classification);
+                            }
+                            context.scores = classification.scores.clone();
+                            context::reroute(&policy, classification.index, context::required_tokens(&json))
+                        }
+                        Err(e) => match e {
+                            GatewayApiError::TritonServiceError {
+                                status_code,
+                                message,
+                            } => {
+                                let body = Full::from(message.into_bytes())
+                                    .map_err(|never| match never {})
+                                    .boxed();
+
+                                let error_response = Response::builder()
+                                    .status(
+                                        StatusCode::from_u16(status_code)
+                                            .unwrap_or(StatusCode::SERVICE_UNAVAILABLE),
+                                    )
+                                    .header(CONTENT_TYPE, "application/json")
+                                    .body(body)?;
+
+                                return Ok(error_response);
+                            }
+                            _ => return Err(e),
+                        },
                     }
-                    Err(e) => match e {
-                        GatewayApiError::TritonServiceError {
-                            status_code,
-                            message,
-                        } => {
-                            let body = Full::from(message.into_bytes())
-                                .map_err(|never| match never {})
-                                .boxed();
-
-                            let error_response = Response::builder()
-                                .status(
-                                    StatusCode::from_u16(status_code)
-                                        .unwrap_or(StatusCode::SERVICE_UNAVAILABLE),
-                                )
-                                .header(CONTENT_TYPE, "application/json")
-                                .body(body)?;
-
-                            return Ok(error_response);
+                }
+                Some(RoutingStrategy::ContextLength) => {
+                    labels.strategy = Some("context_length".to_string());
+                    context::select(&policy, context::required_tokens(&json)).ok_or_else(|| {
+                        GatewayApiError::InvalidRequest {
+                            message: format!(
+                                "Context length routing requires max_context on the LLMs of policy '{}'",
+                                policy.name
+                            ),
                         }
-                        _ => return Err(e),
-                    },
+                    })?
+                }
+                Some(RoutingStrategy::Rules) => {
+                    labels.strategy = Some("rules".to_string());
+                    let facts = Facts::from_request(&json, &context.tags);
+                    match policy.matching_rule(&facts) {
+                        Some(index) => index,
+                        None => {
+                            let error = GatewayApiError::client_error(
+                                StatusCode::BAD_REQUEST,
+                                format!("No routing rule of policy '{}' matched the request", policy.name),
+                                "no_rule_matched",
+                            );
+                            return Ok(error.into_response());
+                        }
+                    }
+                }
+                Some(RoutingStrategy::Split) => {
+                    labels.strategy = Some("split".to_string());
+                    let unit = split::unit(&json, &parts.headers, &caller);
+                    split::assign(&policy, &unit).ok_or_else(|| GatewayApiError::InvalidRequest {
+                        message: format!(
+                            "Split routing requires split arms on policy '{}'",
+                            policy.name
+                        ),
+                    })?
+                }
+                None => {
+                    let error = GatewayApiError::InvalidRequest {
+                        message: "No routing strategy specified".to_string(),
+                    };
+                    return Ok(error.into_response());
                 }
-            }
-            None => {
-                return Err(GatewayApiError::InvalidRequest {
-                    message: "No routing strategy specified".to_string(),
-                });
             }
         };
+        // Explicitly requested LLMs are not replaced by their canary, and a
+        // conversation keeps the canary decision of its first turn.
+        let model_index = if pinned.is_some()
+            || matches!(routing_strategy, Some(RoutingStrategy::Manual))
+        {
+            model_index
+        } else {
+            canary::route(&policy, model_index)
+        };
+        if let (Some((sticky_routing, session)), None, None, false) =
+            (&sticky, remembered, moderated, dry_run)
+        {
+            sticky::remember(sticky_routing, &policy, session, model_index);
+        }
+        // Callers over their token budget are served by the cheaper LLM,
+        // unless moderation chose one.
+        let model_index = downgrade
+            .filter(|_| moderated.is_none())
+            .unwrap_or(model_index);
+        // Spend alerts over their daily limit move their traffic to the
+        // alert's cheaper LLM, unless moderation chose one.
+        let model_index = if moderated.is_none() {
+            let tenant_id = tenant.as_ref().map(|tenant| tenant.id.as_str());
+            spend::downgrade(&config.spend_alerts, &policy, tenant_id, model_index)
+                .unwrap_or(model_index)
+        } else {
+            model_index
+        };
+        // Requests over the chosen LLM's context window move to a sibling
+        // that fits when the policy reroutes them, unless moderation chose
+        // the LLM.
+        let model_index = if moderated.is_none() {
+            context::fit(&policy, model_index, &json)
+        } else {
+            model_index
+        };
+
+        let chosen_llm = policy.get_llm_by_index(model_index).ok_or_else(|| {
+            GatewayApiError::ModelNotFound(format!("LLM not found at index {}", model_index))
+        })?;
 
-        let chosen_llm = policy.get_llm_by_index(model_index).ok_or_else(|| {
-            GatewayApiError::ModelNotFound(format!("LLM not found at index {}", model_index))
-        })?;
-
         let chosen_classifier = policy.get_llm_name_by_index(model_index).ok_or_else(|| {
             GatewayApiError::ModelNotFound(format!("LLM not found at index {}", model_index))
         })?;
 
         info!("Chosen Classifier: {:#?}", &chosen_classifier);
-
-        REQUESTS_PER_MODEL
-            .with_label_values(&[chosen_llm.name.as_str()])
-            .inc();
-
-        let api_base = &chosen_llm.api_base;
-        let api_key = &chosen_llm.api_key;
-        let model = &chosen_llm.model;
-
-        info!("api_base: {:#?}", api_base);
-        info!("model: {:#?}", model);
+        context.strategy = labels.strategy.clone();
+        context.chosen_model = Some(chosen_llm.name.clone());
+        context.timings.model_selection_seconds = model_selection_time;
 
         let json = remove_nim_llm_router_params(json);
-        info!("json after removing nim llm router params: {json:?}");
-
-        let json = modify_model(json, model)?;
-        debug!("json after modifying model: {:#?}", &json);
+        if log_payloads {
+            info!("json after removing nim llm router params: {json:?}");
+        }
 
         // Turn on this line if you want to include usage options in the request
         // let json = if is_stream { include_usage(json) } else { json };
         // info!("json after including usage options: {:#?}", &json);
 
-        let method = http::Method::POST;
-        let mut headers = http::HeaderMap::new();
-        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", api_key))?,
-        );
+        let chain = policy.fallback_chain(model_index);
+        let chain = match &tenant {
+            Some(tenant) => {
+                let requested = matches!(routing_strategy, Some(RoutingStrategy::Manual));
+                match tenant::restrict(tenant, chain, requested) {
+                    Ok(chain) => chain,
+                    Err(error) => return Ok(error.into_response()),
+                }
+            }
+            None => chain,
+        };
+        let chosen_llm = chain[0].clone();
+        context.chosen_model = Some(chosen_llm.name.clone());
+        if dry_run {
+            let decision = serde_json::json!({
+                "policy": policy.name,
+                "strategy": labels.strategy,
+                "model_index": model_index,
+                "llm": chosen_llm.name,
+                "model": chosen_llm.model,
+                "upstream_url": provider::url(&chosen_llm, &forward_uri_path_and_query),
+                "scores": context.scores,
+                "fallbacks": chain.iter().skip(1).map(|llm| &llm.name).collect::<Vec<_>>(),
+                "degraded_routing": degraded_routing.map(|source| source.as_str()),
+                "model_selection_seconds": model_selection_time,
+            });
+            return json_response(StatusCode::OK, &decision);
+        }
+        let breaker_config = config.circuit_breaker.as_ref();
+        let saturation_config = config.saturation.as_ref();
+        let mut upstream = None;
+        // Fallback already raced against the primary.
+        let mut speculated = None;
+        for (position, llm) in chain.iter().enumerate() {
+            let is_primary = position == 0;
+            if speculated.is_some_and(|tried| position <= tried) {
+                continue;
+            }
+
+            // Ineligible fallbacks are skipped, but refusing the chosen LLM
+            // is reported to the caller.
+            context.served_by = Some(llm.name.clone());
+            let prepared = prepare_request(
+                &json,
+                &policy,
+                llm,
+                tenant.as_ref(),
+                &parts.headers,
+                &context,
+                is_embedding,
+            );
+            let llm_json = match prepared {
+                Ok(llm_json) => llm_json,
+                Err(error) if is_primary => return Ok(error.into_response()),
+                Err(error) => {
+                    info!("Skipping fallback '{}': {}", llm.name, error);
+                    continue;
+                }
+            };
+            if log_payloads {
+                debug!("json for {}: {:#?}", llm.name, &llm_json);
+            }
 
-        let uri = format!("{}{}", api_base, forward_uri_path_and_query);
-        let mut reqwest_request = client.request(method, uri).json(&json);
-        info!("reqwest_request: {reqwest_request:#?}");
+            if breaker_config.is_some_and(|c| !breaker::allow(&policy.name, &llm.name, c)) {
+                if position + 1 == chain.len() {
+                    return Err(GatewayApiError::LlmServiceError {
+                        status: StatusCode::SERVICE_UNAVAILABLE,
+                        message: format!("Circuit breaker is open for LLM '{}'", llm.name),
+                        provider: llm.name.clone(),
+                        details: None,
+                    });
+                }
+                warn!("Circuit breaker open for LLM '{}', falling back", llm.name);
+                continue;
+            }
+            let saturated =
+                saturation_config.and_then(|_| saturation::remaining(&policy.name, &llm.name));
+            if let Some(left) = saturated {
+                if position + 1 == chain.len() {
+                    return Err(GatewayApiError::LlmServiceError {
+                        status: StatusCode::TOO_MANY_REQUESTS,
+                        message: format!(
+                            "LLM '{}' is rate limited for another {}s",
+                            llm.name,
+                            left.as_secs().max(1)
+                        ),
+                        provider: llm.name.clone(),
+                        details: None,
+                    });
+                }
+                warn!("LLM '{}' is rate limited, falling back", llm.name);
+                continue;
+            }
+            let permit = match concurrency::try_acquire(&policy.name, llm) {
+                Ok(permit) => permit,
+                Err(limit) if position + 1 == chain.len() => {
+                    return Err(GatewayApiError::LlmServiceError {
+                        status: StatusCode::TOO_MANY_REQUESTS,
+                        message: format!(
+                            "LLM '{}' is at its limit of {} concurrent requests",
+                            llm.name, limit
+                        ),
+                        provider: llm.name.clone(),
+                        details: None,
+                    });
+                }
+                Err(_) => {
+                    warn!("LLM '{}' is at its concurrency limit, falling back", llm.name);
+                    continue;
+                }
+            };
 
-        for (name, value) in headers.iter() {
-            reqwest_request = reqwest_request.header(name, value);
-        }
+            labels.model = Some(llm.name.clone());
+            disconnect.enter(STAGE_UPSTREAM, &labels);
+            info!("api_base: {:#?}", llm.api_base);
+            info!("model: {:#?}", llm.model);
+
+            // Requests to a slow primary also go to the first eligible
+            // fallback, or the configured one, once the policy's soft
+            // deadline passes.
+            let backup = match &policy.speculative_fallback {
+                Some(speculative) if is_primary && (!is_stream || speculative.stream) => chain
+                    .iter()
+                    .enumerate()
+                    .skip(1)
+                    .filter(|(_, backup_llm)| {
+                        speculative
+                            .llm
+                            .as_ref()
+                            .is_none_or(|name| *name == backup_llm.name)
+                    })
+                    .find_map(|(backup_position, backup_llm)| {
+                        prepare_request(
+                            &json,
+                            &policy,
+                            backup_llm,
+                            tenant.as_ref(),
+                            &parts.headers,
+                            &context,
+                            is_embedding,
+                        )
+                            .ok()
+                            .map(|backup_json| (backup_position, backup_llm, backup_json))
+                    })
+                    .map(|backup| (speculative.deadline_ms, backup)),
+                _ => None,
+            };
+            let primary = attempt(
+                &client,
+                &forward_uri_path_and_query,
+                &llm_json,
+                &config,
+                &policy,
+                llm,
+                &labels,
+            )
+            .map(|primary| primary.hold(permit));
+            let (finished, position, llm) = match backup {
+                None => (primary.await, position, llm),
+                Some((deadline_ms, (backup_position, backup_llm, backup_json))) => {
+                    let backup_labels = RequestLabels {
+                        model: Some(backup_llm.name.clone()),
+                        ..labels.clone()
+                    };
+                    let policy = &policy;
+                    let backup = async {
+                        let allowed = breaker_config
+                            .is_none_or(|c| breaker::allow(&policy.name, &backup_llm.name, c));
+                        let upstream_key = stats::upstream_key(&policy.name, &backup_llm.name);
+                        if !allowed {
+                            return Attempt {
+                                result: Err(GatewayApiError::LlmServiceError {
+                                    status: StatusCode::SERVICE_UNAVAILABLE,
+                                    message: format!(
+                                        "Circuit breaker is open for LLM '{}'",
+                                        backup_llm.name
+                                    ),
+                                    provider: backup_llm.name.clone(),
+                                    details: None,
+                                }),
+                                in_flight: stats::begin(&upstream_key),
+                                stream_slot: None,
+                                elapsed: 0.0,
+                            };
+                        }
+                        let Ok(permit) = concurrency::try_acquire(&policy.name, backup_llm) else {
+                            return Attempt {
+                                result: Err(GatewayApiError::LlmServiceError {
+                                    status: StatusCode::TOO_MANY_REQUESTS,
+                                    message: format!(
+                                        "LLM '{}' is at its concurrency limit",
+                                        backup_llm.name
+                                    ),
+                                    provider: backup_llm.name.clone(),
+                                    details: None,
+                                }),
+                                in_flight: stats::begin(&upstream_key),
+                                stream_slot: None,
+                                elapsed: 0.0,
+                            };
+                        };
+                        attempt(
+                            &client,
+                            &forward_uri_path_and_query,
+                            &backup_json,
+                            &config,
+                            policy,
+                            backup_llm,
+                            &backup_labels,
+                        )
+                        .await
+                        .hold(permit)
+                    };
+                    let raced = speculative::race(
+                        primary,
+                        Duration::from_millis(deadline_ms),
+                        backup,
+                        Attempt::usable,
+                    )
+                    .await;
+                    if !raced.launched {
+                        (raced.result, position, llm)
+                    } else {
+                        info!(
+                            "Speculative fallback: policy={} primary={} fallback={} winner={}",
+                            policy.name,
+                            llm.name,
+                            backup_llm.name,
+                            raced.winner.as_str()
+                        );
+                        SPECULATIVE_FALLBACKS
+                            .with_label_values(&[&policy.name, raced.winner.as_str()])
+                            .inc();
+                        speculated = Some(backup_position);
+                        match raced.winner {
+                            Winner::Primary => (raced.result, position, llm),
+                            Winner::Backup => (raced.result, backup_position, backup_llm),
+                        }
+                    }
+                }
+            };
+            let is_last = position + 1 == chain.len();
+            let Attempt {
+                result,
+                in_flight,
+                stream_slot,
+                elapsed,
+            } = finished;
+            labels.model = Some(llm.name.clone());
+            llm_response_time = Some(elapsed);
+            // A stream that breaks before its first chunk can still fall
+            // back; once a chunk is forwarded the client is committed.
+            let result = match (result, policy.stream_failover) {
+                (Ok(response), Some(failover))
+                    if is_stream && !is_last && response.status().is_success() =>
+                {
+                    let timeout = failover.first_chunk_timeout_ms.map(Duration::from_millis);
+                    first_chunk(response, timeout).await.map_err(|message| {
+                        warn!(
+                            "Stream from LLM '{}' failed before its first chunk: {}",
+                            llm.name, message
+                        );
+                        GatewayApiError::LlmServiceError {
+                            status: StatusCode::BAD_GATEWAY,
+                            message,
+                            provider: llm.name.clone(),
+                            details: None,
+                        }
+                    })
+                }
+                (result, _) => result,
+            };
+            if let Some(breaker_config) = breaker_config {
+                let failed = result
+                    .as_ref()
+                    .map_or(true, |response| response.status().is_server_error());
+                breaker::record(&policy.name, &llm.name, breaker_config, failed);
+            }
+            if let Ok(response) = &result {
+                if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                    let headers = response.headers();
+                    saturation::record(&policy.name, &llm.name, headers, saturation_config);
+                }
+            }
 
-        let llm_req_start = Instant::now();
-        let reqwest_response = reqwest_request.send().await.map_err(|e| {
-            error!("Failed to reach LLM server: {:?}", e);
-            GatewayApiError::LlmServiceError {
+            match result {
+                Ok(response) if is_last || !triggers_fallback(&response) => {
+                    upstream = Some((response, in_flight, stream_slot, llm, position));
+                    break;
+                }
+                Ok(response) => warn!(
+                    "LLM '{}' returned {}, falling back",
+                    llm.name,
+                    response.status()
+                ),
+                Err(error) if is_last => return Err(error),
+                Err(_) => warn!("LLM '{}' is unreachable, falling back", llm.name),
+            }
+        }
+        let Some((reqwest_response, in_flight, stream_slot, served_by, position)) = upstream else {
+            return Err(GatewayApiError::LlmServiceError {
                 status: StatusCode::SERVICE_UNAVAILABLE,
-                message: "LLM server is unreachable".to_string(),
+                message: "No eligible LLM is available".to_string(),
                 provider: chosen_llm.name.clone(),
                 details: None,
-            }
-        })?;
-        let current_llm_resp = llm_req_start.elapsed().as_secs_f64();
-        {
-            let mut guard = llm_resp_time_holder.lock().await;
-            *guard = current_llm_resp;
-        }
-        LLM_RESPONSE_TIME
-            .with_label_values(&[chosen_llm.name.as_str()])
-            .observe(current_llm_resp);
+            });
+        };
+        let fallback_llm = (position > 0).then(|| served_by.name.clone());
+        context.served_by = Some(served_by.name.clone());
+        context.timings.llm_response_seconds = llm_response_time;
 
         let status = reqwest_response.status();
-        let headers = reqwest_response.headers().clone();
+        let headers = upstream::response_headers(reqwest_response.headers());
 
         // If status is not successful, pass through the error response
         if !status.is_success() {
-            let error_body = reqwest_response.bytes().await?;
+            let mut headers = headers;
+            let mut error_body = reqwest_response.bytes().await?;
             let status_code = status.as_u16();
             info!("status_code: {status_code:#?}");
+            if config.normalize_errors {
+                error_body = normalize::error_body(status, &error_body);
+                headers.remove(CONTENT_LENGTH);
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            }
 
             // Create a response that directly uses the error body
             let body = Full::from(error_body)
@@ -580,77 +1678,206 @@ pub async fn proxy(
                 "X-Chosen-Classifier",
                 HeaderValue::from_str(&chosen_classifier).unwrap(),
             );
+            insert_fallback_header(error_response.headers_mut(), &fallback_llm);
+            degraded::insert_header(error_response.headers_mut(), degraded_routing);
 
             error!("error_response: {error_response:#?}");
             return Ok(error_response);
         }
 
         if is_stream {
-            let stream = reqwest_response.bytes_stream();
-            let body = ReqwestStreamAdapter {
-                inner: Box::pin(stream),
-                llm_name: chosen_llm.name.clone(),
-            };
+            let stream =
+                provider::translate_stream(served_by, &json, Box::pin(reqwest_response.bytes_stream()));
+            let usage_report = config.stream_usage.clone().map(|stream_usage| {
+                UsageReport::new(
+                    stream_usage,
+                    &served_by.name,
+                    served_by.pricing.clone(),
+                    cost::estimate_prompt_tokens(&json),
+                )
+            });
+            let announce_trailers = config.stream_usage.as_ref().is_some_and(|s| s.trailers);
+            let body = ReqwestStreamAdapter::new(
+                stream,
+                labels.clone(),
+                Some(in_flight),
+                usage_report,
+            )
+            .with_conversation(conversation)
+            .with_account(account)
+            .with_estimate(
+                served_by
+                    .tokenizer
+                    .clone()
+                    .map(|tokenizer| TokenEstimate::new(tokenizer, json.clone())),
+            )
+            .with_pricing(served_by.pricing.clone())
+            .with_spend_alerts(spend::matching(&config.spend_alerts, &labels))
+            .with_context(context)
+            .with_stream_slot(stream_slot)
+            .with_start(overall_start)
+            .with_audit(audit_record.take().map(|mut record| {
+                record.set_status(status);
+                record
+            }))
+            .with_keep_alive(
+                policy
+                    .stream_keep_alive_seconds
+                    .map(|seconds| Duration::from_secs(seconds.max(1))),
+            );
             let boxed_body = BoxBody::new(body);
 
             let mut client_res = Response::new(boxed_body);
             *client_res.status_mut() = status;
             *client_res.headers_mut() = headers;
+            provider::translate_headers(served_by, client_res.headers_mut(), true);
+            if announce_trailers {
+                client_res.headers_mut().remove(http::header::CONTENT_LENGTH);
+                client_res.headers_mut().insert(
+                    http::header::TRAILER,
+                    HeaderValue::from_str(&USAGE_TRAILERS.join(", "))?,
+                );
+            }
             client_res.headers_mut().insert(
                 "X-Chosen-Classifier",
                 HeaderValue::from_str(&chosen_classifier).unwrap(),
             );
+            insert_fallback_header(client_res.headers_mut(), &fallback_llm);
+            degraded::insert_header(client_res.headers_mut(), degraded_routing);
             Ok(client_res)
         } else {
-            let body_bytes = reqwest_response.bytes().await?;
+            let body_bytes =
+                provider::translate_body(served_by, &json, reqwest_response.bytes().await?);
+            let body_bytes = match &restore {
+                Some(vault) => masking::restore_response(body_bytes, vault),
+                None => body_bytes,
+            };
+            if config.validate_responses {
+                if let Err(error) = validation::check(&body_bytes, &served_by.name, is_embedding) {
+                    return Ok(error.into_response());
+                }
+            }
             let body_clone = body_bytes.clone();
-            // Parse and track token usage for non-streaming response
-            if let Ok(json) = serde_json::from_slice::<Value>(&body_clone) {
-                track_token_usage(&json, &chosen_llm.name);
+            if let Some(record) = audit_record.as_mut() {
+                record.set_response(body_bytes.clone());
             }
+            // Priced here rather than in the background, for the cost header.
+            let cost_usd = served_by.pricing.as_ref().and_then(|pricing| {
+                let json = serde_json::from_slice::<Value>(&body_bytes).ok()?;
+                cost::usage_cost_usd(pricing, &json["usage"])
+            });
+            // Parse and track token usage for non-streaming response
+            let (usage_body, usage_labels, usage_context) =
+                (body_bytes.clone(), labels.clone(), context.clone());
+            let spend_alerts = spend::matching(&config.spend_alerts, &labels);
+            // Counted with the LLM's tokenizer when the response has no usage.
+            let estimate = served_by.tokenizer.clone().map(|tokenizer| {
+                (tokenizer, json.clone(), served_by.pricing.clone())
+            });
+            background::submit("usage", move || {
+                let Ok(mut json) = serde_json::from_slice::<Value>(&usage_body) else {
+                    return;
+                };
+                let mut cost_usd = cost_usd;
+                if let (false, Some((tokenizer_config, request, pricing))) =
+                    (json["usage"].is_object(), &estimate)
+                {
+                    let completion = tokenizer::completion_text(&json);
+                    if let Some(usage) = tokenizer::usage(tokenizer_config, request, &completion) {
+                        cost_usd = pricing
+                            .as_ref()
+                            .and_then(|pricing| cost::usage_cost_usd(pricing, &usage));
+                        json["usage"] = usage;
+                    }
+                }
+                if let Some(cost_usd) = cost_usd {
+                    track_cost(&usage_labels, cost_usd);
+                    spend::record(&spend_alerts, cost_usd, &usage_context);
+                }
+                track_token_usage(&json, &usage_labels, false, None);
+                if let Some(usage) = json.get("usage") {
+                    events::publish_usage(&usage_context, usage);
+                    if let Some(conversation) = &conversation {
+                        conversation.record(usage);
+                    }
+                    if let Some(account) = &account {
+                        account.record(usage);
+                    }
+                }
+            });
             let body = Full::from(body_bytes)
                 .map_err(|never| match never {}) // never happens
                 .boxed();
 
             let mut client_res = Response::builder().status(status).body(body)?;
             *client_res.headers_mut() = headers;
+            provider::translate_headers(served_by, client_res.headers_mut(), false);
+            if let Some(cost_usd) = cost_usd {
+                client_res
+                    .headers_mut()
+                    .insert(COST_HEADER, HeaderValue::from_str(&cost_usd.to_string())?);
+            }
             client_res.headers_mut().insert(
                 "X-Chosen-Classifier",
                 HeaderValue::from_str(&chosen_classifier).unwrap(),
             );
+            insert_fallback_header(client_res.headers_mut(), &fallback_llm);
+            degraded::insert_header(client_res.headers_mut(), degraded_routing);
+            if let (Some(claim), Some(idempotency)) = (&idempotency_claim, &config.idempotency) {
+                claim.store(idempotency, status, client_res.headers(), &body_clone);
+            }
             info!("client_res: {client_res:#?}");
             Ok(client_res)
         }
     })
     .await;
+    disconnect.complete();
+    if dry_run {
+        return result;
+    }
 
-    let overall_latency = overall_start.elapsed().as_secs_f64();
-    REQUEST_LATENCY.observe(overall_latency);
-
-    let llm_resp_time = *llm_resp_time_holder.lock().await;
-    let proxy_overhead = overall_latency - llm_resp_time - model_selection_time;
-    PROXY_OVERHEAD_LATENCY.observe(proxy_overhead);
+    let timings = RequestTimings {
+        overall: overall_start.elapsed().as_secs_f64(),
+        model_selection: model_selection_time,
+        llm_response: llm_response_time,
+    };
 
-    match &result {
+    let error_type = match &result {
         Ok(response) => {
             if response.status().is_success() {
-                REQUEST_SUCCESS.inc();
+                None
             } else {
                 let status_code = response.status().as_u16();
-                let error_type = if (400..500).contains(&status_code) {
-                    "4xx"
+                if (400..500).contains(&status_code) {
+                    Some("4xx")
                 } else if (500..600).contains(&status_code) {
-                    "5xx"
+                    Some("5xx")
                 } else {
-                    "other"
-                };
-                REQUEST_FAILURE.with_label_values(&[error_type]).inc();
+                    Some("other")
+                }
             }
         }
-        Err(_err) => {
-            // Handle system-level errors (non-HTTP errors)
-            REQUEST_FAILURE.with_label_values(&["system"]).inc();
-        }
+        // Handle system-level errors (non-HTTP errors)
+        Err(_err) => Some("system"),
+    };
+    record_request(&labels, &timings, error_type);
+    if let Some(mut record) = audit_record {
+        record.set_status(match &result {
+            Ok(response) => response.status(),
+            Err(error) => error.status_code(),
+        });
+        record.finish(&labels, None);
+    }
+    if let Some(policy) = labels
+        .policy
+        .as_deref()
+        .and_then(|name| config.get_policy_by_name(name))
+    {
+        slo::record(
+            &policy,
+            timings.overall,
+            matches!(error_type, Some("5xx" | "system")),
+        );
     }
 
     result
@@ -659,10 +1886,16 @@ pub async fn proxy(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Llm;
-    use hyper::body::Body;
+    use crate::config::{
+        CorsConfig, Fault, FaultInjectionConfig, Llm, MockResponse, MockUpstreamConfig,
+        ModerationConfig, ReadinessConfig, RoutingRule, SpeculativeFallbackConfig,
+        StreamFailoverConfig, UpstreamTimeouts,
+    };
+    use crate::expr::Condition;
     use hyper::Request;
     use serde_json::json;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     fn create_test_config() -> RouterConfig {
         RouterConfig {
@@ -675,15 +1908,19 @@ mod tests {
                         api_base: "https://integrate.api.nvidia.com".to_string(),
                         api_key: "test-key".to_string(),
                         model: "meta/llama-3.1-8b-instruct".to_string(),
+                        ..Default::default()
                     },
                     Llm {
                         name: "Code Generation".to_string(),
                         api_base: "https://integrate.api.nvidia.com".to_string(),
                         api_key: "test-key".to_string(),
                         model: "meta/llama-3.1-8b-instruct".to_string(),
+                        ..Default::default()
                     },
                 ],
+                ..Default::default()
             }],
+            ..Default::default()
         }
     }
 
@@ -721,7 +1958,7 @@ mod tests {
             .method("POST")
             .uri("/v1/chat/completions")
             .header("content-type", "application/json")
-            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
             .expect("Failed to create request");
 
         let response = proxy(req, config).await.unwrap();
@@ -744,10 +1981,1209 @@ mod tests {
             .method("POST")
             .uri("/v1/chat/completions")
             .header("content-type", "application/json")
-            .body(hyper::Body::from(serde_json::to_vec(&body).unwrap()))
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
             .expect("Failed to create request");
 
         let response = proxy(req, config).await.unwrap();
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
+
+    #[tokio::test]
+    async fn test_fallback_on_upstream_failure() {
+        let failing = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&failing)
+            .await;
+        let healthy = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "ok"})))
+            .mount(&healthy)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].llms[0].api_base = failing.uri();
+        config.policies[0].llms[0].fallbacks = vec!["Code Generation".to_string()];
+        config.policies[0].llms[1].api_base = healthy.uri();
+        let body = json!({
+            "messages": [{"role": "user", "content": "Hello"}],
+            "nim-llm-router": {
+                "policy": "test_policy",
+                "routing_strategy": "manual",
+                "model": "Brainstroming"
+            }
+        });
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let response = proxy(req, config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[FALLBACK_LLM_HEADER], "Code Generation");
+        assert_eq!(response.headers()["X-Chosen-Classifier"], "Brainstroming");
+    }
+
+    #[tokio::test]
+    async fn test_normalize_errors() {
+        let upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({"detail": "Bad input"})))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_test_config();
+        config.normalize_errors = true;
+        config.policies[0].llms[0].api_base = upstream.uri();
+        let body = json!({
+            "messages": [{"role": "user", "content": "Hello"}],
+            "nim-llm-router": {
+                "policy": "test_policy",
+                "routing_strategy": "manual",
+                "model": "Brainstroming"
+            }
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let response = proxy(req, config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["error"]["message"], "Bad input");
+        assert_eq!(error["error"]["type"], "invalid_request_error");
+        assert_eq!(error["error"]["details"]["detail"], "Bad input");
+    }
+
+    #[tokio::test]
+    async fn test_cost_header() {
+        let upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "priced",
+                "choices": [{"message": {"role": "assistant", "content": "Hi"}}],
+                "usage": {"prompt_tokens": 1000, "completion_tokens": 500, "total_tokens": 1500}
+            })))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].llms[0].api_base = upstream.uri();
+        config.policies[0].llms[0].pricing = Some(crate::config::Pricing {
+            input_per_million: 2.0,
+            output_per_million: 8.0,
+        });
+        let body = json!({
+            "messages": [{"role": "user", "content": "Hello"}],
+            "nim-llm-router": {
+                "policy": "test_policy",
+                "routing_strategy": "manual",
+                "model": "Brainstroming"
+            }
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let response = proxy(req, config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[COST_HEADER], "0.006");
+    }
+
+    #[tokio::test]
+    async fn test_saturation() {
+        let limited = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "30"))
+            .expect(1)
+            .mount(&limited)
+            .await;
+        let healthy = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "ok"})))
+            .mount(&healthy)
+            .await;
+
+        let mut config = create_test_config();
+        config.saturation = Some(Default::default());
+        config.policies[0].name = "saturation_policy".to_string();
+        config.policies[0].llms[0].api_base = limited.uri();
+        config.policies[0].llms[0].fallbacks = vec!["Code Generation".to_string()];
+        config.policies[0].llms[1].api_base = healthy.uri();
+        let request = || {
+            let body = json!({
+                "messages": [{"role": "user", "content": "Hello"}],
+                "nim-llm-router": {
+                    "policy": "saturation_policy",
+                    "routing_strategy": "manual",
+                    "model": "Brainstroming"
+                }
+            });
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+                .expect("Failed to create request")
+        };
+
+        for _ in 0..2 {
+            let response = proxy(request(), config.clone()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[FALLBACK_LLM_HEADER], "Code Generation");
+        }
+        assert!(saturation::remaining("saturation_policy", "Brainstroming").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_requests() {
+        let mut config = create_test_config();
+        let policy = &mut config.policies[0];
+        policy.name = "concurrency_policy".to_string();
+        for llm in policy.llms.iter_mut() {
+            llm.mock = Some(Default::default());
+        }
+        policy.llms[0].max_concurrent_requests = Some(1);
+        policy.llms[0].fallbacks = vec!["Code Generation".to_string()];
+        let request = || {
+            let body = json!({
+                "messages": [{"role": "user", "content": "Hello"}],
+                "nim-llm-router": {
+                    "policy": "concurrency_policy",
+                    "routing_strategy": "manual",
+                    "model": "Brainstroming"
+                }
+            });
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+                .expect("Failed to create request")
+        };
+
+        let held = concurrency::try_acquire("concurrency_policy", &config.policies[0].llms[0]);
+        assert!(held.as_ref().is_ok_and(Option::is_some));
+        let response = proxy(request(), config.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[FALLBACK_LLM_HEADER], "Code Generation");
+
+        config.policies[0].llms[0].fallbacks.clear();
+        let refused = proxy(request(), config.clone()).await.err();
+        assert_eq!(
+            refused.map(|error| error.status_code()),
+            Some(StatusCode::TOO_MANY_REQUESTS)
+        );
+
+        drop(held);
+        let response = proxy(request(), config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(FALLBACK_LLM_HEADER).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_failover_before_first_chunk() {
+        // Headers sent, then the stream ends without a chunk.
+        let broken = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("content-type", "text/event-stream"),
+            )
+            .mount(&broken)
+            .await;
+        let healthy = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string("data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\ndata: [DONE]\n\n"),
+            )
+            .mount(&healthy)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].llms[0].api_base = broken.uri();
+        config.policies[0].llms[0].fallbacks = vec!["Code Generation".to_string()];
+        config.policies[0].llms[1].api_base = healthy.uri();
+        config.policies[0].stream_failover = Some(StreamFailoverConfig::default());
+        let body = json!({
+            "messages": [{"role": "user", "content": "Hello"}],
+            "stream": true,
+            "nim-llm-router": {
+                "policy": "test_policy",
+                "routing_strategy": "manual",
+                "model": "Brainstroming"
+            }
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let response = proxy(req, config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[FALLBACK_LLM_HEADER], "Code Generation");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&body).contains("\"Hi\""));
+    }
+
+    #[tokio::test]
+    async fn test_moderation_rejects_and_reroutes() {
+        let moderation = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({"input": "Something hateful"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{"flagged": true, "categories": {"hate": true}}]
+            })))
+            .mount(&moderation)
+            .await;
+        let safe = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "safe"})))
+            .mount(&safe)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].llms[1].api_base = safe.uri();
+        config.policies[0].moderation = Some(ModerationConfig {
+            url: moderation.uri(),
+            message: Some("Not allowed here".to_string()),
+            ..Default::default()
+        });
+        let request = || {
+            let body = json!({
+                "messages": [
+                    {"role": "user", "content": "Something hateful"},
+                    {"role": "assistant", "content": "Hello"}
+                ],
+                "nim-llm-router": {
+                    "policy": "test_policy",
+                    "routing_strategy": "manual",
+                    "model": "Brainstroming"
+                }
+            });
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+                .expect("Failed to create request")
+        };
+
+        let response = proxy(request(), config.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&body).contains("Not allowed here"));
+
+        if let Some(moderation) = config.policies[0].moderation.as_mut() {
+            moderation.action = ModerationAction::Reroute;
+            moderation.llm = Some("Code Generation".to_string());
+        }
+        let response = proxy(request(), config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&body).contains("safe"));
+    }
+
+    #[tokio::test]
+    async fn test_speculative_fallback() {
+        let slow = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"id": "slow"}))
+                    .set_delay(Duration::from_secs(2)),
+            )
+            .mount(&slow)
+            .await;
+        let fast = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "fast"})))
+            .mount(&fast)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].speculative_fallback = Some(SpeculativeFallbackConfig {
+            deadline_ms: 50,
+            ..Default::default()
+        });
+        config.policies[0].fallbacks = vec!["Code Generation".to_string()];
+        config.policies[0].llms[0].api_base = slow.uri();
+        config.policies[0].llms[1].api_base = fast.uri();
+        let body = json!({
+            "messages": [{"role": "user", "content": "Hello"}],
+            "nim-llm-router": {
+                "policy": "test_policy",
+                "routing_strategy": "manual",
+                "model": "Brainstroming"
+            }
+        });
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let start = Instant::now();
+        let response = proxy(req, config).await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[FALLBACK_LLM_HEADER], "Code Generation");
+    }
+
+    #[tokio::test]
+    async fn test_speculative_fallback_to_named_llm() {
+        let slow = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"id": "slow"}))
+                    .set_delay(Duration::from_secs(2)),
+            )
+            .mount(&slow)
+            .await;
+        let skipped = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "skipped"})))
+            .mount(&skipped)
+            .await;
+        let other = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "other"})))
+            .mount(&other)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].speculative_fallback = Some(SpeculativeFallbackConfig {
+            deadline_ms: 50,
+            llm: Some("Other Provider".to_string()),
+            ..Default::default()
+        });
+        config.policies[0].fallbacks =
+            vec!["Code Generation".to_string(), "Other Provider".to_string()];
+        config.policies[0].llms[0].api_base = slow.uri();
+        config.policies[0].llms[1].api_base = skipped.uri();
+        let other_llm = Llm {
+            name: "Other Provider".to_string(),
+            api_base: other.uri(),
+            ..config.policies[0].llms[1].clone()
+        };
+        config.policies[0].llms.push(other_llm);
+        let body = json!({
+            "messages": [{"role": "user", "content": "Hello"}],
+            "nim-llm-router": {
+                "policy": "test_policy",
+                "routing_strategy": "manual",
+                "model": "Brainstroming"
+            }
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let response = proxy(req, config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[FALLBACK_LLM_HEADER], "Other Provider");
+        assert!(skipped.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_speculative_fallback_stream() {
+        let slow = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw("data: [DONE]\n\n", "text/event-stream")
+                    .set_delay(Duration::from_secs(2)),
+            )
+            .mount(&slow)
+            .await;
+        let fast = MockServer::start().await;
+        let chunk = json!({
+            "id": "fast",
+            "object": "chat.completion.chunk",
+            "choices": [{"index": 0, "delta": {"content": "Hi"}, "finish_reason": null}]
+        });
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                format!("data: {}\n\ndata: [DONE]\n\n", chunk),
+                "text/event-stream",
+            ))
+            .mount(&fast)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].speculative_fallback = Some(SpeculativeFallbackConfig {
+            deadline_ms: 50,
+            stream: true,
+            ..Default::default()
+        });
+        config.policies[0].fallbacks = vec!["Code Generation".to_string()];
+        config.policies[0].llms[0].api_base = slow.uri();
+        config.policies[0].llms[1].api_base = fast.uri();
+        let body = json!({
+            "messages": [{"role": "user", "content": "Hello"}],
+            "stream": true,
+            "nim-llm-router": {
+                "policy": "test_policy",
+                "routing_strategy": "manual",
+                "model": "Brainstroming"
+            }
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let start = Instant::now();
+        let response = proxy(req, config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[FALLBACK_LLM_HEADER], "Code Generation");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&body).contains("\"fast\""));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_pii_restore_skips_streams() {
+        let mut config = create_test_config();
+        let policy = &mut config.policies[0];
+        policy.llms[0].mock = Some(crate::config::MockUpstreamConfig {
+            content: "Sent to [EMAIL_1]".to_string(),
+            ..Default::default()
+        });
+        policy.pii_redaction = Some(crate::config::PiiRedactionConfig {
+            restore: true,
+            ..Default::default()
+        });
+        let request = |stream: bool| {
+            let body = json!({
+                "messages": [{"role": "user", "content": "Write to jane@example.com"}],
+                "stream": stream,
+                "nim-llm-router": {
+                    "policy": "test_policy",
+                    "routing_strategy": "manual",
+                    "model": "Brainstroming"
+                }
+            });
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+                .expect("Failed to create request")
+        };
+
+        let response = proxy(request(false), config.clone()).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("Sent to jane@example.com"), "{}", body);
+
+        // Streamed placeholders are left as the LLM sent them.
+        let response = proxy(request(true), config).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("[EMAIL_1]"), "{}", body);
+        assert!(!body.contains("jane@example.com"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn test_speculative_fallback_drops_loser() {
+        let slow = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"id": "slow"}))
+                    .set_delay(Duration::from_secs(2)),
+            )
+            .mount(&slow)
+            .await;
+        let fast = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "fast"})))
+            .mount(&fast)
+            .await;
+
+        // A policy of its own, so no other test shares its in-flight counts.
+        let mut config = create_test_config();
+        config.policies[0].name = "speculative_loser_policy".to_string();
+        config.policies[0].speculative_fallback = Some(SpeculativeFallbackConfig {
+            deadline_ms: 50,
+            ..Default::default()
+        });
+        config.policies[0].fallbacks = vec!["Code Generation".to_string()];
+        config.policies[0].llms[0].api_base = slow.uri();
+        config.policies[0].llms[1].api_base = fast.uri();
+        let body = json!({
+            "messages": [{"role": "user", "content": "Hello"}],
+            "nim-llm-router": {
+                "policy": "speculative_loser_policy",
+                "routing_strategy": "manual",
+                "model": "Brainstroming"
+            }
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let response = proxy(req, config).await.unwrap();
+        assert_eq!(response.headers()[FALLBACK_LLM_HEADER], "Code Generation");
+        // The primary was still waiting on its upstream; it is no longer
+        // in flight once the fallback has answered.
+        assert_eq!(slow.received_requests().await.unwrap().len(), 1);
+        let primary = stats::upstream_key("speculative_loser_policy", "Brainstroming");
+        assert_eq!(stats::snapshot(&primary).in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_validated_embeddings() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [{"object": "embedding", "index": 0, "embedding": [0.1, 0.2]}],
+                "usage": {"prompt_tokens": 2, "total_tokens": 2}
+            })))
+            .mount(&server)
+            .await;
+
+        let mut config = create_test_config();
+        config.validate_responses = true;
+        config.policies[0].llms[0].api_base = server.uri();
+        config.policies[0].llms[0].embedding_model = Some("nvidia/nv-embedqa-e5-v5".to_string());
+        let body = json!({
+            "input": "Hello",
+            "nim-llm-router": {
+                "policy": "test_policy",
+                "routing_strategy": "manual",
+                "model": "Brainstroming"
+            }
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri(EMBEDDINGS_PATH)
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let response = proxy(req, config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let hung = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"id": "late"}))
+                    .set_delay(Duration::from_secs(2)),
+            )
+            .mount(&hung)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].llms[0].api_base = hung.uri();
+        config.policies[0].llms[0].timeouts = Some(UpstreamTimeouts {
+            request_ms: Some(50),
+            ..Default::default()
+        });
+        let body = json!({
+            "messages": [{"role": "user", "content": "Hello"}],
+            "nim-llm-router": {
+                "policy": "test_policy",
+                "routing_strategy": "manual",
+                "model": "Brainstroming"
+            }
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let start = Instant::now();
+        match proxy(req, config).await {
+            Err(GatewayApiError::LlmServiceError {
+                status, provider, ..
+            }) => {
+                assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+                assert_eq!(provider, "Brainstroming");
+            }
+            other => panic!("expected a timeout, got {:?}", other.map(|r| r.status())),
+        }
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_embeddings() {
+        let upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(EMBEDDINGS_PATH))
+            .and(body_partial_json(
+                json!({"model": "nvidia/nv-embedqa-e5-v5"}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": []})))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].llms[1].api_base = upstream.uri();
+        config.policies[0].llms[1].embedding_model = Some("nvidia/nv-embedqa-e5-v5".to_string());
+        let request = |model: &str| {
+            let body = json!({
+                "input": ["Hello"],
+                "nim-llm-router": {
+                    "policy": "test_policy",
+                    "routing_strategy": "manual",
+                    "model": model
+                }
+            });
+            Request::builder()
+                .method("POST")
+                .uri(EMBEDDINGS_PATH)
+                .header("content-type", "application/json")
+                .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+                .expect("Failed to create request")
+        };
+
+        let response = proxy(request("Code Generation"), config.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = proxy(request("Brainstroming"), config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_completions() {
+        let upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(COMPLETIONS_PATH))
+            .and(body_partial_json(
+                json!({"model": "meta/llama-3.1-8b-instruct", "prompt": "Once upon a time"}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{"text": ", there was a router.", "finish_reason": "stop"}],
+                "usage": {"prompt_tokens": 4, "completion_tokens": 6, "total_tokens": 10}
+            })))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].llms[1].api_base = upstream.uri();
+        let body = json!({
+            "prompt": "Once upon a time",
+            "nim-llm-router": {
+                "policy": "test_policy",
+                "routing_strategy": "manual",
+                "model": "Code Generation"
+            }
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri(COMPLETIONS_PATH)
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+        let response = handler(req, SharedConfig::new(config, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        assert_eq!(input_text(&json!(["first", "second"])), "first\nsecond");
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let triton = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model_name": "task_router_ensemble",
+                "model_version": "1",
+                "parameters": {"sequence_id": 0, "sequence_start": false, "sequence_end": false},
+                "outputs": [{"name": "OUTPUT", "datatype": "FP32", "shape": [1, 2], "data": [0.2, 0.8]}]
+            })))
+            .mount(&triton)
+            .await;
+        let upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"choices": []})))
+            .expect(0)
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_test_config();
+        config.policies[0].url = triton.uri();
+        config.policies[0].llms[1].api_base = upstream.uri();
+        let body = json!({
+            "messages": [{"role": "user", "content": "Write a sort function"}],
+            "nim-llm-router": {"policy": "test_policy", "routing_strategy": "triton"}
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri(ROUTE_PATH)
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+        let response = handler(req, SharedConfig::new(config, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let decision: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(decision["policy"], "test_policy");
+        assert_eq!(decision["strategy"], "triton");
+        assert_eq!(decision["model_index"], 1);
+        assert_eq!(decision["llm"], "Code Generation");
+        assert_eq!(
+            decision["upstream_url"],
+            format!("{}/v1/chat/completions", upstream.uri())
+        );
+        assert_eq!(decision["scores"].as_array().map(Vec::len), Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection() {
+        let mut config = create_test_config();
+        for llm in &mut config.policies[0].llms {
+            llm.mock = Some(Default::default());
+        }
+        config.policies[0].llms[0].fallbacks = vec!["Code Generation".to_string()];
+        config.fault_injection = Some(FaultInjectionConfig {
+            faults: vec![Fault {
+                llm: Some("Brainstroming".to_string()),
+                error_percent: 100.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let request = || {
+            let body = json!({
+                "messages": [{"role": "user", "content": "Hello"}],
+                "nim-llm-router": {
+                    "policy": "test_policy",
+                    "routing_strategy": "manual",
+                    "model": "Brainstroming"
+                }
+            });
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+                .expect("Failed to create request")
+        };
+
+        let response = proxy(request(), config.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[FALLBACK_LLM_HEADER], "Code Generation");
+
+        config.policies[0].llms[0].fallbacks.clear();
+        let response = proxy(request(), config.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        if let Some(faults) = config.fault_injection.as_mut() {
+            faults.enabled = false;
+        }
+        let response = proxy(request(), config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_mock_upstream() {
+        let mut config = create_test_config();
+        let llm = &mut config.policies[0].llms[0];
+        llm.api_base = "http://127.0.0.1:9".to_string();
+        llm.mock = Some(MockUpstreamConfig {
+            response: MockResponse::Echo,
+            ..Default::default()
+        });
+        let request = |stream: bool| {
+            let body = json!({
+                "messages": [{"role": "user", "content": "Hello there"}],
+                "stream": stream,
+                "nim-llm-router": {
+                    "policy": "test_policy",
+                    "routing_strategy": "manual",
+                    "model": "Brainstroming"
+                }
+            });
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+                .expect("Failed to create request")
+        };
+
+        let response = proxy(request(false), config.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let completion: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(completion["model"], "meta/llama-3.1-8b-instruct");
+        assert_eq!(
+            completion["choices"][0]["message"]["content"],
+            "Hello there"
+        );
+
+        let response = proxy(request(true), config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(r#""content":"there""#));
+        assert!(body.trim_end().ends_with("data: [DONE]"));
+    }
+
+    #[tokio::test]
+    async fn test_default_policy() {
+        let mut config = create_test_config();
+        config.policies[0].llms[0].mock = Some(Default::default());
+        let request = || {
+            let body = json!({"messages": [{"role": "user", "content": "Hello"}]});
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+                .expect("Failed to create request")
+        };
+
+        config.default_policy = Some("test_policy".to_string());
+        let response = proxy(request(), config.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let policy = &mut config.policies[0];
+        policy.default_routing_strategy = Some(RoutingStrategy::Manual);
+        policy.default_model = Some("Brainstroming".to_string());
+        let response = proxy(request(), config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        let mut config = create_test_config();
+        let policy = &mut config.policies[0];
+        policy.llms[0].mock = Some(Default::default());
+        policy.default_routing_strategy = Some(RoutingStrategy::Manual);
+        policy.default_model = Some("Brainstroming".to_string());
+        config.routes = vec![crate::config::Route {
+            path_prefix: Some("/search".to_string()),
+            policy: "test_policy".to_string(),
+            ..Default::default()
+        }];
+        let body = json!({"messages": [{"role": "user", "content": "Hello"}]});
+        let req = Request::builder()
+            .method("POST")
+            .uri("/search/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+
+        let shared = SharedConfig::new(config, None);
+        let response = handler(req, shared).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_routes_with_network_acls() {
+        let mut config = create_test_config();
+        config.routes = vec![crate::config::Route {
+            path_prefix: Some("/search".to_string()),
+            policy: "test_policy".to_string(),
+            ..Default::default()
+        }];
+        config.network_acls = vec![crate::config::NetworkAcl {
+            paths: vec!["/metrics".to_string()],
+            allow: vec!["10.0.0.0/8".parse().unwrap()],
+        }];
+        let shared = SharedConfig::new(config, None);
+        let get = |path: &str, peer: &str| {
+            let mut req = Request::builder()
+                .method("GET")
+                .uri(path)
+                .body(Full::new(Bytes::new()))
+                .expect("Failed to create request");
+            req.extensions_mut()
+                .insert(crate::acl::ClientAddr(peer.parse().unwrap()));
+            req
+        };
+
+        let response = handler(get("/search/metrics", "203.0.113.9:5000"), shared.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = handler(get("/search/metrics", "10.1.2.3:5000"), shared)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_virtual_model() {
+        let mut config = create_test_config();
+        let policy = &mut config.policies[0];
+        policy.llms[0].mock = Some(Default::default());
+        policy.default_model = Some("Brainstroming".to_string());
+        config.virtual_models = vec![crate::config::VirtualModel {
+            name: "router/chat".to_string(),
+            policy: "test_policy".to_string(),
+            routing_strategy: Some(RoutingStrategy::Manual),
+        }];
+        let request = |model: &str| {
+            let body = json!({
+                "model": model,
+                "messages": [{"role": "user", "content": "Hello"}]
+            });
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+                .expect("Failed to create request")
+        };
+
+        let response = proxy(request("router/chat"), config.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = proxy(request("gpt-4o"), config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_merge_header_params() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert("x-nim-llm-router-policy", HeaderValue::from_static("other"));
+        headers.insert(
+            "x-nim-llm-router-strategy",
+            HeaderValue::from_static("manual"),
+        );
+        headers.insert(
+            "x-nim-llm-router-model",
+            HeaderValue::from_static("Brainstroming"),
+        );
+        headers.insert(
+            "x-nim-llm-router-threshold",
+            HeaderValue::from_static("0.7"),
+        );
+        let body = json!({"nim-llm-router": {"policy": "test_policy"}});
+        let params = extract_nim_llm_router_params(&merge_header_params(body, &headers)).unwrap();
+        assert_eq!(params.policy.as_deref(), Some("test_policy"));
+        assert!(matches!(
+            params.routing_strategy,
+            Some(RoutingStrategy::Manual)
+        ));
+        assert_eq!(params.model.as_deref(), Some("Brainstroming"));
+        assert_eq!(params.threshold, Some(0.7));
+
+        let params =
+            extract_nim_llm_router_params(&merge_header_params(json!({}), &headers)).unwrap();
+        assert_eq!(params.policy.as_deref(), Some("other"));
+        let unchanged = merge_header_params(json!({}), &http::HeaderMap::new());
+        assert_eq!(unchanged, json!({}));
+    }
+
+    #[tokio::test]
+    async fn test_degraded_routing() {
+        let upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"choices": []})))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_test_config();
+        config.degraded_routing = Some(Default::default());
+        let policy = &mut config.policies[0];
+        policy.url = "http://127.0.0.1:9/v2/models/degraded/infer".to_string();
+        policy.default_llm = Some("Code Generation".to_string());
+        policy.llms[1].api_base = upstream.uri();
+        let body = json!({
+            "messages": [{"role": "user", "content": "Hello"}],
+            "nim-llm-router": {"policy": "test_policy", "routing_strategy": "triton"}
+        });
+        let req = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+            .expect("Failed to create request");
+        let response = proxy(req, config).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[degraded::DEGRADED_ROUTING_HEADER],
+            "default_llm"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rules_and_guard() {
+        let upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"choices": []})))
+            .mount(&upstream)
+            .await;
+
+        let mut config = create_test_config();
+        let policy = &mut config.policies[0];
+        policy.rules = vec![RoutingRule {
+            when: Condition::parse("has_tools || last_message contains '```'").unwrap(),
+            llm: "Code Generation".to_string(),
+        }];
+        policy.llms[0].guard = Some(Condition::parse("tokens < 100").unwrap());
+        policy.llms[1].api_base = upstream.uri();
+        let request = |strategy: &str, content: &str| {
+            let body = json!({
+                "messages": [{"role": "user", "content": content}],
+                "nim-llm-router": {
+                    "policy": "test_policy",
+                    "routing_strategy": strategy,
+                    "model": "Brainstroming"
+                }
+            });
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Full::new(Bytes::from(serde_json::to_vec(&body).unwrap())))
+                .expect("Failed to create request")
+        };
+
+        let response = proxy(request("rules", "```fn main() {}```"), config.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = proxy(request("rules", "Hello"), config.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        // The policy's Triton URL is unreachable, so only a rule can serve it.
+        let mut ruled = config.clone();
+        ruled.policies[0].rules_before_classifier = true;
+        let response = proxy(request("triton", "```fn main() {}```"), ruled)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = proxy(request("manual", &"a".repeat(1000)), config)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&body).contains("guard `tokens < 100`"));
+    }
+
+    #[tokio::test]
+    async fn test_head_health_and_probe() {
+        let config = RouterConfig {
+            probe_path: Some("/lb-probe".to_string()),
+            ..create_test_config()
+        };
+        let shared = SharedConfig::new(config, None);
+        for path in ["/health", "/"] {
+            let req = Request::builder()
+                .method("HEAD")
+                .uri(path)
+                .body(Full::new(Bytes::new()))
+                .expect("Failed to create request");
+            let response = handler(req, shared.clone()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+            assert_eq!(response.headers()[CONTENT_LENGTH], "15");
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert!(body.is_empty());
+        }
+
+        let req = Request::builder()
+            .method("GET")
+            .uri("/lb-probe")
+            .body(Full::new(Bytes::new()))
+            .expect("Failed to create request");
+        let response = handler(req, shared).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_livez_and_readyz() {
+        let mut config = create_test_config();
+        config.readiness = Some(ReadinessConfig {
+            classifier: false,
+            ..Default::default()
+        });
+        let get = |path: &str| {
+            Request::builder()
+                .method("GET")
+                .uri(path)
+                .body(Full::new(Bytes::new()))
+                .expect("Failed to create request")
+        };
+        let shared = SharedConfig::new(config.clone(), None);
+        let response = handler(get("/livez"), shared.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = handler(get("/readyz"), shared).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["checks"]["config"], "ok");
+        assert!(body["checks"].get("classifier").is_none());
+
+        config.policies.clear();
+        let shared = SharedConfig::new(config, None);
+        let response = handler(get("/readyz"), shared).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_cors() {
+        let mut config = create_test_config();
+        config.cors = Some(CorsConfig {
+            allowed_origins: vec!["https://playground.example.com".to_string()],
+            ..Default::default()
+        });
+        let shared = SharedConfig::new(config, None);
+
+        let req = Request::builder()
+            .method("OPTIONS")
+            .uri("/v1/chat/completions")
+            .header("origin", "https://playground.example.com")
+            .header("access-control-request-method", "POST")
+            .body(Full::new(Bytes::new()))
+            .expect("Failed to create request");
+        let response = handler(req, shared.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            response.headers()["access-control-allow-origin"],
+            "https://playground.example.com"
+        );
+
+        let req = Request::builder()
+            .method("GET")
+            .uri("/health")
+            .header("origin", "https://playground.example.com")
+            .body(Full::new(Bytes::new()))
+            .expect("Failed to create request");
+        let response = handler(req, shared).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["access-control-allow-origin"],
+            "https://playground.example.com"
+        );
+    }
 }
@@ -0,0 +1,204 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rate Limit
+//!
+//! Token bucket rate limiting per caller, checked before a request reaches
+//! the classifier.
+use crate::caller::Caller;
+use crate::config::RateLimitConfig;
+use crate::error::{GatewayApiError, IntoResponse};
+use crate::metrics::RATE_LIMITED_REQUESTS;
+use crate::store;
+use bytes::Bytes;
+use http::{HeaderValue, StatusCode};
+use http_body_util::combinators::BoxBody;
+use hyper::Response;
+use log::warn;
+use reqwest::header::RETRY_AFTER;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest a bucket is kept; one idle for longer starts full again.
+const MAX_BUCKET_TTL: Duration = Duration::from_secs(86_400);
+
+#[derive(Serialize, Deserialize)]
+struct Bucket {
+    tokens: f64,
+    updated_ms: u64,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Takes a token from the caller's bucket, or returns the whole seconds to
+/// wait until one is available. Buckets live in the state store, so the
+/// limit holds across replicas sharing one; the request is let through
+/// when the store fails.
+pub fn acquire(config: &RateLimitConfig, caller: &Caller) -> Result<(), u64> {
+    let known_keys = config.overrides.iter().map(|o| o.key.as_str());
+    let (identity, bucket_key) = caller.identify(config.key_by, known_keys);
+    let (rate, burst) = config
+        .overrides
+        .iter()
+        .find(|o| o.key == identity)
+        .map_or((config.requests_per_second, config.burst), |o| {
+            (o.requests_per_second, o.burst)
+        });
+    let rate = rate.max(f64::MIN_POSITIVE);
+    let burst = f64::from(burst.max(1));
+    // A bucket idle long enough to refill is the same as a new one.
+    let ttl = Duration::try_from_secs_f64(burst / rate)
+        .unwrap_or(MAX_BUCKET_TTL)
+        .clamp(Duration::from_secs(1), MAX_BUCKET_TTL);
+
+    let now = now_ms();
+    let result = store::update(
+        store::get(),
+        &format!("ratelimit:{}", bucket_key),
+        Some(ttl),
+        |current| {
+            let bucket = current
+                .and_then(|value| serde_json::from_slice::<Bucket>(value).ok())
+                .unwrap_or(Bucket {
+                    tokens: burst,
+                    updated_ms: now,
+                });
+            let refill = now.saturating_sub(bucket.updated_ms) as f64 / 1000.0 * rate;
+            let tokens = (bucket.tokens + refill).min(burst);
+            let (tokens, outcome) = if tokens >= 1.0 {
+                (tokens - 1.0, Ok(()))
+            } else {
+                (tokens, Err(((1.0 - tokens) / rate).ceil() as u64))
+            };
+            let bucket = Bucket {
+                tokens,
+                updated_ms: now.max(bucket.updated_ms),
+            };
+            (serde_json::to_vec(&bucket).unwrap_or_default(), outcome)
+        },
+    );
+    store::report("rate_limit", result).unwrap_or(Ok(()))
+}
+
+/// `429` carrying `Retry-After`.
+pub fn limited_response(retry_after: u64) -> Response<BoxBody<Bytes, GatewayApiError>> {
+    let mut response = GatewayApiError::client_error(
+        StatusCode::TOO_MANY_REQUESTS,
+        format!("Rate limit exceeded, retry after {}s", retry_after),
+        "rate_limit_exceeded",
+    )
+    .into_response();
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after.max(1)));
+    response
+}
+
+/// Enforces the limit, returning the response to send when it is exceeded.
+pub fn check(
+    config: &RateLimitConfig,
+    caller: &Caller,
+) -> Option<Response<BoxBody<Bytes, GatewayApiError>>> {
+    acquire(config, caller).err().map(|retry_after| {
+        let key_by = config.key_by.as_str();
+        warn!(
+            "rate limited: key_by={} subject={} ip={:?} retry_after={}s",
+            key_by,
+            caller.subject.unwrap_or("unknown"),
+            caller.ip,
+            retry_after
+        );
+        RATE_LIMITED_REQUESTS.with_label_values(&[key_by]).inc();
+        limited_response(retry_after)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CallerKey, RateLimitOverride};
+    use http::HeaderMap;
+    use reqwest::header::AUTHORIZATION;
+
+    #[test]
+    fn test_bucket_per_key() {
+        let config = RateLimitConfig {
+            requests_per_second: 0.5,
+            burst: 2,
+            key_by: CallerKey::ApiKey,
+            overrides: vec![RateLimitOverride {
+                key: "rate-limit-test-vip".to_string(),
+                requests_per_second: 100.0,
+                burst: 5,
+            }],
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Bearer rate-limit-test-key"),
+        );
+        let caller = Caller::new(&headers, None, Some("198.51.100.6".parse().unwrap()));
+        assert!(acquire(&config, &caller).is_ok());
+        assert!(acquire(&config, &caller).is_ok());
+        assert_eq!(acquire(&config, &caller), Err(2));
+
+        let mut vip = HeaderMap::new();
+        vip.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Bearer rate-limit-test-vip"),
+        );
+        let vip = Caller::new(&vip, None, None);
+        for _ in 0..5 {
+            assert!(acquire(&config, &vip).is_ok());
+        }
+
+        let response = limited_response(2);
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "2");
+    }
+
+    #[test]
+    fn test_unverified_keys_share_a_bucket() {
+        let config = RateLimitConfig {
+            requests_per_second: 0.5,
+            burst: 2,
+            key_by: CallerKey::ApiKey,
+            overrides: Vec::new(),
+        };
+        let ip = Some("198.51.100.7".parse().unwrap());
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer made-up-1"));
+        let first = Caller::new(&headers, None, ip);
+        assert!(acquire(&config, &first).is_ok());
+        assert!(acquire(&config, &first).is_ok());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer made-up-2"));
+        let second = Caller::new(&headers, None, ip);
+        assert_eq!(acquire(&config, &second), Err(2));
+
+        // Keys owned by a tenant are counted on their own.
+        let verified = Caller {
+            key_verified: true,
+            ..Caller::new(&headers, None, ip)
+        };
+        assert!(acquire(&config, &verified).is_ok());
+    }
+}
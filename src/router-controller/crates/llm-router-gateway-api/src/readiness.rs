@@ -0,0 +1,211 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Readiness
+//!
+//! The checks behind `/readyz`. A replica is ready to take traffic once its
+//! configuration is loaded, every Triton classifier answers its readiness
+//! endpoint and every policy has an LLM whose circuit breaker is not open.
+//! `/livez` only tells whether the process is up.
+use crate::breaker;
+use crate::config::{ReadinessConfig, RouterConfig, UpstreamTls};
+use crate::degraded::ready_url;
+use crate::upstream;
+use futures_util::future::join_all;
+use log::warn;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Outcome of the readiness checks: whether all passed, and `ok` or the
+/// reason of the failure for each check that ran.
+pub struct Report {
+    pub ready: bool,
+    pub checks: Map<String, Value>,
+}
+
+impl Report {
+    fn record(&mut self, check: &str, failure: Option<String>) {
+        if let Some(reason) = &failure {
+            warn!("Readiness check '{}' failed: {}", check, reason);
+            self.ready = false;
+        }
+        self.checks.insert(
+            check.to_string(),
+            Value::String(failure.unwrap_or_else(|| "ok".to_string())),
+        );
+    }
+
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "status": if self.ready { "ready" } else { "not ready" },
+            "checks": self.checks,
+        })
+    }
+}
+
+/// Triton URLs the policies classify with, and their TLS settings. Local
+/// and synthetic classifiers have nothing to reach.
+fn classifier_urls(config: &RouterConfig) -> BTreeMap<&str, Option<&UpstreamTls>> {
+    if config.synthetic_classifier.is_some() {
+        return BTreeMap::new();
+    }
+    config
+        .policies
+        .iter()
+        .filter(|policy| policy.local_classifier().is_none() && !policy.url.is_empty())
+        .map(|policy| (policy.url.as_str(), policy.classifier_tls.as_ref()))
+        .collect()
+}
+
+pub(crate) async fn check_classifiers(config: &RouterConfig, timeout: Duration) -> Option<String> {
+    let probes = classifier_urls(config)
+        .into_iter()
+        .map(|(url, tls)| async move {
+            let ready = match (ready_url(url), upstream::client_for(None, tls)) {
+                (Some(ready), Ok(client)) => client
+                    .get(ready)
+                    .timeout(timeout)
+                    .send()
+                    .await
+                    .is_ok_and(|response| response.status().is_success()),
+                _ => false,
+            };
+            (!ready).then_some(url)
+        });
+    let unready: Vec<&str> = join_all(probes).await.into_iter().flatten().collect();
+    (!unready.is_empty()).then(|| format!("classifier not ready: {}", unready.join(", ")))
+}
+
+/// Policies whose every LLM has an open circuit breaker. Nothing is known
+/// about upstream health without `circuit_breaker`.
+fn check_upstreams(config: &RouterConfig) -> Option<String> {
+    config.circuit_breaker.as_ref()?;
+    let down: Vec<&str> = config
+        .policies
+        .iter()
+        .filter(|policy| {
+            !policy.llms.is_empty()
+                && policy
+                    .llms
+                    .iter()
+                    .all(|llm| breaker::is_open(&policy.name, &llm.name))
+        })
+        .map(|policy| policy.name.as_str())
+        .collect();
+    (!down.is_empty()).then(|| format!("no healthy LLM in policy: {}", down.join(", ")))
+}
+
+/// Runs the checks enabled in `readiness`, all of them when it is unset.
+pub async fn check(config: &RouterConfig) -> Report {
+    let ReadinessConfig {
+        classifier,
+        upstreams,
+        timeout_ms,
+    } = config.readiness.clone().unwrap_or_default();
+    let mut report = Report {
+        ready: true,
+        checks: Map::new(),
+    };
+    report.record(
+        "config",
+        config
+            .policies
+            .is_empty()
+            .then(|| "no policies configured".to_string()),
+    );
+    if classifier {
+        let failure = check_classifiers(config, Duration::from_millis(timeout_ms)).await;
+        report.record("classifier", failure);
+    }
+    if upstreams {
+        report.record("upstreams", check_upstreams(config));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CircuitBreakerConfig, ClassifierConfig, ClassifierType, Llm, Policy};
+
+    #[test]
+    fn test_classifier_urls() {
+        let policy = |name: &str, url: &str| Policy {
+            name: name.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let mut config = RouterConfig {
+            policies: vec![
+                policy("a", "http://triton:8000/v2/models/task_router/infer"),
+                policy("b", "http://triton:8000/v2/models/task_router/infer"),
+                Policy {
+                    classifier: Some(ClassifierConfig {
+                        kind: ClassifierType::Local,
+                        ..Default::default()
+                    }),
+                    ..policy("c", "http://other:8000/v2/models/task_router/infer")
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            classifier_urls(&config).into_keys().collect::<Vec<_>>(),
+            vec!["http://triton:8000/v2/models/task_router/infer"]
+        );
+        config.synthetic_classifier = Some(Default::default());
+        assert!(classifier_urls(&config).is_empty());
+    }
+
+    #[test]
+    fn test_upstreams_need_one_closed_breaker() {
+        let breaker_config = CircuitBreakerConfig {
+            window_size: 1,
+            min_requests: 1,
+            failure_rate_threshold: 1.0,
+            open_seconds: 60,
+        };
+        let config = RouterConfig {
+            policies: vec![Policy {
+                name: "readiness_test".to_string(),
+                llms: vec![
+                    Llm {
+                        name: "first".to_string(),
+                        ..Default::default()
+                    },
+                    Llm {
+                        name: "second".to_string(),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            circuit_breaker: Some(breaker_config.clone()),
+            ..Default::default()
+        };
+        assert_eq!(check_upstreams(&config), None);
+
+        breaker::record("readiness_test", "first", &breaker_config, true);
+        assert!(breaker::is_open("readiness_test", "first"));
+        assert_eq!(check_upstreams(&config), None);
+
+        breaker::record("readiness_test", "second", &breaker_config, true);
+        assert_eq!(
+            check_upstreams(&config).as_deref(),
+            Some("no healthy LLM in policy: readiness_test")
+        );
+    }
+}
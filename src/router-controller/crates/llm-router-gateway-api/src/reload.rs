@@ -0,0 +1,109 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reload
+//!
+//! Swaps a changed configuration from a watched source into the running
+//! gateway. A configuration that does not load is refused and the running
+//! one is kept. Settings only read at startup, such as `listeners`,
+//! `upstream_pool` and `state_store`, still need a restart.
+use crate::config::{RouterConfig, SharedConfig};
+use crate::controller;
+use crate::local_classifier;
+use crate::metrics::CONFIG_RELOADS;
+use crate::privacy;
+use crate::tokenizer;
+use log::{error, info};
+
+pub const RESULT_APPLIED: &str = "applied";
+pub const RESULT_REJECTED: &str = "rejected";
+
+/// Replaces the live configuration with `content` from `source`, its
+/// `${NAME}` references resolved by `lookup`, keeping the policies of
+/// `RouterPolicy` resources. Whether it was applied.
+pub fn apply(
+    shared: &SharedConfig,
+    source: &str,
+    content: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> bool {
+    let loaded = RouterConfig::from_yaml(content, lookup).map_err(|e| e.to_string());
+    let updated = loaded.and_then(|config| {
+        shared
+            .update(|live| {
+                *live = config;
+                controller::merge(live);
+                Ok(())
+            })
+            .map_err(|e| e.to_string())
+    });
+    match updated {
+        Ok(config) => {
+            privacy::set_enabled(config.privacy_mode);
+            local_classifier::preload(&config.policies);
+            tokenizer::preload(&config.policies);
+            info!("Reloaded configuration from {}", source);
+            CONFIG_RELOADS
+                .with_label_values(&[source, RESULT_APPLIED])
+                .inc();
+            true
+        }
+        Err(e) => {
+            error!(
+                "Kept the running configuration; {} has an invalid one: {}",
+                source, e
+            );
+            CONFIG_RELOADS
+                .with_label_values(&[source, RESULT_REJECTED])
+                .inc();
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_apply() {
+        let shared = SharedConfig::new(RouterConfig::default(), None);
+        let content = "policies:\n  - name: task_router\n    url: ''\n    llms:\n      - name: Brainstorming\n        api_base: http://nim:8000\n        api_key: ${NIM_KEY}\n        model: meta/llama\n";
+
+        assert!(!apply(&shared, "test", content, |_| None));
+        assert!(shared.snapshot().policies.is_empty());
+
+        let lookup = |name: &str| (name == "NIM_KEY").then(|| "secret".to_string());
+        assert!(apply(&shared, "test", content, lookup));
+        assert_eq!(shared.snapshot().policies[0].llms[0].api_key, "secret");
+    }
+
+    #[tokio::test]
+    async fn test_apply_api_key_file() {
+        let shared = SharedConfig::new(RouterConfig::default(), None);
+        let key_file = std::env::temp_dir().join("reload_test_api_key");
+        let content = format!(
+            "policies:\n  - name: task_router\n    url: ''\n    llms:\n      - name: Brainstorming\n        api_base: http://nim:8000\n        api_key_file: {}\n        model: meta/llama\n",
+            key_file.display()
+        );
+
+        assert!(!apply(&shared, "test", &content, |_| None));
+
+        std::fs::write(&key_file, "from-file\n").unwrap();
+        assert!(apply(&shared, "test", &content, |_| None));
+        assert_eq!(shared.snapshot().policies[0].llms[0].api_key, "from-file");
+        std::fs::remove_file(key_file).unwrap();
+    }
+}
@@ -0,0 +1,447 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Remote
+//!
+//! Configuration kept in etcd or Consul KV, shared by every replica: the
+//! YAML configuration is stored under `<prefix>/config.yaml` and reloaded
+//! by each gateway as soon as the key changes. A local file stands in when
+//! the store cannot be read at startup.
+use crate::config::{RouterConfig, SharedConfig};
+use crate::reload;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use clap::ValueEnum;
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// How long a watch waits for a change before asking again.
+const WAIT: Duration = Duration::from_secs(300);
+/// Pause after a failed read of the store.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Backend {
+    Etcd,
+    Consul,
+}
+
+impl Backend {
+    /// `source` label of reloads from the backend.
+    fn source(self) -> &'static str {
+        match self {
+            Backend::Etcd => "etcd",
+            Backend::Consul => "consul",
+        }
+    }
+}
+
+/// Where the configuration is stored.
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub backend: Backend,
+    /// Base URL of the store's HTTP API, e.g. `http://etcd:2379`.
+    pub endpoint: String,
+    /// Prefix of the configuration key.
+    pub prefix: String,
+}
+
+/// The stored configuration, if the key exists, as of a version of the
+/// store.
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    content: Option<String>,
+    version: u64,
+}
+
+#[derive(Deserialize)]
+struct EtcdHeader {
+    #[serde(default)]
+    revision: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EtcdKv {
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(default)]
+    mod_revision: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EtcdRange {
+    header: EtcdHeader,
+    #[serde(default)]
+    kvs: Vec<EtcdKv>,
+}
+
+#[derive(Deserialize)]
+struct EtcdEvent {
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+    kv: EtcdKv,
+}
+
+#[derive(Deserialize)]
+struct EtcdWatchResult {
+    #[serde(default)]
+    events: Vec<EtcdEvent>,
+}
+
+#[derive(Deserialize)]
+struct EtcdWatch {
+    result: Option<EtcdWatchResult>,
+}
+
+fn revision(value: Option<&str>) -> u64 {
+    value.and_then(|v| v.parse().ok()).unwrap_or_default()
+}
+
+fn decode(value: &str) -> Result<String, String> {
+    let bytes = STANDARD.decode(value).map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+impl Source {
+    pub fn key(&self) -> String {
+        format!("{}/config.yaml", self.prefix.trim_end_matches('/'))
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.endpoint.trim_end_matches('/'), path)
+    }
+
+    /// Reads the configuration, or waits up to `WAIT` for it to change
+    /// after `version` if given. `None` when it did not change in time.
+    async fn read(
+        &self,
+        client: &reqwest::Client,
+        version: Option<u64>,
+    ) -> Result<Option<Snapshot>, String> {
+        match self.backend {
+            Backend::Etcd => match version {
+                None => self.etcd_range(client).await.map(Some),
+                Some(version) => self.etcd_watch(client, version).await,
+            },
+            Backend::Consul => self.consul_get(client, version).await,
+        }
+    }
+
+    async fn etcd_range(&self, client: &reqwest::Client) -> Result<Snapshot, String> {
+        let range: EtcdRange = client
+            .post(self.url("/v3/kv/range"))
+            .json(&json!({ "key": STANDARD.encode(self.key()) }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        let content = match range.kvs.first().and_then(|kv| kv.value.as_deref()) {
+            Some(value) => Some(decode(value)?),
+            None => None,
+        };
+        Ok(Snapshot {
+            content,
+            version: revision(range.header.revision.as_deref()),
+        })
+    }
+
+    async fn etcd_watch(
+        &self,
+        client: &reqwest::Client,
+        version: u64,
+    ) -> Result<Option<Snapshot>, String> {
+        let request = json!({
+            "create_request": {
+                "key": STANDARD.encode(self.key()),
+                "start_revision": (version + 1).to_string(),
+            }
+        });
+        let mut response = client
+            .post(self.url("/v3/watch"))
+            .json(&request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+        let watch = async {
+            // The watch answers with one JSON object per line.
+            let mut buffer = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+                buffer.extend_from_slice(&chunk);
+                while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    let message: EtcdWatch =
+                        serde_json::from_slice(&line).map_err(|e| e.to_string())?;
+                    let Some(event) = message.result.and_then(|r| r.events.into_iter().last())
+                    else {
+                        continue;
+                    };
+                    let content = match (event.kind.as_deref(), event.kv.value.as_deref()) {
+                        (Some("DELETE"), _) | (_, None) => None,
+                        (_, Some(value)) => Some(decode(value)?),
+                    };
+                    return Ok(Some(Snapshot {
+                        content,
+                        version: revision(event.kv.mod_revision.as_deref()),
+                    }));
+                }
+            }
+            Err("etcd closed the watch".to_string())
+        };
+        tokio::time::timeout(WAIT, watch).await.unwrap_or(Ok(None))
+    }
+
+    async fn consul_get(
+        &self,
+        client: &reqwest::Client,
+        version: Option<u64>,
+    ) -> Result<Option<Snapshot>, String> {
+        let mut request = client.get(self.url(&format!("/v1/kv/{}", self.key())));
+        request = match version {
+            Some(version) => request.query(&[
+                ("raw", String::new()),
+                ("index", version.to_string()),
+                ("wait", format!("{}s", WAIT.as_secs())),
+            ]),
+            None => request.query(&[("raw", "")]),
+        };
+        if let Ok(token) = std::env::var("CONSUL_HTTP_TOKEN") {
+            request = request.header("X-Consul-Token", token);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let index = revision(
+            response
+                .headers()
+                .get("X-Consul-Index")
+                .and_then(|v| v.to_str().ok()),
+        );
+        let content = match response.status() {
+            reqwest::StatusCode::NOT_FOUND => None,
+            _ => Some(
+                response
+                    .error_for_status()
+                    .map_err(|e| e.to_string())?
+                    .text()
+                    .await
+                    .map_err(|e| e.to_string())?,
+            ),
+        };
+        if version == Some(index) {
+            return Ok(None);
+        }
+        Ok(Some(Snapshot {
+            content,
+            version: index,
+        }))
+    }
+}
+
+/// What the watch started by `spawn` picks up from.
+pub struct Watch {
+    client: reqwest::Client,
+    snapshot: Option<Snapshot>,
+}
+
+/// Reads the configuration once, for startup, from the store or, when it
+/// cannot be read there, from `fallback`.
+pub async fn load(
+    source: &Source,
+    fallback: Option<&str>,
+) -> Result<(RouterConfig, Watch), String> {
+    let client = reqwest::Client::new();
+    let read = source.read(&client, None).await.and_then(|snapshot| {
+        snapshot
+            .filter(|snapshot| snapshot.content.is_some())
+            .ok_or_else(|| format!("key '{}' does not exist", source.key()))
+    });
+    match (read, fallback) {
+        (Ok(snapshot), _) => {
+            let content = snapshot.content.as_deref().unwrap_or_default();
+            let config = RouterConfig::from_yaml(content, |name| std::env::var(name).ok())
+                .map_err(|e| e.to_string())?;
+            info!(
+                "Loaded configuration from {} key '{}'",
+                source.backend.source(),
+                source.key()
+            );
+            let snapshot = Some(snapshot);
+            Ok((config, Watch { client, snapshot }))
+        }
+        (Err(e), Some(path)) => {
+            warn!(
+                "Cannot read the configuration from {} ({}); falling back to {}",
+                source.backend.source(),
+                e,
+                path
+            );
+            let config = RouterConfig::load_config(path).map_err(|e| e.to_string())?;
+            let snapshot = None;
+            Ok((config, Watch { client, snapshot }))
+        }
+        (Err(e), None) => Err(format!("{}: {}", source.backend.source(), e)),
+    }
+}
+
+/// Watches the configuration key of `source`, reloading `shared` when it
+/// changes. Deleting the key keeps the running configuration.
+pub fn spawn(source: Source, watch: Watch, shared: SharedConfig) {
+    let Watch { client, snapshot } = watch;
+    tokio::spawn(async move {
+        info!(
+            "Watching {} key '{}' for configuration changes",
+            source.backend.source(),
+            source.key()
+        );
+        let mut version = snapshot.as_ref().map(|snapshot| snapshot.version);
+        // Content last applied, so changes back and forth do not reload.
+        let mut applied = snapshot.and_then(|snapshot| snapshot.content);
+        loop {
+            let snapshot = match source.read(&client, version).await {
+                Ok(Some(snapshot)) => snapshot,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("{} watch failed, retrying: {}", source.backend.source(), e);
+                    // Start over from a fresh read, as the version watched
+                    // from may have been compacted away.
+                    version = None;
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            version = Some(snapshot.version);
+            match snapshot.content {
+                None => warn!(
+                    "{} key '{}' is missing; keeping the running configuration",
+                    source.backend.source(),
+                    source.key()
+                ),
+                Some(content) if applied.as_ref() != Some(&content) => {
+                    reload::apply(&shared, source.backend.source(), &content, |name| {
+                        std::env::var(name).ok()
+                    });
+                    applied = Some(content);
+                }
+                Some(_) => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const CONFIG: &str = "policies: []\n";
+
+    fn source(backend: Backend, endpoint: String) -> Source {
+        Source {
+            backend,
+            endpoint,
+            prefix: "llm-router/prod/".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_etcd() {
+        let server = MockServer::start().await;
+        let key = STANDARD.encode("llm-router/prod/config.yaml");
+        Mock::given(method("POST"))
+            .and(path("/v3/kv/range"))
+            .and(body_partial_json(json!({ "key": key })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "header": { "revision": "7" },
+                "kvs": [{ "value": STANDARD.encode(CONFIG), "mod_revision": "5" }]
+            })))
+            .mount(&server)
+            .await;
+        let watch = [
+            json!({ "result": { "created": true } }),
+            json!({ "result": { "events": [{
+                "kv": { "value": STANDARD.encode("policies: [1]\n"), "mod_revision": "9" }
+            }] } }),
+        ]
+        .map(|message| message.to_string() + "\n")
+        .concat();
+        Mock::given(method("POST"))
+            .and(path("/v3/watch"))
+            .and(body_partial_json(
+                json!({ "create_request": { "start_revision": "8" } }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_string(watch))
+            .mount(&server)
+            .await;
+
+        let source = source(Backend::Etcd, server.uri());
+        let client = reqwest::Client::new();
+        let snapshot = source.read(&client, None).await.unwrap().unwrap();
+        assert_eq!(snapshot.content.as_deref(), Some(CONFIG));
+        assert_eq!(snapshot.version, 7);
+        let changed = source.read(&client, Some(7)).await.unwrap().unwrap();
+        assert_eq!(changed.content.as_deref(), Some("policies: [1]\n"));
+        assert_eq!(changed.version, 9);
+    }
+
+    #[tokio::test]
+    async fn test_consul() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/kv/llm-router/prod/config.yaml"))
+            .and(query_param("index", "12"))
+            .respond_with(ResponseTemplate::new(200).insert_header("X-Consul-Index", "12"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/kv/llm-router/prod/config.yaml"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("X-Consul-Index", "12")
+                    .set_body_string(CONFIG),
+            )
+            .mount(&server)
+            .await;
+
+        let source = source(Backend::Consul, server.uri());
+        let client = reqwest::Client::new();
+        let snapshot = source.read(&client, None).await.unwrap().unwrap();
+        assert_eq!(snapshot.content.as_deref(), Some(CONFIG));
+        assert_eq!(snapshot.version, 12);
+        assert_eq!(source.read(&client, Some(12)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_load_fallback() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404).insert_header("X-Consul-Index", "3"))
+            .mount(&server)
+            .await;
+        let source = source(Backend::Consul, server.uri());
+        assert!(load(&source, None).await.is_err());
+
+        let fallback = std::env::temp_dir().join("remote_fallback_config.yaml");
+        std::fs::write(&fallback, CONFIG).unwrap();
+        let (config, watch) = load(&source, fallback.to_str()).await.unwrap();
+        assert!(config.policies.is_empty());
+        assert_eq!(watch.snapshot, None);
+        std::fs::remove_file(fallback).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Residency
+use crate::config::{Llm, Policy, Tenant};
+use crate::error::GatewayApiError;
+use crate::events;
+use crate::request_context::RequestContext;
+use http::{HeaderMap, StatusCode};
+use log::warn;
+use serde_json::json;
+
+/// Request header carrying a comma separated list of regions the caller's
+/// data may be processed in.
+pub const DATA_RESIDENCY_HEADER: &str = "X-Data-Residency";
+
+fn requested_regions(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(DATA_RESIDENCY_HEADER)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|region| region.trim().to_lowercase())
+        .filter(|region| !region.is_empty())
+        .collect()
+}
+
+fn region_allowed(region: &str, allowed: &[String]) -> bool {
+    allowed
+        .iter()
+        .any(|a| a.trim().eq_ignore_ascii_case(region))
+}
+
+/// Refuses to route to `llm` when its region is outside the policy's
+/// `data_residency` list, the tenant's, or the regions requested by the
+/// caller. Refusals are written to the `audit` log target.
+pub fn check(
+    policy: &Policy,
+    llm: &Llm,
+    tenant: Option<&Tenant>,
+    headers: &HeaderMap,
+    context: &RequestContext,
+) -> Result<(), GatewayApiError> {
+    let requested = requested_regions(headers);
+    let tenant_regions = tenant.map_or(&[][..], |tenant| &tenant.data_residency);
+    if policy.data_residency.is_empty() && tenant_regions.is_empty() && requested.is_empty() {
+        return Ok(());
+    }
+
+    let region = llm.region.as_deref().unwrap_or("").trim();
+    let permitted = |allowed: &[String]| allowed.is_empty() || region_allowed(region, allowed);
+    if permitted(&policy.data_residency) && permitted(tenant_regions) && permitted(&requested) {
+        return Ok(());
+    }
+
+    warn!(
+        target: "audit",
+        "data residency refusal: policy={} llm={} llm_region={} policy_regions={:?} tenant_regions={:?} requested_regions={:?}",
+        policy.name,
+        llm.name,
+        if region.is_empty() { "unset" } else { region },
+        policy.data_residency,
+        tenant_regions,
+        requested
+    );
+
+    events::publish(
+        "data_residency_refusal",
+        context,
+        json!({
+            "policy": policy.name,
+            "llm": llm.name,
+            "llm_region": region,
+            "policy_regions": policy.data_residency,
+            "tenant_regions": tenant_regions,
+            "requested_regions": requested,
+        }),
+    );
+
+    Err(GatewayApiError::client_error(
+        StatusCode::FORBIDDEN,
+        format!(
+            "LLM '{}' in policy '{}' is outside the allowed data residency regions",
+            llm.name, policy.name
+        ),
+        "data_residency_violation",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn llm_in(region: Option<&str>) -> Llm {
+        Llm {
+            name: "test".to_string(),
+            region: region.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_policy_residency() {
+        let policy = Policy {
+            name: "eu_only".to_string(),
+            data_residency: vec!["eu".to_string()],
+            ..Default::default()
+        };
+        let headers = HeaderMap::new();
+        let context = RequestContext::default();
+        assert!(check(&policy, &llm_in(Some("EU")), None, &headers, &context).is_ok());
+        assert!(check(&policy, &llm_in(Some("us")), None, &headers, &context).is_err());
+        assert!(check(&policy, &llm_in(None), None, &headers, &context).is_err());
+    }
+
+    #[test]
+    fn test_requested_residency() {
+        let policy = Policy::default();
+        let mut headers = HeaderMap::new();
+        headers.insert(DATA_RESIDENCY_HEADER, HeaderValue::from_static("us, ca"));
+        let context = RequestContext::default();
+        assert!(check(&policy, &llm_in(Some("ca")), None, &headers, &context).is_ok());
+        assert!(check(&policy, &llm_in(Some("eu")), None, &headers, &context).is_err());
+        assert!(check(
+            &policy,
+            &llm_in(Some("eu")),
+            None,
+            &HeaderMap::new(),
+            &context
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_tenant_residency() {
+        let policy = Policy::default();
+        let tenant = Tenant {
+            id: "eu_team".to_string(),
+            data_residency: vec!["eu".to_string()],
+            ..Default::default()
+        };
+        let context = RequestContext::default();
+        let headers = HeaderMap::new();
+        assert!(check(
+            &policy,
+            &llm_in(Some("eu")),
+            Some(&tenant),
+            &headers,
+            &context
+        )
+        .is_ok());
+        assert!(check(
+            &policy,
+            &llm_in(Some("us")),
+            Some(&tenant),
+            &headers,
+            &context
+        )
+        .is_err());
+
+        // The header can narrow the tenant's regions, never widen them.
+        let mut headers = HeaderMap::new();
+        headers.insert(DATA_RESIDENCY_HEADER, HeaderValue::from_static("us"));
+        assert!(check(
+            &policy,
+            &llm_in(Some("us")),
+            Some(&tenant),
+            &headers,
+            &context
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,201 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retry
+use crate::config::{Provider, RetryConfig};
+use crate::metrics::{record_retry, RequestLabels};
+use crate::retryability::{self, ErrorClass};
+use http_body_util::BodyExt;
+use log::warn;
+use rand::Rng;
+use reqwest::{RequestBuilder, Response};
+use std::time::Duration;
+
+/// Backoff before retry number `retry` (starting at 1), with jitter drawn
+/// from the upper half of the exponential delay.
+pub fn backoff(config: &RetryConfig, retry: u32) -> Duration {
+    let exponential = config.initial_backoff_ms as f64
+        * config
+            .multiplier
+            .max(1.0)
+            .powi(retry.saturating_sub(1) as i32);
+    let capped = exponential.min(config.max_backoff_ms as f64);
+    let jittered = rand::thread_rng().gen_range(capped / 2.0..=capped.max(f64::MIN_POSITIVE));
+    Duration::from_millis(jittered as u64)
+}
+
+/// Buffers the body of an error response to look it up in the
+/// retryability table. The returned response carries the same body, with the
+/// `ErrorClass` (if any) in its extensions.
+pub async fn classify(response: Response, provider: Provider) -> Result<Response, reqwest::Error> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let (mut parts, body) = http::Response::from(response).into_parts();
+    let body = body.collect().await?.to_bytes();
+    if let Some(class) = retryability::classify(provider, parts.status.as_u16(), &body) {
+        parts.extensions.insert(class);
+    }
+    Ok(Response::from(http::Response::from_parts(parts, body)))
+}
+
+fn retry_reason(config: &RetryConfig, result: &Result<Response, reqwest::Error>) -> Option<String> {
+    match result {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let retryable = match response.extensions().get::<ErrorClass>() {
+                Some(class) => *class == ErrorClass::Retryable,
+                None => config.retry_on_status.contains(&status),
+            };
+            retryable.then(|| status.to_string())
+        }
+        Err(e) if e.is_connect() => Some("connection_error".to_string()),
+        Err(_) => None,
+    }
+}
+
+async fn send_once(
+    request: RequestBuilder,
+    provider: Provider,
+) -> Result<Response, reqwest::Error> {
+    classify(request.send().await?, provider).await
+}
+
+/// Sends `request`, retrying per `config`. Error responses the provider's
+/// retryability table marks as retryable are retried even when their status
+/// is not in `retry_on_status`, and ones it marks otherwise never are.
+/// Without a retry policy, or when the body cannot be cloned, the request is
+/// sent once. The last attempt's result is returned as is, classified.
+pub async fn send(
+    request: RequestBuilder,
+    config: Option<&RetryConfig>,
+    labels: &RequestLabels,
+    provider: Provider,
+) -> Result<Response, reqwest::Error> {
+    let Some(config) = config.filter(|c| c.max_attempts > 1) else {
+        return send_once(request, provider).await;
+    };
+
+    let mut attempt = 1;
+    loop {
+        let Some(next) = request.try_clone() else {
+            return send_once(request, provider).await;
+        };
+        let result = send_once(next, provider).await;
+        if attempt >= config.max_attempts {
+            return result;
+        }
+        let Some(reason) = retry_reason(config, &result) else {
+            return result;
+        };
+
+        let delay = backoff(config, attempt);
+        warn!(
+            "Retrying upstream call: model={} attempt={} reason={} backoff_ms={}",
+            labels.values()[1],
+            attempt,
+            reason,
+            delay.as_millis()
+        );
+        record_retry(labels, &reason);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let config = RetryConfig::default();
+        let first = backoff(&config, 1).as_millis();
+        assert!((50..=100).contains(&first));
+        let third = backoff(&config, 3).as_millis();
+        assert!((200..=400).contains(&third));
+        let capped = backoff(&config, 20).as_millis();
+        assert!((1000..=2000).contains(&capped));
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let config = RetryConfig {
+            initial_backoff_ms: 1,
+            ..Default::default()
+        };
+        let request = reqwest::Client::new().post(server.uri()).body("{}");
+        let response = send(
+            request,
+            Some(&config),
+            &RequestLabels::default(),
+            Provider::OpenAi,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failover_errors_are_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).set_body_json(serde_json::json!({
+                "error": {"type": "insufficient_quota", "code": "insufficient_quota"}
+            })))
+            .mount(&server)
+            .await;
+
+        let config = RetryConfig {
+            initial_backoff_ms: 1,
+            retry_on_status: vec![429],
+            ..Default::default()
+        };
+        let request = reqwest::Client::new().post(server.uri()).body("{}");
+        let response = send(
+            request,
+            Some(&config),
+            &RequestLabels::default(),
+            Provider::OpenAi,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), 429);
+        assert_eq!(
+            response.extensions().get::<ErrorClass>(),
+            Some(&ErrorClass::Failover)
+        );
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("insufficient_quota"));
+    }
+}
@@ -0,0 +1,146 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Routes
+//!
+//! Policies chosen by where a request arrives rather than by its body, so
+//! an application is moved to the router by changing its OpenAI base URL,
+//! e.g. to `http://router/search/v1`.
+use crate::config::Route;
+use http::header::HOST;
+use hyper::{Request, Uri};
+use log::{info, warn};
+
+/// Policy of the route a request matched, attached as a request extension.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutedPolicy(pub String);
+
+/// `path` without `prefix`, if it lies under it.
+fn strip_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(prefix.trim_end_matches('/'))?;
+    match rest {
+        "" => Some("/"),
+        rest if rest.starts_with('/') => Some(rest),
+        _ => None,
+    }
+}
+
+fn matches<B>(route: &Route, req: &Request<B>) -> bool {
+    if route
+        .path_prefix
+        .as_ref()
+        .is_some_and(|prefix| strip_prefix(req.uri().path(), prefix).is_none())
+    {
+        return false;
+    }
+    if let Some(host) = &route.host {
+        let actual = req
+            .headers()
+            .get(HOST)
+            .and_then(|value| value.to_str().ok())
+            .or_else(|| req.uri().authority().map(|authority| authority.as_str()))
+            .map(|value| value.rsplit_once(':').map_or(value, |(host, _)| host));
+        if !actual.is_some_and(|actual| actual.eq_ignore_ascii_case(host)) {
+            return false;
+        }
+    }
+    route.headers.iter().all(|(name, value)| {
+        req.headers()
+            .get(name.as_str())
+            .is_some_and(|actual| actual.as_bytes() == value.as_bytes())
+    })
+}
+
+/// Finds the first of `routes` matching `req`, removes its path prefix from
+/// the URI and records its policy on the request.
+pub fn apply<B>(routes: &[Route], req: &mut Request<B>) {
+    let Some(route) = routes.iter().find(|route| matches(route, req)) else {
+        return;
+    };
+    if let Some(prefix) = &route.path_prefix {
+        let path = strip_prefix(req.uri().path(), prefix).unwrap_or("/");
+        let path_and_query = match req.uri().query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path.to_string(),
+        };
+        match path_and_query.parse::<Uri>() {
+            Ok(uri) => *req.uri_mut() = uri,
+            Err(e) => warn!("Kept the path of a request to route '{}': {}", prefix, e),
+        }
+    }
+    info!("Request routed to policy '{}'", route.policy);
+    req.extensions_mut()
+        .insert(RoutedPolicy(route.policy.clone()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn routes() -> Vec<Route> {
+        vec![
+            Route {
+                path_prefix: Some("/search/".to_string()),
+                policy: "task_router".to_string(),
+                ..Default::default()
+            },
+            Route {
+                host: Some("chat.example.com".to_string()),
+                headers: BTreeMap::from([("x-app".to_string(), "support".to_string())]),
+                policy: "complexity_router".to_string(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn routed<B>(req: &Request<B>) -> Option<&str> {
+        req.extensions()
+            .get::<RoutedPolicy>()
+            .map(|policy| policy.0.as_str())
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut req = Request::post("/search/v1/chat/completions?debug=1")
+            .body(())
+            .unwrap();
+        apply(&routes(), &mut req);
+        assert_eq!(routed(&req), Some("task_router"));
+        assert_eq!(req.uri().to_string(), "/v1/chat/completions?debug=1");
+
+        let mut req = Request::post("/searches/v1/chat/completions")
+            .body(())
+            .unwrap();
+        apply(&routes(), &mut req);
+        assert_eq!(routed(&req), None);
+
+        let mut req = Request::post("/v1/chat/completions")
+            .header("host", "Chat.Example.com:8084")
+            .header("x-app", "support")
+            .body(())
+            .unwrap();
+        apply(&routes(), &mut req);
+        assert_eq!(routed(&req), Some("complexity_router"));
+        assert_eq!(req.uri().path(), "/v1/chat/completions");
+
+        let mut req = Request::post("/v1/chat/completions")
+            .header("host", "chat.example.com")
+            .body(())
+            .unwrap();
+        apply(&routes(), &mut req);
+        assert_eq!(routed(&req), None);
+    }
+}
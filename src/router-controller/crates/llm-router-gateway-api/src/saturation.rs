@@ -0,0 +1,178 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Saturation
+//!
+//! Upstream LLMs that answered `429`. An LLM is left out of the fallback
+//! chain until the reset time its provider announced in `Retry-After` or
+//! the `x-ratelimit-reset-*` headers, so its siblings and fallbacks serve
+//! the traffic meanwhile.
+use crate::config::SaturationConfig;
+use crate::metrics::UPSTREAM_RATE_LIMITS;
+use crate::store;
+use http::HeaderMap;
+use log::warn;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn key(policy: &str, llm: &str) -> String {
+    format!("saturation:{}:{}", policy, llm)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// A duration written as OpenAI's reset headers do, such as `20ms`, `6s`
+/// or `1m30.5s`. A bare number is seconds.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    if let Ok(seconds) = text.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let mut total_ms = 0.0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .filter(|&end| end > 0)?;
+        let value: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        total_ms += value
+            * match &rest[..unit] {
+                "ms" => 1.0,
+                "s" => 1_000.0,
+                "m" => 60_000.0,
+                "h" => 3_600_000.0,
+                _ => return None,
+            };
+        rest = &rest[unit..];
+    }
+    Some(Duration::from_millis(total_ms.round() as u64))
+}
+
+/// How long the provider asks to wait, from the headers of a `429`: the
+/// longest of `retry-after-ms`, `Retry-After` in seconds or as an HTTP
+/// date, and the `x-ratelimit-reset-requests` and `-tokens` durations.
+pub fn cooldown(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let retry_after = header("retry-after").and_then(|value| {
+        parse_duration(value).or_else(|| {
+            let at = httpdate::parse_http_date(value).ok()?;
+            Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+        })
+    });
+    let retry_after_ms = header("retry-after-ms")
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|ms| ms.is_finite() && *ms >= 0.0)
+        .map(|ms| Duration::from_millis(ms.round() as u64));
+    let resets = ["x-ratelimit-reset-requests", "x-ratelimit-reset-tokens"]
+        .into_iter()
+        .filter_map(|name| header(name).and_then(parse_duration));
+    [retry_after, retry_after_ms]
+        .into_iter()
+        .flatten()
+        .chain(resets)
+        .max()
+}
+
+/// Counts a `429` from `llm` and, when `config` is set, keeps the LLM out
+/// of the chain for the cool-down its headers ask for.
+pub fn record(policy: &str, llm: &str, headers: &HeaderMap, config: Option<&SaturationConfig>) {
+    UPSTREAM_RATE_LIMITS.with_label_values(&[policy, llm]).inc();
+    let Some(config) = config else {
+        return;
+    };
+    let cooldown = cooldown(headers)
+        .unwrap_or(Duration::from_secs(config.default_cooldown_seconds))
+        .min(Duration::from_secs(config.max_cooldown_seconds));
+    if cooldown.is_zero() {
+        return;
+    }
+    warn!(
+        "LLM '{}' of policy '{}' is rate limited for {:.1}s",
+        llm,
+        policy,
+        cooldown.as_secs_f64()
+    );
+    let until_ms = now_ms().saturating_add(cooldown.as_millis() as u64);
+    let stored = store::get().set(
+        &key(policy, llm),
+        until_ms.to_string().as_bytes(),
+        Some(cooldown),
+    );
+    store::report("saturation", stored);
+}
+
+/// What is left of the cool-down of `llm`, if it is rate limited. LLMs are
+/// taken as available when the state store fails.
+pub fn remaining(policy: &str, llm: &str) -> Option<Duration> {
+    let value = store::report("saturation", store::get().get(&key(policy, llm)))??;
+    let until_ms: u64 = std::str::from_utf8(&value).ok()?.parse().ok()?;
+    let remaining = until_ms.checked_sub(now_ms())?;
+    (remaining > 0).then(|| Duration::from_millis(remaining))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_duration("6s"), Some(Duration::from_secs(6)));
+        assert_eq!(
+            parse_duration("1m30.5s"),
+            Some(Duration::from_millis(90_500))
+        );
+        assert_eq!(parse_duration("2"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration("5x"), None);
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(cooldown(&headers), None);
+        headers.insert("retry-after", HeaderValue::from_static("3"));
+        headers.insert("x-ratelimit-reset-tokens", HeaderValue::from_static("1m0s"));
+        assert_eq!(cooldown(&headers), Some(Duration::from_secs(60)));
+
+        let past = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(60));
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_str(&past).unwrap());
+        assert_eq!(cooldown(&headers), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_record() {
+        let config = SaturationConfig::default();
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after-ms", HeaderValue::from_static("30000"));
+        record("saturation_test", "Chatbot", &headers, Some(&config));
+        let left = remaining("saturation_test", "Chatbot").unwrap();
+        assert!(left > Duration::from_secs(29));
+        assert_eq!(remaining("saturation_test", "Other"), None);
+
+        record("saturation_test", "Unset", &headers, None);
+        assert_eq!(remaining("saturation_test", "Unset"), None);
+    }
+}
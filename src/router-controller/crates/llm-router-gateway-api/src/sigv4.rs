@@ -0,0 +1,542 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SigV4
+//!
+//! AWS Signature Version 4 request signing, and the credentials it signs
+//! with.
+use crate::config::BedrockConfig;
+use crate::error::GatewayApiError;
+use http::StatusCode;
+use lazy_static::lazy_static;
+use log::warn;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, RwLock};
+
+/// Temporary credentials are replaced this long before they expire.
+const EXPIRY_MARGIN: u64 = 300;
+/// Credentials of the shared credentials file, which carry no expiry, are
+/// read again after this long.
+const FILE_REFRESH_SECONDS: u64 = 300;
+const ECS_ENDPOINT: &str = "http://169.254.170.2";
+const IMDS_ENDPOINT: &str = "http://169.254.169.254";
+
+/// Characters SigV4 leaves unencoded, besides alphanumerics.
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+fn non_empty_env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// `[profile]` section of an AWS shared credentials file.
+fn from_credentials_file(content: &str, profile: &str) -> Option<Credentials> {
+    let mut in_profile = false;
+    let mut access_key_id = None;
+    let mut secret_access_key = None;
+    let mut session_token = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_profile = section.trim() == profile;
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_profile) else {
+            continue;
+        };
+        let value = Some(value.trim().to_string());
+        match key.trim() {
+            "aws_access_key_id" => access_key_id = value,
+            "aws_secret_access_key" => secret_access_key = value,
+            "aws_session_token" => session_token = value,
+            _ => {}
+        }
+    }
+    Some(Credentials {
+        access_key_id: access_key_id?,
+        secret_access_key: secret_access_key?,
+        session_token,
+    })
+}
+
+/// Credentials and the Unix time they expire at.
+type Provided = (Credentials, Option<u64>);
+
+struct Cached {
+    credentials: Credentials,
+    refresh_at: u64,
+}
+
+lazy_static! {
+    static ref CACHE: RwLock<Option<Cached>> = RwLock::new(None);
+    /// Held while credentials are fetched, so concurrent requests wait for
+    /// one fetch rather than each starting their own.
+    static ref REFRESH: Mutex<()> = Mutex::new(());
+    static ref CLIENT: reqwest::Client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .unwrap_or_default();
+}
+
+async fn get_json(request: reqwest::RequestBuilder, source: &str) -> Option<Value> {
+    let response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    match response {
+        Ok(response) => response.json().await.ok(),
+        Err(e) => {
+            warn!("AWS credentials from {} unavailable: {}", source, e);
+            None
+        }
+    }
+}
+
+/// `AccessKeyId`, `SecretAccessKey`, the session token under `token` and
+/// `Expiration`, as either Unix seconds or an ISO 8601 time.
+fn from_json(json: &Value, token: &str) -> Option<Provided> {
+    let field = |name: &str| json[name].as_str().map(str::to_string);
+    let expires = match &json["Expiration"] {
+        Value::Number(unix) => unix.as_f64().map(|unix| unix as u64),
+        Value::String(iso) => unix_from_iso(iso),
+        _ => None,
+    };
+    let credentials = Credentials {
+        access_key_id: field("AccessKeyId")?,
+        secret_access_key: field("SecretAccessKey")?,
+        session_token: field(token),
+    };
+    Some((credentials, expires))
+}
+
+/// The `AWS_PROFILE` (or `default`) profile of `AWS_SHARED_CREDENTIALS_FILE`
+/// or `~/.aws/credentials`.
+async fn from_profile() -> Option<Provided> {
+    let path = non_empty_env("AWS_SHARED_CREDENTIALS_FILE")
+        .or_else(|| non_empty_env("HOME").map(|home| format!("{}/.aws/credentials", home)))?;
+    let profile = non_empty_env("AWS_PROFILE").unwrap_or_else(|| "default".to_string());
+    let content = tokio::fs::read_to_string(path).await.ok()?;
+    from_credentials_file(&content, &profile).map(|credentials| (credentials, None))
+}
+
+/// `AssumeRoleWithWebIdentity` with the token of
+/// `AWS_WEB_IDENTITY_TOKEN_FILE`, as set up by EKS IAM roles for service
+/// accounts.
+async fn from_web_identity() -> Option<Provided> {
+    let token_file = non_empty_env("AWS_WEB_IDENTITY_TOKEN_FILE")?;
+    let role_arn = non_empty_env("AWS_ROLE_ARN")?;
+    let session_name =
+        non_empty_env("AWS_ROLE_SESSION_NAME").unwrap_or_else(|| "llm-router".to_string());
+    let token = tokio::fs::read_to_string(&token_file).await.ok()?;
+    let endpoint = match non_empty_env("AWS_REGION").or_else(|| non_empty_env("AWS_DEFAULT_REGION"))
+    {
+        Some(region) => format!("https://sts.{}.amazonaws.com/", region),
+        None => "https://sts.amazonaws.com/".to_string(),
+    };
+    let request = CLIENT
+        .get(endpoint)
+        .header(ACCEPT, "application/json")
+        .query(&[
+            ("Action", "AssumeRoleWithWebIdentity"),
+            ("Version", "2011-06-15"),
+            ("RoleArn", role_arn.as_str()),
+            ("RoleSessionName", session_name.as_str()),
+            ("WebIdentityToken", token.trim()),
+        ]);
+    let response = get_json(request, "web identity").await?;
+    let result = &response["AssumeRoleWithWebIdentityResponse"]["AssumeRoleWithWebIdentityResult"];
+    from_json(&result["Credentials"], "SessionToken")
+}
+
+/// The ECS (or EKS Pod Identity) container credentials endpoint.
+async fn from_container() -> Option<Provided> {
+    let url = match non_empty_env("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI") {
+        Some(relative) => format!("{}{}", ECS_ENDPOINT, relative),
+        None => non_empty_env("AWS_CONTAINER_CREDENTIALS_FULL_URI")?,
+    };
+    let authorization = match non_empty_env("AWS_CONTAINER_AUTHORIZATION_TOKEN_FILE") {
+        Some(path) => tokio::fs::read_to_string(path)
+            .await
+            .ok()
+            .map(|token| token.trim().to_string()),
+        None => non_empty_env("AWS_CONTAINER_AUTHORIZATION_TOKEN"),
+    };
+    let mut request = CLIENT.get(url);
+    if let Some(authorization) = authorization {
+        request = request.header(AUTHORIZATION, authorization);
+    }
+    from_json(&get_json(request, "the container endpoint").await?, "Token")
+}
+
+/// The instance profile, from the EC2 instance metadata service (IMDSv2).
+async fn from_instance_metadata() -> Option<Provided> {
+    if non_empty_env("AWS_EC2_METADATA_DISABLED").is_some_and(|v| v.eq_ignore_ascii_case("true")) {
+        return None;
+    }
+    let endpoint = non_empty_env("AWS_EC2_METADATA_SERVICE_ENDPOINT")
+        .unwrap_or_else(|| IMDS_ENDPOINT.to_string());
+    let endpoint = endpoint.trim_end_matches('/');
+    let token = CLIENT
+        .put(format!("{}/latest/api/token", endpoint))
+        .header("x-aws-ec2-metadata-token-ttl-seconds", "21600")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+    let roles = format!("{}/latest/meta-data/iam/security-credentials/", endpoint);
+    let role = CLIENT
+        .get(&roles)
+        .header("x-aws-ec2-metadata-token", &token)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+    let role = role.lines().next()?.trim();
+    let request = CLIENT
+        .get(format!("{}{}", roles, role))
+        .header("x-aws-ec2-metadata-token", &token);
+    from_json(&get_json(request, "instance metadata").await?, "Token")
+}
+
+/// The first provider of the AWS default chain, after the environment,
+/// that has credentials.
+async fn provide() -> Option<Provided> {
+    if let Some(provided) = from_profile().await {
+        return Some(provided);
+    }
+    if let Some(provided) = from_web_identity().await {
+        return Some(provided);
+    }
+    if let Some(provided) = from_container().await {
+        return Some(provided);
+    }
+    from_instance_metadata().await
+}
+
+async fn cached(now: u64) -> Option<Credentials> {
+    let cache = CACHE.read().await;
+    cache
+        .as_ref()
+        .filter(|cached| now < cached.refresh_at)
+        .map(|cached| cached.credentials.clone())
+}
+
+/// Credentials from the LLM's config, else the environment, else the
+/// shared credentials file, web identity, container or instance metadata
+/// providers. Credentials of a provider are cached until shortly before
+/// they expire.
+pub async fn credentials(config: &BedrockConfig) -> Result<Credentials, GatewayApiError> {
+    if let (Some(access_key_id), Some(secret_access_key)) =
+        (&config.access_key_id, &config.secret_access_key)
+    {
+        return Ok(Credentials {
+            access_key_id: access_key_id.clone(),
+            secret_access_key: secret_access_key.clone(),
+            session_token: config.session_token.clone(),
+        });
+    }
+    if let (Some(access_key_id), Some(secret_access_key)) = (
+        non_empty_env("AWS_ACCESS_KEY_ID"),
+        non_empty_env("AWS_SECRET_ACCESS_KEY"),
+    ) {
+        return Ok(Credentials {
+            access_key_id,
+            secret_access_key,
+            session_token: non_empty_env("AWS_SESSION_TOKEN"),
+        });
+    }
+    if let Some(credentials) = cached(now()).await {
+        return Ok(credentials);
+    }
+    let _refreshing = REFRESH.lock().await;
+    // Another request may have fetched them while this one waited.
+    if let Some(credentials) = cached(now()).await {
+        return Ok(credentials);
+    }
+    let Some((credentials, expires)) = provide().await else {
+        return Err(GatewayApiError::LlmServiceError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: "No AWS credentials found for Bedrock".to_string(),
+            provider: "bedrock".to_string(),
+            details: None,
+        });
+    };
+    let refresh_at = match expires {
+        Some(expires) => expires.saturating_sub(EXPIRY_MARGIN),
+        None => now() + FILE_REFRESH_SECONDS,
+    };
+    *CACHE.write().await = Some(Cached {
+        credentials: credentials.clone(),
+        refresh_at,
+    });
+    Ok(credentials)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let key = PKey::hmac(key).expect("valid hmac key");
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).expect("valid hmac signer");
+    signer.update(data.as_bytes()).expect("hmac update");
+    signer.sign_to_vec().expect("hmac sign")
+}
+
+/// `YYYYMMDD'T'HHMMSS'Z'` for Unix seconds.
+fn amz_date(unix: u64) -> String {
+    let days = (unix / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let seconds = unix % 86_400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Unix seconds of an ISO 8601 UTC time such as `2024-01-31T12:00:00Z`.
+fn unix_from_iso(iso: &str) -> Option<u64> {
+    let number = |range: std::ops::Range<usize>| iso.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let mp = (month + 9) % 12;
+    let day_of_year = (153 * mp + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    u64::try_from(days * 86_400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// One request to sign. `path` is the already encoded request path.
+pub struct SigningRequest<'a> {
+    pub method: &'a str,
+    pub host: &'a str,
+    pub path: &'a str,
+    pub query: &'a str,
+    /// Extra headers to sign, lowercase names.
+    pub headers: &'a [(&'a str, &'a str)],
+    pub body: &'a [u8],
+}
+
+/// Headers to add to the request: `x-amz-date`, `x-amz-security-token`
+/// for temporary credentials, and `authorization`.
+pub fn sign(
+    request: &SigningRequest,
+    credentials: &Credentials,
+    region: &str,
+    service: &str,
+    unix: u64,
+) -> Vec<(&'static str, String)> {
+    let datetime = amz_date(unix);
+    let date = &datetime[..8];
+
+    let mut headers: Vec<(String, String)> = request
+        .headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.trim().to_string()))
+        .collect();
+    headers.push(("host".to_string(), request.host.to_string()));
+    headers.push(("x-amz-date".to_string(), datetime.clone()));
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    headers.sort();
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    // Every service but S3 encodes the path a second time.
+    let canonical_path: String = request
+        .path
+        .split('/')
+        .map(|segment| utf8_percent_encode(segment, UNRESERVED).to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method,
+        canonical_path,
+        request.query,
+        canonical_headers,
+        signed_headers,
+        hex(&openssl::sha::sha256(request.body))
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        datetime,
+        scope,
+        hex(&openssl::sha::sha256(canonical_request.as_bytes()))
+    );
+
+    let key = hmac(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date,
+    );
+    let key = hmac(&key, region);
+    let key = hmac(&key, service);
+    let key = hmac(&key, "aws4_request");
+    let signature = hex(&hmac(&key, &string_to_sign));
+
+    let mut signed = vec![("x-amz-date", datetime)];
+    if let Some(token) = &credentials.session_token {
+        signed.push(("x-amz-security-token", token.clone()));
+    }
+    signed.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    signed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_vanilla() {
+        // `get-vanilla` from the AWS SigV4 test suite.
+        let credentials = Credentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        };
+        let request = SigningRequest {
+            method: "GET",
+            host: "example.amazonaws.com",
+            path: "/",
+            query: "",
+            headers: &[],
+            body: b"",
+        };
+        let signed = sign(
+            &request,
+            &credentials,
+            "us-east-1",
+            "service",
+            1_440_938_160,
+        );
+        assert_eq!(signed[0], ("x-amz-date", "20150830T123600Z".to_string()));
+        assert_eq!(
+            signed[1].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+
+        let file = "[default]\naws_access_key_id = A\naws_secret_access_key = B\n\n[ci]\naws_access_key_id=C\naws_secret_access_key=D\naws_session_token=E\n";
+        assert_eq!(
+            from_credentials_file(file, "ci"),
+            Some(Credentials {
+                access_key_id: "C".to_string(),
+                secret_access_key: "D".to_string(),
+                session_token: Some("E".to_string()),
+            })
+        );
+        assert_eq!(from_credentials_file(file, "missing"), None);
+    }
+
+    #[tokio::test]
+    async fn test_container_credentials() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/creds"))
+            .and(header("authorization", "pod-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "AccessKeyId": "ASIA",
+                "SecretAccessKey": "secret",
+                "Token": "session",
+                "Expiration": "2015-08-30T12:36:00Z"
+            })))
+            .mount(&server)
+            .await;
+        std::env::set_var(
+            "AWS_CONTAINER_CREDENTIALS_FULL_URI",
+            format!("{}/creds", server.uri()),
+        );
+        std::env::set_var("AWS_CONTAINER_AUTHORIZATION_TOKEN", "pod-token");
+        let (credentials, expires) = from_container().await.unwrap();
+        std::env::remove_var("AWS_CONTAINER_CREDENTIALS_FULL_URI");
+        std::env::remove_var("AWS_CONTAINER_AUTHORIZATION_TOKEN");
+        assert_eq!(credentials.access_key_id, "ASIA");
+        assert_eq!(credentials.session_token.as_deref(), Some("session"));
+        assert_eq!(expires, Some(1_440_938_160));
+        assert_eq!(amz_date(expires.unwrap()), "20150830T123600Z");
+
+        let sts = serde_json::json!({
+            "AccessKeyId": "ASIA",
+            "SecretAccessKey": "secret",
+            "SessionToken": "session",
+            "Expiration": 1.44093816E9
+        });
+        let (credentials, expires) = from_json(&sts, "SessionToken").unwrap();
+        assert_eq!(credentials.session_token.as_deref(), Some("session"));
+        assert_eq!(expires, Some(1_440_938_160));
+    }
+}
@@ -0,0 +1,257 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SLO
+//!
+//! Availability and latency objectives per policy. Requests are counted in
+//! ten second buckets and burn rates are computed over each configured
+//! window: a burn rate of 1 spends the error budget exactly as fast as the
+//! objective allows.
+use crate::config::{Policy, RouterConfig, SharedConfig, SloConfig};
+use crate::metrics::SLO_BURN_RATE;
+use lazy_static::lazy_static;
+use log::info;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const BUCKET_SECONDS: u64 = 10;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    start: u64,
+    total: u64,
+    errors: u64,
+    slow: u64,
+}
+
+lazy_static! {
+    static ref BUCKETS: Mutex<HashMap<String, VecDeque<Bucket>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WindowReport {
+    pub window_seconds: u64,
+    pub requests: u64,
+    pub bad: u64,
+    pub burn_rate: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ObjectiveReport {
+    pub objective: &'static str,
+    pub target: f64,
+    pub windows: Vec<WindowReport>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SloReport {
+    pub policy: String,
+    pub objectives: Vec<ObjectiveReport>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn retention(config: &SloConfig) -> u64 {
+    config.windows_seconds.iter().copied().max().unwrap_or(0)
+}
+
+fn record_at(policy: &str, config: &SloConfig, latency_seconds: f64, failed: bool, now: u64) {
+    let start = now - now % BUCKET_SECONDS;
+    let mut buckets = BUCKETS.lock().unwrap_or_else(|e| e.into_inner());
+    let policy_buckets = buckets.entry(policy.to_string()).or_default();
+    if policy_buckets.back().is_none_or(|b| b.start != start) {
+        policy_buckets.push_back(Bucket {
+            start,
+            ..Default::default()
+        });
+    }
+    let cutoff = now.saturating_sub(retention(config) + BUCKET_SECONDS);
+    while policy_buckets.front().is_some_and(|b| b.start < cutoff) {
+        policy_buckets.pop_front();
+    }
+    if let Some(bucket) = policy_buckets.back_mut() {
+        bucket.total += 1;
+        if failed {
+            bucket.errors += 1;
+        }
+        if config
+            .latency_seconds
+            .is_some_and(|threshold| latency_seconds > threshold)
+        {
+            bucket.slow += 1;
+        }
+    }
+}
+
+/// Counts one finished request against its policy's objectives. Failed
+/// requests count against availability only.
+pub fn record(policy: &Policy, latency_seconds: f64, failed: bool) {
+    if let Some(config) = &policy.slo {
+        record_at(&policy.name, config, latency_seconds, failed, now_secs());
+    }
+}
+
+fn burn_rate(bad: u64, requests: u64, target: f64) -> f64 {
+    if requests == 0 {
+        return 0.0;
+    }
+    let budget = (1.0 - target).max(f64::MIN_POSITIVE);
+    (bad as f64 / requests as f64) / budget
+}
+
+fn policy_report(policy: &Policy, config: &SloConfig, now: u64) -> SloReport {
+    let buckets = BUCKETS.lock().unwrap_or_else(|e| e.into_inner());
+    let empty = VecDeque::new();
+    let policy_buckets = buckets.get(&policy.name).unwrap_or(&empty);
+    let windows = |target: f64, bad: fn(&Bucket) -> u64| -> Vec<WindowReport> {
+        config
+            .windows_seconds
+            .iter()
+            .map(|&window_seconds| {
+                let since = now.saturating_sub(window_seconds);
+                let (requests, bad) = policy_buckets
+                    .iter()
+                    .filter(|b| b.start + BUCKET_SECONDS > since)
+                    .fold((0, 0), |(requests, total_bad), b| {
+                        (requests + b.total, total_bad + bad(b))
+                    });
+                WindowReport {
+                    window_seconds,
+                    requests,
+                    bad,
+                    burn_rate: burn_rate(bad, requests, target),
+                }
+            })
+            .collect()
+    };
+
+    let mut objectives = Vec::new();
+    if let Some(target) = config.availability {
+        objectives.push(ObjectiveReport {
+            objective: "availability",
+            target,
+            windows: windows(target, |b| b.errors),
+        });
+    }
+    if config.latency_seconds.is_some() {
+        objectives.push(ObjectiveReport {
+            objective: "latency",
+            target: config.latency_target,
+            windows: windows(config.latency_target, |b| b.slow),
+        });
+    }
+    SloReport {
+        policy: policy.name.clone(),
+        objectives,
+    }
+}
+
+/// Current burn rates of every policy with an `slo`.
+pub fn report(config: &RouterConfig) -> Vec<SloReport> {
+    let now = now_secs();
+    config
+        .policies
+        .iter()
+        .filter_map(|policy| {
+            let slo = policy.slo.as_ref()?;
+            Some(policy_report(policy, slo, now))
+        })
+        .collect()
+}
+
+fn publish(reports: &[SloReport]) {
+    for report in reports {
+        for objective in &report.objectives {
+            for window in &objective.windows {
+                SLO_BURN_RATE
+                    .with_label_values(&[
+                        &report.policy,
+                        objective.objective,
+                        &window.window_seconds.to_string(),
+                    ])
+                    .set(window.burn_rate);
+            }
+        }
+    }
+}
+
+/// Refreshes the burn rate gauges every bucket while any policy has an
+/// `slo`.
+pub fn spawn(config: SharedConfig) {
+    if !config.snapshot().policies.iter().any(|p| p.slo.is_some()) {
+        return;
+    }
+    info!("SLO tracking enabled");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(BUCKET_SECONDS));
+        loop {
+            interval.tick().await;
+            publish(&report(&config.snapshot()));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burn_rates() {
+        let config = SloConfig {
+            availability: Some(0.99),
+            latency_seconds: Some(10.0),
+            latency_target: 0.9,
+            windows_seconds: vec![60, 3600],
+        };
+        let policy = Policy {
+            name: "slo_test".to_string(),
+            slo: Some(config.clone()),
+            ..Default::default()
+        };
+        let now = 1_000_000;
+        // An hour ago: 100 healthy requests.
+        for _ in 0..100 {
+            record_at(&policy.name, &config, 1.0, false, now - 3000);
+        }
+        // Last minute: 10 requests, one failed and two slow.
+        for i in 0..10 {
+            record_at(
+                &policy.name,
+                &config,
+                if i < 2 { 12.0 } else { 1.0 },
+                i == 9,
+                now,
+            );
+        }
+
+        let report = policy_report(&policy, &config, now);
+        let availability = &report.objectives[0];
+        assert_eq!(availability.objective, "availability");
+        assert_eq!(availability.windows[0].requests, 10);
+        assert!((availability.windows[0].burn_rate - 10.0).abs() < 1e-9);
+        assert_eq!(availability.windows[1].requests, 110);
+
+        let latency = &report.objectives[1];
+        assert_eq!(latency.windows[0].bad, 2);
+        assert!((latency.windows[0].burn_rate - 2.0).abs() < 1e-9);
+    }
+}
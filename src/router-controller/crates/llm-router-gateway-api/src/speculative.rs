@@ -0,0 +1,157 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Speculative
+//!
+//! Races a backup request against a slow primary: the backup only starts
+//! once the primary has missed its soft deadline, and the primary stays in
+//! flight until one of them produces a usable response.
+use std::future::Future;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Winner {
+    Primary,
+    Backup,
+}
+
+impl Winner {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Primary => "primary",
+            Self::Backup => "fallback",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Raced<T> {
+    pub result: T,
+    pub winner: Winner,
+    /// Whether the deadline passed and the backup was started.
+    pub launched: bool,
+}
+
+/// Awaits `primary`, starting `backup` if it has not finished within
+/// `deadline`. The first usable result wins; when neither is usable the
+/// backup's result is returned, as the fallback chain would.
+pub async fn race<T, P, B>(
+    primary: P,
+    deadline: Duration,
+    backup: B,
+    usable: impl Fn(&T) -> bool,
+) -> Raced<T>
+where
+    P: Future<Output = T>,
+    B: Future<Output = T>,
+{
+    tokio::pin!(primary);
+    if let Ok(result) = tokio::time::timeout(deadline, &mut primary).await {
+        return Raced {
+            result,
+            winner: Winner::Primary,
+            launched: false,
+        };
+    }
+
+    tokio::pin!(backup);
+    let (result, winner) = tokio::select! {
+        result = &mut primary => {
+            if usable(&result) {
+                (result, Winner::Primary)
+            } else {
+                (backup.await, Winner::Backup)
+            }
+        }
+        result = &mut backup => {
+            if usable(&result) {
+                (result, Winner::Backup)
+            } else {
+                match primary.await {
+                    primary if usable(&primary) => (primary, Winner::Primary),
+                    _ => (result, Winner::Backup),
+                }
+            }
+        }
+    };
+    Raced {
+        result,
+        winner,
+        launched: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::sleep;
+
+    async fn answer(
+        after_ms: u64,
+        value: Result<&'static str, &'static str>,
+    ) -> Result<&'static str, &'static str> {
+        sleep(Duration::from_millis(after_ms)).await;
+        value
+    }
+
+    #[tokio::test]
+    async fn test_race() {
+        let deadline = Duration::from_millis(50);
+        let fast = race(
+            answer(5, Ok("primary")),
+            deadline,
+            answer(0, Ok("backup")),
+            Result::is_ok,
+        )
+        .await;
+        assert_eq!(
+            (fast.result, fast.winner, fast.launched),
+            (Ok("primary"), Winner::Primary, false)
+        );
+
+        let slow = race(
+            answer(500, Ok("primary")),
+            deadline,
+            answer(10, Ok("backup")),
+            Result::is_ok,
+        )
+        .await;
+        assert_eq!(
+            (slow.result, slow.winner, slow.launched),
+            (Ok("backup"), Winner::Backup, true)
+        );
+
+        let failed_backup = race(
+            answer(100, Ok("primary")),
+            deadline,
+            answer(0, Err("backup")),
+            Result::is_ok,
+        )
+        .await;
+        assert_eq!(
+            (failed_backup.result, failed_backup.winner),
+            (Ok("primary"), Winner::Primary)
+        );
+
+        let both = race(
+            answer(100, Err("primary")),
+            deadline,
+            answer(0, Err("backup")),
+            Result::is_ok,
+        )
+        .await;
+        assert_eq!((both.result, both.winner), (Err("backup"), Winner::Backup));
+    }
+}
@@ -0,0 +1,219 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Spend
+//!
+//! Daily spend alerts. The priced usage of each completion is added to the
+//! alerts it matches. The completion taking an alert over its limit logs
+//! it, publishes a `spend_alert` event and notifies the alert's webhook;
+//! for the rest of the UTC day the alert's requests can be served by a
+//! cheaper LLM. Spend is kept in the state store in micro-dollars, so
+//! replicas sharing one alert once.
+use crate::config::{Policy, SpendAlert};
+use crate::events;
+use crate::metrics::{RequestLabels, SPEND_ALERTS, SPEND_DOWNGRADES};
+use crate::request_context::RequestContext;
+use crate::store;
+use crate::upstream;
+use log::{info, warn};
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn key(alert: &SpendAlert, day: u64) -> String {
+    format!("spend:{}:{}", alert.name, day)
+}
+
+fn micros(usd: f64) -> i64 {
+    (usd * 1_000_000.0).round() as i64
+}
+
+fn matches(alert: &SpendAlert, policy: &str, model: &str, tenant: Option<&str>) -> bool {
+    alert.policy.as_ref().is_none_or(|p| p == policy)
+        && alert.model.as_ref().is_none_or(|m| m == model)
+        && alert.tenant.as_deref().is_none_or(|t| Some(t) == tenant)
+}
+
+/// The alerts the spend of a completion with `labels` counts towards.
+pub fn matching(alerts: &[SpendAlert], labels: &RequestLabels) -> Vec<SpendAlert> {
+    let (Some(policy), Some(model)) = (&labels.policy, &labels.model) else {
+        return Vec::new();
+    };
+    alerts
+        .iter()
+        .filter(|alert| matches(alert, policy, model, labels.tenant.as_deref()))
+        .cloned()
+        .collect()
+}
+
+/// Whether `alert` has crossed its limit today. Alerts are taken as under
+/// their limit when the state store fails.
+fn exceeded(alert: &SpendAlert, now: u64) -> bool {
+    let spent = store::get().get(&key(alert, now / SECONDS_PER_DAY));
+    store::report("spend_alerts", spent)
+        .flatten()
+        .and_then(|value| String::from_utf8(value).ok()?.parse::<i64>().ok())
+        .is_some_and(|spent| spent >= micros(alert.daily_limit_usd))
+}
+
+fn notify(url: String, event: Value) {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        warn!("No runtime to notify spend alert webhook {}", url);
+        return;
+    };
+    runtime.spawn(async move {
+        let result = upstream::client()
+            .post(&url)
+            .timeout(Duration::from_secs(10))
+            .json(&event)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            warn!("Spend alert webhook {} failed: {:?}", url, e);
+        }
+    });
+}
+
+fn fire(alert: &SpendAlert, spent_usd: f64, context: &RequestContext) {
+    warn!(
+        "Spend alert '{}' fired: ${:.2} spent today, limit ${:.2}",
+        alert.name, spent_usd, alert.daily_limit_usd
+    );
+    SPEND_ALERTS.with_label_values(&[&alert.name]).inc();
+    let event = json!({
+        "alert": alert.name,
+        "daily_limit_usd": alert.daily_limit_usd,
+        "spent_usd": spent_usd,
+        "policy": alert.policy,
+        "model": alert.model,
+        "tenant": alert.tenant,
+        "downgrade_to": alert.downgrade_to,
+    });
+    if let Some(url) = &alert.webhook_url {
+        let mut notification = event.clone();
+        notification["type"] = json!("spend_alert");
+        notify(url.clone(), notification);
+    }
+    events::publish("spend_alert", context, event);
+}
+
+/// Returns how many of `alerts` the cost took over their limit.
+fn add(alerts: &[SpendAlert], cost_usd: f64, context: &RequestContext, now: u64) -> usize {
+    let amount = micros(cost_usd);
+    if amount <= 0 {
+        return 0;
+    }
+    let day = now / SECONDS_PER_DAY;
+    // Counters of earlier days are dead weight once a new one starts.
+    let ttl = Duration::from_secs(((day + 1) * SECONDS_PER_DAY).saturating_sub(now).max(1));
+    let mut fired = 0;
+    for alert in alerts {
+        let added = store::get().incr(&key(alert, day), amount, Some(ttl));
+        let Some(spent) = store::report("spend_alerts", added) else {
+            continue;
+        };
+        let limit = micros(alert.daily_limit_usd);
+        if spent >= limit && spent - amount < limit {
+            fire(alert, spent as f64 / 1_000_000.0, context);
+            fired += 1;
+        }
+    }
+    fired
+}
+
+/// Adds the cost of a completion to the `alerts` it matches.
+pub fn record(alerts: &[SpendAlert], cost_usd: f64, context: &RequestContext) {
+    add(alerts, cost_usd, context, now_secs());
+}
+
+/// Index in `policy` of the `downgrade_to` LLM of the first alert over its
+/// limit that covers requests for the LLM at `index`.
+pub fn downgrade(
+    alerts: &[SpendAlert],
+    policy: &Policy,
+    tenant: Option<&str>,
+    index: usize,
+) -> Option<usize> {
+    let chosen = policy.llms.get(index)?;
+    let now = now_secs();
+    let (alert, downgrade) = alerts.iter().find_map(|alert| {
+        let target = alert.downgrade_to.as_ref()?;
+        let downgrade = policy.llms.iter().position(|llm| &llm.name == target)?;
+        (downgrade != index
+            && matches(alert, &policy.name, &chosen.name, tenant)
+            && exceeded(alert, now))
+        .then_some((alert, downgrade))
+    })?;
+    info!(
+        "Spend alert '{}' over its limit: policy={} llm={} downgraded to {}",
+        alert.name, policy.name, chosen.name, policy.llms[downgrade].name
+    );
+    SPEND_DOWNGRADES.with_label_values(&[&alert.name]).inc();
+    Some(downgrade)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Llm;
+
+    fn llm(name: &str) -> Llm {
+        Llm {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_alert_and_downgrade() {
+        let alert = SpendAlert {
+            name: "spend_test_large".to_string(),
+            daily_limit_usd: 1.0,
+            model: Some("Large".to_string()),
+            downgrade_to: Some("Small".to_string()),
+            ..Default::default()
+        };
+        let policy = Policy {
+            name: "spend_test_policy".to_string(),
+            llms: vec![llm("Large"), llm("Small")],
+            ..Default::default()
+        };
+        let labels = RequestLabels {
+            policy: Some(policy.name.clone()),
+            model: Some("Small".to_string()),
+            ..Default::default()
+        };
+        assert!(matching(std::slice::from_ref(&alert), &labels).is_empty());
+
+        let alerts = [alert];
+        let context = RequestContext::default();
+        let now = now_secs();
+        assert_eq!(add(&alerts, 0.6, &context, now), 0);
+        assert_eq!(downgrade(&alerts, &policy, None, 0), None);
+        assert_eq!(add(&alerts, 0.6, &context, now), 1);
+        assert_eq!(add(&alerts, 0.6, &context, now), 0);
+        assert_eq!(downgrade(&alerts, &policy, None, 0), Some(1));
+        assert_eq!(downgrade(&alerts, &policy, None, 1), None);
+    }
+}
@@ -0,0 +1,145 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Split
+//!
+//! The "split" routing strategy: traffic is divided between a policy's
+//! `split` arms by percentage. A request is placed by a hash of who sent it,
+//! so the same user keeps landing in the same arm.
+use crate::caller::Caller;
+use crate::config::{CallerKey, Policy};
+use crate::conversation::SESSION_ID_HEADER;
+use crate::metrics::SPLIT_ASSIGNMENTS;
+use http::HeaderMap;
+use log::info;
+use serde_json::Value;
+
+/// Who a request is assigned for: the JWT subject, the OpenAI `user` field,
+/// the `X-Session-Id` header, or else the caller's API key or address.
+pub fn unit(json: &Value, headers: &HeaderMap, caller: &Caller) -> String {
+    if let Some(subject) = caller.subject {
+        return format!("sub:{}", subject);
+    }
+    if let Some(user) = json["user"].as_str().filter(|user| !user.is_empty()) {
+        return format!("user:{}", user);
+    }
+    let session = headers
+        .get(SESSION_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty());
+    match session {
+        Some(session) => format!("session:{}", session),
+        // Assignment only has to be sticky, so any bearer key will do.
+        None => caller.identify(CallerKey::ApiKey, caller.api_key).1,
+    }
+}
+
+/// Position of `unit` in `[0, 100)`, stable across restarts and replicas.
+/// The policy name is mixed in so arms of different policies are
+/// independent.
+fn point(policy: &str, unit: &str) -> f64 {
+    let digest = openssl::sha::sha256(format!("{}\n{}", policy, unit).as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(bytes) % 10_000) as f64 / 100.0
+}
+
+/// Index of the LLM of the arm `unit` falls into, or `None` when the policy
+/// has no split.
+pub fn assign(policy: &Policy, unit: &str) -> Option<usize> {
+    let point = point(&policy.name, unit);
+    let mut upper = 0.0;
+    let arm = policy
+        .split
+        .iter()
+        .find(|arm| {
+            upper += arm.percent;
+            point < upper
+        })
+        .or_else(|| policy.split.iter().rev().find(|arm| arm.percent > 0.0))?;
+    let index = policy.llms.iter().position(|llm| llm.name == arm.llm)?;
+    info!(
+        "Split assignment: policy={} arm={} point={:.2}",
+        policy.name, arm.llm, point
+    );
+    SPLIT_ASSIGNMENTS
+        .with_label_values(&[&policy.name, &arm.llm])
+        .inc();
+    Some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Llm, SplitArm};
+    use http::HeaderValue;
+    use serde_json::json;
+
+    fn policy(percents: &[(&str, f64)]) -> Policy {
+        Policy {
+            name: "split_test".to_string(),
+            llms: percents
+                .iter()
+                .map(|(name, _)| Llm {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            split: percents
+                .iter()
+                .map(|(name, percent)| SplitArm {
+                    llm: name.to_string(),
+                    percent: *percent,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_assignment_is_sticky_and_follows_percentages() {
+        let split = policy(&[("control", 80.0), ("candidate", 20.0)]);
+        let mut counts = [0; 2];
+        for user in 0..2000 {
+            let unit = format!("user:{}", user);
+            let index = assign(&split, &unit).unwrap();
+            assert_eq!(assign(&split, &unit), Some(index));
+            counts[index] += 1;
+        }
+        assert!((1500..1700).contains(&counts[0]), "{:?}", counts);
+
+        let everything = policy(&[("control", 0.0), ("candidate", 100.0)]);
+        assert_eq!(assign(&everything, "user:1"), Some(1));
+        assert_eq!(assign(&Policy::default(), "user:1"), None);
+    }
+
+    #[test]
+    fn test_unit_prefers_user_over_session() {
+        let mut headers = HeaderMap::new();
+        headers.insert(SESSION_ID_HEADER, HeaderValue::from_static("s-1"));
+        let caller = Caller::new(&headers, None, None);
+        assert_eq!(
+            unit(&json!({"user": "jane"}), &headers, &caller),
+            "user:jane"
+        );
+        assert_eq!(unit(&json!({}), &headers, &caller), "session:s-1");
+        let subject = Caller::new(&headers, Some("u-42"), None);
+        assert_eq!(
+            unit(&json!({"user": "jane"}), &headers, &subject),
+            "sub:u-42"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sticky
+//!
+//! Conversation affinity: the LLM chosen for the first turn of a
+//! conversation is kept in the state store and reused for its later turns,
+//! so a conversation does not switch models midway and keeps hitting the
+//! provider's prompt cache. The entry expires `ttl_seconds` after the last
+//! turn.
+use crate::config::{Policy, StickyRoutingConfig};
+use crate::conversation::SESSION_ID_HEADER;
+use crate::store;
+use http::HeaderMap;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Longest conversation id that is tracked; longer ids are ignored.
+const MAX_SESSION_ID_LEN: usize = 256;
+
+/// The conversation of a request, from the `X-Session-Id` header or the
+/// `session_id` of the `nim-llm-router` block.
+pub fn session_id(headers: &HeaderMap, json: &Value) -> Option<String> {
+    headers
+        .get(SESSION_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| json["nim-llm-router"]["session_id"].as_str())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_SESSION_ID_LEN)
+        .map(str::to_string)
+}
+
+fn store_key(policy: &Policy, session: &str) -> String {
+    format!("sticky:{}:{}", policy.name, session)
+}
+
+/// Index of the LLM that served the conversation's earlier turns, extending
+/// its lifetime. LLMs since removed from the policy are not reused.
+pub fn lookup(config: &StickyRoutingConfig, policy: &Policy, session: &str) -> Option<usize> {
+    let key = store_key(policy, session);
+    let name = store::report("sticky_routing", store::get().get(&key)).flatten()?;
+    let index = policy
+        .llms
+        .iter()
+        .position(|llm| llm.name.as_bytes() == name.as_slice())?;
+    store::report(
+        "sticky_routing",
+        store::get().expire(&key, Duration::from_secs(config.ttl_seconds)),
+    );
+    Some(index)
+}
+
+/// Records the LLM chosen for the conversation's first turn.
+pub fn remember(config: &StickyRoutingConfig, policy: &Policy, session: &str, index: usize) {
+    let Some(llm) = policy.llms.get(index) else {
+        return;
+    };
+    store::report(
+        "sticky_routing",
+        store::get().set(
+            &store_key(policy, session),
+            llm.name.as_bytes(),
+            Some(Duration::from_secs(config.ttl_seconds)),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Llm;
+    use http::HeaderValue;
+    use serde_json::json;
+
+    #[test]
+    fn test_session_id() {
+        let body = json!({"nim-llm-router": {"policy": "p", "session_id": " chat-1 "}});
+        assert_eq!(
+            session_id(&HeaderMap::new(), &body),
+            Some("chat-1".to_string())
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(SESSION_ID_HEADER, HeaderValue::from_static("chat-2"));
+        assert_eq!(session_id(&headers, &body), Some("chat-2".to_string()));
+        assert_eq!(session_id(&HeaderMap::new(), &json!({})), None);
+    }
+
+    #[test]
+    fn test_remember_and_lookup() {
+        let config = StickyRoutingConfig::default();
+        let mut policy = Policy {
+            name: "sticky_test".to_string(),
+            llms: vec![
+                Llm {
+                    name: "small".to_string(),
+                    ..Default::default()
+                },
+                Llm {
+                    name: "large".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(lookup(&config, &policy, "chat-3"), None);
+        remember(&config, &policy, "chat-3", 1);
+        assert_eq!(lookup(&config, &policy, "chat-3"), Some(1));
+
+        policy.llms.remove(1);
+        assert_eq!(lookup(&config, &policy, "chat-3"), None);
+    }
+}
@@ -0,0 +1,877 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Store
+//!
+//! Key/value state behind rate limiting, token budgets, conversations,
+//! idempotency and circuit breakers. The backend is picked by
+//! `state_store`: `memory` keeps state per process, `sqlite` in a file
+//! shared by the processes of one host, and `redis` on a server shared by
+//! every replica. Operations are synchronous and run on the request path,
+//! so a failing backend is reported and the feature using it fails open.
+//! While a SQLite or Redis call blocks, the runtime moves the worker's
+//! other tasks to another thread.
+use crate::config::{StateBackend, StateStoreConfig};
+use crate::metrics::STATE_STORE_ERRORS;
+use lazy_static::lazy_static;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Attempts `update` makes before giving up on a contended key.
+const MAX_UPDATE_ATTEMPTS: usize = 8;
+/// Idle Redis connections kept for reuse.
+const MAX_IDLE_CONNECTIONS: usize = 32;
+/// How often the SQLite backend deletes expired rows.
+const SQLITE_PURGE_INTERVAL: Duration = Duration::from_secs(60);
+const WRONG_TYPE: &str = "operation against a key holding the wrong kind of value";
+
+static STORE: OnceLock<Box<dyn StateStore>> = OnceLock::new();
+
+#[derive(Debug)]
+pub struct StoreError(String);
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "state store error: {}", self.0)
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(err: rusqlite::Error) -> Self {
+        Self(err.to_string())
+    }
+}
+
+impl From<redis::RedisError> for StoreError {
+    fn from(err: redis::RedisError) -> Self {
+        Self(err.to_string())
+    }
+}
+
+/// Operations the stateful features need. Values are opaque bytes, except
+/// for `incr`, which keeps integers in decimal as Redis does. A `ttl` sets
+/// the key's expiry; `None` stores it without one, except in `incr` and
+/// `push`, which then leave the expiry as it is.
+pub trait StateStore: Send + Sync {
+    fn backend(&self) -> StateBackend;
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError>;
+
+    fn set(&self, key: &str, value: &[u8], ttl: Option<Duration>) -> Result<(), StoreError>;
+
+    /// Stores `value` only if the key still holds `current`, or is absent
+    /// when `current` is `None`. Returns whether it was stored.
+    fn compare_and_set(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<bool, StoreError>;
+
+    /// Returns whether the key existed.
+    fn delete(&self, key: &str) -> Result<bool, StoreError>;
+
+    /// Adds `by` to the integer at `key`, absent keys counting as zero, and
+    /// returns the result.
+    fn incr(&self, key: &str, by: i64, ttl: Option<Duration>) -> Result<i64, StoreError>;
+
+    /// Returns whether the key exists.
+    fn expire(&self, key: &str, ttl: Duration) -> Result<bool, StoreError>;
+
+    /// Appends to the list at `key`, keeping its newest `max_len` items.
+    fn push(
+        &self,
+        key: &str,
+        value: &[u8],
+        max_len: usize,
+        ttl: Option<Duration>,
+    ) -> Result<(), StoreError>;
+
+    /// The list at `key`, oldest first.
+    fn list(&self, key: &str) -> Result<Vec<Vec<u8>>, StoreError>;
+}
+
+/// Opens the configured backend. Only the first call has an effect.
+pub fn init(config: &StateStoreConfig) -> Result<(), StoreError> {
+    let store: Box<dyn StateStore> = match config.backend {
+        StateBackend::Memory => Box::new(MemoryStore::new(config.max_entries)),
+        StateBackend::Sqlite => Box::new(SqliteStore::open(
+            config.path.as_deref().unwrap_or_default(),
+            Duration::from_millis(config.timeout_ms),
+        )?),
+        StateBackend::Redis => Box::new(RedisStore::open(
+            config.url.as_deref().unwrap_or_default(),
+            &config.key_prefix,
+            Duration::from_millis(config.timeout_ms),
+        )?),
+    };
+    if STORE.set(store).is_ok() {
+        info!(
+            "State store initialized: backend={}",
+            config.backend.as_str()
+        );
+    }
+    Ok(())
+}
+
+/// The shared store, in memory with defaults if `init` was not called.
+pub fn get() -> &'static dyn StateStore {
+    STORE
+        .get_or_init(|| Box::new(MemoryStore::new(StateStoreConfig::default().max_entries)))
+        .as_ref()
+}
+
+/// Logs and counts a failed operation, leaving the caller to fall back.
+pub fn report<T>(operation: &'static str, result: Result<T, StoreError>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            let backend = get().backend().as_str();
+            warn!("{} failed: backend={} {}", operation, backend, e);
+            STATE_STORE_ERRORS
+                .with_label_values(&[backend, operation])
+                .inc();
+            None
+        }
+    }
+}
+
+/// Replaces the value at `key` with the one `update` derives from it,
+/// retrying when another writer changed the key in between. Returns the
+/// second element `update` produced for the value that was stored.
+pub fn update<T>(
+    store: &dyn StateStore,
+    key: &str,
+    ttl: Option<Duration>,
+    mut update: impl FnMut(Option<&[u8]>) -> (Vec<u8>, T),
+) -> Result<T, StoreError> {
+    for _ in 0..MAX_UPDATE_ATTEMPTS {
+        let current = store.get(key)?;
+        let (value, result) = update(current.as_deref());
+        if store.compare_and_set(key, current.as_deref(), &value, ttl)? {
+            return Ok(result);
+        }
+    }
+    Err(StoreError(format!(
+        "key '{}' changed on every attempt",
+        key
+    )))
+}
+
+/// Runs a backend call that waits on disk or the network. On the
+/// multi-threaded runtime the calling worker hands its other tasks off
+/// first, so unrelated requests keep being served meanwhile.
+fn blocking<T>(f: impl FnOnce() -> T) -> T {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(f)
+        }
+        _ => f(),
+    }
+}
+
+fn parse_int(value: &[u8]) -> Result<i64, StoreError> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| StoreError("value is not an integer".to_string()))
+}
+
+enum Value {
+    Bytes(Vec<u8>),
+    List(VecDeque<Vec<u8>>),
+}
+
+struct Entry {
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+/// Per process store. Once `max_entries` keys are held, expired keys are
+/// purged and then those closest to expiry are evicted.
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, Entry>>,
+    max_entries: usize,
+}
+
+impl MemoryStore {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            max_entries: max_entries.max(1),
+        }
+    }
+
+    /// Runs `f` on the entries, after dropping `key` if it has expired.
+    fn with_entries<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut HashMap<String, Entry>, Instant) -> T,
+    ) -> T {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries
+            .get(key)
+            .is_some_and(|entry| entry.expires_at.is_some_and(|at| at <= now))
+        {
+            entries.remove(key);
+        }
+        f(&mut entries, now)
+    }
+
+    fn insert(&self, entries: &mut HashMap<String, Entry>, key: &str, entry: Entry, now: Instant) {
+        if !entries.contains_key(key) && entries.len() >= self.max_entries {
+            entries.retain(|_, entry| entry.expires_at.is_none_or(|at| at > now));
+            if entries.len() >= self.max_entries {
+                if let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| (entry.expires_at.is_none(), entry.expires_at))
+                    .map(|(k, _)| k.clone())
+                {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key.to_string(), entry);
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn backend(&self) -> StateBackend {
+        StateBackend::Memory
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        self.with_entries(key, |entries, _| match entries.get(key) {
+            None => Ok(None),
+            Some(Entry {
+                value: Value::Bytes(value),
+                ..
+            }) => Ok(Some(value.clone())),
+            Some(_) => Err(StoreError(WRONG_TYPE.to_string())),
+        })
+    }
+
+    fn set(&self, key: &str, value: &[u8], ttl: Option<Duration>) -> Result<(), StoreError> {
+        self.with_entries(key, |entries, now| {
+            let entry = Entry {
+                value: Value::Bytes(value.to_vec()),
+                expires_at: ttl.map(|ttl| now + ttl),
+            };
+            self.insert(entries, key, entry, now);
+            Ok(())
+        })
+    }
+
+    fn compare_and_set(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<bool, StoreError> {
+        self.with_entries(key, |entries, now| {
+            let matches = match (entries.get(key), current) {
+                (None, None) => true,
+                (
+                    Some(Entry {
+                        value: Value::Bytes(stored),
+                        ..
+                    }),
+                    Some(current),
+                ) => stored.as_slice() == current,
+                _ => false,
+            };
+            if matches {
+                let entry = Entry {
+                    value: Value::Bytes(value.to_vec()),
+                    expires_at: ttl.map(|ttl| now + ttl),
+                };
+                self.insert(entries, key, entry, now);
+            }
+            Ok(matches)
+        })
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, StoreError> {
+        self.with_entries(key, |entries, _| Ok(entries.remove(key).is_some()))
+    }
+
+    fn incr(&self, key: &str, by: i64, ttl: Option<Duration>) -> Result<i64, StoreError> {
+        self.with_entries(key, |entries, now| {
+            let (current, expires_at) = match entries.get(key) {
+                None => (0, None),
+                Some(Entry {
+                    value: Value::Bytes(value),
+                    expires_at,
+                }) => (parse_int(value)?, *expires_at),
+                Some(_) => return Err(StoreError(WRONG_TYPE.to_string())),
+            };
+            let total = current.saturating_add(by);
+            let entry = Entry {
+                value: Value::Bytes(total.to_string().into_bytes()),
+                expires_at: ttl.map(|ttl| now + ttl).or(expires_at),
+            };
+            self.insert(entries, key, entry, now);
+            Ok(total)
+        })
+    }
+
+    fn expire(&self, key: &str, ttl: Duration) -> Result<bool, StoreError> {
+        self.with_entries(key, |entries, now| match entries.get_mut(key) {
+            Some(entry) => {
+                entry.expires_at = Some(now + ttl);
+                Ok(true)
+            }
+            None => Ok(false),
+        })
+    }
+
+    fn push(
+        &self,
+        key: &str,
+        value: &[u8],
+        max_len: usize,
+        ttl: Option<Duration>,
+    ) -> Result<(), StoreError> {
+        self.with_entries(key, |entries, now| {
+            let (mut items, expires_at) = match entries.remove(key) {
+                None => (VecDeque::new(), None),
+                Some(Entry {
+                    value: Value::List(items),
+                    expires_at,
+                }) => (items, expires_at),
+                Some(entry) => {
+                    entries.insert(key.to_string(), entry);
+                    return Err(StoreError(WRONG_TYPE.to_string()));
+                }
+            };
+            items.push_back(value.to_vec());
+            while items.len() > max_len {
+                items.pop_front();
+            }
+            let entry = Entry {
+                value: Value::List(items),
+                expires_at: ttl.map(|ttl| now + ttl).or(expires_at),
+            };
+            self.insert(entries, key, entry, now);
+            Ok(())
+        })
+    }
+
+    fn list(&self, key: &str) -> Result<Vec<Vec<u8>>, StoreError> {
+        self.with_entries(key, |entries, _| match entries.get(key) {
+            None => Ok(Vec::new()),
+            Some(Entry {
+                value: Value::List(items),
+                ..
+            }) => Ok(items.iter().cloned().collect()),
+            Some(_) => Err(StoreError(WRONG_TYPE.to_string())),
+        })
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn expiry_ms(ttl: Option<Duration>, now: i64) -> Option<i64> {
+    ttl.map(|ttl| now.saturating_add(ttl.as_millis() as i64))
+}
+
+/// Lists are stored as one value, each item prefixed with its length as a
+/// big-endian `u32`.
+fn encode_list(items: &VecDeque<Vec<u8>>) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for item in items {
+        encoded.extend_from_slice(&(item.len() as u32).to_be_bytes());
+        encoded.extend_from_slice(item);
+    }
+    encoded
+}
+
+fn decode_list(mut encoded: &[u8]) -> Result<VecDeque<Vec<u8>>, StoreError> {
+    let mut items = VecDeque::new();
+    while !encoded.is_empty() {
+        let corrupt = || StoreError(WRONG_TYPE.to_string());
+        let (len, rest) = encoded.split_first_chunk::<4>().ok_or_else(corrupt)?;
+        let len = u32::from_be_bytes(*len) as usize;
+        if rest.len() < len {
+            return Err(corrupt());
+        }
+        items.push_back(rest[..len].to_vec());
+        encoded = &rest[len..];
+    }
+    Ok(items)
+}
+
+/// A database file. Every operation runs in an immediate transaction, so
+/// several processes can share the file. Expiry times are wall clock
+/// milliseconds.
+/// A live row: its value and expiry in milliseconds since the epoch.
+type Stored = (Vec<u8>, Option<i64>);
+
+pub struct SqliteStore {
+    connection: Mutex<(Connection, Instant)>,
+}
+
+impl SqliteStore {
+    pub fn open(path: &str, busy_timeout: Duration) -> Result<Self, StoreError> {
+        let connection = Connection::open(path)?;
+        connection.busy_timeout(busy_timeout)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS state (
+                key TEXT PRIMARY KEY,
+                value BLOB NOT NULL,
+                expires_at INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS state_expires_at ON state (expires_at);",
+        )?;
+        Ok(Self {
+            connection: Mutex::new((connection, Instant::now())),
+        })
+    }
+
+    fn transaction<T>(
+        &self,
+        f: impl FnOnce(&Transaction, i64) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        blocking(|| {
+            let mut guard = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+            let (connection, last_purge) = &mut *guard;
+            let now = now_ms();
+            let tx = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            if last_purge.elapsed() >= SQLITE_PURGE_INTERVAL {
+                tx.execute("DELETE FROM state WHERE expires_at <= ?1", params![now])?;
+                *last_purge = Instant::now();
+            }
+            let result = f(&tx, now)?;
+            tx.commit()?;
+            Ok(result)
+        })
+    }
+
+    fn read(tx: &Transaction, key: &str, now: i64) -> Result<Option<Stored>, StoreError> {
+        Ok(tx
+            .query_row(
+                "SELECT value, expires_at FROM state
+                 WHERE key = ?1 AND (expires_at IS NULL OR expires_at > ?2)",
+                params![key, now],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?)
+    }
+
+    fn write(
+        tx: &Transaction,
+        key: &str,
+        value: &[u8],
+        expires_at: Option<i64>,
+    ) -> Result<(), StoreError> {
+        tx.execute(
+            "INSERT INTO state (key, value, expires_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value, expires_at = excluded.expires_at",
+            params![key, value, expires_at],
+        )?;
+        Ok(())
+    }
+}
+
+impl StateStore for SqliteStore {
+    fn backend(&self) -> StateBackend {
+        StateBackend::Sqlite
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        self.transaction(|tx, now| Ok(Self::read(tx, key, now)?.map(|(value, _)| value)))
+    }
+
+    fn set(&self, key: &str, value: &[u8], ttl: Option<Duration>) -> Result<(), StoreError> {
+        self.transaction(|tx, now| Self::write(tx, key, value, expiry_ms(ttl, now)))
+    }
+
+    fn compare_and_set(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<bool, StoreError> {
+        self.transaction(|tx, now| {
+            let stored = Self::read(tx, key, now)?.map(|(value, _)| value);
+            if stored.as_deref() != current {
+                return Ok(false);
+            }
+            Self::write(tx, key, value, expiry_ms(ttl, now))?;
+            Ok(true)
+        })
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, StoreError> {
+        self.transaction(|tx, now| {
+            let existed = Self::read(tx, key, now)?.is_some();
+            tx.execute("DELETE FROM state WHERE key = ?1", params![key])?;
+            Ok(existed)
+        })
+    }
+
+    fn incr(&self, key: &str, by: i64, ttl: Option<Duration>) -> Result<i64, StoreError> {
+        self.transaction(|tx, now| {
+            let (current, expires_at) = match Self::read(tx, key, now)? {
+                Some((value, expires_at)) => (parse_int(&value)?, expires_at),
+                None => (0, None),
+            };
+            let total = current.saturating_add(by);
+            let expires_at = expiry_ms(ttl, now).or(expires_at);
+            Self::write(tx, key, total.to_string().as_bytes(), expires_at)?;
+            Ok(total)
+        })
+    }
+
+    fn expire(&self, key: &str, ttl: Duration) -> Result<bool, StoreError> {
+        self.transaction(|tx, now| match Self::read(tx, key, now)? {
+            Some(_) => {
+                tx.execute(
+                    "UPDATE state SET expires_at = ?2 WHERE key = ?1",
+                    params![key, expiry_ms(Some(ttl), now)],
+                )?;
+                Ok(true)
+            }
+            None => Ok(false),
+        })
+    }
+
+    fn push(
+        &self,
+        key: &str,
+        value: &[u8],
+        max_len: usize,
+        ttl: Option<Duration>,
+    ) -> Result<(), StoreError> {
+        self.transaction(|tx, now| {
+            let (mut items, expires_at) = match Self::read(tx, key, now)? {
+                Some((encoded, expires_at)) => (decode_list(&encoded)?, expires_at),
+                None => (VecDeque::new(), None),
+            };
+            items.push_back(value.to_vec());
+            while items.len() > max_len {
+                items.pop_front();
+            }
+            let expires_at = expiry_ms(ttl, now).or(expires_at);
+            Self::write(tx, key, &encode_list(&items), expires_at)
+        })
+    }
+
+    fn list(&self, key: &str) -> Result<Vec<Vec<u8>>, StoreError> {
+        self.transaction(|tx, now| match Self::read(tx, key, now)? {
+            Some((encoded, _)) => Ok(decode_list(&encoded)?.into()),
+            None => Ok(Vec::new()),
+        })
+    }
+}
+
+lazy_static! {
+    /// `GET` then `SET` only when the value is still the expected one.
+    /// ARGV: whether a value is expected (`1`/`0`), the expected value, the
+    /// new value, and the expiry in milliseconds (`0` for none).
+    static ref COMPARE_AND_SET: redis::Script = redis::Script::new(
+        r"
+        local current = redis.call('GET', KEYS[1])
+        if ARGV[1] == '1' then
+            if current ~= ARGV[2] then return 0 end
+        elseif current then
+            return 0
+        end
+        if ARGV[4] == '0' then
+            redis.call('SET', KEYS[1], ARGV[3])
+        else
+            redis.call('SET', KEYS[1], ARGV[3], 'PX', ARGV[4])
+        end
+        return 1
+        ",
+    );
+}
+
+fn ttl_ms(ttl: Duration) -> u64 {
+    (ttl.as_millis() as u64).max(1)
+}
+
+/// A Redis server shared by every replica. Connections are opened on
+/// demand and reused; one that fails with an I/O error is dropped.
+pub struct RedisStore {
+    client: redis::Client,
+    idle: Mutex<Vec<redis::Connection>>,
+    prefix: String,
+    timeout: Duration,
+}
+
+impl RedisStore {
+    pub fn open(url: &str, prefix: &str, timeout: Duration) -> Result<Self, StoreError> {
+        let store = Self {
+            client: redis::Client::open(url)?,
+            idle: Mutex::new(Vec::new()),
+            prefix: prefix.to_string(),
+            timeout,
+        };
+        // Fails startup on a wrong URL or password rather than on the first
+        // request.
+        store.with_connection(|connection| redis::cmd("PING").query::<()>(connection))?;
+        Ok(store)
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    fn with_connection<T>(
+        &self,
+        f: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>,
+    ) -> Result<T, StoreError> {
+        blocking(|| self.query(f))
+    }
+
+    fn query<T>(
+        &self,
+        f: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>,
+    ) -> Result<T, StoreError> {
+        let idle = self.idle.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        let mut connection = match idle {
+            Some(connection) => connection,
+            None => {
+                let connection = self.client.get_connection_with_timeout(self.timeout)?;
+                connection.set_read_timeout(Some(self.timeout))?;
+                connection.set_write_timeout(Some(self.timeout))?;
+                connection
+            }
+        };
+        let result = f(&mut connection);
+        let broken = result
+            .as_ref()
+            .is_err_and(|e| e.is_io_error() || e.is_timeout() || e.is_connection_dropped());
+        if !broken {
+            let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+            if idle.len() < MAX_IDLE_CONNECTIONS {
+                idle.push(connection);
+            }
+        }
+        Ok(result?)
+    }
+}
+
+impl StateStore for RedisStore {
+    fn backend(&self) -> StateBackend {
+        StateBackend::Redis
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        let key = self.key(key);
+        self.with_connection(|connection| redis::cmd("GET").arg(&key).query(connection))
+    }
+
+    fn set(&self, key: &str, value: &[u8], ttl: Option<Duration>) -> Result<(), StoreError> {
+        let key = self.key(key);
+        self.with_connection(|connection| {
+            let mut cmd = redis::cmd("SET");
+            cmd.arg(&key).arg(value);
+            if let Some(ttl) = ttl {
+                cmd.arg("PX").arg(ttl_ms(ttl));
+            }
+            cmd.query(connection)
+        })
+    }
+
+    fn compare_and_set(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<bool, StoreError> {
+        let key = self.key(key);
+        let stored: i64 = self.with_connection(|connection| {
+            COMPARE_AND_SET
+                .key(&key)
+                .arg(if current.is_some() { "1" } else { "0" })
+                .arg(current.unwrap_or_default())
+                .arg(value)
+                .arg(ttl.map_or(0, ttl_ms))
+                .invoke(connection)
+        })?;
+        Ok(stored == 1)
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, StoreError> {
+        let key = self.key(key);
+        let removed: i64 =
+            self.with_connection(|connection| redis::cmd("DEL").arg(&key).query(connection))?;
+        Ok(removed > 0)
+    }
+
+    fn incr(&self, key: &str, by: i64, ttl: Option<Duration>) -> Result<i64, StoreError> {
+        let key = self.key(key);
+        let (total,): (i64,) = self.with_connection(|connection| {
+            let mut pipe = redis::pipe();
+            pipe.atomic().cmd("INCRBY").arg(&key).arg(by);
+            if let Some(ttl) = ttl {
+                pipe.cmd("PEXPIRE").arg(&key).arg(ttl_ms(ttl)).ignore();
+            }
+            pipe.query(connection)
+        })?;
+        Ok(total)
+    }
+
+    fn expire(&self, key: &str, ttl: Duration) -> Result<bool, StoreError> {
+        let key = self.key(key);
+        let set: i64 = self.with_connection(|connection| {
+            redis::cmd("PEXPIRE")
+                .arg(&key)
+                .arg(ttl_ms(ttl))
+                .query(connection)
+        })?;
+        Ok(set == 1)
+    }
+
+    fn push(
+        &self,
+        key: &str,
+        value: &[u8],
+        max_len: usize,
+        ttl: Option<Duration>,
+    ) -> Result<(), StoreError> {
+        let key = self.key(key);
+        self.with_connection(|connection| {
+            let mut pipe = redis::pipe();
+            pipe.atomic()
+                .cmd("RPUSH")
+                .arg(&key)
+                .arg(value)
+                .ignore()
+                .cmd("LTRIM")
+                .arg(&key)
+                .arg(-(max_len.max(1) as i64))
+                .arg(-1)
+                .ignore();
+            if let Some(ttl) = ttl {
+                pipe.cmd("PEXPIRE").arg(&key).arg(ttl_ms(ttl)).ignore();
+            }
+            pipe.query(connection)
+        })
+    }
+
+    fn list(&self, key: &str) -> Result<Vec<Vec<u8>>, StoreError> {
+        let key = self.key(key);
+        self.with_connection(|connection| {
+            redis::cmd("LRANGE")
+                .arg(&key)
+                .arg(0)
+                .arg(-1)
+                .query(connection)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise(store: &dyn StateStore) {
+        assert_eq!(store.get("a").unwrap(), None);
+        store.set("a", b"1", None).unwrap();
+        assert_eq!(store.get("a").unwrap().as_deref(), Some(&b"1"[..]));
+        assert_eq!(store.incr("a", 4, None).unwrap(), 5);
+        assert_eq!(store.incr("b", -2, None).unwrap(), -2);
+
+        assert!(!store.compare_and_set("a", Some(b"1"), b"x", None).unwrap());
+        assert!(store.compare_and_set("a", Some(b"5"), b"x", None).unwrap());
+        assert!(!store.compare_and_set("a", None, b"y", None).unwrap());
+        assert!(store.compare_and_set("c", None, b"y", None).unwrap());
+
+        let doubled = update(store, "n", None, |current| {
+            let n = current.map_or(1, |v| parse_int(v).unwrap()) * 2;
+            (n.to_string().into_bytes(), n)
+        })
+        .unwrap();
+        assert_eq!(doubled, 2);
+
+        for item in [b"1", b"2", b"3"] {
+            store.push("l", item, 2, None).unwrap();
+        }
+        assert_eq!(store.list("l").unwrap(), vec![b"2".to_vec(), b"3".to_vec()]);
+        assert!(store.list("missing").unwrap().is_empty());
+
+        assert!(store.expire("a", Duration::ZERO).unwrap());
+        assert!(!store.expire("missing", Duration::ZERO).unwrap());
+        store.set("t", b"v", Some(Duration::ZERO)).unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(store.get("a").unwrap(), None);
+        assert_eq!(store.get("t").unwrap(), None);
+        assert!(store.compare_and_set("t", None, b"w", None).unwrap());
+
+        assert!(store.delete("c").unwrap());
+        assert!(!store.delete("c").unwrap());
+    }
+
+    #[test]
+    fn test_memory_store() {
+        exercise(&MemoryStore::new(100));
+
+        let store = MemoryStore::new(2);
+        store
+            .set("short", b"1", Some(Duration::from_secs(1)))
+            .unwrap();
+        store
+            .set("long", b"2", Some(Duration::from_secs(60)))
+            .unwrap();
+        store.set("new", b"3", None).unwrap();
+        assert_eq!(store.get("short").unwrap(), None);
+        assert!(store.get("long").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let path = std::env::temp_dir().join(format!("llm-router-store-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = SqliteStore::open(path.to_str().unwrap(), Duration::from_millis(500)).unwrap();
+        exercise(&store);
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Needs a Redis server: `REDIS_URL=redis://localhost:6379 cargo test
+    /// -- --ignored`.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn test_redis_store() {
+        let url = std::env::var("REDIS_URL").unwrap_or("redis://127.0.0.1:6379".to_string());
+        let prefix = format!("llm-router-test-{}:", std::process::id());
+        let store = RedisStore::open(&url, &prefix, Duration::from_secs(1)).unwrap();
+        for key in ["a", "b", "c", "n", "l", "t"] {
+            store.delete(key).unwrap();
+        }
+        exercise(&store);
+    }
+}
@@ -14,21 +14,580 @@
 // limitations under the License.
 
 //! Stream
+use crate::audit;
+use crate::background;
+use crate::budget::Account;
+use crate::config::{Pricing, SpendAlert, StreamUsageConfig, TokenizerConfig};
+use crate::conversation::Conversation;
+use crate::cost;
+use crate::disconnect::{self, STAGE_STREAM};
 use crate::error::GatewayApiError;
-use crate::metrics::track_token_usage;
-use bytes::Bytes;
-use futures_util::Stream;
+use crate::events;
+use crate::metrics::{
+    record_stream_throughput, record_time_to_first_token, track_cost, track_token_usage,
+    RequestLabels,
+};
+use crate::request_context::RequestContext;
+use crate::spend;
+use crate::stats::InFlightGuard;
+use crate::tokenizer;
+use crate::upstream::StreamSlot;
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt};
+use http::{HeaderMap, HeaderValue};
 use http_body::Frame;
+use http_body_util::BodyDataStream;
 use log::{debug, info, warn};
 use pin_project_lite::pin_project;
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::future::Future;
 use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tokio::time::Sleep;
+
+/// SSE event type of the usage summary sent at the end of a stream.
+pub const USAGE_EVENT: &str = "nim-llm-router.usage";
+/// Header, or trailer of streamed responses, carrying a response's cost.
+pub const COST_HEADER: &str = "x-usage-cost-usd";
+/// Trailers carrying the usage summary, announced in the `Trailer` header.
+pub const USAGE_TRAILERS: &[&str] = &[
+    "x-usage-prompt-tokens",
+    "x-usage-completion-tokens",
+    "x-usage-total-tokens",
+    COST_HEADER,
+    "x-usage-estimated",
+];
+
+const DONE_EVENT: &str = "data: [DONE]";
+
+/// SSE comment sent while the upstream is silent. Clients ignore comments.
+const KEEP_ALIVE_COMMENT: &[u8] = b": keep-alive\n\n";
+
+/// Sends a keep-alive comment after `interval` without an upstream chunk,
+/// so idle timeouts of proxies in front of the gateway do not cut the
+/// stream.
+struct KeepAlive {
+    interval: Duration,
+    timer: Pin<Box<Sleep>>,
+}
+
+impl KeepAlive {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            timer: Box::pin(tokio::time::sleep(interval)),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.timer
+            .as_mut()
+            .reset(tokio::time::Instant::now() + self.interval);
+    }
+}
+
+/// Completion text in a chunk.
+fn completion_deltas(json: &Value) -> impl Iterator<Item = &str> {
+    json["choices"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|choice| {
+            choice["delta"]["content"]
+                .as_str()
+                .or_else(|| choice["text"].as_str())
+        })
+}
+
+/// Characters of completion text in a chunk.
+fn completion_chars(json: &Value) -> usize {
+    completion_deltas(json)
+        .map(|content| content.chars().count())
+        .sum()
+}
+
+/// Collects a stream's completion text, to count its usage with the LLM's
+/// tokenizer when the provider reports none.
+#[derive(Debug)]
+pub struct TokenEstimate {
+    tokenizer: TokenizerConfig,
+    request: Value,
+    completion: String,
+}
+
+impl TokenEstimate {
+    pub fn new(tokenizer: TokenizerConfig, request: Value) -> Self {
+        Self {
+            tokenizer,
+            request,
+            completion: String::new(),
+        }
+    }
+
+    fn observe(&mut self, json: &Value) {
+        self.completion.extend(completion_deltas(json));
+    }
+
+    /// A chunk carrying the counted usage, `None` while the tokenizer is
+    /// not loaded.
+    fn usage(&self) -> Option<Value> {
+        let usage = tokenizer::usage(&self.tokenizer, &self.request, &self.completion)?;
+        Some(json!({ "usage": usage }))
+    }
+}
+
+/// When completion text arrived, for the time to first token and the
+/// generation speed of a stream.
+#[derive(Debug)]
+struct TokenTiming {
+    start: Instant,
+    first: Option<Instant>,
+    last: Option<Instant>,
+    completion_chars: usize,
+}
+
+impl TokenTiming {
+    fn new(start: Instant) -> Self {
+        Self {
+            start,
+            first: None,
+            last: None,
+            completion_chars: 0,
+        }
+    }
+
+    fn observe(&mut self, json: &Value, labels: &RequestLabels) {
+        let chars = completion_chars(json);
+        if chars == 0 {
+            return;
+        }
+        let now = Instant::now();
+        if self.first.is_none() {
+            self.first = Some(now);
+            record_time_to_first_token(labels, now.duration_since(self.start).as_secs_f64());
+        }
+        self.last = Some(now);
+        self.completion_chars += chars;
+    }
+
+    /// Completion tokens per second between the first and last token, from
+    /// the reported usage or else estimated from the text.
+    fn tokens_per_second(&self, usage: Option<&Value>) -> Option<f64> {
+        let seconds = self.last?.duration_since(self.first?).as_secs_f64();
+        let tokens = usage
+            .and_then(|json| json["usage"]["completion_tokens"].as_u64())
+            .unwrap_or_else(|| self.completion_chars.div_ceil(cost::CHARS_PER_TOKEN) as u64);
+        (seconds > 0.0 && tokens > 1).then(|| tokens as f64 / seconds)
+    }
+}
+
+/// Aggregates usage over a streamed response so it can be reported to the
+/// client when the stream ends. Usage reported by the provider is used when
+/// present; otherwise it is estimated from the prompt and streamed content.
+#[derive(Debug, Clone)]
+pub struct UsageReport {
+    config: StreamUsageConfig,
+    model: String,
+    pricing: Option<Pricing>,
+    prompt_tokens_estimate: u64,
+    completion_chars: usize,
+    reported: Option<Value>,
+    sent_event: bool,
+}
+
+impl UsageReport {
+    pub fn new(
+        config: StreamUsageConfig,
+        model: &str,
+        pricing: Option<Pricing>,
+        prompt_tokens_estimate: u64,
+    ) -> Self {
+        Self {
+            config,
+            model: model.to_string(),
+            pricing,
+            prompt_tokens_estimate,
+            completion_chars: 0,
+            reported: None,
+            sent_event: false,
+        }
+    }
+
+    fn observe(&mut self, json: &Value) {
+        if let Some(usage) = json.get("usage").filter(|u| u.is_object()) {
+            self.reported = Some(usage.clone());
+        }
+        self.completion_chars += completion_chars(json);
+    }
+
+    /// `(prompt, completion, estimated)` token counts.
+    fn tokens(&self) -> (u64, u64, bool) {
+        match &self.reported {
+            Some(usage) => (
+                usage["prompt_tokens"].as_u64().unwrap_or(0),
+                usage["completion_tokens"].as_u64().unwrap_or(0),
+                false,
+            ),
+            None => (
+                self.prompt_tokens_estimate,
+                self.completion_chars.div_ceil(cost::CHARS_PER_TOKEN) as u64,
+                true,
+            ),
+        }
+    }
+
+    pub fn summary(&self) -> Value {
+        let (prompt, completion, estimated) = self.tokens();
+        json!({
+            "object": USAGE_EVENT,
+            "model": self.model,
+            "usage": {
+                "prompt_tokens": prompt,
+                "completion_tokens": completion,
+                "total_tokens": prompt + completion,
+            },
+            "cost_usd": self
+                .pricing
+                .as_ref()
+                .map(|pricing| cost::cost_usd(pricing, prompt, completion)),
+            "estimated": estimated,
+        })
+    }
+
+    fn event(&mut self) -> Option<Bytes> {
+        if !self.config.sse_event || self.sent_event {
+            return None;
+        }
+        self.sent_event = true;
+        Some(Bytes::from(format!(
+            "event: {}\ndata: {}\n\n",
+            USAGE_EVENT,
+            self.summary()
+        )))
+    }
+
+    fn trailers(&self) -> Option<HeaderMap> {
+        if !self.config.trailers {
+            return None;
+        }
+        let summary = self.summary();
+        let values = [
+            summary["usage"]["prompt_tokens"].to_string(),
+            summary["usage"]["completion_tokens"].to_string(),
+            summary["usage"]["total_tokens"].to_string(),
+            summary["cost_usd"].to_string(),
+            summary["estimated"].to_string(),
+        ];
+        let mut trailers = HeaderMap::new();
+        for (name, value) in USAGE_TRAILERS.iter().zip(values) {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                trailers.insert(*name, value);
+            }
+        }
+        Some(trailers)
+    }
+}
 
 pin_project! {
     pub struct ReqwestStreamAdapter {
         #[pin]
         pub inner: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send + Sync>>,
-        pub llm_name: String,
+        pub labels: RequestLabels,
+        // Keeps the upstream counted as busy until the stream is dropped.
+        pub in_flight: Option<InFlightGuard>,
+        pub usage_report: Option<UsageReport>,
+        // Frames queued behind the one just returned.
+        pub pending: VecDeque<Frame<Bytes>>,
+        pub finished: bool,
+        // Last finish reason and usage chunk seen, counted when the stream
+        // is dropped, however it ended.
+        pub finish_reason: Option<String>,
+        pub usage: Option<Value>,
+        // Counts the usage when no chunk carried any.
+        pub estimate: Option<TokenEstimate>,
+        pub conversation: Option<Conversation>,
+        pub account: Option<Account>,
+        // Prices the usage counted in `llm_cost_usd_total`.
+        pub pricing: Option<Pricing>,
+        // Spend alerts the priced usage is added to.
+        pub spend_alerts: Vec<SpendAlert>,
+        pub context: RequestContext,
+        // Holds the upstream host's stream slot until the stream is dropped.
+        pub stream_slot: Option<StreamSlot>,
+        keep_alive: Option<KeepAlive>,
+        // Written to the audit log when the stream is dropped.
+        audit: Option<audit::Record>,
+        // Bytes of an event not yet terminated by a blank line.
+        buffer: BytesMut,
+        timing: TokenTiming,
+    }
+
+    impl PinnedDrop for ReqwestStreamAdapter {
+        fn drop(this: Pin<&mut Self>) {
+            let this = this.project();
+            // Dropping `inner` with the adapter cancels the upstream
+            // request, so an unread stream stops generating.
+            if !*this.finished {
+                disconnect::report(STAGE_STREAM, this.labels);
+            }
+            if let Some(record) = this.audit.take() {
+                record.finish(this.labels, this.usage.as_ref().map(|json| &json["usage"]));
+            }
+            if let Some(rate) = this.timing.tokens_per_second(this.usage.as_ref()) {
+                record_stream_throughput(this.labels, rate);
+            }
+            let reported = this.usage.take();
+            let estimate = this.estimate.take().filter(|_| reported.is_none());
+            if reported.is_some() || estimate.is_some() {
+                let labels = std::mem::take(this.labels);
+                let finish_reason = this.finish_reason.take();
+                let context = std::mem::take(this.context);
+                let conversation = this.conversation.take();
+                let account = this.account.take();
+                let pricing = this.pricing.take();
+                let spend_alerts = std::mem::take(this.spend_alerts);
+                background::submit("usage", move || {
+                    let Some(json) = reported.or_else(|| estimate?.usage()) else {
+                        return;
+                    };
+                    let usage = &json["usage"];
+                    info!(
+                        "Usage statistics: prompt={}, completion={}, total={}",
+                        usage["prompt_tokens"].as_u64().unwrap_or(0),
+                        usage["completion_tokens"].as_u64().unwrap_or(0),
+                        usage["total_tokens"].as_u64().unwrap_or(0)
+                    );
+                    track_token_usage(&json, &labels, true, finish_reason.as_deref());
+                    if let Some(cost) = pricing
+                        .as_ref()
+                        .and_then(|pricing| cost::usage_cost_usd(pricing, usage))
+                    {
+                        track_cost(&labels, cost);
+                        spend::record(&spend_alerts, cost, &context);
+                    }
+                    events::publish_usage(&context, usage);
+                    if let Some(conversation) = &conversation {
+                        conversation.record(usage);
+                    }
+                    if let Some(account) = &account {
+                        account.record(usage);
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl ReqwestStreamAdapter {
+    pub fn new(
+        inner: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send + Sync>>,
+        labels: RequestLabels,
+        in_flight: Option<InFlightGuard>,
+        usage_report: Option<UsageReport>,
+    ) -> Self {
+        Self {
+            inner,
+            labels,
+            in_flight,
+            usage_report,
+            pending: VecDeque::new(),
+            finished: false,
+            finish_reason: None,
+            usage: None,
+            estimate: None,
+            conversation: None,
+            account: None,
+            pricing: None,
+            spend_alerts: Vec::new(),
+            context: RequestContext::default(),
+            stream_slot: None,
+            keep_alive: None,
+            audit: None,
+            buffer: BytesMut::new(),
+            timing: TokenTiming::new(Instant::now()),
+        }
+    }
+
+    /// When the request was received, for the time to first token. The
+    /// stream's creation by default.
+    pub fn with_start(mut self, start: Instant) -> Self {
+        self.timing = TokenTiming::new(start);
+        self
+    }
+
+    /// Context attached to the usage event published when the stream ends.
+    pub fn with_context(mut self, context: RequestContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Adds the stream's usage to `conversation` when it ends.
+    pub fn with_conversation(mut self, conversation: Option<Conversation>) -> Self {
+        self.conversation = conversation;
+        self
+    }
+
+    /// Adds the stream's usage to the caller's token budget when it ends.
+    pub fn with_account(mut self, account: Option<Account>) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// Counts the stream's usage with `estimate` when the provider reports
+    /// none.
+    pub fn with_estimate(mut self, estimate: Option<TokenEstimate>) -> Self {
+        self.estimate = estimate;
+        self
+    }
+
+    /// Counts the stream's spend at `pricing` when it ends.
+    pub fn with_pricing(mut self, pricing: Option<Pricing>) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Adds the stream's spend to `spend_alerts` when it ends.
+    pub fn with_spend_alerts(mut self, spend_alerts: Vec<SpendAlert>) -> Self {
+        self.spend_alerts = spend_alerts;
+        self
+    }
+
+    /// Keeps the upstream's stream slot taken while the stream is read.
+    pub fn with_stream_slot(mut self, stream_slot: Option<StreamSlot>) -> Self {
+        self.stream_slot = stream_slot;
+        self
+    }
+
+    /// Writes `record` to the audit log with the stream's usage when it
+    /// ends.
+    pub fn with_audit(mut self, record: Option<audit::Record>) -> Self {
+        self.audit = record;
+        self
+    }
+
+    /// Sends `: keep-alive` comments while the upstream sends nothing for
+    /// `interval`.
+    pub fn with_keep_alive(mut self, interval: Option<Duration>) -> Self {
+        self.keep_alive = interval.map(KeepAlive::new);
+        self
+    }
+}
+
+/// Waits for the first chunk of a streamed response, for at most `timeout`,
+/// so a stream that fails before sending anything can still fall back. The
+/// response is rebuilt with that chunk ahead of the rest of its body.
+pub async fn first_chunk(
+    response: reqwest::Response,
+    timeout: Option<Duration>,
+) -> Result<reqwest::Response, String> {
+    let (parts, body) = http::Response::from(response).into_parts();
+    let mut rest = BodyDataStream::new(body);
+    let read = async {
+        loop {
+            match rest.next().await {
+                Some(Ok(chunk)) if chunk.is_empty() => continue,
+                Some(Ok(chunk)) => return Ok(chunk),
+                Some(Err(error)) => return Err(error.to_string()),
+                None => return Err("stream ended before its first chunk".to_string()),
+            }
+        }
+    };
+    let chunk = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, read)
+            .await
+            .map_err(|_| format!("no chunk within {} ms", timeout.as_millis()))??,
+        None => read.await?,
+    };
+    let body = reqwest::Body::wrap_stream(
+        futures_util::stream::once(async move { Ok::<_, reqwest::Error>(chunk) }).chain(rest),
+    );
+    Ok(reqwest::Response::from(http::Response::from_parts(
+        parts, body,
+    )))
+}
+
+/// Offset and length of the first event delimiter in `buffer`, `\n\n` or
+/// `\r\n\r\n`.
+pub(crate) fn event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    let find = |delimiter: &[u8]| {
+        buffer
+            .windows(delimiter.len())
+            .position(|window| window == delimiter)
+            .map(|at| (at, delimiter.len()))
+    };
+    match (find(b"\n\n"), find(b"\r\n\r\n")) {
+        (Some(lf), Some(crlf)) => Some(if crlf.0 < lf.0 { crlf } else { lf }),
+        (lf, crlf) => lf.or(crlf),
+    }
+}
+
+/// Splits the complete events off the front of `buffer`.
+fn take_events(buffer: &mut BytesMut) -> Bytes {
+    let mut len = 0;
+    while let Some((end, delimiter)) = event_end(&buffer[len..]) {
+        len += end + delimiter;
+    }
+    buffer.split_to(len).freeze()
+}
+
+/// The data of each event in `events`: its `data:` lines, joined by
+/// newlines. Comments, other fields and events without data are skipped.
+fn event_data(events: &str) -> Vec<String> {
+    let mut data = Vec::new();
+    let mut lines: Vec<&str> = Vec::new();
+    for line in events.lines().chain([""]) {
+        if line.is_empty() {
+            if !lines.is_empty() {
+                data.push(lines.join("\n"));
+                lines.clear();
+            }
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("data:") {
+            lines.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    data
+}
+
+/// Picks the finish reason, usage and token timing out of complete events.
+fn observe_events(
+    events: &[u8],
+    labels: &RequestLabels,
+    timing: &mut TokenTiming,
+    usage_report: &mut Option<UsageReport>,
+    finish_reason: &mut Option<String>,
+    usage: &mut Option<Value>,
+    estimate: &mut Option<TokenEstimate>,
+) {
+    for data in event_data(&String::from_utf8_lossy(events)) {
+        if data == "[DONE]" {
+            continue;
+        }
+        debug!("Processing event: {}", data);
+        let json = match serde_json::from_str::<Value>(&data) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to parse JSON: {} in {}", e, data);
+                continue;
+            }
+        };
+        if let Some(report) = usage_report.as_mut() {
+            report.observe(&json);
+        }
+        timing.observe(&json, labels);
+        if let Some(estimate) = estimate.as_mut() {
+            estimate.observe(&json);
+        }
+        if let Some(reason) = json["choices"][0]["finish_reason"].as_str() {
+            *finish_reason = Some(reason.to_string());
+        }
+        // Some providers repeat cumulative usage on every chunk, so only
+        // the last one seen is counted.
+        if json.get("usage").is_some_and(Value::is_object) {
+            *usage = Some(json);
+        }
     }
 }
 
@@ -40,52 +599,302 @@ impl http_body::Body for ReqwestStreamAdapter {
         self: Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
-        let this = self.project();
-        match this.inner.poll_next(cx) {
-            std::task::Poll::Ready(Some(Ok(chunk))) => {
-                let chunk_str = String::from_utf8_lossy(&chunk);
-                for event in chunk_str.split("\n\n") {
-                    let cleaned_event = event.trim().strip_prefix("data: ").unwrap_or(event);
-
-                    if cleaned_event.is_empty() || cleaned_event == "[DONE]" {
-                        continue;
+        let mut this = self.project();
+        if let Some(frame) = this.pending.pop_front() {
+            return std::task::Poll::Ready(Some(Ok(frame)));
+        }
+        if *this.finished {
+            return std::task::Poll::Ready(None);
+        }
+        loop {
+            let chunk = match this.inner.as_mut().poll_next(cx) {
+                std::task::Poll::Ready(Some(Ok(chunk))) => chunk,
+                std::task::Poll::Ready(Some(Err(e))) => {
+                    *this.finished = true;
+                    return std::task::Poll::Ready(Some(Err(GatewayApiError::from(e))));
+                }
+                std::task::Poll::Ready(None) => {
+                    *this.finished = true;
+                    // An unterminated last event is passed on as is.
+                    let rest = this.buffer.split().freeze();
+                    if !rest.is_empty() {
+                        observe_events(
+                            &rest,
+                            this.labels,
+                            this.timing,
+                            this.usage_report,
+                            this.finish_reason,
+                            this.usage,
+                            this.estimate,
+                        );
+                        this.pending.push_back(Frame::data(rest));
                     }
-
-                    debug!("Processing event: {}", cleaned_event);
-
-                    match serde_json::from_str::<Value>(cleaned_event) {
-                        Ok(json) => {
-                            // Handle final usage statistics
-                            if let Some(finish_reason) =
-                                json["choices"][0]["finish_reason"].as_str()
-                            {
-                                if finish_reason == "stop" {
-                                    if let Some(usage) = json.get("usage") {
-                                        let prompt = usage["prompt_tokens"].as_u64().unwrap_or(0);
-                                        let completion =
-                                            usage["completion_tokens"].as_u64().unwrap_or(0);
-                                        let total = usage["total_tokens"].as_u64().unwrap_or(0);
-                                        info!(
-                                            "Usage statistics: prompt={}, completion={}, total={}",
-                                            prompt, completion, total
-                                        );
-                                        track_token_usage(&json, this.llm_name);
-                                    }
-                                }
-                            }
+                    if let Some(report) = this.usage_report.as_mut() {
+                        if let Some(event) = report.event() {
+                            this.pending.push_back(Frame::data(event));
+                        }
+                        if let Some(trailers) = report.trailers() {
+                            this.pending.push_back(Frame::trailers(trailers));
                         }
-                        Err(e) => {
-                            warn!("Failed to parse JSON: {} in {}", e, cleaned_event);
+                    }
+                    return std::task::Poll::Ready(this.pending.pop_front().map(Ok));
+                }
+                std::task::Poll::Pending => {
+                    if let Some(keep_alive) = this.keep_alive.as_mut() {
+                        if keep_alive.timer.as_mut().poll(cx).is_ready() {
+                            keep_alive.reset();
+                            let comment = Bytes::from_static(KEEP_ALIVE_COMMENT);
+                            return std::task::Poll::Ready(Some(Ok(Frame::data(comment))));
                         }
                     }
+                    return std::task::Poll::Pending;
                 }
-                std::task::Poll::Ready(Some(Ok(Frame::data(chunk))))
+            };
+            if let Some(keep_alive) = this.keep_alive.as_mut() {
+                keep_alive.reset();
             }
-            std::task::Poll::Ready(Some(Err(e))) => {
-                std::task::Poll::Ready(Some(Err(GatewayApiError::from(e))))
+            // Events are passed on once complete, so one split across
+            // chunks is read whole.
+            this.buffer.extend_from_slice(&chunk);
+            let events = take_events(this.buffer);
+            if events.is_empty() {
+                continue;
             }
-            std::task::Poll::Ready(None) => std::task::Poll::Ready(None),
-            std::task::Poll::Pending => std::task::Poll::Pending,
+            observe_events(
+                &events,
+                this.labels,
+                this.timing,
+                this.usage_report,
+                this.finish_reason,
+                this.usage,
+                this.estimate,
+            );
+
+            // The usage event goes out just ahead of the terminating
+            // `[DONE]` so clients that stop reading there still see it.
+            let done_at = events
+                .windows(DONE_EVENT.len())
+                .position(|window| window == DONE_EVENT.as_bytes());
+            let event = done_at.and_then(|_| this.usage_report.as_mut()?.event());
+            if let (Some(index), Some(event)) = (done_at, event) {
+                this.pending.push_back(Frame::data(event));
+                this.pending.push_back(Frame::data(events.slice(index..)));
+                return std::task::Poll::Ready(Some(Ok(Frame::data(events.slice(..index)))));
+            }
+            return std::task::Poll::Ready(Some(Ok(Frame::data(events))));
         }
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    #[tokio::test]
+    async fn test_usage_event_and_trailers() {
+        let chunks: Vec<Result<Bytes, reqwest::Error>> = vec![
+            Ok(Bytes::from(
+                "data: {\"choices\":[{\"delta\":{\"content\":\"Hello there\"}}]}\n\n",
+            )),
+            Ok(Bytes::from("data: [DONE]\n\n")),
+        ];
+        let report = UsageReport::new(
+            StreamUsageConfig::default(),
+            "llm",
+            Some(Pricing {
+                input_per_million: 1_000_000.0,
+                output_per_million: 0.0,
+            }),
+            7,
+        );
+        let adapter = ReqwestStreamAdapter::new(
+            Box::pin(futures_util::stream::iter(chunks)),
+            RequestLabels::default(),
+            None,
+            Some(report),
+        );
+
+        let collected = adapter.collect().await.unwrap();
+        let trailers = collected.trailers().cloned().unwrap();
+        let body = String::from_utf8(collected.to_bytes().to_vec()).unwrap();
+        let (before_done, _) = body.split_once(DONE_EVENT).unwrap();
+        assert!(before_done.contains(&format!("event: {}", USAGE_EVENT)));
+        assert!(before_done.contains("\"completion_tokens\":3"));
+        assert_eq!(trailers["x-usage-prompt-tokens"], "7");
+        assert_eq!(trailers["x-usage-cost-usd"], "7.0");
+        assert_eq!(trailers["x-usage-estimated"], "true");
+    }
+
+    #[tokio::test]
+    async fn test_events_split_across_chunks() {
+        let upstream = concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"héllo\"}}]}\r\n\r\n",
+            ": comment\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":2,\"completion_tokens\":5,\"total_tokens\":7}}\n\n",
+            "data: [DONE]\n\n",
+        );
+        // Seven byte chunks split events, the JSON and the two bytes of `é`.
+        let chunks: Vec<Result<Bytes, reqwest::Error>> = upstream
+            .as_bytes()
+            .chunks(7)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        let report = UsageReport::new(StreamUsageConfig::default(), "llm", None, 0);
+        let adapter = ReqwestStreamAdapter::new(
+            Box::pin(futures_util::stream::iter(chunks)),
+            RequestLabels::default(),
+            None,
+            Some(report),
+        );
+
+        let body = adapter.collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let (before_done, after_done) = body.split_once(DONE_EVENT).unwrap();
+        let (forwarded, usage_event) = before_done.split_once("event: ").unwrap();
+        assert_eq!(
+            format!("{}{}{}", forwarded, DONE_EVENT, after_done),
+            upstream
+        );
+        assert!(usage_event.contains("\"completion_tokens\":5"));
+        assert!(usage_event.contains("\"estimated\":false"));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_stream_releases_upstream() {
+        // Stands in for the upstream connection, held by its body stream.
+        let connection = std::sync::Arc::new(());
+        let held = std::sync::Arc::clone(&connection);
+        let chunks: Vec<Result<Bytes, reqwest::Error>> = vec![Ok(Bytes::from(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+        ))];
+        let upstream = futures_util::stream::iter(chunks)
+            .chain(futures_util::stream::pending())
+            .map(move |chunk| {
+                let _ = &held;
+                chunk
+            });
+        let mut adapter =
+            ReqwestStreamAdapter::new(Box::pin(upstream), RequestLabels::default(), None, None);
+
+        let frame = adapter.frame().await.unwrap().unwrap();
+        assert!(frame.is_data());
+        assert!(!adapter.finished);
+        assert_eq!(std::sync::Arc::strong_count(&connection), 2);
+        // The client going away drops the body mid-stream.
+        drop(adapter);
+        assert_eq!(std::sync::Arc::strong_count(&connection), 1);
+    }
+
+    #[tokio::test]
+    async fn test_first_chunk() {
+        let response = |chunks: Vec<Result<Bytes, std::io::Error>>| {
+            let body = futures_util::stream::iter(chunks).chain(futures_util::stream::pending());
+            reqwest::Response::from(http::Response::new(reqwest::Body::wrap_stream(body)))
+        };
+        let timeout = Some(Duration::from_millis(50));
+
+        let chunks = vec![Ok(Bytes::new()), Ok(Bytes::from("data: 1\n\n"))];
+        let mut peeked = first_chunk(response(chunks), timeout)
+            .await
+            .unwrap()
+            .bytes_stream();
+        assert_eq!(peeked.next().await.unwrap().unwrap(), "data: 1\n\n");
+
+        let stalled = first_chunk(response(vec![Ok(Bytes::new())]), timeout).await;
+        assert_eq!(stalled.unwrap_err(), "no chunk within 50 ms");
+        let broken = vec![Err(std::io::Error::other("reset"))];
+        assert!(first_chunk(response(broken), timeout).await.is_err());
+    }
+
+    #[test]
+    fn test_token_timing() {
+        let start = Instant::now();
+        let mut timing = TokenTiming::new(start);
+        let labels = RequestLabels::default();
+        timing.observe(
+            &json!({"choices": [{"delta": {"role": "assistant", "content": ""}}]}),
+            &labels,
+        );
+        assert_eq!(timing.first, None);
+        timing.observe(
+            &json!({"choices": [{"delta": {"content": "Hello"}}]}),
+            &labels,
+        );
+        assert!(timing.first.is_some());
+        assert_eq!(timing.tokens_per_second(None), None);
+
+        timing.first = Some(start);
+        timing.last = Some(start + Duration::from_secs(2));
+        timing.completion_chars = 80;
+        assert_eq!(timing.tokens_per_second(None), Some(10.0));
+        let usage = json!({"usage": {"completion_tokens": 100}});
+        assert_eq!(timing.tokens_per_second(Some(&usage)), Some(50.0));
+    }
+
+    #[test]
+    fn test_event_data() {
+        let events = "event: usage\ndata: {\"a\":\ndata: 1}\n\n: keep-alive\n\ndata:[DONE]";
+        assert_eq!(event_data(events), vec!["{\"a\":\n1}", "[DONE]"]);
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_during_silence() {
+        let chunks = futures_util::stream::unfold(0, |sent| async move {
+            if sent == 1 {
+                return None;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let chunk: Result<Bytes, reqwest::Error> = Ok(Bytes::from("data: [DONE]\n\n"));
+            Some((chunk, sent + 1))
+        });
+        let adapter =
+            ReqwestStreamAdapter::new(Box::pin(chunks), RequestLabels::default(), None, None)
+                .with_keep_alive(Some(Duration::from_millis(20)));
+
+        let body = adapter.collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let (before_done, _) = body.split_once(DONE_EVENT).unwrap();
+        assert!(
+            before_done.matches(": keep-alive\n\n").count() >= 2,
+            "{}",
+            body
+        );
+        assert_eq!(before_done.replace(": keep-alive\n\n", ""), "");
+    }
+
+    #[tokio::test]
+    async fn test_usage_counted_for_any_finish_reason() {
+        let chunks: Vec<Result<Bytes, reqwest::Error>> = vec![
+            Ok(Bytes::from(
+                "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"length\"}]}\n\n",
+            )),
+            Ok(Bytes::from(
+                "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":2,\"completion_tokens\":5,\"total_tokens\":7}}\n\n",
+            )),
+        ];
+        let labels = RequestLabels {
+            policy: Some("stream_finish_reason_test".to_string()),
+            ..Default::default()
+        };
+        let adapter = ReqwestStreamAdapter::new(
+            Box::pin(futures_util::stream::iter(chunks)),
+            labels,
+            None,
+            None,
+        );
+        adapter.collect().await.unwrap();
+
+        let completion = crate::metrics::TOKEN_USAGE
+            .with_label_values(&[
+                "stream_finish_reason_test",
+                "unknown",
+                "unknown",
+                "unknown",
+                "completion",
+                "true",
+                "length",
+            ])
+            .get();
+        assert_eq!(completion, 5);
+    }
+}
@@ -0,0 +1,207 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tenant
+//!
+//! Teams sharing one gateway. A request's tenant is resolved from its
+//! bearer key, then from the JWT tenant claim, and only for requests
+//! carrying no credentials from the configured header. The request is kept
+//! to the policies and LLMs of that tenant.
+use crate::caller::Caller;
+use crate::config::{Llm, Tenant, TenantsConfig};
+use crate::error::GatewayApiError;
+use http::{HeaderMap, StatusCode};
+use log::warn;
+
+/// The tenant of a request, `None` for requests of no tenant when those
+/// are allowed. `claimed` is the tenant claim of the caller's JWT.
+pub fn resolve<'a>(
+    config: &'a TenantsConfig,
+    caller: &Caller,
+    headers: &HeaderMap,
+    claimed: Option<&str>,
+) -> Result<Option<&'a Tenant>, GatewayApiError> {
+    if let Some(key) = caller.api_key {
+        let owner = config.tenants.iter().find(|tenant| tenant.owns_key(key));
+        if owner.is_some() {
+            return Ok(owner);
+        }
+    }
+    // The header is set by whatever sits in front of the gateway, so it is
+    // only trusted for requests the gateway cannot identify itself.
+    let named = claimed.or_else(|| {
+        if caller.api_key.is_some() || caller.subject.is_some() {
+            return None;
+        }
+        config
+            .header
+            .as_ref()
+            .and_then(|header| headers.get(header.as_str()))
+            .and_then(|value| value.to_str().ok())
+    });
+    match named {
+        Some(id) => match config.tenants.iter().find(|tenant| tenant.id == id) {
+            Some(tenant) => Ok(Some(tenant)),
+            None => {
+                warn!("Refused request of unknown tenant '{}'", id);
+                Err(GatewayApiError::client_error(
+                    StatusCode::FORBIDDEN,
+                    format!("Tenant '{}' is not known", id),
+                    "unknown_tenant",
+                ))
+            }
+        },
+        None if config.required => Err(GatewayApiError::client_error(
+            StatusCode::UNAUTHORIZED,
+            "Requests must identify their tenant",
+            "tenant_required",
+        )),
+        None => Ok(None),
+    }
+}
+
+pub fn check_policy(tenant: &Tenant, policy: &str) -> Result<(), GatewayApiError> {
+    if tenant.allows_policy(policy) {
+        return Ok(());
+    }
+    Err(GatewayApiError::client_error(
+        StatusCode::FORBIDDEN,
+        format!("Tenant '{}' may not use policy '{}'", tenant.id, policy),
+        "policy_not_allowed",
+    ))
+}
+
+/// The LLMs of `chain` the tenant may be served by, in order. An LLM the
+/// caller asked for by name must be one of them; otherwise the chosen LLM
+/// is replaced by its first allowed fallback.
+pub fn restrict(
+    tenant: &Tenant,
+    chain: Vec<Llm>,
+    requested: bool,
+) -> Result<Vec<Llm>, GatewayApiError> {
+    let chosen = chain
+        .first()
+        .map(|llm| llm.name.clone())
+        .unwrap_or_default();
+    let allowed: Vec<Llm> = chain
+        .into_iter()
+        .filter(|llm| tenant.allows_model(&llm.name))
+        .collect();
+    let refused = match allowed.first() {
+        Some(first) => requested && first.name != chosen,
+        None => true,
+    };
+    if refused {
+        return Err(GatewayApiError::client_error(
+            StatusCode::FORBIDDEN,
+            format!("Tenant '{}' may not use LLM '{}'", tenant.id, chosen),
+            "model_not_allowed",
+        ));
+    }
+    Ok(allowed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn config() -> TenantsConfig {
+        TenantsConfig {
+            header: Some("x-tenant-id".to_string()),
+            required: true,
+            tenants: vec![
+                Tenant {
+                    id: "search".to_string(),
+                    api_keys: vec!["search-key".to_string()],
+                    allowed_policies: vec!["task_router".to_string()],
+                    allowed_models: vec!["Chatbot".to_string()],
+                    ..Default::default()
+                },
+                Tenant {
+                    id: "research".to_string(),
+                    ..Default::default()
+                },
+            ],
+        }
+    }
+
+    fn llm(name: &str) -> Llm {
+        Llm {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let config = config();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer search-key"),
+        );
+        headers.insert("x-tenant-id", HeaderValue::from_static("research"));
+        let caller = Caller::new(&headers, None, None);
+        let tenant = resolve(&config, &caller, &headers, None).unwrap();
+        assert_eq!(tenant.map(|t| t.id.as_str()), Some("search"));
+
+        headers.remove("authorization");
+        let caller = Caller::new(&headers, None, None);
+        let tenant = resolve(&config, &caller, &headers, None).unwrap();
+        assert_eq!(tenant.map(|t| t.id.as_str()), Some("research"));
+
+        let empty = HeaderMap::new();
+        let caller = Caller::new(&empty, None, None);
+        let tenant = resolve(&config, &caller, &empty, Some("search")).unwrap();
+        assert_eq!(tenant.map(|t| t.id.as_str()), Some("search"));
+        assert!(resolve(&config, &caller, &empty, Some("unknown")).is_err());
+        assert!(resolve(&config, &caller, &empty, None).is_err());
+    }
+
+    #[test]
+    fn test_resolve_ignores_header_of_credentialed_callers() {
+        let config = config();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant-id", HeaderValue::from_static("search"));
+        headers.insert("authorization", HeaderValue::from_static("Bearer jwt"));
+        let caller = Caller::new(&headers, Some("alice"), None);
+        let tenant = resolve(&config, &caller, &headers, Some("research")).unwrap();
+        assert_eq!(tenant.map(|t| t.id.as_str()), Some("research"));
+        assert!(resolve(&config, &caller, &headers, None).is_err());
+
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer search-kex"),
+        );
+        let caller = Caller::new(&headers, None, None);
+        assert!(resolve(&config, &caller, &headers, None).is_err());
+    }
+
+    #[test]
+    fn test_restrict() {
+        let config = config();
+        let search = &config.tenants[0];
+        assert!(check_policy(search, "task_router").is_ok());
+        assert!(check_policy(search, "complexity_router").is_err());
+
+        let chain = vec![llm("Brainstorming"), llm("Chatbot")];
+        let allowed = restrict(search, chain.clone(), false).unwrap();
+        assert_eq!(allowed.len(), 1);
+        assert_eq!(allowed[0].name, "Chatbot");
+        assert!(restrict(search, chain, true).is_err());
+        assert!(restrict(search, vec![llm("Brainstorming")], false).is_err());
+    }
+}
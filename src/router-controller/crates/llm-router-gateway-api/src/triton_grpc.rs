@@ -0,0 +1,278 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Triton gRPC
+//!
+//! Client for the KServe v2 `ModelInfer` call, for Triton deployments that
+//! only expose gRPC or where the JSON encoding adds noticeable latency.
+//! `https://` URLs are served over TLS with the system's trusted roots.
+use crate::config::TritonGrpcConfig;
+use crate::error::GatewayApiError;
+use lazy_static::lazy_static;
+use log::{error, info};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Request, Status};
+
+/// Messages and client generated from `proto/grpc_service.proto`.
+pub mod inference {
+    tonic::include_proto!("inference");
+}
+
+use inference::grpc_inference_service_client::GrpcInferenceServiceClient;
+use inference::model_infer_request::InferInputTensor;
+use inference::{InferTensorContents, ModelInferRequest, ModelInferResponse};
+
+/// Deadline of calls from policies without a classifier `request_ms`.
+pub const DEFAULT_DEADLINE: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+lazy_static! {
+    /// One channel per Triton URL, multiplexing every request over HTTP/2.
+    static ref CHANNELS: Mutex<HashMap<String, Channel>> = Mutex::new(HashMap::new());
+}
+
+fn service_error(status_code: u16, message: String) -> GatewayApiError {
+    error!("{}", message);
+    GatewayApiError::TritonServiceError {
+        status_code,
+        message,
+    }
+}
+
+fn channel(url: &str) -> Result<Channel, GatewayApiError> {
+    let mut channels = CHANNELS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(channel) = channels.get(url) {
+        return Ok(channel.clone());
+    }
+    let channel = Endpoint::new(url.to_string())
+        .map_err(|e| service_error(500, format!("Invalid Triton gRPC url: {}", e)))?
+        .connect_timeout(CONNECT_TIMEOUT)
+        .connect_lazy();
+    channels.insert(url.to_string(), channel.clone());
+    Ok(channel)
+}
+
+/// A `ModelInferRequest` with one `[1, 1]` BYTES tensor named `INPUT`, the
+/// same input the HTTP API is sent.
+fn infer_request(config: &TritonGrpcConfig, text_input: &str) -> ModelInferRequest {
+    ModelInferRequest {
+        model_name: config.model_name.clone(),
+        model_version: config.model_version.clone(),
+        inputs: vec![InferInputTensor {
+            name: "INPUT".to_string(),
+            datatype: "BYTES".to_string(),
+            shape: vec![1, 1],
+            contents: Some(InferTensorContents {
+                bytes_contents: vec![text_input.as_bytes().to_vec()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+fn decode_floats(datatype: &str, raw: &[u8]) -> Option<Vec<f64>> {
+    match datatype {
+        "FP32" | "" => Some(
+            raw.chunks_exact(4)
+                .map(|b| f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]])))
+                .collect(),
+        ),
+        "FP64" => Some(
+            raw.chunks_exact(8)
+                .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Scores of the first output tensor, read from `raw_output_contents` when
+/// Triton sends them there and from the typed contents otherwise.
+fn output_scores(response: &ModelInferResponse) -> Option<Vec<f64>> {
+    let output = response.outputs.first()?;
+    if let Some(raw) = response.raw_output_contents.first() {
+        return decode_floats(&output.datatype, raw);
+    }
+    let contents = output.contents.as_ref()?;
+    Some(
+        contents
+            .fp32_contents
+            .iter()
+            .map(|&score| f64::from(score))
+            .chain(contents.fp64_contents.iter().copied())
+            .collect(),
+    )
+}
+
+fn status_error(status: Status) -> GatewayApiError {
+    let status_code = match status.code() {
+        // Reported while a model is loading, or when Triton is unreachable.
+        Code::Unavailable => 503,
+        // Tonic servers answer `CANCELLED` to calls past their `grpc-timeout`.
+        Code::DeadlineExceeded | Code::Cancelled => 504,
+        _ => 500,
+    };
+    service_error(
+        status_code,
+        format!(
+            "Triton gRPC error {:?}: {}",
+            status.code(),
+            status.message()
+        ),
+    )
+}
+
+/// Raw class probabilities for `text_input` from Triton's gRPC endpoint.
+/// Calls not answered within `deadline`, or [`DEFAULT_DEADLINE`], fail with
+/// a 504 and are cancelled on Triton's side too.
+pub async fn infer(
+    config: &TritonGrpcConfig,
+    text_input: &str,
+    deadline: Option<Duration>,
+) -> Result<Vec<f64>, GatewayApiError> {
+    let deadline = deadline.unwrap_or(DEFAULT_DEADLINE);
+    let mut client = GrpcInferenceServiceClient::new(channel(&config.url)?);
+    let mut request = Request::new(infer_request(config, text_input));
+    request.set_timeout(deadline);
+
+    let response = tokio::time::timeout(deadline, client.model_infer(request))
+        .await
+        .map_err(|_| {
+            service_error(
+                504,
+                format!("Triton did not respond within {} ms", deadline.as_millis()),
+            )
+        })?
+        .map_err(status_error)?
+        .into_inner();
+    let scores = output_scores(&response)
+        .filter(|scores| !scores.is_empty())
+        .ok_or_else(|| service_error(500, "Invalid Triton gRPC response".to_string()))?;
+    info!("Triton gRPC scores: {:?}", scores);
+    Ok(scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inference::grpc_inference_service_server::{
+        GrpcInferenceService, GrpcInferenceServiceServer,
+    };
+    use inference::model_infer_response::InferOutputTensor;
+    use tonic::transport::server::TcpIncoming;
+    use tonic::transport::Server;
+    use tonic::Response;
+
+    struct Triton;
+
+    #[tonic::async_trait]
+    impl GrpcInferenceService for Triton {
+        async fn model_infer(
+            &self,
+            request: Request<ModelInferRequest>,
+        ) -> Result<Response<ModelInferResponse>, Status> {
+            let request = request.into_inner();
+            let input = &request.inputs[0];
+            assert_eq!(input.shape, vec![1, 1]);
+            assert_eq!(
+                input.contents.as_ref().unwrap().bytes_contents,
+                vec![b"Hello world!".to_vec()]
+            );
+            let response = match request.model_name.as_str() {
+                "raw" => ModelInferResponse {
+                    outputs: vec![InferOutputTensor {
+                        datatype: "FP32".to_string(),
+                        ..Default::default()
+                    }],
+                    raw_output_contents: vec![[0.25f32, 0.75]
+                        .iter()
+                        .flat_map(|v| v.to_le_bytes())
+                        .collect()],
+                    ..Default::default()
+                },
+                "typed" => ModelInferResponse {
+                    outputs: vec![InferOutputTensor {
+                        datatype: "FP64".to_string(),
+                        contents: Some(InferTensorContents {
+                            fp64_contents: vec![0.5, 0.5],
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                "slow" => {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    ModelInferResponse::default()
+                }
+                _ => return Err(Status::unavailable("model is loading")),
+            };
+            Ok(Response::new(response))
+        }
+    }
+
+    async fn start_triton() -> String {
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let url = format!("http://{}", incoming.local_addr().unwrap());
+        tokio::spawn(
+            Server::builder()
+                .add_service(GrpcInferenceServiceServer::new(Triton))
+                .serve_with_incoming(incoming),
+        );
+        url
+    }
+
+    #[tokio::test]
+    async fn test_infer() {
+        let url = start_triton().await;
+        let config = |model_name: &str| TritonGrpcConfig {
+            url: url.clone(),
+            model_name: model_name.to_string(),
+            ..Default::default()
+        };
+
+        let scores = infer(&config("raw"), "Hello world!", None).await.unwrap();
+        assert_eq!(scores, vec![0.25, 0.75]);
+        let scores = infer(&config("typed"), "Hello world!", None).await.unwrap();
+        assert_eq!(scores, vec![0.5, 0.5]);
+
+        let loading = infer(&config("loading"), "Hello world!", None).await;
+        assert!(matches!(
+            loading,
+            Err(GatewayApiError::TritonServiceError {
+                status_code: 503,
+                ..
+            })
+        ));
+        let slow = infer(
+            &config("slow"),
+            "Hello world!",
+            Some(Duration::from_millis(50)),
+        )
+        .await;
+        assert!(matches!(
+            slow,
+            Err(GatewayApiError::TritonServiceError {
+                status_code: 504,
+                ..
+            })
+        ));
+    }
+}
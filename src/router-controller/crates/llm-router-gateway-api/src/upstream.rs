@@ -0,0 +1,507 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Upstream
+//!
+//! The HTTP client shared by all upstream calls, so connections are pooled
+//! across requests, instrumented with connection level metrics: DNS
+//! resolution time and the cost of every new connection, per host. Upstreams
+//! with their own connect timeout or TLS settings share a client per
+//! combination of the two. Requests in flight are counted per host and can
+//! be capped, so HTTP/2 connections multiplex a bounded number of streams.
+//! Compressed responses are decoded by the client, and only end-to-end
+//! headers of a response are passed on to the caller.
+use crate::config::{Http2Mode, UpstreamPoolConfig, UpstreamTls};
+use crate::metrics::{
+    UPSTREAM_ACTIVE_STREAMS, UPSTREAM_CONNECT_TIME, UPSTREAM_DNS_TIME, UPSTREAM_NEW_CONNECTIONS,
+    UPSTREAM_REQUESTS,
+};
+use http::header::{
+    HeaderMap, HeaderName, CONNECTION, CONTENT_LENGTH, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, TE,
+    TRAILER, TRANSFER_ENCODING, UPGRADE,
+};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower_layer::Layer;
+use tower_service::Service;
+
+static POOL: OnceLock<UpstreamPoolConfig> = OnceLock::new();
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+/// Clients by connect timeout in milliseconds and TLS settings.
+type ClientKey = (Option<u64>, Option<UpstreamTls>);
+static CLIENTS: OnceLock<Mutex<HashMap<ClientKey, reqwest::Client>>> = OnceLock::new();
+/// Stream slots by host, when `max_streams_per_host` is set.
+static STREAM_LIMITS: OnceLock<Mutex<HashMap<String, Arc<Semaphore>>>> = OnceLock::new();
+
+/// Headers describing a single connection rather than the response.
+static HOP_BY_HOP_HEADERS: [HeaderName; 8] = [
+    CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    PROXY_AUTHENTICATE,
+    PROXY_AUTHORIZATION,
+    TE,
+    TRAILER,
+    TRANSFER_ENCODING,
+    UPGRADE,
+];
+
+/// Host label for connections made without a DNS lookup (IP literals).
+const UNRESOLVED_HOST: &str = "unresolved";
+
+/// What the resolver learned while a connection was being set up.
+#[derive(Debug, Default)]
+struct ConnectContext {
+    host: Option<String>,
+    dns: Duration,
+}
+
+tokio::task_local! {
+    static CONNECT: Arc<Mutex<ConnectContext>>;
+}
+
+/// Resolves through the system resolver and times each lookup.
+#[derive(Debug, Clone, Copy, Default)]
+struct TimedResolver;
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let context = CONNECT.try_with(Arc::clone).ok();
+        Box::pin(async move {
+            let start = Instant::now();
+            let addrs: Vec<_> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            let elapsed = start.elapsed();
+            UPSTREAM_DNS_TIME
+                .with_label_values(&[&host])
+                .observe(elapsed.as_secs_f64());
+            if let Some(context) = context {
+                let mut context = context.lock().unwrap_or_else(|e| e.into_inner());
+                context.host = Some(host);
+                context.dns = elapsed;
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Times every new connection the pool opens.
+#[derive(Debug, Clone, Copy, Default)]
+struct ConnectMetricsLayer;
+
+impl<S> Layer<S> for ConnectMetricsLayer {
+    type Service = ConnectMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectMetrics { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ConnectMetrics<S> {
+    inner: S,
+}
+
+impl<S, R> Service<R> for ConnectMetrics<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let connecting = self.inner.call(request);
+        Box::pin(async move {
+            let context = Arc::new(Mutex::new(ConnectContext::default()));
+            let start = Instant::now();
+            let result = CONNECT.scope(Arc::clone(&context), connecting).await;
+            let elapsed = start.elapsed();
+
+            let context = context.lock().unwrap_or_else(|e| e.into_inner());
+            let host = context.host.as_deref().unwrap_or(UNRESOLVED_HOST);
+            let outcome = if result.is_ok() { "success" } else { "error" };
+            UPSTREAM_NEW_CONNECTIONS
+                .with_label_values(&[host, outcome])
+                .inc();
+            if result.is_ok() {
+                UPSTREAM_CONNECT_TIME
+                    .with_label_values(&[host])
+                    .observe(elapsed.saturating_sub(context.dns).as_secs_f64());
+            }
+            result
+        })
+    }
+}
+
+fn read_pem(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("cannot read '{}': {}", path, e))
+}
+
+/// Adds the CA bundle and client identity of `tls` to `builder`.
+fn with_tls(
+    builder: reqwest::ClientBuilder,
+    tls: &UpstreamTls,
+) -> Result<reqwest::ClientBuilder, String> {
+    let mut builder = builder;
+    if let Some(path) = &tls.ca_cert {
+        let certs = reqwest::Certificate::from_pem_bundle(&read_pem(path)?)
+            .map_err(|e| format!("invalid CA bundle '{}': {}", path, e))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if let (Some(cert_path), Some(key_path)) = (&tls.client_cert, &tls.client_key) {
+        let identity =
+            reqwest::Identity::from_pkcs8_pem(&read_pem(cert_path)?, &read_pem(key_path)?)
+                .map_err(|e| format!("invalid client certificate '{}': {}", cert_path, e))?;
+        builder = builder.identity(identity);
+    }
+    Ok(builder)
+}
+
+fn build(config: &UpstreamPoolConfig, connect_timeout: Option<Duration>) -> reqwest::Client {
+    build_with(config, connect_timeout, None).unwrap_or_else(|e| {
+        log::error!("Failed to build instrumented upstream client: {}", e);
+        reqwest::Client::new()
+    })
+}
+
+fn build_with(
+    config: &UpstreamPoolConfig,
+    connect_timeout: Option<Duration>,
+    tls: Option<&UpstreamTls>,
+) -> Result<reqwest::Client, String> {
+    let builder = reqwest::Client::builder()
+        .dns_resolver(Arc::new(TimedResolver))
+        .connector_layer(ConnectMetricsLayer)
+        .pool_max_idle_per_host(config.max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(config.idle_timeout_seconds))
+        // Bodies are read as JSON and SSE, so they are taken uncompressed.
+        .gzip(true)
+        .brotli(true)
+        .deflate(true);
+    let builder = match config.http2 {
+        Http2Mode::Auto => builder,
+        Http2Mode::PriorKnowledge => builder.http2_prior_knowledge(),
+        Http2Mode::Disabled => builder.http1_only(),
+    };
+    let builder = match connect_timeout {
+        Some(timeout) => builder.connect_timeout(timeout),
+        None => builder,
+    };
+    let builder = match tls {
+        Some(tls) => with_tls(builder, tls)?,
+        None => builder,
+    };
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Builds the shared client. Only the first call has an effect.
+pub fn init(config: &UpstreamPoolConfig) {
+    let _ = POOL.set(config.clone());
+    let _ = CLIENT.set(build(config, None));
+}
+
+fn pool() -> &'static UpstreamPoolConfig {
+    POOL.get_or_init(UpstreamPoolConfig::default)
+}
+
+/// The shared upstream client, built with defaults if `init` was not called.
+pub fn client() -> reqwest::Client {
+    CLIENT.get_or_init(|| build(pool(), None)).clone()
+}
+
+/// The client for upstreams that give up connecting after `connect_ms` or
+/// connect with `tls`. Fails when the TLS files cannot be loaded; the next
+/// call tries again.
+pub fn client_for(
+    connect_ms: Option<u64>,
+    tls: Option<&UpstreamTls>,
+) -> Result<reqwest::Client, String> {
+    if connect_ms.is_none() && tls.is_none() {
+        return Ok(client());
+    }
+    let key = (connect_ms, tls.cloned());
+    let mut clients = CLIENTS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(client) = clients.get(&key) {
+        return Ok(client.clone());
+    }
+    let client = build_with(pool(), connect_ms.map(Duration::from_millis), tls)?;
+    clients.insert(key, client.clone());
+    Ok(client)
+}
+
+/// A request in flight to a host, holding one of its stream slots until
+/// dropped together with the response.
+#[derive(Debug)]
+pub struct StreamSlot {
+    host: String,
+    _permit: Option<OwnedSemaphorePermit>,
+    /// Slot of the LLM's `max_concurrent_requests`, if it has one.
+    _llm_permit: Option<OwnedSemaphorePermit>,
+}
+
+impl StreamSlot {
+    /// Keeps `permit` until the response is dropped.
+    pub fn hold(mut self, permit: Option<OwnedSemaphorePermit>) -> Self {
+        self._llm_permit = permit;
+        self
+    }
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        UPSTREAM_ACTIVE_STREAMS
+            .with_label_values(&[&self.host])
+            .dec();
+    }
+}
+
+fn host_label(url: &reqwest::Url) -> String {
+    url.host_str().unwrap_or(UNRESOLVED_HOST).to_string()
+}
+
+/// Waits for a stream slot of the host of `url` when
+/// `max_streams_per_host` is set, for up to `wait` when given. `None` when
+/// no slot freed up in time.
+pub async fn acquire(url: &reqwest::Url, wait: Option<Duration>) -> Option<StreamSlot> {
+    acquire_with(url, pool().max_streams_per_host, wait).await
+}
+
+async fn acquire_with(
+    url: &reqwest::Url,
+    limit: Option<usize>,
+    wait: Option<Duration>,
+) -> Option<StreamSlot> {
+    let host = host_label(url);
+    let permit = match limit {
+        Some(limit) => {
+            let semaphore = STREAM_LIMITS
+                .get_or_init(Default::default)
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(host.clone())
+                .or_insert_with(|| Arc::new(Semaphore::new(limit.max(1))))
+                .clone();
+            let acquired = match wait {
+                Some(wait) => tokio::time::timeout(wait, semaphore.acquire_owned())
+                    .await
+                    .ok()?,
+                None => semaphore.acquire_owned().await,
+            };
+            // The semaphore is never closed.
+            acquired.ok()
+        }
+        None => None,
+    };
+    UPSTREAM_ACTIVE_STREAMS.with_label_values(&[&host]).inc();
+    Some(StreamSlot {
+        host,
+        _permit: permit,
+        _llm_permit: None,
+    })
+}
+
+/// The headers of an upstream response to pass on to the caller: those
+/// about the upstream connection, including any it names in `Connection`,
+/// are dropped. So is `Content-Length`, which the body the caller receives
+/// need not match once decoded or translated; it is set again from that
+/// body.
+pub fn response_headers(headers: &HeaderMap) -> HeaderMap {
+    let named: Vec<HeaderName> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    let mut headers = headers.clone();
+    for name in HOP_BY_HOP_HEADERS.iter().chain(&named) {
+        headers.remove(name);
+    }
+    headers.remove(CONTENT_LENGTH);
+    headers
+}
+
+/// Counts a response by host and HTTP version.
+pub fn observe(response: &reqwest::Response) {
+    let version = format!("{:?}", response.version());
+    UPSTREAM_REQUESTS
+        .with_label_values(&[&host_label(response.url()), &version])
+        .inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::x509::{X509NameBuilder, X509};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Writes a self-signed certificate and its key, returning their paths.
+    fn write_certificate(name: &str) -> (String, String) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut subject = X509NameBuilder::new().unwrap();
+        subject.append_entry_by_text("CN", "llm-router").unwrap();
+        let subject = subject.build();
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&subject).unwrap();
+        cert.set_issuer_name(&subject).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        cert.sign(&key, MessageDigest::sha256()).unwrap();
+
+        let dir = std::env::temp_dir();
+        let prefix = format!("llm-router-tls-{}-{}", name, std::process::id());
+        let cert_path = dir.join(format!("{}.crt", prefix));
+        let key_path = dir.join(format!("{}.key", prefix));
+        std::fs::write(&cert_path, cert.build().to_pem().unwrap()).unwrap();
+        std::fs::write(&key_path, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+        (
+            cert_path.to_string_lossy().into_owned(),
+            key_path.to_string_lossy().into_owned(),
+        )
+    }
+
+    #[test]
+    fn test_tls_clients() {
+        let (cert, key) = write_certificate("client");
+        let tls = UpstreamTls {
+            ca_cert: Some(cert.clone()),
+            client_cert: Some(cert),
+            client_key: Some(key),
+        };
+        assert!(client_for(Some(1000), Some(&tls)).is_ok());
+
+        let missing = UpstreamTls {
+            ca_cert: Some("/nonexistent/ca.pem".to_string()),
+            ..Default::default()
+        };
+        let error = client_for(None, Some(&missing)).unwrap_err();
+        assert!(error.contains("/nonexistent/ca.pem"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_stream_slots() {
+        let url = reqwest::Url::parse("https://slots-test.invalid/v1/chat/completions").unwrap();
+        let active = || {
+            UPSTREAM_ACTIVE_STREAMS
+                .with_label_values(&["slots-test.invalid"])
+                .get()
+        };
+        let first = acquire_with(&url, Some(1), None).await.unwrap();
+        assert_eq!(active(), 1);
+        let waiting = acquire_with(&url, Some(1), None);
+        let waiting = tokio::time::timeout(Duration::from_millis(50), waiting);
+        assert!(waiting.await.is_err());
+        let bounded = acquire_with(&url, Some(1), Some(Duration::from_millis(20)));
+        assert!(bounded.await.is_none());
+        assert_eq!(active(), 1);
+
+        drop(first);
+        assert_eq!(active(), 0);
+        let second = acquire_with(&url, Some(1), None).await.unwrap();
+        let unlimited = acquire_with(&url, None, None).await.unwrap();
+        assert_eq!(active(), 2);
+        drop((second, unlimited));
+        assert_eq!(active(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connections_are_measured_per_host() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let url = format!("http://localhost:{}/", server.address().port());
+
+        let client = build(&UpstreamPoolConfig::default(), None);
+        for _ in 0..2 {
+            client.get(&url).send().await.unwrap();
+        }
+        let opened = UPSTREAM_NEW_CONNECTIONS
+            .with_label_values(&["localhost", "success"])
+            .get();
+        assert!(opened >= 1);
+        assert!(
+            UPSTREAM_DNS_TIME
+                .with_label_values(&["localhost"])
+                .get_sample_count()
+                >= 1
+        );
+        assert!(
+            UPSTREAM_CONNECT_TIME
+                .with_label_values(&["localhost"])
+                .get_sample_count()
+                >= 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compressed_response() {
+        // `{"id":"compressed"}`, gzipped.
+        let gzipped: &[u8] = &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xab, 0x56, 0xca, 0x4c,
+            0x51, 0xb2, 0x52, 0x4a, 0xce, 0xcf, 0x2d, 0x28, 0x4a, 0x2d, 0x2e, 0x4e, 0x4d, 0x51,
+            0xaa, 0x05, 0x00, 0x2b, 0x72, 0x45, 0xa3, 0x13, 0x00, 0x00, 0x00,
+        ];
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-encoding", "gzip")
+                    .insert_header("connection", "keep-alive, x-upstream-hop")
+                    .insert_header("x-upstream-hop", "1")
+                    .insert_header("x-request-id", "abc")
+                    .set_body_raw(gzipped, "application/json"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = build(&UpstreamPoolConfig::default(), None);
+        let response = client.get(server.uri()).send().await.unwrap();
+        let headers = response_headers(response.headers());
+        assert!(headers.get("content-encoding").is_none());
+        assert!(headers.get(CONTENT_LENGTH).is_none());
+        assert!(headers.get(CONNECTION).is_none());
+        assert!(headers.get("x-upstream-hop").is_none());
+        assert_eq!(headers["x-request-id"], "abc");
+        assert_eq!(response.text().await.unwrap(), r#"{"id":"compressed"}"#);
+    }
+}
@@ -0,0 +1,173 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation
+use crate::error::GatewayApiError;
+use http::StatusCode;
+use log::warn;
+use serde_json::{json, Value};
+
+const KNOWN_FINISH_REASONS: &[&str] = &[
+    "stop",
+    "length",
+    "tool_calls",
+    "content_filter",
+    "function_call",
+];
+
+fn validate_message(index: usize, choice: &Value) -> Result<(), String> {
+    let message = choice
+        .get("message")
+        .filter(|m| m.is_object())
+        .ok_or_else(|| format!("choices[{}] has no message", index))?;
+    match message.get("role").and_then(Value::as_str) {
+        Some("assistant") => {}
+        Some(role) => return Err(format!("choices[{}] has invalid role '{}'", index, role)),
+        None => return Err(format!("choices[{}].message has no role", index)),
+    }
+    let content = message.get("content").unwrap_or(&Value::Null);
+    let has_tool_calls = message.get("tool_calls").is_some_and(Value::is_array);
+    if content.is_string() || (content.is_null() && has_tool_calls) {
+        Ok(())
+    } else {
+        Err(format!("choices[{}].message has no content", index))
+    }
+}
+
+fn validate_choice(index: usize, choice: &Value) -> Result<(), String> {
+    if !choice.is_object() {
+        return Err(format!("choices[{}] is not an object", index));
+    }
+    // Legacy completions carry `text` instead of a message.
+    if !choice.get("text").is_some_and(Value::is_string) {
+        validate_message(index, choice)?;
+    }
+    match choice.get("finish_reason") {
+        None | Some(Value::Null) => Ok(()),
+        Some(Value::String(reason)) if KNOWN_FINISH_REASONS.contains(&reason.as_str()) => Ok(()),
+        Some(reason) => Err(format!(
+            "choices[{}] has unknown finish_reason {}",
+            index, reason
+        )),
+    }
+}
+
+/// Checks a non-streaming completion body against the OpenAI response
+/// schema, returning the first violation found.
+pub fn validate_completion(json: &Value) -> Result<(), String> {
+    if !json.is_object() {
+        return Err("response is not a JSON object".to_string());
+    }
+    let choices = json
+        .get("choices")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "response has no choices".to_string())?;
+    if choices.is_empty() {
+        return Err("response has an empty choices list".to_string());
+    }
+    for (index, choice) in choices.iter().enumerate() {
+        validate_choice(index, choice)?;
+    }
+    Ok(())
+}
+
+/// Checks a `/v1/embeddings` body: a non-empty `data` list whose entries
+/// carry an `embedding`, as floats or base64.
+pub fn validate_embeddings(json: &Value) -> Result<(), String> {
+    let data = json
+        .get("data")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "response has no data".to_string())?;
+    if data.is_empty() {
+        return Err("response has an empty data list".to_string());
+    }
+    for (index, entry) in data.iter().enumerate() {
+        match entry.get("embedding") {
+            Some(Value::Array(values)) if values.iter().all(Value::is_number) => {}
+            Some(Value::String(_)) => {}
+            _ => return Err(format!("data[{}] has no embedding", index)),
+        }
+    }
+    Ok(())
+}
+
+/// Validates an upstream completion, or embeddings when `is_embedding`,
+/// converting malformed responses into a 502 attributed to `provider`.
+pub fn check(body: &[u8], provider: &str, is_embedding: bool) -> Result<(), GatewayApiError> {
+    let validate = if is_embedding {
+        validate_embeddings
+    } else {
+        validate_completion
+    };
+    let reason = match serde_json::from_slice::<Value>(body) {
+        Ok(json) => match validate(&json) {
+            Ok(()) => return Ok(()),
+            Err(reason) => reason,
+        },
+        Err(e) => format!("response is not valid JSON: {}", e),
+    };
+    warn!("Malformed response from LLM '{}': {}", provider, reason);
+    Err(GatewayApiError::LlmServiceError {
+        status: StatusCode::BAD_GATEWAY,
+        message: format!("LLM '{}' returned a malformed response", provider),
+        provider: provider.to_string(),
+        details: Some(json!({ "reason": reason })),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_completion() {
+        let valid = json!({
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi"},
+                "finish_reason": "stop"
+            }]
+        });
+        assert!(validate_completion(&valid).is_ok());
+
+        let tool_call = json!({
+            "choices": [{
+                "message": {"role": "assistant", "content": null, "tool_calls": []},
+                "finish_reason": "tool_calls"
+            }]
+        });
+        assert!(validate_completion(&tool_call).is_ok());
+
+        assert!(validate_completion(&json!({"object": "chat.completion"})).is_err());
+        let bad_role = json!({"choices": [{"message": {"role": "user", "content": "x"}}]});
+        assert!(validate_completion(&bad_role).is_err());
+        let bad_reason = json!({"choices": [{"text": "x", "finish_reason": "exploded"}]});
+        assert!(validate_completion(&bad_reason).is_err());
+
+        let embeddings = json!({"object": "list", "data": [{"embedding": [0.1, -0.2]}]});
+        assert!(validate_embeddings(&embeddings).is_ok());
+        assert!(validate_embeddings(&json!({"data": [{"embedding": "AAAA"}]})).is_ok());
+        assert!(validate_embeddings(&json!({"data": []})).is_err());
+        assert!(validate_embeddings(&valid).is_err());
+        assert!(check(embeddings.to_string().as_bytes(), "llm", true).is_ok());
+        assert!(check(embeddings.to_string().as_bytes(), "llm", false).is_err());
+
+        let error = check(b"<html>", "llm", false).unwrap_err();
+        assert!(matches!(
+            error,
+            GatewayApiError::LlmServiceError { status, .. } if status == StatusCode::BAD_GATEWAY
+        ));
+    }
+}
@@ -0,0 +1,273 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Vault
+//!
+//! API keys kept in HashiCorp Vault: an `api_key` of `vault:<path>#<key>`
+//! is replaced by that key of the secret when the upstream request is
+//! built. Secrets are read at startup, read again before their lease runs
+//! out, and read on first use when a reload references new ones.
+use crate::config::{Llm, RouterConfig, SharedConfig, VaultConfig, VaultRef};
+use crate::error::GatewayApiError;
+use http::StatusCode;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use serde::Deserialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// A secret as read, with the lease Vault granted it, if any.
+#[derive(Debug, Clone, Default)]
+struct Secret {
+    data: HashMap<String, String>,
+    lease: Option<Duration>,
+}
+
+lazy_static! {
+    /// Secrets read, by path.
+    static ref SECRETS: RwLock<HashMap<String, Secret>> = RwLock::new(HashMap::new());
+}
+
+#[derive(Deserialize)]
+struct ReadResponse {
+    #[serde(default)]
+    lease_duration: u64,
+    #[serde(default)]
+    data: Value,
+}
+
+async fn read(vault: &VaultConfig, path: &str) -> Result<Secret, String> {
+    let url = format!("{}/v1/{}", vault.address.trim_end_matches('/'), path);
+    let mut request = reqwest::Client::new()
+        .get(url)
+        .header("X-Vault-Token", &vault.token)
+        .timeout(Duration::from_secs(10));
+    if let Some(namespace) = &vault.namespace {
+        request = request.header("X-Vault-Namespace", namespace);
+    }
+    let response: ReadResponse = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("reading '{}' failed: {}", path, e))?
+        .json()
+        .await
+        .map_err(|e| format!("reading '{}' failed: {}", path, e))?;
+    // Version 2 of the KV engine nests the secret next to its metadata.
+    let fields = match (response.data.get("data"), response.data.get("metadata")) {
+        (Some(data), Some(_)) => data,
+        _ => &response.data,
+    };
+    let data = fields
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+        .collect();
+    Ok(Secret {
+        data,
+        lease: (response.lease_duration > 0).then(|| Duration::from_secs(response.lease_duration)),
+    })
+}
+
+/// Paths of the secrets the API keys of `config` reference.
+fn paths(config: &RouterConfig) -> BTreeSet<String> {
+    config
+        .policies
+        .iter()
+        .flat_map(|policy| &policy.llms)
+        .filter_map(|llm| VaultRef::parse(&llm.api_key))
+        .map(|reference| reference.path)
+        .collect()
+}
+
+fn lookup(reference: &VaultRef) -> Option<String> {
+    let secrets = SECRETS.read().unwrap_or_else(|e| e.into_inner());
+    secrets
+        .get(&reference.path)?
+        .data
+        .get(&reference.key)
+        .cloned()
+}
+
+fn store(path: &str, secret: Secret) {
+    SECRETS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(path.to_string(), secret);
+}
+
+/// Reads every secret `config` references, failing if one cannot be read
+/// or lacks the referenced key.
+pub async fn resolve(config: &RouterConfig) -> Result<(), String> {
+    let Some(vault) = &config.vault else {
+        return Ok(());
+    };
+    for path in paths(config) {
+        store(&path, read(vault, &path).await?);
+    }
+    let references = config
+        .policies
+        .iter()
+        .flat_map(|policy| &policy.llms)
+        .filter_map(|llm| VaultRef::parse(&llm.api_key));
+    for reference in references {
+        if lookup(&reference).is_none() {
+            return Err(format!(
+                "secret '{}' has no key '{}'",
+                reference.path, reference.key
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `llm` with its API key read from Vault if it references it.
+pub async fn resolved<'a>(
+    vault: Option<&VaultConfig>,
+    llm: &'a Llm,
+) -> Result<Cow<'a, Llm>, GatewayApiError> {
+    let Some(reference) = VaultRef::parse(&llm.api_key) else {
+        return Ok(Cow::Borrowed(llm));
+    };
+    let mut api_key = lookup(&reference);
+    if let (None, Some(vault)) = (&api_key, vault) {
+        match read(vault, &reference.path).await {
+            Ok(secret) => {
+                store(&reference.path, secret);
+                api_key = lookup(&reference);
+            }
+            Err(e) => error!("Vault {}", e),
+        }
+    }
+    let api_key = api_key.ok_or_else(|| {
+        error!(
+            "API key of LLM '{}' is not in Vault secret '{}'",
+            llm.name, reference.path
+        );
+        GatewayApiError::LlmServiceError {
+            status: StatusCode::BAD_GATEWAY,
+            message: "API key could not be read from Vault".to_string(),
+            provider: llm.name.clone(),
+            details: None,
+        }
+    })?;
+    Ok(Cow::Owned(Llm {
+        api_key,
+        ..llm.clone()
+    }))
+}
+
+/// Reads the referenced secrets again every `refresh_secs`, or at two
+/// thirds of the shortest lease when sooner. A secret that cannot be read
+/// keeps its last value.
+pub fn spawn(config: SharedConfig) {
+    if config.snapshot().vault.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            let snapshot = config.snapshot();
+            let refresh = snapshot
+                .vault
+                .as_ref()
+                .map_or(0, |vault| vault.refresh_secs);
+            let shortest = SECRETS
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .values()
+                .filter_map(|secret| secret.lease)
+                .min();
+            let wait = shortest
+                .map(|lease| lease * 2 / 3)
+                .unwrap_or(Duration::MAX)
+                .min(Duration::from_secs(refresh.max(1)));
+            tokio::time::sleep(wait).await;
+
+            let snapshot = config.snapshot();
+            let Some(vault) = &snapshot.vault else {
+                continue;
+            };
+            for path in paths(&snapshot) {
+                match read(vault, &path).await {
+                    Ok(secret) => store(&path, secret),
+                    Err(e) => warn!("Kept the last value of a Vault secret; {}", e),
+                }
+            }
+            info!("Refreshed API keys from Vault");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Policy;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/vault-test"))
+            .and(header("X-Vault-Token", "root"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "lease_duration": 0,
+                "data": {
+                    "data": { "nvidia_api_key": "nvapi-secret" },
+                    "metadata": { "version": 3 }
+                }
+            })))
+            .mount(&server)
+            .await;
+        let vault = VaultConfig {
+            address: server.uri(),
+            token: "root".to_string(),
+            ..Default::default()
+        };
+        let llm = |api_key: &str| Llm {
+            name: "Chatbot".to_string(),
+            api_key: api_key.to_string(),
+            ..Default::default()
+        };
+        let config = RouterConfig {
+            policies: vec![Policy {
+                llms: vec![llm("vault:secret/data/vault-test#nvidia_api_key")],
+                ..Default::default()
+            }],
+            vault: Some(vault.clone()),
+            ..Default::default()
+        };
+
+        resolve(&config).await.unwrap();
+        let chatbot = resolved(Some(&vault), &config.policies[0].llms[0])
+            .await
+            .unwrap();
+        assert_eq!(chatbot.api_key, "nvapi-secret");
+
+        let plain = llm("nvapi-plain");
+        assert!(matches!(
+            resolved(Some(&vault), &plain).await.unwrap(),
+            Cow::Borrowed(_)
+        ));
+        let missing = llm("vault:secret/data/vault-test#other");
+        assert!(resolved(Some(&vault), &missing).await.is_err());
+    }
+}
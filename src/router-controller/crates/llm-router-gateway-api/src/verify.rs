@@ -0,0 +1,133 @@
+// SPDX-FileCopyrightText: Copyright (c) 2025 NVIDIA CORPORATION & AFFILIATES. All rights reserved.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verify
+//!
+//! The `validate` subcommand: loads a configuration the way the gateway
+//! does, environment variables and validation included, and optionally
+//! checks that its classifiers and LLMs can be reached, so a deploy can be
+//! gated on it.
+use crate::config::{RouterConfig, UpstreamTls};
+use crate::readiness::check_classifiers;
+use crate::upstream;
+use futures_util::future::join_all;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// `api_base` of every LLM that is not mocked, and its TLS settings.
+fn api_bases(config: &RouterConfig) -> BTreeMap<&str, Option<&UpstreamTls>> {
+    config
+        .policies
+        .iter()
+        .flat_map(|policy| &policy.llms)
+        .filter(|llm| llm.mock.is_none() && !llm.api_base.is_empty())
+        .map(|llm| (llm.api_base.as_str(), llm.tls.as_ref()))
+        .collect()
+}
+
+/// LLM base URLs that did not answer. Any HTTP response, errors included,
+/// shows the upstream is reachable.
+async fn check_api_bases(config: &RouterConfig, timeout: Duration) -> Vec<String> {
+    let probes = api_bases(config).into_iter().map(|(url, tls)| async move {
+        let client = upstream::client_for(None, tls).map_err(|e| format!("{}: {}", url, e))?;
+        match client.get(url).timeout(timeout).send().await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{}: {}", url, e)),
+        }
+    });
+    join_all(probes)
+        .await
+        .into_iter()
+        .filter_map(Result::err)
+        .collect()
+}
+
+/// Problems with the configuration at `path`: none when it loads and, with
+/// `ping`, every classifier is ready and every LLM answers.
+pub async fn run(path: &str, ping: bool, timeout: Duration) -> Vec<String> {
+    let config = match RouterConfig::load_config(path) {
+        Ok(config) => config,
+        Err(e) => return vec![e.to_string()],
+    };
+    if !ping {
+        return Vec::new();
+    }
+    let mut problems: Vec<String> = check_classifiers(&config, timeout)
+        .await
+        .into_iter()
+        .collect();
+    problems.extend(
+        check_api_bases(&config, timeout)
+            .await
+            .into_iter()
+            .map(|problem| format!("LLM unreachable: {}", problem)),
+    );
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn write_config(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("verify_test_{}.yaml", name));
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let llm = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&llm)
+            .await;
+        std::env::set_var("VERIFY_TEST_API_KEY", "secret");
+        let config = |api_base: &str, key_var: &str| {
+            format!(
+                "policies:\n  - name: task_router\n    url: ''\n    llms:\n      - name: Brainstorming\n        api_base: {}\n        api_key: ${{{}}}\n        model: meta/llama\n",
+                api_base, key_var
+            )
+        };
+        let timeout = Duration::from_secs(2);
+
+        let path = write_config("ok", &config(&llm.uri(), "VERIFY_TEST_API_KEY"));
+        assert!(run(&path, true, timeout).await.is_empty());
+        let loaded = RouterConfig::load_config(&path).unwrap();
+        assert_eq!(loaded.policies[0].llms[0].api_key, "secret");
+
+        let path = write_config("env", &config(&llm.uri(), "VERIFY_TEST_UNSET"));
+        let problems = run(&path, false, timeout).await;
+        assert!(problems[0].contains("VERIFY_TEST_UNSET"));
+
+        let duplicated = config(&llm.uri(), "VERIFY_TEST_API_KEY").replace(
+            "policies:\n",
+            "policies:\n  - name: task_router\n    url: ''\n    llms: []\n",
+        );
+        let path = write_config("duplicate", &duplicated);
+        let problems = run(&path, false, timeout).await;
+        assert!(problems[0].contains("is used by another policy"));
+
+        let path = write_config(
+            "unreachable",
+            &config("http://127.0.0.1:1", "VERIFY_TEST_API_KEY"),
+        );
+        assert!(run(&path, false, timeout).await.is_empty());
+        let problems = run(&path, true, timeout).await;
+        assert!(problems[0].starts_with("LLM unreachable: http://127.0.0.1:1"));
+    }
+}